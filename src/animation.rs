@@ -0,0 +1,1804 @@
+//! SMIL animation: the `animate`, `set`, `animateTransform`, `animateMotion` and
+//! `mpath` elements.
+//!
+//! https://www.w3.org/TR/SVG11/animate.html
+//!
+//! Librsvg does not play animations.  Instead, a document can be rendered at a
+//! given point in time.  The animation elements in a document are collected into a
+//! [`Timeline`] when it is loaded; setting the timeline's time evaluates all the
+//! animations at that time, and makes their target elements use the animated
+//! attributes, as if the document had been written that way.  The elements as they
+//! were parsed are kept, so that the document can go back to them.
+//!
+//! Only offset values are supported for `begin` and `end`; syncbase, event, and
+//! wallclock values are ignored.
+
+use cssparser::{Parser, Token};
+use markup5ever::{expanded_name, local_name, namespace_url, ns, LocalName, Prefix, QualName};
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::document::NodeId;
+use crate::element::{Draw, Element, ElementResult, SetAttributes};
+use crate::error::*;
+use crate::href::{is_href, set_href};
+use crate::node::{Node, NodeAnimate, NodeBorrow};
+use crate::parsers::{optional_comma, Parse, ParseValue};
+use crate::path_builder::{FlattenedPath, PathBuilder};
+use crate::properties;
+use crate::util::{clamp, total_cmp};
+use crate::xml::Attributes;
+
+/// A clock value like `2s`, `150ms` or `01:30`, in seconds.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ClockValue(pub f64);
+
+impl Parse for ClockValue {
+    fn parse<'i>(parser: &mut Parser<'i, '_>) -> Result<ClockValue, ParseError<'i>> {
+        let loc = parser.current_source_location();
+        let token = parser.next()?.clone();
+
+        let seconds = match token {
+            // Timecount values without a metric, and full or partial clock values
+            // like "1:30" or "01:02:30.5".
+            Token::Number { value, .. } => {
+                let mut seconds = f64::from(value);
+                while parser.try_parse(|p| p.expect_colon()).is_ok() {
+                    seconds = seconds * 60.0 + f64::from(parser.expect_number()?);
+                }
+                seconds
+            }
+
+            Token::Dimension {
+                value, ref unit, ..
+            } => {
+                let value = f64::from(value);
+                match unit.as_ref() {
+                    "h" => value * 3600.0,
+                    "min" => value * 60.0,
+                    "s" => value,
+                    "ms" => value / 1000.0,
+                    _ => {
+                        return Err(loc.new_custom_error(ValueErrorKind::parse_error(
+                            "invalid metric for clock value",
+                        )))
+                    }
+                }
+            }
+
+            t => return Err(loc.new_unexpected_token_error(t)),
+        };
+
+        Ok(ClockValue(seconds))
+    }
+}
+
+/// A simple duration for `dur` or `repeatDur`, in seconds; `indefinite` is infinite.
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct Duration(f64);
+
+impl Parse for Duration {
+    fn parse<'i>(parser: &mut Parser<'i, '_>) -> Result<Duration, ParseError<'i>> {
+        if parser
+            .try_parse(|p| p.expect_ident_matching("indefinite"))
+            .is_ok()
+        {
+            return Ok(Duration(f64::INFINITY));
+        }
+
+        let loc = parser.current_source_location();
+        let ClockValue(seconds) = ClockValue::parse(parser)?;
+
+        if seconds > 0.0 {
+            Ok(Duration(seconds))
+        } else {
+            Err(loc.new_custom_error(ValueErrorKind::value_error(
+                "duration must be greater than zero",
+            )))
+        }
+    }
+}
+
+/// The `repeatCount` attribute; `indefinite` is infinite.
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct RepeatCount(f64);
+
+impl Parse for RepeatCount {
+    fn parse<'i>(parser: &mut Parser<'i, '_>) -> Result<RepeatCount, ParseError<'i>> {
+        if parser
+            .try_parse(|p| p.expect_ident_matching("indefinite"))
+            .is_ok()
+        {
+            return Ok(RepeatCount(f64::INFINITY));
+        }
+
+        let loc = parser.current_source_location();
+        let count = f64::parse(parser)?;
+
+        if count > 0.0 {
+            Ok(RepeatCount(count))
+        } else {
+            Err(loc.new_custom_error(ValueErrorKind::value_error(
+                "repeatCount must be greater than zero",
+            )))
+        }
+    }
+}
+
+/// The `calcMode` attribute.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum CalcMode {
+    Discrete,
+    Linear,
+    Paced,
+    Spline,
+}
+
+impl Parse for CalcMode {
+    fn parse<'i>(parser: &mut Parser<'i, '_>) -> Result<CalcMode, ParseError<'i>> {
+        Ok(parse_identifiers!(
+            parser,
+            "discrete" => CalcMode::Discrete,
+            "linear" => CalcMode::Linear,
+            "paced" => CalcMode::Paced,
+            "spline" => CalcMode::Spline,
+        )?)
+    }
+}
+
+/// The `fill` attribute of animation elements, not to be confused with the property.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum AnimationFill {
+    Remove,
+    Freeze,
+}
+
+enum_default!(AnimationFill, AnimationFill::Remove);
+
+impl Parse for AnimationFill {
+    fn parse<'i>(parser: &mut Parser<'i, '_>) -> Result<AnimationFill, ParseError<'i>> {
+        Ok(parse_identifiers!(
+            parser,
+            "remove" => AnimationFill::Remove,
+            "freeze" => AnimationFill::Freeze,
+        )?)
+    }
+}
+
+/// The `additive` attribute.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Additive {
+    Replace,
+    Sum,
+}
+
+enum_default!(Additive, Additive::Replace);
+
+impl Parse for Additive {
+    fn parse<'i>(parser: &mut Parser<'i, '_>) -> Result<Additive, ParseError<'i>> {
+        Ok(parse_identifiers!(
+            parser,
+            "replace" => Additive::Replace,
+            "sum" => Additive::Sum,
+        )?)
+    }
+}
+
+/// The `accumulate` attribute.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Accumulate {
+    None,
+    Sum,
+}
+
+enum_default!(Accumulate, Accumulate::None);
+
+impl Parse for Accumulate {
+    fn parse<'i>(parser: &mut Parser<'i, '_>) -> Result<Accumulate, ParseError<'i>> {
+        Ok(parse_identifiers!(
+            parser,
+            "none" => Accumulate::None,
+            "sum" => Accumulate::Sum,
+        )?)
+    }
+}
+
+/// The `attributeType` attribute.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum AttributeType {
+    Auto,
+    Css,
+    Xml,
+}
+
+enum_default!(AttributeType, AttributeType::Auto);
+
+impl Parse for AttributeType {
+    fn parse<'i>(parser: &mut Parser<'i, '_>) -> Result<AttributeType, ParseError<'i>> {
+        Ok(parse_identifiers!(
+            parser,
+            "auto" => AttributeType::Auto,
+            "CSS" => AttributeType::Css,
+            "XML" => AttributeType::Xml,
+        )?)
+    }
+}
+
+/// The `type` attribute of the `animateTransform` element.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum TransformType {
+    Translate,
+    Scale,
+    Rotate,
+    SkewX,
+    SkewY,
+}
+
+enum_default!(TransformType, TransformType::Translate);
+
+impl Parse for TransformType {
+    fn parse<'i>(parser: &mut Parser<'i, '_>) -> Result<TransformType, ParseError<'i>> {
+        Ok(parse_identifiers!(
+            parser,
+            "translate" => TransformType::Translate,
+            "scale" => TransformType::Scale,
+            "rotate" => TransformType::Rotate,
+            "skewX" => TransformType::SkewX,
+            "skewY" => TransformType::SkewY,
+        )?)
+    }
+}
+
+impl fmt::Display for TransformType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            TransformType::Translate => "translate",
+            TransformType::Scale => "scale",
+            TransformType::Rotate => "rotate",
+            TransformType::SkewX => "skewX",
+            TransformType::SkewY => "skewY",
+        })
+    }
+}
+
+impl TransformType {
+    /// Parses the parameters of a transform of this type, with the defaults filled in
+    /// so that all the values of an animation can be interpolated.
+    fn parse_params(self, s: &str) -> Option<AnimatedValue> {
+        let numbers = parse_numbers(s)?;
+
+        let params = match (self, numbers.as_slice()) {
+            (TransformType::Translate, &[tx]) => vec![tx, 0.0],
+            (TransformType::Translate, &[tx, ty]) => vec![tx, ty],
+            (TransformType::Scale, &[s]) => vec![s, s],
+            (TransformType::Scale, &[sx, sy]) => vec![sx, sy],
+            (TransformType::Rotate, &[angle]) => vec![angle, 0.0, 0.0],
+            (TransformType::Rotate, &[angle, cx, cy]) => vec![angle, cx, cy],
+            (TransformType::SkewX, &[angle]) | (TransformType::SkewY, &[angle]) => vec![angle],
+            _ => return None,
+        };
+
+        Some(AnimatedValue::from_numbers(&params))
+    }
+}
+
+/// The `rotate` attribute of the `animateMotion` element.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum MotionRotate {
+    Auto,
+    AutoReverse,
+    Angle(f64),
+}
+
+enum_default!(MotionRotate, MotionRotate::Angle(0.0));
+
+impl Parse for MotionRotate {
+    fn parse<'i>(parser: &mut Parser<'i, '_>) -> Result<MotionRotate, ParseError<'i>> {
+        if let Ok(angle) = parser.try_parse(f64::parse) {
+            return Ok(MotionRotate::Angle(angle));
+        }
+
+        Ok(parse_identifiers!(
+            parser,
+            "auto" => MotionRotate::Auto,
+            "auto-reverse" => MotionRotate::AutoReverse,
+        )?)
+    }
+}
+
+/// One of the cubic Bézier curves in the `keySplines` attribute.
+///
+/// CSS animations use these for their `cubic-bezier()` timing functions, too.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct KeySpline {
+    x1: f64,
+    y1: f64,
+    x2: f64,
+    y2: f64,
+}
+
+impl Parse for KeySpline {
+    fn parse<'i>(parser: &mut Parser<'i, '_>) -> Result<KeySpline, ParseError<'i>> {
+        let mut values = [0.0; 4];
+
+        for (i, value) in values.iter_mut().enumerate() {
+            if i > 0 {
+                optional_comma(parser);
+            }
+
+            let loc = parser.current_source_location();
+            *value = f64::parse(parser)?;
+
+            if !(0.0..=1.0).contains(value) {
+                return Err(loc.new_custom_error(ValueErrorKind::value_error(
+                    "keySplines values must be between 0 and 1",
+                )));
+            }
+        }
+
+        let [x1, y1, x2, y2] = values;
+        Ok(KeySpline { x1, y1, x2, y2 })
+    }
+}
+
+impl KeySpline {
+    pub fn new(x1: f64, y1: f64, x2: f64, y2: f64) -> KeySpline {
+        KeySpline { x1, y1, x2, y2 }
+    }
+
+    /// Maps a fraction of an interval's time to the fraction of its values.
+    pub fn apply(&self, t: f64) -> f64 {
+        fn bezier(p1: f64, p2: f64, s: f64) -> f64 {
+            let ms = 1.0 - s;
+            3.0 * ms * ms * s * p1 + 3.0 * ms * s * s * p2 + s * s * s
+        }
+
+        // The x coordinate of the curve is monotonic since the control points
+        // are between 0 and 1, so we can bisect for the parameter.
+        let (mut low, mut high) = (0.0, 1.0);
+        for _ in 0..32 {
+            let mid = (low + high) / 2.0;
+            if bezier(self.x1, self.x2, mid) < t {
+                low = mid;
+            } else {
+                high = mid;
+            }
+        }
+
+        bezier(self.y1, self.y2, (low + high) / 2.0)
+    }
+}
+
+/// Parses a semicolon-separated list like the ones in `keyTimes` and `keySplines`.
+fn parse_semicolon_list<T: Parse>(attr: &QualName, value: &str) -> Result<Vec<T>, ElementError> {
+    value
+        .split(';')
+        .map(str::trim)
+        .filter(|s| !s.is_empty())
+        .map(|s| attr.parse(s))
+        .collect()
+}
+
+/// Parses the values of a `begin` or `end` attribute.
+///
+/// Only offset values are supported; the other kinds of values get ignored, and so
+/// does `indefinite`.
+fn parse_time_list(value: &str) -> Vec<f64> {
+    value
+        .split(';')
+        .map(str::trim)
+        .filter_map(|s| match ClockValue::parse_str(s) {
+            Ok(ClockValue(t)) => Some(t),
+            Err(_) => {
+                if s != "indefinite" {
+                    rsvg_log!("ignoring unsupported time value \"{}\"", s);
+                }
+                None
+            }
+        })
+        .collect()
+}
+
+/// Parses a list of numbers separated by commas or whitespace.
+fn parse_numbers(s: &str) -> Option<Vec<f64>> {
+    s.split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|s| !s.is_empty())
+        .map(|s| s.parse().ok().filter(|n: &f64| n.is_finite()))
+        .collect()
+}
+
+/// Parses a coordinate pair for `animateMotion`.
+fn parse_point(s: &str) -> Option<(f64, f64)> {
+    match parse_numbers(s)?.as_slice() {
+        &[x, y] => Some((x, y)),
+        _ => None,
+    }
+}
+
+/// Parses the `attributeName` attribute.
+///
+/// The only namespace prefix that we know about is `xlink`.
+fn parse_attribute_name(value: &str) -> QualName {
+    match value.strip_prefix("xlink:") {
+        Some(local) => QualName::new(
+            Some(Prefix::from("xlink")),
+            ns!(xlink),
+            LocalName::from(local),
+        ),
+        None => QualName::new(None, ns!(), LocalName::from(value)),
+    }
+}
+
+/// Where an active animation is in its simple duration.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Progress {
+    /// Fraction of the simple duration, from 0 to 1.
+    simple: f64,
+
+    /// Number of repetitions that have already completed.
+    iteration: f64,
+}
+
+/// A value that can be interpolated, added and scaled.
+#[derive(Debug, Clone, PartialEq)]
+enum AnimatedValue {
+    /// Red, green and blue from 0 to 255, and alpha from 0 to 1.
+    Color([f64; 4]),
+
+    /// The numbers in a value and the text around them, like the number and unit
+    /// in `12px`, or the commands and coordinates in path data.
+    ///
+    /// There is one more piece of text than there are numbers.  Two values can
+    /// be interpolated if all their text is the same.
+    Numbers(Vec<String>, Vec<f64>),
+}
+
+impl AnimatedValue {
+    fn parse(s: &str) -> AnimatedValue {
+        let s = s.trim();
+
+        if let Ok(rgba) = <cssparser::RGBA as Parse>::parse_str(s) {
+            return AnimatedValue::Color([
+                f64::from(rgba.red),
+                f64::from(rgba.green),
+                f64::from(rgba.blue),
+                f64::from(rgba.alpha_f32()),
+            ]);
+        }
+
+        let mut pieces = vec![String::new()];
+        let mut numbers = Vec::new();
+        let mut i = 0;
+
+        while i < s.len() {
+            let rest = &s[i..];
+
+            if rest.starts_with('#') {
+                // Hex colors and fragment identifiers are not numbers.
+                let len = rest
+                    .find(|c: char| c.is_whitespace() || c == ')' || c == ',' || c == ';')
+                    .unwrap_or(rest.len());
+                pieces.last_mut().unwrap().push_str(&rest[..len]);
+                i += len;
+            } else if let Some(len) = number_length(rest) {
+                numbers.push(rest[..len].parse().unwrap());
+                pieces.push(String::new());
+                i += len;
+            } else {
+                let c = rest.chars().next().unwrap();
+                pieces.last_mut().unwrap().push(c);
+                i += c.len_utf8();
+            }
+        }
+
+        AnimatedValue::Numbers(pieces, numbers)
+    }
+
+    /// Creates a value out of space-separated numbers.
+    fn from_numbers(numbers: &[f64]) -> AnimatedValue {
+        let mut pieces = vec![String::new()];
+        for i in 0..numbers.len() {
+            pieces.push(if i + 1 < numbers.len() { " " } else { "" }.to_string());
+        }
+
+        AnimatedValue::Numbers(pieces, numbers.to_vec())
+    }
+
+    fn is_compatible(&self, other: &AnimatedValue) -> bool {
+        match (self, other) {
+            (AnimatedValue::Color(_), AnimatedValue::Color(_)) => true,
+            (AnimatedValue::Numbers(a, _), AnimatedValue::Numbers(b, _)) => a == b,
+            _ => false,
+        }
+    }
+
+    /// Combines the components of two compatible values.
+    fn combine(&self, other: &AnimatedValue, f: impl Fn(f64, f64) -> f64) -> AnimatedValue {
+        match (self, other) {
+            (AnimatedValue::Color(a), AnimatedValue::Color(b)) => {
+                AnimatedValue::Color([f(a[0], b[0]), f(a[1], b[1]), f(a[2], b[2]), f(a[3], b[3])])
+            }
+
+            (AnimatedValue::Numbers(pieces, a), AnimatedValue::Numbers(_, b)) => {
+                AnimatedValue::Numbers(
+                    pieces.clone(),
+                    a.iter().zip(b).map(|(&a, &b)| f(a, b)).collect(),
+                )
+            }
+
+            _ => unreachable!(),
+        }
+    }
+
+    fn interpolate(&self, to: &AnimatedValue, t: f64) -> AnimatedValue {
+        if self.is_compatible(to) {
+            self.combine(to, |a, b| a + (b - a) * t)
+        } else if t < 1.0 {
+            self.clone()
+        } else {
+            to.clone()
+        }
+    }
+
+    fn add(&self, other: &AnimatedValue) -> AnimatedValue {
+        if self.is_compatible(other) {
+            self.combine(other, |a, b| a + b)
+        } else {
+            other.clone()
+        }
+    }
+
+    fn scale(&self, factor: f64) -> AnimatedValue {
+        self.combine(self, |a, _| a * factor)
+    }
+
+    fn zero(&self) -> AnimatedValue {
+        self.scale(0.0)
+    }
+
+    /// Distance between two values, for paced animations.
+    fn distance(&self, other: &AnimatedValue) -> f64 {
+        match (self, other) {
+            (AnimatedValue::Color(a), AnimatedValue::Color(b)) => a[..3]
+                .iter()
+                .zip(&b[..3])
+                .map(|(a, b)| (a - b) * (a - b))
+                .sum::<f64>()
+                .sqrt(),
+
+            (AnimatedValue::Numbers(_, a), AnimatedValue::Numbers(_, b))
+                if self.is_compatible(other) =>
+            {
+                a.iter()
+                    .zip(b)
+                    .map(|(a, b)| (a - b) * (a - b))
+                    .sum::<f64>()
+                    .sqrt()
+            }
+
+            _ => 0.0,
+        }
+    }
+}
+
+impl fmt::Display for AnimatedValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AnimatedValue::Color([r, g, b, a]) => write!(
+                f,
+                "rgba({}, {}, {}, {})",
+                clamp(r.round(), 0.0, 255.0),
+                clamp(g.round(), 0.0, 255.0),
+                clamp(b.round(), 0.0, 255.0),
+                clamp(*a, 0.0, 1.0)
+            ),
+
+            AnimatedValue::Numbers(pieces, numbers) => {
+                for (i, piece) in pieces.iter().enumerate() {
+                    // Numbers that were written next to each other, like in "M10-5",
+                    // need a separator now that their signs may have changed.
+                    if piece.is_empty() && i > 0 && i < numbers.len() {
+                        f.write_str(" ")?;
+                    }
+
+                    f.write_str(piece)?;
+
+                    if let Some(n) = numbers.get(i) {
+                        write!(f, "{}", n)?;
+                    }
+                }
+
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Returns the length of the number at the start of `s`, if there is one.
+fn number_length(s: &str) -> Option<usize> {
+    let bytes = s.as_bytes();
+    let digits_from = |start: usize| {
+        bytes[start..]
+            .iter()
+            .position(|b| !b.is_ascii_digit())
+            .map_or(bytes.len(), |n| start + n)
+    };
+
+    let mut i = 0;
+    if matches!(bytes.first(), Some(b'+') | Some(b'-')) {
+        i += 1;
+    }
+
+    let int_end = digits_from(i);
+    let mut has_digits = int_end > i;
+    i = int_end;
+
+    if bytes.get(i) == Some(&b'.') {
+        let frac_end = digits_from(i + 1);
+        if frac_end > i + 1 {
+            has_digits = true;
+            i = frac_end;
+        }
+    }
+
+    if !has_digits {
+        return None;
+    }
+
+    // Exponents, but not units like "em" or "ex".
+    if matches!(bytes.get(i), Some(b'e') | Some(b'E')) {
+        let mut j = i + 1;
+        if matches!(bytes.get(j), Some(b'+') | Some(b'-')) {
+            j += 1;
+        }
+
+        let exp_end = digits_from(j);
+        if exp_end > j {
+            i = exp_end;
+        }
+    }
+
+    Some(i)
+}
+
+/// Finds the interval between a list of `n` values where an animation is at
+/// `progress`, a fraction of its simple duration.
+///
+/// Returns the index of the value that starts the interval, and the fraction of
+/// the way to the next value, after applying the `keySplines`.  The `distances`
+/// between consecutive values are only used for paced animations.
+fn interval_at(
+    progress: f64,
+    n: usize,
+    calc_mode: CalcMode,
+    key_times: &[f64],
+    key_splines: &[KeySpline],
+    distances: &[f64],
+) -> (usize, f64) {
+    if n < 2 {
+        return (0, 0.0);
+    }
+
+    let progress = clamp(progress, 0.0, 1.0);
+    let total_distance: f64 = distances.iter().sum();
+
+    let times: Vec<f64> = if calc_mode == CalcMode::Paced && total_distance > 0.0 {
+        let mut distance = 0.0;
+        let mut times = vec![0.0];
+        for d in distances {
+            distance += d;
+            times.push(distance / total_distance);
+        }
+        times
+    } else if calc_mode != CalcMode::Paced && valid_key_times(key_times, n, calc_mode) {
+        key_times.to_vec()
+    } else if calc_mode == CalcMode::Discrete {
+        (0..n).map(|i| i as f64 / n as f64).collect()
+    } else {
+        (0..n).map(|i| i as f64 / (n - 1) as f64).collect()
+    };
+
+    let i = times.iter().rposition(|&t| t <= progress).unwrap_or(0);
+
+    if calc_mode == CalcMode::Discrete {
+        return (i, 0.0);
+    }
+
+    if i >= n - 1 {
+        return (n - 2, 1.0);
+    }
+
+    let length = times[i + 1] - times[i];
+    let t = if length > 0.0 {
+        (progress - times[i]) / length
+    } else {
+        1.0
+    };
+
+    if calc_mode == CalcMode::Spline && key_splines.len() == n - 1 {
+        (i, key_splines[i].apply(t))
+    } else {
+        (i, t)
+    }
+}
+
+fn valid_key_times(key_times: &[f64], n: usize, calc_mode: CalcMode) -> bool {
+    key_times.len() == n
+        && key_times[0] == 0.0
+        && key_times.windows(2).all(|w| w[0] <= w[1])
+        && (calc_mode == CalcMode::Discrete || key_times[n - 1] == 1.0)
+        && key_times[n - 1] <= 1.0
+}
+
+/// The attributes that all animation elements have.
+#[derive(Debug, Clone)]
+pub struct Animation {
+    link: Option<NodeId>,
+    attribute_name: Option<QualName>,
+    attribute_type: AttributeType,
+    begin: Vec<f64>,
+    dur: f64,
+    end: Vec<f64>,
+    repeat_count: Option<f64>,
+    repeat_dur: Option<f64>,
+    fill: AnimationFill,
+    values: Vec<String>,
+    from: Option<String>,
+    to: Option<String>,
+    by: Option<String>,
+    key_times: Vec<f64>,
+    key_splines: Vec<KeySpline>,
+    calc_mode: Option<CalcMode>,
+    additive: Additive,
+    accumulate: Accumulate,
+}
+
+impl Default for Animation {
+    fn default() -> Animation {
+        Animation {
+            link: None,
+            attribute_name: None,
+            attribute_type: Default::default(),
+            begin: vec![0.0],
+            dur: f64::INFINITY,
+            end: Vec::new(),
+            repeat_count: None,
+            repeat_dur: None,
+            fill: Default::default(),
+            values: Vec::new(),
+            from: None,
+            to: None,
+            by: None,
+            key_times: Vec::new(),
+            key_splines: Vec::new(),
+            calc_mode: None,
+            additive: Default::default(),
+            accumulate: Default::default(),
+        }
+    }
+}
+
+impl SetAttributes for Animation {
+    fn set_attributes(&mut self, attrs: &Attributes) -> ElementResult {
+        for (attr, value) in attrs.iter() {
+            match attr.expanded() {
+                ref a if is_href(a) => set_href(
+                    a,
+                    &mut self.link,
+                    NodeId::parse(value).attribute(attr.clone())?,
+                ),
+                expanded_name!("", "attributeName") => {
+                    self.attribute_name = Some(parse_attribute_name(value.trim()))
+                }
+                expanded_name!("", "attributeType") => self.attribute_type = attr.parse(value)?,
+                expanded_name!("", "begin") => self.begin = parse_time_list(value),
+                expanded_name!("", "dur") => {
+                    let Duration(dur) = attr.parse(value)?;
+                    self.dur = dur;
+                }
+                expanded_name!("", "end") => self.end = parse_time_list(value),
+                expanded_name!("", "repeatCount") => {
+                    let RepeatCount(count) = attr.parse(value)?;
+                    self.repeat_count = Some(count);
+                }
+                expanded_name!("", "repeatDur") => {
+                    let Duration(dur) = attr.parse(value)?;
+                    self.repeat_dur = Some(dur);
+                }
+                expanded_name!("", "fill") => self.fill = attr.parse(value)?,
+                expanded_name!("", "values") => {
+                    self.values = value
+                        .split(';')
+                        .map(str::trim)
+                        .filter(|s| !s.is_empty())
+                        .map(String::from)
+                        .collect()
+                }
+                expanded_name!("", "from") => self.from = Some(value.trim().to_string()),
+                expanded_name!("", "to") => self.to = Some(value.trim().to_string()),
+                expanded_name!("", "by") => self.by = Some(value.trim().to_string()),
+                expanded_name!("", "keyTimes") => {
+                    self.key_times = parse_semicolon_list(&attr, value)?
+                }
+                expanded_name!("", "keySplines") => {
+                    self.key_splines = parse_semicolon_list(&attr, value)?
+                }
+                expanded_name!("", "calcMode") => self.calc_mode = attr.parse(value)?,
+                expanded_name!("", "additive") => self.additive = attr.parse(value)?,
+                expanded_name!("", "accumulate") => self.accumulate = attr.parse(value)?,
+                _ => (),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Animation {
+    /// Finds where the animation is at `time`.
+    ///
+    /// Returns the time when the current interval began, and the progress in it; or
+    /// `None` if the animation has no effect at that time.
+    fn progress_at(&self, time: f64) -> Option<(f64, Progress)> {
+        let begin = self
+            .begin
+            .iter()
+            .copied()
+            .filter(|&b| b <= time)
+            .max_by(|a, b| total_cmp(*a, *b))?;
+
+        let mut active = match (self.repeat_count, self.repeat_dur) {
+            (None, None) => self.dur,
+            (Some(count), None) => self.dur * count,
+            (None, Some(repeat_dur)) => repeat_dur,
+            (Some(count), Some(repeat_dur)) => (self.dur * count).min(repeat_dur),
+        };
+
+        if let Some(end) = self
+            .end
+            .iter()
+            .copied()
+            .filter(|&e| e >= begin)
+            .min_by(|a, b| total_cmp(*a, *b))
+        {
+            active = active.min(end - begin);
+        }
+
+        let elapsed = time - begin;
+
+        if elapsed < active {
+            Some((begin, self.progress(elapsed)))
+        } else if self.fill == AnimationFill::Freeze {
+            Some((begin, self.frozen_progress(active)))
+        } else {
+            None
+        }
+    }
+
+    fn progress(&self, elapsed: f64) -> Progress {
+        if self.dur.is_finite() {
+            let iteration = (elapsed / self.dur).floor();
+            Progress {
+                simple: elapsed / self.dur - iteration,
+                iteration,
+            }
+        } else {
+            Progress {
+                simple: 0.0,
+                iteration: 0.0,
+            }
+        }
+    }
+
+    /// The progress at the end of the active duration, for animations that freeze.
+    ///
+    /// If the active duration ends at the end of a repetition, the value is the one at
+    /// the end of that repetition, not the one at the start of the next.
+    fn frozen_progress(&self, active: f64) -> Progress {
+        let progress = self.progress(active);
+
+        if progress.simple == 0.0 && progress.iteration > 0.0 {
+            Progress {
+                simple: 1.0,
+                iteration: progress.iteration - 1.0,
+            }
+        } else {
+            progress
+        }
+    }
+
+    /// Whether the animation applies to a property instead of an XML attribute.
+    fn is_property(&self, name: &QualName) -> bool {
+        self.attribute_type != AttributeType::Xml
+            && name.expanded() != expanded_name!("", "transform")
+            && properties::is_property(name)
+    }
+
+    /// Computes the animation's value at `progress`.
+    ///
+    /// The `base` is the underlying value of the attribute, which `to` animations start
+    /// from.  Returns the value, and whether it must be added to the underlying value.
+    fn value_at(
+        &self,
+        progress: Progress,
+        base: Option<&AnimatedValue>,
+        default_calc_mode: CalcMode,
+        parse: &dyn Fn(&str) -> Option<AnimatedValue>,
+    ) -> Option<(AnimatedValue, bool)> {
+        let additive = self.additive == Additive::Sum;
+        let accumulate = self.accumulate == Accumulate::Sum;
+
+        let (values, additive, accumulate) = if !self.values.is_empty() {
+            let values = self
+                .values
+                .iter()
+                .map(|v| parse(v))
+                .collect::<Option<Vec<_>>>()?;
+            (values, additive, accumulate)
+        } else {
+            match (self.from.as_deref(), self.to.as_deref(), self.by.as_deref()) {
+                (Some(from), Some(to), _) => (vec![parse(from)?, parse(to)?], additive, accumulate),
+
+                (Some(from), None, Some(by)) => {
+                    let from = parse(from)?;
+                    let to = from.add(&parse(by)?);
+                    (vec![from, to], additive, accumulate)
+                }
+
+                // to animations start from the underlying value, and are never additive.
+                (None, Some(to), _) => match base {
+                    Some(base) => (vec![base.clone(), parse(to)?], false, false),
+                    None => (vec![parse(to)?], false, false),
+                },
+
+                (None, None, Some(by)) => {
+                    let by = parse(by)?;
+                    (vec![by.zero(), by], true, accumulate)
+                }
+
+                _ => return None,
+            }
+        };
+
+        // Values that cannot be interpolated are animated discretely.
+        let calc_mode = if values.windows(2).all(|w| w[0].is_compatible(&w[1])) {
+            self.calc_mode.unwrap_or(default_calc_mode)
+        } else {
+            CalcMode::Discrete
+        };
+
+        let distances: Vec<f64> = if calc_mode == CalcMode::Paced {
+            values.windows(2).map(|w| w[0].distance(&w[1])).collect()
+        } else {
+            Vec::new()
+        };
+
+        let (i, t) = interval_at(
+            progress.simple,
+            values.len(),
+            calc_mode,
+            &self.key_times,
+            &self.key_splines,
+            &distances,
+        );
+
+        let mut value = match values.get(i + 1) {
+            Some(next) => values[i].interpolate(next, t),
+            None => values[i].clone(),
+        };
+
+        if accumulate && progress.iteration > 0.0 {
+            let last = &values[values.len() - 1];
+            value = last.scale(progress.iteration).add(&value);
+        }
+
+        Some((value, additive))
+    }
+}
+
+/// The attributes of an animation target, as its animations change them.
+pub struct AnimatedAttributes {
+    attributes: Attributes,
+
+    /// Values of animated properties, which override the element's style.
+    properties: Vec<(QualName, String)>,
+
+    /// Supplemental transform from `animateMotion`.
+    motion: Option<String>,
+}
+
+impl AnimatedAttributes {
+    fn new(attributes: Attributes) -> AnimatedAttributes {
+        AnimatedAttributes {
+            attributes,
+            properties: Vec::new(),
+            motion: None,
+        }
+    }
+
+    /// Gets the current value of an attribute or property.
+    ///
+    /// FIXME: for properties this is the presentation attribute, not the computed value.
+    fn get(&self, name: &QualName, is_property: bool) -> Option<&str> {
+        if is_property {
+            if let Some((_, value)) = self
+                .properties
+                .iter()
+                .find(|(n, _)| n.expanded() == name.expanded())
+            {
+                return Some(value);
+            }
+        }
+
+        self.attributes.get(name)
+    }
+
+    fn set(&mut self, name: &QualName, is_property: bool, value: String) {
+        if is_property {
+            self.properties
+                .retain(|(n, _)| n.expanded() != name.expanded());
+            self.properties.push((name.clone(), value));
+        } else {
+            self.attributes.set(name, &value);
+        }
+    }
+
+    /// Returns the animated attributes, and declarations for the animated properties.
+    fn into_attributes_and_properties(self) -> (Attributes, String) {
+        let mut attributes = self.attributes;
+
+        if let Some(motion) = self.motion {
+            let name = QualName::new(None, ns!(), local_name!("transform"));
+            let transform = match attributes.get(&name) {
+                Some(transform) => format!("{} {}", motion, transform),
+                None => motion,
+            };
+            attributes.set(&name, &transform);
+        }
+
+        let properties = self
+            .properties
+            .iter()
+            .map(|(property, value)| format!("{}:{};", property.local, value))
+            .collect();
+
+        (attributes, properties)
+    }
+}
+
+/// Interface for the animation elements, so the [`Timeline`] can evaluate them.
+pub trait AnimationElement {
+    fn animation(&self) -> &Animation;
+
+    /// Applies the value at `progress` to the attributes of the animation's target.
+    ///
+    /// The `motion_path` is the path data of the `mpath` child of an `animateMotion`.
+    fn apply(&self, progress: Progress, target: &mut AnimatedAttributes, motion_path: Option<&str>);
+}
+
+/// The `<animate>` element.
+#[derive(Default)]
+pub struct Animate {
+    animation: Animation,
+}
+
+impl SetAttributes for Animate {
+    fn set_attributes(&mut self, attrs: &Attributes) -> ElementResult {
+        self.animation.set_attributes(attrs)
+    }
+}
+
+impl Draw for Animate {}
+
+impl AnimationElement for Animate {
+    fn animation(&self) -> &Animation {
+        &self.animation
+    }
+
+    fn apply(&self, progress: Progress, target: &mut AnimatedAttributes, _: Option<&str>) {
+        let name = match self.animation.attribute_name {
+            Some(ref name) => name,
+            None => return,
+        };
+
+        let is_property = self.animation.is_property(name);
+        let base = target.get(name, is_property).map(AnimatedValue::parse);
+
+        if let Some((value, additive)) =
+            self.animation
+                .value_at(progress, base.as_ref(), CalcMode::Linear, &|s| {
+                    Some(AnimatedValue::parse(s))
+                })
+        {
+            let value = match base {
+                Some(ref base) if additive => base.add(&value),
+                _ => value,
+            };
+
+            target.set(name, is_property, value.to_string());
+        }
+    }
+}
+
+/// The `<set>` element.
+#[derive(Default)]
+pub struct Set {
+    animation: Animation,
+}
+
+impl SetAttributes for Set {
+    fn set_attributes(&mut self, attrs: &Attributes) -> ElementResult {
+        self.animation.set_attributes(attrs)
+    }
+}
+
+impl Draw for Set {}
+
+impl AnimationElement for Set {
+    fn animation(&self) -> &Animation {
+        &self.animation
+    }
+
+    fn apply(&self, _: Progress, target: &mut AnimatedAttributes, _: Option<&str>) {
+        if let (Some(ref name), Some(ref to)) = (&self.animation.attribute_name, &self.animation.to)
+        {
+            target.set(name, self.animation.is_property(name), to.clone());
+        }
+    }
+}
+
+/// The `<animateTransform>` element.
+#[derive(Default)]
+pub struct AnimateTransform {
+    animation: Animation,
+    transform_type: TransformType,
+}
+
+impl SetAttributes for AnimateTransform {
+    fn set_attributes(&mut self, attrs: &Attributes) -> ElementResult {
+        self.animation.set_attributes(attrs)?;
+
+        for (attr, value) in attrs.iter() {
+            if let expanded_name!("", "type") = attr.expanded() {
+                self.transform_type = attr.parse(value)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Draw for AnimateTransform {}
+
+impl AnimationElement for AnimateTransform {
+    fn animation(&self) -> &Animation {
+        &self.animation
+    }
+
+    fn apply(&self, progress: Progress, target: &mut AnimatedAttributes, _: Option<&str>) {
+        let name = match self.animation.attribute_name {
+            Some(ref name) => name,
+            None => return,
+        };
+
+        let transform_type = self.transform_type;
+
+        if let Some((value, additive)) =
+            self.animation
+                .value_at(progress, None, CalcMode::Linear, &|s| {
+                    transform_type.parse_params(s)
+                })
+        {
+            let transform = format!("{}({})", transform_type, value);
+            let transform = match target.get(name, false) {
+                Some(base) if additive => format!("{} {}", base, transform),
+                _ => transform,
+            };
+
+            target.set(name, false, transform);
+        }
+    }
+}
+
+/// The `<animateMotion>` element.
+#[derive(Default)]
+pub struct AnimateMotion {
+    animation: Animation,
+    path: Option<String>,
+    key_points: Vec<f64>,
+    rotate: MotionRotate,
+}
+
+impl SetAttributes for AnimateMotion {
+    fn set_attributes(&mut self, attrs: &Attributes) -> ElementResult {
+        self.animation.set_attributes(attrs)?;
+
+        for (attr, value) in attrs.iter() {
+            match attr.expanded() {
+                expanded_name!("", "path") => self.path = Some(value.to_string()),
+                expanded_name!("", "keyPoints") => {
+                    self.key_points = parse_semicolon_list(&attr, value)?
+                }
+                expanded_name!("", "rotate") => self.rotate = attr.parse(value)?,
+                _ => (),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Draw for AnimateMotion {}
+
+impl AnimateMotion {
+    /// The points in `values`, `from`, `to` and `by`, to move along straight lines.
+    fn points(&self) -> Option<Vec<(f64, f64)>> {
+        let anim = &self.animation;
+
+        if !anim.values.is_empty() {
+            return anim.values.iter().map(|v| parse_point(v)).collect();
+        }
+
+        let from = anim.from.as_deref().map(parse_point);
+        let to = anim.to.as_deref().map(parse_point);
+        let by = anim.by.as_deref().map(parse_point);
+
+        match (from, to, by) {
+            (Some(from), Some(to), _) => Some(vec![from?, to?]),
+            (Some(from), None, Some(by)) => {
+                let ((x, y), (dx, dy)) = (from?, by?);
+                Some(vec![(x, y), (x + dx, y + dy)])
+            }
+            (None, Some(to), _) => Some(vec![(0.0, 0.0), to?]),
+            (None, None, Some(by)) => Some(vec![(0.0, 0.0), by?]),
+            _ => None,
+        }
+    }
+
+    /// Finds the motion path, and the distance along it of each of the points from
+    /// `values`, if that is where it came from.
+    fn path(&self, motion_path: Option<&str>) -> Option<(FlattenedPath, Option<Vec<f64>>)> {
+        if let Some(d) = motion_path.or(self.path.as_deref()) {
+            let mut builder = PathBuilder::default();
+            if let Err(e) = builder.parse(d) {
+                rsvg_log!("invalid motion path: {}", e);
+            }
+
+            return Some((builder.into_path().flatten(), None));
+        }
+
+        let points = self.points()?;
+        let mut builder = PathBuilder::default();
+        let mut distances = Vec::new();
+        let mut distance = 0.0;
+
+        for (i, &(x, y)) in points.iter().enumerate() {
+            if i == 0 {
+                builder.move_to(x, y);
+            } else {
+                let (px, py) = points[i - 1];
+                distance += ((x - px) * (x - px) + (y - py) * (y - py)).sqrt();
+                builder.line_to(x, y);
+            }
+            distances.push(distance);
+        }
+
+        Some((builder.into_path().flatten(), Some(distances)))
+    }
+}
+
+impl AnimationElement for AnimateMotion {
+    fn animation(&self) -> &Animation {
+        &self.animation
+    }
+
+    fn apply(
+        &self,
+        progress: Progress,
+        target: &mut AnimatedAttributes,
+        motion_path: Option<&str>,
+    ) {
+        let anim = &self.animation;
+        let calc_mode = anim.calc_mode.unwrap_or(CalcMode::Paced);
+
+        let (path, vertices) = match self.path(motion_path) {
+            Some(path) => path,
+            None => return,
+        };
+
+        let length = path.length();
+
+        let distance = if !self.key_points.is_empty() && calc_mode != CalcMode::Paced {
+            let points = &self.key_points;
+            let (i, t) = interval_at(
+                progress.simple,
+                points.len(),
+                calc_mode,
+                &anim.key_times,
+                &anim.key_splines,
+                &[],
+            );
+            let next = points.get(i + 1).unwrap_or(&points[i]);
+            (points[i] + (next - points[i]) * t) * length
+        } else {
+            match vertices {
+                Some(ref distances) if calc_mode != CalcMode::Paced => {
+                    let (i, t) = interval_at(
+                        progress.simple,
+                        distances.len(),
+                        calc_mode,
+                        &anim.key_times,
+                        &anim.key_splines,
+                        &[],
+                    );
+                    let next = distances.get(i + 1).unwrap_or(&distances[i]);
+                    distances[i] + (next - distances[i]) * t
+                }
+
+                _ => progress.simple * length,
+            }
+        };
+
+        // FIXME: accumulate is not supported for motion.
+        let ((x, y), angle) = match path.point_at(clamp(distance, 0.0, length)) {
+            Some(point) => point,
+            None => return,
+        };
+
+        let angle = match self.rotate {
+            MotionRotate::Auto => angle.to_degrees(),
+            MotionRotate::AutoReverse => angle.to_degrees() + 180.0,
+            MotionRotate::Angle(a) => a,
+        };
+
+        let motion = format!("translate({} {}) rotate({})", x, y, angle);
+        target.motion = match target.motion.take() {
+            Some(base) if anim.additive == Additive::Sum => Some(format!("{} {}", base, motion)),
+            _ => Some(motion),
+        };
+    }
+}
+
+/// The `<mpath>` element.
+#[derive(Default)]
+pub struct MPath {
+    link: Option<NodeId>,
+}
+
+impl SetAttributes for MPath {
+    fn set_attributes(&mut self, attrs: &Attributes) -> ElementResult {
+        for (attr, value) in attrs.iter() {
+            match attr.expanded() {
+                ref a if is_href(a) => set_href(
+                    a,
+                    &mut self.link,
+                    NodeId::parse(value).attribute(attr.clone())?,
+                ),
+                _ => (),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Draw for MPath {}
+
+struct TimelineEntry {
+    /// The animation element.
+    node: Node,
+
+    /// Index of the animation's target in [`Timeline::targets`].
+    target: usize,
+
+    /// Path data from the `mpath` of an `animateMotion`.
+    motion_path: Option<String>,
+}
+
+/// The animations in a document, and the elements they change.
+pub struct Timeline {
+    animations: Vec<TimelineEntry>,
+
+    /// Elements that get animated.
+    targets: Vec<Node>,
+
+    /// Time when the targets were last animated, or `None` if they have their
+    /// original attributes.
+    time: Cell<Option<f64>>,
+}
+
+impl Timeline {
+    pub fn new(root: &Node, ids: &HashMap<String, Node>) -> Timeline {
+        let mut animations = Vec::new();
+        let mut targets: Vec<Node> = Vec::new();
+
+        for node in root.descendants().filter(|n| n.is_element()) {
+            let element = node.borrow_element();
+
+            let animation = match element.as_animation() {
+                Some(a) if !element.is_in_error() => a.animation(),
+                _ => continue,
+            };
+
+            let target = match animation.link {
+                Some(NodeId::Internal(ref id)) => ids.get(id).cloned(),
+                Some(NodeId::External(..)) => None,
+                None => node.parent(),
+            };
+
+            let target = match target {
+                Some(target) if target.is_element() => target,
+                _ => {
+                    rsvg_log!("ignoring {} without a target element", *element);
+                    continue;
+                }
+            };
+
+            let index = match targets.iter().position(|n| *n == target) {
+                Some(index) => index,
+                None => {
+                    targets.push(target);
+                    targets.len() - 1
+                }
+            };
+
+            let motion_path = if let Element::AnimateMotion(_) = *element {
+                node.children()
+                    .find(|c| c.is_element() && is_element_of_type!(c, MPath))
+                    .and_then(|mpath| match borrow_element_as!(mpath, MPath).link {
+                        Some(NodeId::Internal(ref id)) => ids.get(id).cloned(),
+                        _ => None,
+                    })
+                    .and_then(|path| {
+                        let d = QualName::new(None, ns!(), local_name!("d"));
+                        let element = path.borrow_element();
+                        element.get_attributes().get(&d).map(String::from)
+                    })
+            } else {
+                None
+            };
+
+            animations.push(TimelineEntry {
+                node: node.clone(),
+                target: index,
+                motion_path,
+            });
+        }
+
+        Timeline {
+            animations,
+            targets,
+            time: Cell::new(None),
+        }
+    }
+
+    /// Evaluates the animations at `time`, in seconds, and makes their targets use the
+    /// resulting attributes.  With `None`, the targets go back to the elements as they
+    /// were parsed.
+    ///
+    /// The parsed elements are not modified; each target gets a new element with the
+    /// animated attributes instead, which the rest of librsvg sees in place of the parsed
+    /// one.
+    ///
+    /// Returns the targets that changed, whose subtrees need to be cascaded again.
+    pub fn set_time(&self, time: Option<f64>) -> &[Node] {
+        if self.time.get() == time || self.animations.is_empty() {
+            return &[];
+        }
+
+        self.time.set(time);
+
+        for (index, node) in self.targets.iter().enumerate() {
+            let time = match time {
+                Some(time) => time,
+                None => {
+                    node.clone().set_animated_element(None);
+                    continue;
+                }
+            };
+
+            let mut active: Vec<(f64, &TimelineEntry, Progress)> = self
+                .animations
+                .iter()
+                .filter(|entry| entry.target == index)
+                .filter_map(|entry| {
+                    let element = entry.node.borrow_element();
+                    let (begin, progress) =
+                        element.as_animation()?.animation().progress_at(time)?;
+                    Some((begin, entry, progress))
+                })
+                .collect();
+
+            // Animations that began later have priority, and then the ones that
+            // come later in the document; the sort is stable.
+            active.sort_by(|a, b| total_cmp(a.0, b.0));
+
+            if active.is_empty() {
+                node.clone().set_animated_element(None);
+                continue;
+            }
+
+            let (name, attributes) = {
+                let parsed = node.borrow_parsed_element();
+                (
+                    parsed.element_name().clone(),
+                    parsed.get_attributes().clone(),
+                )
+            };
+
+            let mut animated = AnimatedAttributes::new(attributes);
+
+            for (_, entry, progress) in active {
+                if let Some(animation) = entry.node.borrow_element().as_animation() {
+                    animation.apply(progress, &mut animated, entry.motion_path.as_deref());
+                }
+            }
+
+            let (attributes, properties) = animated.into_attributes_and_properties();
+            let element = Element::new(&name, attributes);
+            node.clone()
+                .set_animated_element(Some((element, properties)));
+        }
+
+        &self.targets
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_clock_values() {
+        assert_eq!(ClockValue::parse_str("2s").unwrap(), ClockValue(2.0));
+        assert_eq!(ClockValue::parse_str("150ms").unwrap(), ClockValue(0.15));
+        assert_eq!(ClockValue::parse_str("1.5").unwrap(), ClockValue(1.5));
+        assert_eq!(ClockValue::parse_str("2min").unwrap(), ClockValue(120.0));
+        assert_eq!(ClockValue::parse_str("1h").unwrap(), ClockValue(3600.0));
+        assert_eq!(ClockValue::parse_str("01:30").unwrap(), ClockValue(90.0));
+        assert_eq!(
+            ClockValue::parse_str("1:00:10").unwrap(),
+            ClockValue(3610.0)
+        );
+        assert_eq!(ClockValue::parse_str("-1s").unwrap(), ClockValue(-1.0));
+
+        assert!(ClockValue::parse_str("2px").is_err());
+        assert!(ClockValue::parse_str("foo").is_err());
+
+        assert_eq!(
+            Duration::parse_str("indefinite").unwrap(),
+            Duration(f64::INFINITY)
+        );
+        assert!(Duration::parse_str("0s").is_err());
+        assert!(RepeatCount::parse_str("-1").is_err());
+    }
+
+    #[test]
+    fn parses_time_lists() {
+        assert_eq!(parse_time_list("1s; 3s"), vec![1.0, 3.0]);
+        assert_eq!(parse_time_list("foo.click; 2s; indefinite"), vec![2.0]);
+    }
+
+    #[test]
+    fn parses_animated_values() {
+        assert_eq!(
+            AnimatedValue::parse("12.5px"),
+            AnimatedValue::Numbers(vec!["".to_string(), "px".to_string()], vec![12.5])
+        );
+
+        assert_eq!(
+            AnimatedValue::parse("red"),
+            AnimatedValue::Color([255.0, 0.0, 0.0, 1.0])
+        );
+
+        assert_eq!(
+            AnimatedValue::parse("url(#a1) 2em"),
+            AnimatedValue::Numbers(vec!["url(#a1) ".to_string(), "em".to_string()], vec![2.0])
+        );
+
+        assert_eq!(
+            AnimatedValue::parse("M10-5.5.5").to_string(),
+            "M10 -5.5 0.5"
+        );
+    }
+
+    #[test]
+    fn interpolates_values() {
+        let a = AnimatedValue::parse("M 0 0 L 10 20");
+        let b = AnimatedValue::parse("M 10 10 L 20 40");
+        assert_eq!(a.interpolate(&b, 0.5).to_string(), "M 5 5 L 15 30");
+
+        let a = AnimatedValue::parse("red");
+        let b = AnimatedValue::parse("blue");
+        assert_eq!(a.interpolate(&b, 0.5).to_string(), "rgba(128, 0, 128, 1)");
+
+        let a = AnimatedValue::parse("visible");
+        let b = AnimatedValue::parse("hidden");
+        assert_eq!(a.interpolate(&b, 0.5), a);
+    }
+
+    #[test]
+    fn finds_intervals() {
+        assert_eq!(
+            interval_at(0.5, 3, CalcMode::Linear, &[], &[], &[]),
+            (1, 0.0)
+        );
+        assert_eq!(
+            interval_at(0.25, 3, CalcMode::Linear, &[], &[], &[]),
+            (0, 0.5)
+        );
+        assert_eq!(
+            interval_at(1.0, 3, CalcMode::Linear, &[], &[], &[]),
+            (1, 1.0)
+        );
+
+        assert_eq!(
+            interval_at(0.5, 2, CalcMode::Discrete, &[], &[], &[]),
+            (1, 0.0)
+        );
+        assert_eq!(
+            interval_at(0.4, 2, CalcMode::Discrete, &[], &[], &[]),
+            (0, 0.0)
+        );
+
+        assert_eq!(
+            interval_at(0.4, 3, CalcMode::Linear, &[0.0, 0.8, 1.0], &[], &[]),
+            (0, 0.5)
+        );
+
+        // invalid keyTimes are ignored
+        assert_eq!(
+            interval_at(0.25, 3, CalcMode::Linear, &[0.0, 0.8], &[], &[]),
+            (0, 0.5)
+        );
+
+        assert_eq!(
+            interval_at(0.5, 3, CalcMode::Paced, &[], &[], &[1.0, 3.0]),
+            (1, 1.0 / 3.0)
+        );
+
+        let ease_in = KeySpline {
+            x1: 0.5,
+            y1: 0.0,
+            x2: 1.0,
+            y2: 1.0,
+        };
+        let (i, t) = interval_at(0.5, 2, CalcMode::Spline, &[], &[ease_in], &[]);
+        assert_eq!(i, 0);
+        assert!(t < 0.5);
+    }
+
+    #[test]
+    fn computes_progress() {
+        let animation = Animation {
+            begin: vec![1.0, 10.0],
+            dur: 2.0,
+            repeat_count: Some(2.0),
+            ..Default::default()
+        };
+
+        assert_eq!(animation.progress_at(0.5), None);
+        assert_eq!(
+            animation.progress_at(2.0),
+            Some((
+                1.0,
+                Progress {
+                    simple: 0.5,
+                    iteration: 0.0
+                }
+            ))
+        );
+        assert_eq!(
+            animation.progress_at(4.0),
+            Some((
+                1.0,
+                Progress {
+                    simple: 0.5,
+                    iteration: 1.0
+                }
+            ))
+        );
+        assert_eq!(animation.progress_at(6.0), None);
+        assert_eq!(
+            animation.progress_at(10.0),
+            Some((
+                10.0,
+                Progress {
+                    simple: 0.0,
+                    iteration: 0.0
+                }
+            ))
+        );
+
+        let frozen = Animation {
+            fill: AnimationFill::Freeze,
+            ..animation
+        };
+
+        assert_eq!(
+            frozen.progress_at(6.0),
+            Some((
+                1.0,
+                Progress {
+                    simple: 1.0,
+                    iteration: 1.0
+                }
+            ))
+        );
+    }
+
+    #[test]
+    fn ignores_nan_begin_and_end_times() {
+        let animation = Animation {
+            begin: vec![f64::NAN, 1.0],
+            end: vec![f64::NAN, 2.0],
+            dur: 2.0,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            animation.progress_at(1.5),
+            Some((
+                1.0,
+                Progress {
+                    simple: 0.25,
+                    iteration: 0.0
+                }
+            ))
+        );
+        assert_eq!(animation.progress_at(2.5), None);
+    }
+
+    #[test]
+    fn computes_values() {
+        let parse = |s: &str| Some(AnimatedValue::parse(s));
+        let at = |simple, iteration| Progress { simple, iteration };
+
+        let animation = Animation {
+            values: vec!["0".to_string(), "10".to_string(), "30".to_string()],
+            ..Default::default()
+        };
+        let value = |a: &Animation, p| {
+            a.value_at(p, None, CalcMode::Linear, &parse)
+                .map(|(v, _)| v.to_string())
+        };
+
+        assert_eq!(value(&animation, at(0.75, 0.0)), Some("20".to_string()));
+
+        let paced = Animation {
+            calc_mode: Some(CalcMode::Paced),
+            ..animation.clone()
+        };
+        assert_eq!(value(&paced, at(0.5, 0.0)), Some("15".to_string()));
+
+        let accumulated = Animation {
+            accumulate: Accumulate::Sum,
+            ..animation
+        };
+        assert_eq!(value(&accumulated, at(0.0, 2.0)), Some("60".to_string()));
+
+        let by = Animation {
+            by: Some("5".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            by.value_at(at(0.5, 0.0), None, CalcMode::Linear, &parse),
+            Some((AnimatedValue::parse("2.5"), true))
+        );
+
+        let to = Animation {
+            to: Some("10".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            to.value_at(
+                at(0.5, 0.0),
+                Some(&AnimatedValue::parse("20")),
+                CalcMode::Linear,
+                &parse
+            ),
+            Some((AnimatedValue::parse("15"), false))
+        );
+    }
+}
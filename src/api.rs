@@ -7,6 +7,8 @@
 pub use crate::{
     accept_language::{AcceptLanguage, Language, UserLanguage},
     error::{ImplementationLimit, LoadingError, RenderingError},
+    handle::TextOutline,
+    layout::TextRun,
     length::{LengthUnit, RsvgLength as Length},
 };
 
@@ -20,6 +22,7 @@ use gio::Cancellable;
 use crate::{
     dpi::Dpi,
     handle::{Handle, LoadOptions},
+    structure::ViewSpec,
     url_resolver::UrlResolver,
 };
 
@@ -246,6 +249,9 @@ pub struct CairoRenderer<'a> {
     handle: &'a SvgHandle,
     dpi: Dpi,
     user_language: UserLanguage,
+    text_as_paths: bool,
+    time: Option<f64>,
+    view: Option<ViewSpec>,
     is_testing: bool,
 }
 
@@ -274,7 +280,8 @@ pub struct IntrinsicDimensions {
     /// `height` attribute of the `<svg>`, if present
     pub height: Option<Length>,
 
-    /// `viewBox` attribute of the `<svg>`, if present
+    /// `viewBox` attribute of the `<svg>`, if present, as overridden by the view selected
+    /// with [`CairoRenderer::with_view`]
     pub vbox: Option<cairo::Rectangle>,
 }
 
@@ -290,6 +297,9 @@ impl<'a> CairoRenderer<'a> {
             handle,
             dpi: Dpi::new(DEFAULT_DPI_X, DEFAULT_DPI_Y),
             user_language: UserLanguage::new(&Language::FromEnvironment),
+            text_as_paths: false,
+            time: None,
+            view: None,
             is_testing: false,
         }
     }
@@ -328,6 +338,60 @@ impl<'a> CairoRenderer<'a> {
         }
     }
 
+    /// Configures whether to render text as paths.
+    ///
+    /// Normally text is rendered with Cairo's glyph functions, so vector outputs like PDF
+    /// or SVG surfaces get actual text or font glyphs.  With this option, text is rendered
+    /// by filling and stroking the outlines of its glyphs instead, so that vector outputs
+    /// only have plain paths.  The default is `false`.
+    ///
+    /// See also [`text_outlines`] to get those outlines directly.
+    ///
+    /// [`text_outlines`]: #method.text_outlines
+    pub fn with_text_as_paths(self, text_as_paths: bool) -> Self {
+        CairoRenderer {
+            text_as_paths,
+            ..self
+        }
+    }
+
+    /// Configures the point in time, in seconds, at which to render the SVG's animations.
+    ///
+    /// SVG documents can have SMIL animations with the `<animate>`, `<set>`,
+    /// `<animateTransform>`, and `<animateMotion>` elements, and CSS animations from
+    /// `@keyframes` rules.  Librsvg does not play them, but with this option the document
+    /// is rendered as it would be `seconds` after the animations start.  By default,
+    /// animations are ignored and the document is rendered with its attributes as written.
+    /// If `seconds` is not a finite number, it is ignored.
+    ///
+    /// This affects all the functions that render or measure the document.
+    pub fn at_time(self, seconds: f64) -> Self {
+        if !seconds.is_finite() {
+            return self;
+        }
+
+        CairoRenderer {
+            time: Some(seconds),
+            ..self
+        }
+    }
+
+    /// Configures the view with which to render the SVG, from a fragment identifier.
+    ///
+    /// The `fragment` can be the `id` of a `<view>` element in the document, or a view
+    /// specification like `svgView(viewBox(0,0,24,24))`, with or without a leading `#`.
+    /// The view overrides the `viewBox` and `preserveAspectRatio` of the toplevel `<svg>`
+    /// element, which is useful to pick an icon out of a sprite sheet.  If the fragment
+    /// does not select a view, the whole document is rendered as usual.
+    ///
+    /// This affects all the functions that render or measure the document.
+    pub fn with_view(self, fragment: &str) -> Self {
+        CairoRenderer {
+            view: self.handle.0.resolve_view(fragment),
+            ..self
+        }
+    }
+
     /// Queries the `width`, `height`, and `viewBox` attributes in an SVG document.
     ///
     /// If you are calling this function to compute a scaling factor to render the SVG,
@@ -340,7 +404,7 @@ impl<'a> CairoRenderer<'a> {
     /// [`render_document`]: #method.render_document
     /// [`intrinsic_size_in_pixels`]: #method.intrinsic_size_in_pixels
     pub fn intrinsic_dimensions(&self) -> IntrinsicDimensions {
-        let d = self.handle.0.get_intrinsic_dimensions();
+        let d = self.handle.0.get_intrinsic_dimensions(self.time, self.view);
 
         IntrinsicDimensions {
             width: d.width.map(Into::into),
@@ -360,7 +424,9 @@ impl<'a> CairoRenderer<'a> {
     /// kind require more information to be resolved to pixels; for example, the calling
     /// application can use a viewport size to scale percentage-based dimensions.
     pub fn intrinsic_size_in_pixels(&self) -> Option<(f64, f64)> {
-        self.handle.0.get_intrinsic_size_in_pixels(self.dpi)
+        self.handle
+            .0
+            .get_intrinsic_size_in_pixels(self.dpi, self.time, self.view)
     }
 
     /// Renders the whole SVG document fitted to a viewport
@@ -376,9 +442,16 @@ impl<'a> CairoRenderer<'a> {
         cr: &cairo::Context,
         viewport: &cairo::Rectangle,
     ) -> Result<(), RenderingError> {
-        self.handle
-            .0
-            .render_document(cr, viewport, &self.user_language, self.dpi, self.is_testing)
+        self.handle.0.render_document(
+            cr,
+            viewport,
+            &self.user_language,
+            self.dpi,
+            self.is_testing,
+            self.text_as_paths,
+            self.time,
+            self.view,
+        )
     }
 
     /// Computes the (ink_rect, logical_rect) of an SVG element, as if
@@ -412,7 +485,15 @@ impl<'a> CairoRenderer<'a> {
     ) -> Result<(cairo::Rectangle, cairo::Rectangle), RenderingError> {
         self.handle
             .0
-            .get_geometry_for_layer(id, viewport, &self.user_language, self.dpi, self.is_testing)
+            .get_geometry_for_layer(
+                id,
+                viewport,
+                &self.user_language,
+                self.dpi,
+                self.is_testing,
+                self.time,
+                self.view,
+            )
             .map(|(i, l)| (i, l))
     }
 
@@ -448,6 +529,9 @@ impl<'a> CairoRenderer<'a> {
             &self.user_language,
             self.dpi,
             self.is_testing,
+            self.text_as_paths,
+            self.time,
+            self.view,
         )
     }
 
@@ -487,7 +571,14 @@ impl<'a> CairoRenderer<'a> {
     ) -> Result<(cairo::Rectangle, cairo::Rectangle), RenderingError> {
         self.handle
             .0
-            .get_geometry_for_element(id, &self.user_language, self.dpi, self.is_testing)
+            .get_geometry_for_element(
+                id,
+                &self.user_language,
+                self.dpi,
+                self.is_testing,
+                self.time,
+                self.view,
+            )
             .map(|(i, l)| (i, l))
     }
 
@@ -520,6 +611,55 @@ impl<'a> CairoRenderer<'a> {
             &self.user_language,
             self.dpi,
             self.is_testing,
+            self.text_as_paths,
+            self.time,
+            self.view,
+        )
+    }
+
+    /// Computes the glyph outlines of each `<text>` element, as if the SVG were rendered
+    /// to a specific viewport.
+    ///
+    /// This runs the same text layout as rendering, and returns one [`TextOutline`] for
+    /// each `<text>` element that has something to render.  The outlines are given as SVG
+    /// path data, in the same coordinate system as the `viewport`; that is, as the paths
+    /// would be placed on a Cairo context with an identity transformation.
+    ///
+    /// Only the geometry of the glyphs and text decorations is returned; it does not
+    /// include strokes, or the effects of clipping paths, masks, or filters.
+    pub fn text_outlines(
+        &self,
+        viewport: &cairo::Rectangle,
+    ) -> Result<Vec<TextOutline>, RenderingError> {
+        self.handle.0.get_text_outlines(
+            viewport,
+            &self.user_language,
+            self.dpi,
+            self.is_testing,
+            self.time,
+            self.view,
+        )
+    }
+
+    /// Extracts the runs of text that get rendered, as if the SVG were rendered to a
+    /// specific viewport.
+    ///
+    /// This is useful to index the text in SVG documents, or to highlight parts of it.
+    /// The text gets laid out in the same way as for rendering, and each [`TextRun`] has the
+    /// text's string, font, and the `id` and language of the element it comes from.  Its
+    /// bounds, and the bounds of each character, are in the same coordinate system as the
+    /// `viewport`.
+    ///
+    /// The runs are returned in document order.  Text that is not rendered, like text with
+    /// `visibility="hidden"` or within a `<defs>`, is omitted.
+    pub fn text_runs(&self, viewport: &cairo::Rectangle) -> Result<Vec<TextRun>, RenderingError> {
+        self.handle.0.get_text_runs(
+            viewport,
+            &self.user_language,
+            self.dpi,
+            self.is_testing,
+            self.time,
+            self.view,
         )
     }
 
@@ -0,0 +1,664 @@
+//! CSS basic shapes for the `clip-path` property.
+//!
+//! Besides references to `<clipPath>` elements, `clip-path` can take one of the basic
+//! shape functions from the CSS Shapes Module, like `circle(50%)` or `inset(10px round
+//! 5px)`, optionally with a reference box like `fill-box`.  The shape's percentages and
+//! its origin are resolved against that box, which comes from the element's bounding
+//! box; see [`ShapeBox`].
+//!
+//! https://www.w3.org/TR/css-shapes-1/#basic-shape-functions
+//!
+//! https://www.w3.org/TR/css-masking-1/#the-clip-path
+
+use cssparser::Parser;
+
+use crate::error::*;
+use crate::length::*;
+use crate::parsers::Parse;
+use crate::path_builder::{LargeArc, Path, PathBuilder, Sweep};
+use crate::properties::FillRule;
+use crate::rect::Rect;
+use crate::shapes::make_ellipse;
+
+/// Reference box against which a basic shape is resolved.
+///
+/// https://www.w3.org/TR/css-masking-1/#typedef-geometry-box
+///
+/// SVG elements don't have CSS layout boxes, so `content-box` and `padding-box` are
+/// taken as `fill-box`, and `border-box` and `margin-box` as `stroke-box`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ShapeBox {
+    Fill,
+    Stroke,
+    View,
+}
+
+impl Default for ShapeBox {
+    /// The default reference box is `border-box`, that is, `stroke-box`.
+    fn default() -> ShapeBox {
+        ShapeBox::Stroke
+    }
+}
+
+impl Parse for ShapeBox {
+    fn parse<'i>(parser: &mut Parser<'i, '_>) -> Result<ShapeBox, ParseError<'i>> {
+        Ok(parse_identifiers!(
+            parser,
+            "fill-box" => ShapeBox::Fill,
+            "content-box" => ShapeBox::Fill,
+            "padding-box" => ShapeBox::Fill,
+            "stroke-box" => ShapeBox::Stroke,
+            "border-box" => ShapeBox::Stroke,
+            "margin-box" => ShapeBox::Stroke,
+            "view-box" => ShapeBox::View,
+        )?)
+    }
+}
+
+/// Radius of a `circle()`, or one of the radii of an `ellipse()`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ShapeRadius {
+    Length(ULength<Both>),
+    ClosestSide,
+    FarthestSide,
+}
+
+impl Parse for ShapeRadius {
+    fn parse<'i>(parser: &mut Parser<'i, '_>) -> Result<ShapeRadius, ParseError<'i>> {
+        if let Ok(length) = parser.try_parse(ULength::<Both>::parse) {
+            return Ok(ShapeRadius::Length(length));
+        }
+
+        Ok(parse_identifiers!(
+            parser,
+            "closest-side" => ShapeRadius::ClosestSide,
+            "farthest-side" => ShapeRadius::FarthestSide,
+        )?)
+    }
+}
+
+/// Center of a `circle()` or `ellipse()`, from the `at <position>` part.
+///
+/// Keywords are stored as the equivalent percentages.  Only the one and two-value forms
+/// of `<position>` are supported.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Position {
+    x: Length<Both>,
+    y: Length<Both>,
+}
+
+impl Default for Position {
+    fn default() -> Position {
+        Position {
+            x: percent(0.5),
+            y: percent(0.5),
+        }
+    }
+}
+
+#[derive(Copy, Clone)]
+enum PositionComponent {
+    Horizontal(Length<Both>),
+    Vertical(Length<Both>),
+    Center,
+    Length(Length<Both>),
+}
+
+impl Parse for PositionComponent {
+    fn parse<'i>(parser: &mut Parser<'i, '_>) -> Result<PositionComponent, ParseError<'i>> {
+        if let Ok(length) = parser.try_parse(Length::<Both>::parse) {
+            return Ok(PositionComponent::Length(length));
+        }
+
+        Ok(parse_identifiers!(
+            parser,
+            "left" => PositionComponent::Horizontal(percent(0.0)),
+            "right" => PositionComponent::Horizontal(percent(1.0)),
+            "top" => PositionComponent::Vertical(percent(0.0)),
+            "bottom" => PositionComponent::Vertical(percent(1.0)),
+            "center" => PositionComponent::Center,
+        )?)
+    }
+}
+
+impl Parse for Position {
+    fn parse<'i>(parser: &mut Parser<'i, '_>) -> Result<Position, ParseError<'i>> {
+        use PositionComponent::*;
+
+        let loc = parser.current_source_location();
+
+        let first = PositionComponent::parse(parser)?;
+        let second = parser.try_parse(PositionComponent::parse).ok();
+
+        let center = percent(0.5);
+
+        let (x, y) = match (first, second) {
+            (Horizontal(x), None) | (Length(x), None) => (x, center),
+            (Vertical(y), None) => (center, y),
+            (Center, None) => (center, center),
+
+            (Horizontal(x), Some(Vertical(y)))
+            | (Horizontal(x), Some(Length(y)))
+            | (Length(x), Some(Vertical(y)))
+            | (Length(x), Some(Length(y)))
+            | (Vertical(y), Some(Horizontal(x))) => (x, y),
+
+            (Horizontal(x), Some(Center)) | (Length(x), Some(Center)) => (x, center),
+            (Center, Some(Vertical(y))) | (Center, Some(Length(y))) => (center, y),
+            (Vertical(y), Some(Center)) => (center, y),
+            (Center, Some(Horizontal(x))) => (x, center),
+            (Center, Some(Center)) => (center, center),
+
+            _ => {
+                return Err(loc.new_custom_error(ValueErrorKind::parse_error(
+                    "invalid position in basic shape",
+                )))
+            }
+        };
+
+        Ok(Position { x, y })
+    }
+}
+
+/// One of the basic shape functions.
+///
+/// https://www.w3.org/TR/css-shapes-1/#typedef-basic-shape
+#[derive(Debug, Clone, PartialEq)]
+pub enum BasicShape {
+    Circle {
+        r: ShapeRadius,
+        center: Position,
+    },
+
+    Ellipse {
+        rx: ShapeRadius,
+        ry: ShapeRadius,
+        center: Position,
+    },
+
+    /// Offsets are top, right, bottom, left; corner radii are top-left, top-right,
+    /// bottom-right, bottom-left.
+    Inset {
+        offsets: [Length<Both>; 4],
+        radii: [ULength<Both>; 4],
+    },
+
+    Polygon {
+        fill_rule: FillRule,
+        points: Vec<(Length<Both>, Length<Both>)>,
+    },
+
+    /// The path data is kept as a string, since it has already been validated.
+    Path {
+        fill_rule: FillRule,
+        path: String,
+    },
+}
+
+fn percent(p: f64) -> Length<Both> {
+    Length::<Both>::new(p, LengthUnit::Percent)
+}
+
+/// Expands one to four values per the CSS box shorthands, like `margin` does.
+fn expand_sides<T: Copy>(values: &[T]) -> [T; 4] {
+    match *values {
+        [a] => [a, a, a, a],
+        [a, b] => [a, b, a, b],
+        [a, b, c] => [a, b, c, b],
+        [a, b, c, d] => [a, b, c, d],
+        _ => unreachable!(),
+    }
+}
+
+/// Parses one to four values separated by whitespace.
+fn parse_one_to_four<'i, T: Parse + Copy>(
+    parser: &mut Parser<'i, '_>,
+) -> Result<[T; 4], ParseError<'i>> {
+    let mut values = vec![T::parse(parser)?];
+
+    while values.len() < 4 {
+        match parser.try_parse(T::parse) {
+            Ok(v) => values.push(v),
+            Err(_) => break,
+        }
+    }
+
+    Ok(expand_sides(&values))
+}
+
+/// Parses the optional `<fill-rule>,` at the start of `polygon()` and `path()`.
+fn parse_fill_rule<'i>(parser: &mut Parser<'i, '_>) -> FillRule {
+    parser
+        .try_parse(|p| -> Result<FillRule, ParseError<'_>> {
+            let fill_rule = FillRule::parse(p)?;
+            p.expect_comma()?;
+            Ok(fill_rule)
+        })
+        .unwrap_or_default()
+}
+
+fn parse_circle<'i>(parser: &mut Parser<'i, '_>) -> Result<BasicShape, ParseError<'i>> {
+    let r = parser
+        .try_parse(ShapeRadius::parse)
+        .unwrap_or(ShapeRadius::ClosestSide);
+
+    let center = parse_center(parser)?;
+
+    Ok(BasicShape::Circle { r, center })
+}
+
+fn parse_ellipse<'i>(parser: &mut Parser<'i, '_>) -> Result<BasicShape, ParseError<'i>> {
+    let (rx, ry) = parser
+        .try_parse(|p| -> Result<_, ParseError<'_>> {
+            Ok((ShapeRadius::parse(p)?, ShapeRadius::parse(p)?))
+        })
+        .unwrap_or((ShapeRadius::ClosestSide, ShapeRadius::ClosestSide));
+
+    let center = parse_center(parser)?;
+
+    Ok(BasicShape::Ellipse { rx, ry, center })
+}
+
+fn parse_center<'i>(parser: &mut Parser<'i, '_>) -> Result<Position, ParseError<'i>> {
+    if parser.try_parse(|p| p.expect_ident_matching("at")).is_ok() {
+        Position::parse(parser)
+    } else {
+        Ok(Position::default())
+    }
+}
+
+fn parse_inset<'i>(parser: &mut Parser<'i, '_>) -> Result<BasicShape, ParseError<'i>> {
+    let offsets = parse_one_to_four::<Length<Both>>(parser)?;
+
+    // The corners go clockwise from the top-left one, like the sides go from the top one.
+    let radii = if parser
+        .try_parse(|p| p.expect_ident_matching("round"))
+        .is_ok()
+    {
+        parse_one_to_four::<ULength<Both>>(parser)?
+    } else {
+        [ULength::<Both>::new(0.0, LengthUnit::Px); 4]
+    };
+
+    Ok(BasicShape::Inset { offsets, radii })
+}
+
+fn parse_polygon<'i>(parser: &mut Parser<'i, '_>) -> Result<BasicShape, ParseError<'i>> {
+    let fill_rule = parse_fill_rule(parser);
+
+    let points = parser
+        .parse_comma_separated(|p| Ok((Length::<Both>::parse(p)?, Length::<Both>::parse(p)?)))?;
+
+    Ok(BasicShape::Polygon { fill_rule, points })
+}
+
+fn parse_path<'i>(parser: &mut Parser<'i, '_>) -> Result<BasicShape, ParseError<'i>> {
+    let fill_rule = parse_fill_rule(parser);
+
+    let loc = parser.current_source_location();
+    let path = parser.expect_string()?.to_string();
+
+    let mut builder = PathBuilder::default();
+    if builder.parse(&path).is_err() {
+        return Err(loc.new_custom_error(ValueErrorKind::parse_error("invalid path data")));
+    }
+
+    Ok(BasicShape::Path { fill_rule, path })
+}
+
+impl Parse for BasicShape {
+    fn parse<'i>(parser: &mut Parser<'i, '_>) -> Result<BasicShape, ParseError<'i>> {
+        let loc = parser.current_source_location();
+        let name = parser.expect_function()?.clone();
+
+        parser.parse_nested_block(|p| match name.as_ref() {
+            "circle" => parse_circle(p),
+            "ellipse" => parse_ellipse(p),
+            "inset" => parse_inset(p),
+            "polygon" => parse_polygon(p),
+            "path" => parse_path(p),
+            _ => Err(loc.new_custom_error(ValueErrorKind::parse_error("expected basic shape"))),
+        })
+    }
+}
+
+/// Resolves a length against a size in the reference box, for percentages.
+fn resolve<V: Validate>(length: &CssLength<Both, V>, size: f64, params: &NormalizeParams) -> f64 {
+    if length.unit == LengthUnit::Percent {
+        length.length * size
+    } else {
+        length.to_user(params)
+    }
+}
+
+impl ShapeRadius {
+    /// Resolves the radius for a center at `c` in a box side of length `size`.
+    fn resolve(&self, c: f64, size: f64, params: &NormalizeParams) -> f64 {
+        match *self {
+            ShapeRadius::Length(ref l) => resolve(l, size, params),
+            ShapeRadius::ClosestSide => c.abs().min((size - c).abs()),
+            ShapeRadius::FarthestSide => c.abs().max((size - c).abs()),
+        }
+    }
+}
+
+impl BasicShape {
+    /// Builds the shape's path for a reference box of the given size.
+    ///
+    /// The path is in the coordinate system of the reference box, i.e. its origin is the
+    /// box's top-left corner; the caller has to translate it to the box's position.
+    pub fn to_path(&self, w: f64, h: f64, params: &NormalizeParams) -> (Path, FillRule) {
+        let point =
+            |x: &Length<Both>, y: &Length<Both>| (resolve(x, w, params), resolve(y, h, params));
+
+        match *self {
+            BasicShape::Circle { ref r, ref center } => {
+                let (cx, cy) = point(&center.x, &center.y);
+
+                let r = match *r {
+                    // Percentages refer to the normalized diagonal of the reference box.
+                    ShapeRadius::Length(ref l) => resolve(l, w.hypot(h) / 2f64.sqrt(), params),
+                    ShapeRadius::ClosestSide => cx
+                        .abs()
+                        .min((w - cx).abs())
+                        .min(cy.abs())
+                        .min((h - cy).abs()),
+                    ShapeRadius::FarthestSide => cx
+                        .abs()
+                        .max((w - cx).abs())
+                        .max(cy.abs())
+                        .max((h - cy).abs()),
+                };
+
+                (make_ellipse(cx, cy, r, r), FillRule::NonZero)
+            }
+
+            BasicShape::Ellipse {
+                ref rx,
+                ref ry,
+                ref center,
+            } => {
+                let (cx, cy) = point(&center.x, &center.y);
+
+                let rx = rx.resolve(cx, w, params);
+                let ry = ry.resolve(cy, h, params);
+
+                (make_ellipse(cx, cy, rx, ry), FillRule::NonZero)
+            }
+
+            BasicShape::Inset {
+                ref offsets,
+                ref radii,
+            } => {
+                let [top, right, bottom, left] = offsets;
+
+                let rect = Rect::new(
+                    resolve(left, w, params),
+                    resolve(top, h, params),
+                    w - resolve(right, w, params),
+                    h - resolve(bottom, h, params),
+                );
+
+                (
+                    make_rounded_rect(&rect, radii, w, h, params),
+                    FillRule::NonZero,
+                )
+            }
+
+            BasicShape::Polygon {
+                fill_rule,
+                ref points,
+            } => {
+                let mut builder = PathBuilder::default();
+
+                for (i, (x, y)) in points.iter().enumerate() {
+                    let (x, y) = point(x, y);
+
+                    if i == 0 {
+                        builder.move_to(x, y);
+                    } else {
+                        builder.line_to(x, y);
+                    }
+                }
+
+                builder.close_path();
+
+                (builder.into_path(), fill_rule)
+            }
+
+            BasicShape::Path {
+                fill_rule,
+                ref path,
+            } => {
+                let mut builder = PathBuilder::default();
+
+                // The path was validated while parsing.
+                let _ = builder.parse(path);
+
+                (builder.into_path(), fill_rule)
+            }
+        }
+    }
+}
+
+/// Builds the path for an `inset()`, whose corner radii are resolved against the size
+/// of the reference box.
+///
+/// Radii that don't fit in the rectangle get scaled down proportionally, as for the
+/// CSS `border-radius` property.
+fn make_rounded_rect(
+    rect: &Rect,
+    radii: &[ULength<Both>; 4],
+    w: f64,
+    h: f64,
+    params: &NormalizeParams,
+) -> Path {
+    let mut builder = PathBuilder::default();
+
+    if rect.is_empty() {
+        return builder.into_path();
+    }
+
+    let mut rx = [0.0; 4];
+    let mut ry = [0.0; 4];
+
+    for i in 0..4 {
+        rx[i] = resolve(&radii[i], w, params);
+        ry[i] = resolve(&radii[i], h, params);
+    }
+
+    let (width, height) = (rect.width(), rect.height());
+
+    let mut scale: f64 = 1.0;
+
+    for &(sum, side) in &[
+        (rx[0] + rx[1], width),
+        (rx[3] + rx[2], width),
+        (ry[0] + ry[3], height),
+        (ry[1] + ry[2], height),
+    ] {
+        if sum > side {
+            scale = scale.min(side / sum);
+        }
+    }
+
+    for i in 0..4 {
+        rx[i] *= scale;
+        ry[i] *= scale;
+    }
+
+    let Rect { x0, y0, x1, y1 } = *rect;
+
+    let corner = |builder: &mut PathBuilder, i: usize, from: (f64, f64), to: (f64, f64)| {
+        builder.arc(
+            from.0,
+            from.1,
+            rx[i],
+            ry[i],
+            0.0,
+            LargeArc(false),
+            Sweep::Positive,
+            to.0,
+            to.1,
+        );
+    };
+
+    builder.move_to(x0 + rx[0], y0);
+
+    builder.line_to(x1 - rx[1], y0);
+    corner(&mut builder, 1, (x1 - rx[1], y0), (x1, y0 + ry[1]));
+
+    builder.line_to(x1, y1 - ry[2]);
+    corner(&mut builder, 2, (x1, y1 - ry[2]), (x1 - rx[2], y1));
+
+    builder.line_to(x0 + rx[3], y1);
+    corner(&mut builder, 3, (x0 + rx[3], y1), (x0, y1 - ry[3]));
+
+    builder.line_to(x0, y0 + ry[0]);
+    corner(&mut builder, 0, (x0, y0 + ry[0]), (x0 + rx[0], y0));
+
+    builder.close_path();
+
+    builder.into_path()
+}
+
+/// A basic shape with the box against which it gets resolved, as in
+/// `clip-path: circle(50%) fill-box`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShapeClip {
+    pub shape: BasicShape,
+    pub reference_box: ShapeBox,
+}
+
+impl Parse for ShapeClip {
+    /// Parses `<basic-shape> || <geometry-box>`.
+    ///
+    /// A box by itself clips to the box, like `inset(0)` would.
+    fn parse<'i>(parser: &mut Parser<'i, '_>) -> Result<ShapeClip, ParseError<'i>> {
+        let mut reference_box = parser.try_parse(ShapeBox::parse).ok();
+
+        let shape = match parser.try_parse(BasicShape::parse) {
+            Ok(shape) => shape,
+            Err(e) => {
+                if reference_box.is_none() {
+                    return Err(e);
+                }
+
+                BasicShape::Inset {
+                    offsets: [percent(0.0); 4],
+                    radii: [ULength::<Both>::new(0.0, LengthUnit::Px); 4],
+                }
+            }
+        };
+
+        if reference_box.is_none() {
+            reference_box = parser.try_parse(ShapeBox::parse).ok();
+        }
+
+        Ok(ShapeClip {
+            shape,
+            reference_box: reference_box.unwrap_or_default(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn px(l: f64) -> Length<Both> {
+        Length::<Both>::new(l, LengthUnit::Px)
+    }
+
+    #[test]
+    fn parses_circle() {
+        assert_eq!(
+            BasicShape::parse_str("circle()").unwrap(),
+            BasicShape::Circle {
+                r: ShapeRadius::ClosestSide,
+                center: Position::default(),
+            }
+        );
+
+        assert_eq!(
+            BasicShape::parse_str("circle(50% at left 10px)").unwrap(),
+            BasicShape::Circle {
+                r: ShapeRadius::Length(ULength::<Both>::new(0.5, LengthUnit::Percent)),
+                center: Position {
+                    x: percent(0.0),
+                    y: px(10.0),
+                },
+            }
+        );
+
+        assert_eq!(
+            BasicShape::parse_str("circle(farthest-side at bottom)").unwrap(),
+            BasicShape::Circle {
+                r: ShapeRadius::FarthestSide,
+                center: Position {
+                    x: percent(0.5),
+                    y: percent(1.0),
+                },
+            }
+        );
+
+        assert!(BasicShape::parse_str("circle(-5px)").is_err());
+        assert!(BasicShape::parse_str("circle(at left right)").is_err());
+    }
+
+    #[test]
+    fn parses_inset_and_polygon() {
+        assert_eq!(
+            BasicShape::parse_str("inset(1px 2px round 3px)").unwrap(),
+            BasicShape::Inset {
+                offsets: [px(1.0), px(2.0), px(1.0), px(2.0)],
+                radii: [ULength::<Both>::new(3.0, LengthUnit::Px); 4],
+            }
+        );
+
+        assert_eq!(
+            BasicShape::parse_str("polygon(evenodd, 0 0, 10px 50%)").unwrap(),
+            BasicShape::Polygon {
+                fill_rule: FillRule::EvenOdd,
+                points: vec![(px(0.0), px(0.0)), (px(10.0), percent(0.5))],
+            }
+        );
+
+        assert!(BasicShape::parse_str("inset()").is_err());
+        assert!(BasicShape::parse_str("polygon(0 0, 10px)").is_err());
+        assert!(BasicShape::parse_str("path('M 0 0 L')").is_err());
+    }
+
+    #[test]
+    fn parses_shape_with_box() {
+        assert_eq!(
+            ShapeClip::parse_str("view-box ellipse(1px 2px)").unwrap(),
+            ShapeClip {
+                shape: BasicShape::Ellipse {
+                    rx: ShapeRadius::Length(ULength::<Both>::new(1.0, LengthUnit::Px)),
+                    ry: ShapeRadius::Length(ULength::<Both>::new(2.0, LengthUnit::Px)),
+                    center: Position::default(),
+                },
+                reference_box: ShapeBox::View,
+            }
+        );
+
+        assert_eq!(
+            ShapeClip::parse_str("path('M 0 0 h 10') fill-box").unwrap(),
+            ShapeClip {
+                shape: BasicShape::Path {
+                    fill_rule: FillRule::NonZero,
+                    path: String::from("M 0 0 h 10"),
+                },
+                reference_box: ShapeBox::Fill,
+            }
+        );
+
+        assert_eq!(
+            ShapeClip::parse_str("padding-box").unwrap().reference_box,
+            ShapeBox::Fill
+        );
+
+        assert!(ShapeClip::parse_str("fill-box stroke-box").is_err());
+    }
+}
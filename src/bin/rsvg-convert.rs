@@ -29,7 +29,7 @@ use librsvg::{
 };
 use once_cell::unsync::OnceCell;
 use std::ops::Deref;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 #[derive(Debug)]
 pub struct Error(String);
@@ -430,12 +430,17 @@ struct Converter {
     pub page_size: Option<(ULength<Horizontal>, ULength<Vertical>)>,
     pub format: Format,
     pub export_id: Option<String>,
+    pub view: Option<String>,
     pub keep_aspect_ratio: bool,
     pub background_color: Option<Color>,
     pub stylesheet: Option<PathBuf>,
     pub language: Language,
     pub unlimited: bool,
     pub keep_image_data: bool,
+    pub text_to_path: bool,
+    pub time: Option<f64>,
+    pub frames: u32,
+    pub frame_rate: f64,
     pub input: Vec<Input>,
     pub output: Output,
 }
@@ -475,11 +480,26 @@ impl Converter {
                     .map_err(|e| error!("Error applying stylesheet: {}", e))?;
             }
 
-            let renderer = CairoRenderer::new(&handle)
-                .with_dpi(self.dpi.0, self.dpi.1)
-                .with_language(&self.language);
+            let renderer_for_frame = |frame| {
+                let renderer = CairoRenderer::new(&handle)
+                    .with_dpi(self.dpi.0, self.dpi.1)
+                    .with_language(&self.language)
+                    .with_text_as_paths(self.text_to_path);
 
-            let geometry = natural_geometry(&renderer, input, self.export_id.as_deref())?;
+                let renderer = match self.view {
+                    Some(ref view) => renderer.with_view(view),
+                    None => renderer,
+                };
+
+                match self.frame_time(frame) {
+                    Some(time) => renderer.at_time(time),
+                    None => renderer,
+                }
+            };
+
+            // All the frames use the geometry of the first one
+            let geometry =
+                natural_geometry(&renderer_for_frame(0), input, self.export_id.as_deref())?;
 
             // natural_size is in pixels
             let natural_size = Size::new(geometry.width, geometry.height);
@@ -551,23 +571,36 @@ impl Converter {
 
             let final_size = self.final_size(&strategy, &natural_size, input)?;
 
-            // Create the surface once on the first input
             let page_size = page_size.unwrap_or(final_size);
-            let s = surface.get_or_try_init(|| self.create_surface(page_size))?;
 
             let left = self.left.map(|l| l.to_user(&params)).unwrap_or(0.0);
             let top = self.top.map(|l| l.to_user(&params)).unwrap_or(0.0);
 
-            s.render(
-                &renderer,
-                left,
-                top,
-                final_size,
-                geometry,
-                self.background_color,
-                self.export_id.as_deref(),
-            )
-            .map_err(|e| error!("Error rendering SVG {}: {}", input, e))?
+            for frame in 0..self.frames {
+                let render = |s: &Surface| {
+                    s.render(
+                        &renderer_for_frame(frame),
+                        left,
+                        top,
+                        final_size,
+                        geometry,
+                        self.background_color,
+                        self.export_id.as_deref(),
+                    )
+                    .map_err(|e| error!("Error rendering SVG {}: {}", input, e))
+                };
+
+                if self.frames > 1 && matches!(self.format, Format::Png | Format::Svg) {
+                    // Formats without pages get one output file per frame
+                    let s = self.create_surface(page_size, Some(frame))?;
+                    render(&s)?;
+                    s.finish()
+                        .map_err(|e| error!("Error saving output {}: {}", self.output, e))?
+                } else {
+                    // Create the surface once on the first input; each frame is a page
+                    render(surface.get_or_try_init(|| self.create_surface(page_size, None))?)?
+                }
+            }
         }
 
         if let Some(s) = surface.take() {
@@ -592,11 +625,21 @@ impl Converter {
             .ok_or_else(|| error!("The SVG {} has no dimensions", input))
     }
 
-    fn create_surface(&self, size: Size) -> Result<Surface, Error> {
+    /// Computes the time at which to render a frame, if animations are to be rendered.
+    fn frame_time(&self, frame: u32) -> Option<f64> {
+        self.time
+            .map(|time| time + f64::from(frame) / self.frame_rate)
+    }
+
+    fn create_surface(&self, size: Size, frame: Option<u32>) -> Result<Surface, Error> {
         let output_stream = match self.output {
             Output::Stdout => Stdout::stream(),
             Output::Path(ref p) => {
-                let file = gio::File::for_path(p);
+                let path = match frame {
+                    Some(frame) => frame_path(p, frame).unwrap(),
+                    None => p.clone(),
+                };
+                let file = gio::File::for_path(&path);
                 let stream = file
                     .replace(None, false, FileCreateFlags::NONE, None::<&Cancellable>)
                     .map_err(|e| error!("Error opening output \"{}\": {}", self.output, e))?;
@@ -608,6 +651,30 @@ impl Converter {
     }
 }
 
+/// Replaces the `%d` or `%0Nd` placeholder in an output filename with a frame number.
+///
+/// Returns `None` if the filename has no placeholder.
+fn frame_path(path: &Path, frame: u32) -> Option<PathBuf> {
+    let s = path.to_str()?;
+    let start = s.find('%')?;
+    let rest = &s[start + 1..];
+    let end = rest.find('d')?;
+
+    let width = match &rest[..end] {
+        "" => 0,
+        spec if spec.starts_with('0') => spec[1..].parse().ok()?,
+        _ => return None,
+    };
+
+    Some(PathBuf::from(format!(
+        "{}{:0width$}{}",
+        &s[..start],
+        frame,
+        &rest[end + 1..],
+        width = width
+    )))
+}
+
 fn natural_geometry(
     renderer: &CairoRenderer,
     input: &Input,
@@ -764,6 +831,13 @@ fn parse_args() -> Result<Converter, Error> {
                 .value_name("object id")
                 .help("SVG id of object to export [default is to export all objects]"),
         )
+        .arg(
+            clap::Arg::with_name("view")
+                .long("view")
+                .empty_values(false)
+                .value_name("fragment")
+                .help("Render with the view named by a <view> id or an svgView(...) fragment"),
+        )
         .arg(
             clap::Arg::with_name("accept-language")
                 .short("l")
@@ -810,6 +884,37 @@ fn parse_args() -> Result<Converter, Error> {
                 .long("no-keep-image-data")
                 .help("Do not keep image data"),
         )
+        .arg(
+            clap::Arg::with_name("text_to_path")
+                .long("text-to-path")
+                .help("Render text as paths instead of glyphs, for vector output formats"),
+        )
+        .arg(
+            clap::Arg::with_name("time")
+                .long("time")
+                .takes_value(true)
+                .value_name("seconds")
+                .validator(is_valid_time)
+                .help("Render animations at this point in time [default is to ignore animations]"),
+        )
+        .arg(
+            clap::Arg::with_name("frames")
+                .long("frames")
+                .takes_value(true)
+                .value_name("number")
+                .default_value("1")
+                .validator(is_valid_frame_count)
+                .help("Number of animation frames to render, starting at --time [defaults to 1]"),
+        )
+        .arg(
+            clap::Arg::with_name("frame_rate")
+                .long("frame-rate")
+                .takes_value(true)
+                .value_name("number")
+                .default_value("10")
+                .validator(is_valid_frame_rate)
+                .help("Frames per second for --frames"),
+        )
         .arg(
             clap::Arg::with_name("FILE")
                 .help("The input file(s) to convert")
@@ -906,6 +1011,34 @@ fn parse_args() -> Result<Converter, Error> {
         ));
     }
 
+    let output = matches
+        .value_of_os("output")
+        .map(PathBuf::from)
+        .map(Output::Path)
+        .unwrap_or(Output::Stdout);
+
+    let frames = value_t!(matches, "frames", u32)?;
+
+    // Rendering several frames implies rendering animations, starting at 0 by default
+    let time = match value_t!(matches, "time", f64).or_none()? {
+        None if frames > 1 => Some(0.0),
+        time => time,
+    };
+
+    // Formats without pages get one output file per frame.  They only allow a single input
+    // file, so the frame number is enough to tell the files apart.
+    if frames > 1 && matches!(format, Format::Png | Format::Svg) {
+        match output {
+            Output::Path(ref p) if frame_path(p, 0).is_some() => (),
+            _ => {
+                return Err(error!(
+                    "Multiple frames need an output filename with a frame number placeholder, \
+                     like \"frame-%03d.png\"."
+                ))
+            }
+        }
+    }
+
     Ok(Converter {
         dpi: (
             value_t!(matches, "res_x", f64)?,
@@ -924,18 +1057,19 @@ fn parse_args() -> Result<Converter, Error> {
         export_id: value_t!(matches, "export_id", String)
             .or_none()?
             .map(lookup_id),
+        view: value_t!(matches, "view", String).or_none()?,
         keep_aspect_ratio: matches.is_present("keep_aspect"),
         background_color: background_color.or_none()?,
         stylesheet: matches.value_of_os("stylesheet").map(PathBuf::from),
         unlimited: matches.is_present("unlimited"),
         keep_image_data,
+        text_to_path: matches.is_present("text_to_path"),
+        time,
+        frames,
+        frame_rate: value_t!(matches, "frame_rate", f64)?,
         language: language?,
         input,
-        output: matches
-            .value_of_os("output")
-            .map(PathBuf::from)
-            .map(Output::Path)
-            .unwrap_or(Output::Stdout),
+        output,
     })
 }
 
@@ -955,6 +1089,30 @@ fn is_valid_zoom_factor(v: String) -> Result<(), String> {
     }
 }
 
+fn is_valid_time(v: String) -> Result<(), String> {
+    match v.parse::<f64>() {
+        Ok(time) if time.is_finite() => Ok(()),
+        Ok(_) => Err(String::from("Invalid time")),
+        Err(e) => Err(format!("{}", e)),
+    }
+}
+
+fn is_valid_frame_count(v: String) -> Result<(), String> {
+    match v.parse::<u32>() {
+        Ok(frames) if frames > 0 => Ok(()),
+        Ok(_) => Err(String::from("Invalid number of frames")),
+        Err(e) => Err(format!("{}", e)),
+    }
+}
+
+fn is_valid_frame_rate(v: String) -> Result<(), String> {
+    match v.parse::<f64>() {
+        Ok(rate) if rate > 0.0 && rate.is_finite() => Ok(()),
+        Ok(_) => Err(String::from("Invalid frame rate")),
+        Err(e) => Err(format!("{}", e)),
+    }
+}
+
 trait NotFound {
     type Ok;
     type Error;
@@ -1065,4 +1223,26 @@ mod tests {
             );
         }
     }
+
+    mod frame_path {
+        use super::super::*;
+
+        #[test]
+        fn replaces_placeholder() {
+            assert_eq!(
+                frame_path(Path::new("out-%d.png"), 7),
+                Some(PathBuf::from("out-7.png"))
+            );
+            assert_eq!(
+                frame_path(Path::new("out-%03d.png"), 7),
+                Some(PathBuf::from("out-007.png"))
+            );
+        }
+
+        #[test]
+        fn requires_placeholder() {
+            assert_eq!(frame_path(Path::new("out.png"), 7), None);
+            assert_eq!(frame_path(Path::new("out-%s.png"), 7), None);
+        }
+    }
 }
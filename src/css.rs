@@ -24,8 +24,10 @@
 //! Let's look at each rule:
 //!
 //! `@import` is an **at-rule**.  This rule has a prelude, but no block.
-//! There are other at-rules like `@media` and some of them may have a block,
-//! but librsvg doesn't support those yet.
+//! There are other at-rules like `@media` and some of them may have a block;
+//! librsvg only supports `@font-face` among those, whose block is a list of
+//! font descriptors, and `@keyframes`, whose block is a list of keyframes
+//! for CSS animations.
 //!
 //! The prelude of the following rule is `foo, .bar`.
 //! It is a **selector list** with two **selectors**, one for
@@ -74,9 +76,9 @@
 //! matches by specificity and apply the result to each element.
 
 use cssparser::{
-    self, match_ignore_ascii_case, parse_important, AtRuleParser, AtRuleType, BasicParseErrorKind,
-    CowRcStr, DeclarationListParser, DeclarationParser, Parser, ParserInput, QualifiedRuleParser,
-    RuleListParser, SourceLocation, ToCss, _cssparser_internal_to_lowercase,
+    self, _cssparser_internal_to_lowercase, match_ignore_ascii_case, parse_important, AtRuleParser,
+    AtRuleType, BasicParseErrorKind, CowRcStr, DeclarationListParser, DeclarationParser, Parser,
+    ParserInput, QualifiedRuleParser, RuleListParser, SourceLocation, ToCss, Token,
 };
 use data_url::mime::Mime;
 use markup5ever::{namespace_url, ns, LocalName, Namespace, Prefix, QualName};
@@ -88,8 +90,10 @@ use std::fmt;
 use std::str;
 
 use crate::error::*;
+use crate::font_face::{FontFace, FontFaceDescriptor};
 use crate::io::{self, BinaryData};
-use crate::node::{Node, NodeBorrow, NodeCascade};
+use crate::keyframes::{parse_keyframe_selector, CssAnimations, Keyframes};
+use crate::node::{Node, NodeAnimate, NodeBorrow, NodeCascade};
 use crate::properties::{parse_property, ComputedValues, ParsedProperty};
 use crate::url_resolver::UrlResolver;
 
@@ -145,6 +149,80 @@ impl<'i> AtRuleParser<'i> for DeclParser {
     type Error = ValueErrorKind;
 }
 
+/// Dummy struct required to use `cssparser::DeclarationListParser` for the block
+/// of an `@font-face` rule
+///
+/// It implements `cssparser::DeclarationParser`, which knows how to parse
+/// the font descriptors in the block.
+pub struct FontFaceDeclParser;
+
+impl<'i> DeclarationParser<'i> for FontFaceDeclParser {
+    type Declaration = FontFaceDescriptor;
+    type Error = ValueErrorKind;
+
+    fn parse_value<'t>(
+        &mut self,
+        name: CowRcStr<'i>,
+        input: &mut Parser<'i, 't>,
+    ) -> Result<FontFaceDescriptor, ParseError<'i>> {
+        FontFaceDescriptor::parse(&name, input)
+    }
+}
+
+impl<'i> AtRuleParser<'i> for FontFaceDeclParser {
+    type PreludeBlock = ();
+    type PreludeNoBlock = ();
+    type AtRule = FontFaceDescriptor;
+    type Error = ValueErrorKind;
+}
+
+/// Dummy struct required to use `cssparser::RuleListParser` for the block
+/// of a `@keyframes` rule
+///
+/// It implements `cssparser::QualifiedRuleParser`, which knows how to parse
+/// each keyframe: a list of offsets like `from, 50%` and a declaration list.
+pub struct KeyframeListParser;
+
+impl<'i> QualifiedRuleParser<'i> for KeyframeListParser {
+    type Prelude = Vec<f64>;
+    type QualifiedRule = (Vec<f64>, Vec<Declaration>);
+    type Error = ValueErrorKind;
+
+    fn parse_prelude<'t>(
+        &mut self,
+        input: &mut Parser<'i, 't>,
+    ) -> Result<Self::Prelude, ParseError<'i>> {
+        input.parse_comma_separated(parse_keyframe_selector)
+    }
+
+    fn parse_block<'t>(
+        &mut self,
+        prelude: Self::Prelude,
+        _location: SourceLocation,
+        input: &mut Parser<'i, 't>,
+    ) -> Result<Self::QualifiedRule, ParseError<'i>> {
+        let declarations = DeclarationListParser::new(input, DeclParser)
+            .filter_map(|r| match r {
+                Ok(decl) => Some(decl),
+                Err(e) => {
+                    rsvg_log!("Invalid declaration in keyframe; ignoring: {:?}", e);
+                    None
+                }
+            })
+            .collect();
+
+        Ok((prelude, declarations))
+    }
+}
+
+// There are no at-rules inside `@keyframes`.
+impl<'i> AtRuleParser<'i> for KeyframeListParser {
+    type PreludeBlock = ();
+    type PreludeNoBlock = ();
+    type AtRule = (Vec<f64>, Vec<Declaration>);
+    type Error = ValueErrorKind;
+}
+
 /// Dummy struct to implement cssparser::QualifiedRuleParser and
 /// cssparser::AtRuleParser
 pub struct RuleParser;
@@ -170,11 +248,15 @@ pub struct QualifiedRule {
 /// Prelude of at-rule used in the AtRuleParser.
 pub enum AtRulePrelude {
     Import(String),
+    FontFace,
+    Keyframes(String),
 }
 
 /// A CSS at-rule (or ruleset)
 pub enum AtRule {
     Import(String),
+    FontFace(Vec<FontFaceDescriptor>),
+    Keyframes(Keyframes),
 }
 
 /// A CSS rule (or ruleset)
@@ -260,9 +342,9 @@ impl<'i> QualifiedRuleParser<'i> for RuleParser {
 
 // Required by `cssparser::RuleListParser`.
 //
-// This only handles the `@import` at-rule.
+// This only handles the `@import`, `@font-face` and `@keyframes` at-rules.
 impl<'i> AtRuleParser<'i> for RuleParser {
-    type PreludeBlock = ();
+    type PreludeBlock = AtRulePrelude;
     type PreludeNoBlock = AtRulePrelude;
     type AtRule = Rule;
     type Error = ParseErrorKind<'i>;
@@ -283,6 +365,27 @@ impl<'i> AtRuleParser<'i> for RuleParser {
                 Ok(AtRuleType::WithoutBlock(AtRulePrelude::Import(url)))
             },
 
+            "font-face" => Ok(AtRuleType::WithBlock(AtRulePrelude::FontFace)),
+
+            "keyframes" => {
+                let loc = input.current_source_location();
+
+                // The name is a <custom-ident> or a string, but not "none"
+                let name = match *input.next()? {
+                    Token::Ident(ref s)
+                        if !["none", "initial", "inherit", "unset", "default"]
+                            .iter()
+                            .any(|k| s.eq_ignore_ascii_case(k)) =>
+                    {
+                        s.as_ref().to_owned()
+                    }
+                    Token::QuotedString(ref s) => s.as_ref().to_owned(),
+                    ref t => return Err(loc.new_unexpected_token_error(t.clone())),
+                };
+
+                Ok(AtRuleType::WithBlock(AtRulePrelude::Keyframes(name)))
+            },
+
             _ => Err(input.new_error(BasicParseErrorKind::AtRuleInvalid(name))),
         }
     }
@@ -292,8 +395,50 @@ impl<'i> AtRuleParser<'i> for RuleParser {
         prelude: Self::PreludeNoBlock,
         _location: SourceLocation,
     ) -> Self::AtRule {
-        let AtRulePrelude::Import(url) = prelude;
-        Rule::AtRule(AtRule::Import(url))
+        match prelude {
+            AtRulePrelude::Import(url) => Rule::AtRule(AtRule::Import(url)),
+            AtRulePrelude::FontFace => unreachable!("@font-face always has a block"),
+            AtRulePrelude::Keyframes(_) => unreachable!("@keyframes always has a block"),
+        }
+    }
+
+    fn parse_block<'t>(
+        &mut self,
+        prelude: Self::PreludeBlock,
+        _location: SourceLocation,
+        input: &mut Parser<'i, 't>,
+    ) -> Result<Self::AtRule, cssparser::ParseError<'i, Self::Error>> {
+        match prelude {
+            AtRulePrelude::FontFace => {
+                let descriptors = DeclarationListParser::new(input, FontFaceDeclParser)
+                    .filter_map(|r| match r {
+                        Ok(descriptor) => Some(descriptor),
+                        Err(e) => {
+                            rsvg_log!("Invalid @font-face descriptor; ignoring: {:?}", e);
+                            None
+                        }
+                    })
+                    .collect();
+
+                Ok(Rule::AtRule(AtRule::FontFace(descriptors)))
+            }
+
+            AtRulePrelude::Keyframes(name) => {
+                let rules = RuleListParser::new_for_nested_rule(input, KeyframeListParser)
+                    .filter_map(|r| match r {
+                        Ok(rule) => Some(rule),
+                        Err(e) => {
+                            rsvg_log!("Invalid keyframe; ignoring: {:?}", e);
+                            None
+                        }
+                    })
+                    .collect();
+
+                Ok(Rule::AtRule(AtRule::Keyframes(Keyframes::new(name, rules))))
+            }
+
+            AtRulePrelude::Import(_) => unreachable!("@import never has a block"),
+        }
     }
 }
 
@@ -592,6 +737,8 @@ pub enum Origin {
 pub struct Stylesheet {
     origin: Origin,
     qualified_rules: Vec<QualifiedRule>,
+    font_faces: Vec<FontFace>,
+    keyframes: Vec<Keyframes>,
 }
 
 /// A match during the selector matching process
@@ -637,6 +784,8 @@ impl Stylesheet {
         Stylesheet {
             origin,
             qualified_rules: Vec::new(),
+            font_faces: Vec::new(),
+            keyframes: Vec::new(),
         }
     }
 
@@ -681,6 +830,14 @@ impl Stylesheet {
                     // ignore invalid imports
                     let _ = self.load(&url, url_resolver);
                 }
+                Rule::AtRule(AtRule::FontFace(descriptors)) => {
+                    if let Some(face) = FontFace::new(descriptors, url_resolver) {
+                        self.font_faces.push(face);
+                    } else {
+                        rsvg_log!("@font-face without font-family or a usable src; ignoring");
+                    }
+                }
+                Rule::AtRule(AtRule::Keyframes(keyframes)) => self.keyframes.push(keyframes),
                 Rule::QualifiedRule(qr) => self.qualified_rules.push(qr),
             });
 
@@ -720,6 +877,16 @@ impl Stylesheet {
             .and_then(|utf8| self.parse(&utf8, &UrlResolver::new(Some((*aurl).clone()))))
     }
 
+    /// Returns the fonts declared with `@font-face` rules, including imported ones
+    pub fn font_faces(&self) -> &[FontFace] {
+        &self.font_faces
+    }
+
+    /// Returns the `@keyframes` rules, including imported ones
+    pub fn keyframes(&self) -> &[Keyframes] {
+        &self.keyframes
+    }
+
     /// Appends the style declarations that match a specified node to a given vector
     fn get_matches<'a>(
         &'a self,
@@ -758,44 +925,123 @@ fn is_text_css(mime_type: &Mime) -> bool {
 }
 
 /// Runs the CSS cascade on the specified tree from all the stylesheets
+///
+/// If a `time` in seconds is given, the CSS animations from the stylesheets'
+/// `@keyframes` rules get evaluated at that time.
 pub fn cascade(
     root: &mut Node,
     ua_stylesheets: &[Stylesheet],
     author_stylesheets: &[Stylesheet],
     user_stylesheets: &[Stylesheet],
+    time: Option<f64>,
 ) {
     for mut node in root.descendants().filter(|n| n.is_element()) {
-        let mut matches = Vec::new();
-
-        let mut match_ctx = MatchingContext::new(
-            MatchingMode::Normal,
-            // FIXME: how the fuck does one set up a bloom filter here?
-            None,
-            // n_index_cache,
-            None,
-            QuirksMode::NoQuirks,
+        apply_stylesheets(
+            &mut node,
+            ua_stylesheets,
+            author_stylesheets,
+            user_stylesheets,
         );
+    }
 
-        for s in ua_stylesheets
-            .iter()
-            .chain(author_stylesheets)
-            .chain(user_stylesheets)
-        {
-            s.get_matches(&node, &mut match_ctx, &mut matches);
-        }
+    let animations = css_animations(ua_stylesheets, author_stylesheets, user_stylesheets, time);
+
+    let values = ComputedValues::default();
+    root.cascade(&values, &animations);
+}
+
+/// Runs the CSS cascade again on the subtrees of the `roots`, after their animations
+/// changed them.
+///
+/// The `roots` get the declarations from the stylesheets matched again, since they may
+/// have been replaced by animated elements; their descendants just get their computed
+/// values updated.
+pub fn cascade_subtrees(
+    roots: &[Node],
+    ua_stylesheets: &[Stylesheet],
+    author_stylesheets: &[Stylesheet],
+    user_stylesheets: &[Stylesheet],
+    time: Option<f64>,
+) {
+    for root in roots {
+        apply_stylesheets(
+            &mut root.clone(),
+            ua_stylesheets,
+            author_stylesheets,
+            user_stylesheets,
+        );
+    }
 
-        matches.as_mut_slice().sort();
+    let animations = css_animations(ua_stylesheets, author_stylesheets, user_stylesheets, time);
 
-        for m in matches {
-            node.borrow_element_mut()
-                .apply_style_declaration(m.declaration, m.origin);
+    for root in roots {
+        // Roots inside another root get cascaded along with it.
+        if root.ancestors().skip(1).any(|a| roots.contains(&a)) {
+            continue;
         }
 
-        node.borrow_element_mut().set_style_attribute();
+        let values = root
+            .parent()
+            .filter(|p| p.is_element())
+            .map(|p| p.borrow_element().get_computed_values().clone())
+            .unwrap_or_default();
+
+        root.clone().cascade(&values, &animations);
     }
+}
 
-    let values = ComputedValues::default();
-    root.cascade(&values);
+/// Applies the declarations from the stylesheets that match an element, and the ones
+/// from its style attribute and its animations, to its specified values.
+fn apply_stylesheets(
+    node: &mut Node,
+    ua_stylesheets: &[Stylesheet],
+    author_stylesheets: &[Stylesheet],
+    user_stylesheets: &[Stylesheet],
+) {
+    let mut matches = Vec::new();
+
+    let mut match_ctx = MatchingContext::new(
+        MatchingMode::Normal,
+        // FIXME: how the fuck does one set up a bloom filter here?
+        None,
+        // n_index_cache,
+        None,
+        QuirksMode::NoQuirks,
+    );
+
+    for s in ua_stylesheets
+        .iter()
+        .chain(author_stylesheets)
+        .chain(user_stylesheets)
+    {
+        s.get_matches(node, &mut match_ctx, &mut matches);
+    }
+
+    matches.as_mut_slice().sort();
+
+    for m in matches {
+        node.borrow_element_mut()
+            .apply_style_declaration(m.declaration, m.origin);
+    }
+
+    node.borrow_element_mut().set_style_attribute();
+    node.apply_animated_properties();
+}
+
+fn css_animations<'a>(
+    ua_stylesheets: &'a [Stylesheet],
+    author_stylesheets: &'a [Stylesheet],
+    user_stylesheets: &'a [Stylesheet],
+    time: Option<f64>,
+) -> CssAnimations<'a> {
+    let keyframes = ua_stylesheets
+        .iter()
+        .chain(author_stylesheets)
+        .chain(user_stylesheets)
+        .flat_map(Stylesheet::keyframes)
+        .collect();
+
+    CssAnimations::new(keyframes, time)
 }
 
 #[cfg(test)]
@@ -880,4 +1126,21 @@ mod tests {
         assert!(d.is_empty());
         assert!(!a.is_empty());
     }
+
+    #[test]
+    fn parses_keyframes() {
+        let stylesheet = Stylesheet::from_data(
+            r#"
+@keyframes spin { from { fill: red; } 50%, to { fill: blue; } }
+@keyframes none { to { fill: blue; } }
+@keyframes "fade out" { 120% { opacity: 0; } to { opacity: 0; } }
+"#,
+            &UrlResolver::new(None),
+            Origin::Author,
+        )
+        .unwrap();
+
+        let names: Vec<&str> = stylesheet.keyframes().iter().map(Keyframes::name).collect();
+        assert_eq!(names, vec!["spin", "fade out"]);
+    }
 }
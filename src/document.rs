@@ -4,7 +4,7 @@ use data_url::mime::Mime;
 use gdk_pixbuf::{prelude::PixbufLoaderExt, PixbufLoader};
 use markup5ever::QualName;
 use once_cell::sync::Lazy;
-use std::cell::RefCell;
+use std::cell::{Cell, RefCell};
 use std::collections::hash_map::Entry;
 use std::collections::HashMap;
 use std::fmt;
@@ -12,13 +12,18 @@ use std::include_str;
 use std::rc::Rc;
 use std::str::FromStr;
 
+use crate::animation::Timeline;
 use crate::css::{self, Origin, Stylesheet};
 use crate::error::{AcquireError, AllowedUrlError, LoadingError, NodeIdError};
+use crate::font_face::{FontFace, WebFonts};
 use crate::handle::LoadOptions;
 use crate::io::{self, BinaryData};
 use crate::limits;
 use crate::node::{Node, NodeBorrow, NodeData};
+use crate::parsers::Parse;
+use crate::structure::ViewSpec;
 use crate::surface_utils::shared_surface::SharedImageSurface;
+use crate::svg_font::SvgFonts;
 use crate::url_resolver::{AllowedUrl, UrlResolver};
 use crate::xml::{xml_load_from_possibly_compressed_stream, Attributes};
 
@@ -89,6 +94,24 @@ pub struct Document {
 
     /// Stylesheets defined in the document
     stylesheets: Vec<Stylesheet>,
+
+    /// Stylesheets supplied by the caller
+    user_stylesheets: Vec<Stylesheet>,
+
+    /// Animations in the document
+    timeline: Timeline,
+
+    /// Time at which the CSS animations get evaluated, or `None` to ignore them
+    animation_time: Cell<Option<f64>>,
+
+    /// Fonts from `@font-face` rules in the stylesheets
+    web_fonts: Option<Rc<WebFonts>>,
+
+    /// The `@font-face` rules that `web_fonts` was loaded from
+    font_faces: Vec<FontFace>,
+
+    /// Fonts from `<font>` elements
+    svg_fonts: Rc<SvgFonts>,
 }
 
 impl Document {
@@ -155,12 +178,125 @@ impl Document {
         self.images.borrow_mut().lookup(&self.load_options, &aurl)
     }
 
+    /// Looks up the view selected by a fragment identifier.
+    ///
+    /// The fragment can be the `id` of a `<view>` element, or an `svgView(...)` specification.
+    pub fn lookup_view(&self, fragment: &str) -> Option<ViewSpec> {
+        if fragment.starts_with("svgView(") {
+            ViewSpec::parse_str(fragment).ok()
+        } else {
+            self.lookup_internal_node(fragment)
+                .filter(|node| is_element_of_type!(node, View))
+                .map(|node| borrow_element_as!(node, View).get_view_spec())
+        }
+    }
+
+    /// Looks up the root element of an external document and the view selected in it.
+    fn lookup_external_view(&self, url: &str, fragment: &str) -> Option<(Node, ViewSpec)> {
+        let doc = self
+            .externs
+            .borrow_mut()
+            .get_extern_document(&self.load_options, url)
+            .ok()?;
+
+        doc.lookup_view(fragment).map(|view| (doc.root(), view))
+    }
+
+    /// Resolves the view with which to render the document, from a fragment identifier.
+    ///
+    /// Returns `None` if the fragment does not select a view, in which case the document
+    /// is rendered with its root element's own `viewBox` and `preserveAspectRatio`.
+    pub fn resolve_view(&self, fragment: &str) -> Option<ViewSpec> {
+        let fragment = fragment.strip_prefix('#').unwrap_or(fragment);
+        let view = self.lookup_view(fragment);
+
+        if view.is_none() {
+            rsvg_log!("fragment \"{}\" does not select a view", fragment);
+        }
+
+        view
+    }
+
     /// Runs the CSS cascade on the document tree
     ///
     /// This uses the default UserAgent stylesheet, the document's internal stylesheets,
-    /// plus an extra set of stylesheets supplied by the caller.
-    pub fn cascade(&mut self, extra: &[Stylesheet]) {
-        css::cascade(&mut self.tree, &UA_STYLESHEETS, &self.stylesheets, extra);
+    /// plus an extra set of stylesheets supplied by the caller.  The latter are kept
+    /// so that the document can be cascaded again when its animations change it.
+    pub fn cascade(&mut self, extra: Vec<Stylesheet>) {
+        self.user_stylesheets = extra;
+
+        css::cascade(
+            &mut self.tree,
+            &UA_STYLESHEETS,
+            &self.stylesheets,
+            &self.user_stylesheets,
+            self.animation_time.get(),
+        );
+
+        let font_faces: Vec<FontFace> = self
+            .stylesheets
+            .iter()
+            .chain(self.user_stylesheets.iter())
+            .flat_map(|stylesheet| stylesheet.font_faces())
+            .cloned()
+            .collect();
+
+        // Loading the fonts means fetching them and writing them to disk, so only do it
+        // when the set of fonts changes.
+        if font_faces != self.font_faces {
+            self.web_fonts = WebFonts::load(&font_faces).map(Rc::new);
+            self.font_faces = font_faces;
+        }
+    }
+
+    /// Evaluates the document's animations at `time`, in seconds.
+    ///
+    /// This covers both SMIL animation elements and CSS animations from `@keyframes`
+    /// rules.  With `None`, the document is rendered as if it had no animations.
+    ///
+    /// The handle calls this at the start of each operation, with the time of the renderer
+    /// that asked for it, so the time is stored in the document until the next call.  When
+    /// it changes, the targets of SMIL animations get elements with the animated attributes
+    /// in place of the parsed ones, and their subtrees are cascaded again; the whole tree
+    /// only gets cascaded again if the document has CSS animations.
+    pub fn set_time(&self, time: Option<f64>) {
+        let smil_targets = self.timeline.set_time(time);
+        let css_changed = self.has_css_animations() && self.animation_time.replace(time) != time;
+
+        if css_changed {
+            css::cascade(
+                &mut self.tree.clone(),
+                &UA_STYLESHEETS,
+                &self.stylesheets,
+                &self.user_stylesheets,
+                time,
+            );
+        } else if !smil_targets.is_empty() {
+            css::cascade_subtrees(
+                smil_targets,
+                &UA_STYLESHEETS,
+                &self.stylesheets,
+                &self.user_stylesheets,
+                time,
+            );
+        }
+    }
+
+    fn has_css_animations(&self) -> bool {
+        self.stylesheets
+            .iter()
+            .chain(self.user_stylesheets.iter())
+            .any(|stylesheet| !stylesheet.keyframes().is_empty())
+    }
+
+    /// Gets the fonts from the document's `@font-face` rules, if it has any.
+    pub fn web_fonts(&self) -> Option<&Rc<WebFonts>> {
+        self.web_fonts.as_ref()
+    }
+
+    /// Gets the fonts defined with `<font>` elements in the document.
+    pub fn svg_fonts(&self) -> &Rc<SvgFonts> {
+        &self.svg_fonts
     }
 }
 
@@ -350,6 +486,7 @@ impl AcquiredNode {
 /// trying to acquire "foo" again, you will obtain a None the second time.
 pub struct AcquiredNodes<'i> {
     document: &'i Document,
+    view: Option<ViewSpec>,
     num_elements_acquired: usize,
     node_stack: Rc<RefCell<NodeStack>>,
 }
@@ -358,15 +495,49 @@ impl<'i> AcquiredNodes<'i> {
     pub fn new(document: &Document) -> AcquiredNodes<'_> {
         AcquiredNodes {
             document,
+            view: None,
             num_elements_acquired: 0,
             node_stack: Rc::new(RefCell::new(NodeStack::new())),
         }
     }
 
+    /// Renders the document's root element with `view` instead of its own `viewBox`.
+    pub fn with_view(self, view: Option<ViewSpec>) -> Self {
+        AcquiredNodes { view, ..self }
+    }
+
     pub fn lookup_image(&self, href: &str) -> Result<SharedImageSurface, LoadingError> {
         self.document.lookup_image(href)
     }
 
+    pub fn web_fonts(&self) -> Option<&Rc<WebFonts>> {
+        self.document.web_fonts()
+    }
+
+    /// Gets the view selected for the document, if `node` is its root element.
+    pub fn root_view(&self, node: &Node) -> Option<ViewSpec> {
+        if *node == self.document.root() {
+            self.view
+        } else {
+            None
+        }
+    }
+
+    /// Looks up an external document's root element and the view selected by `node_id`.
+    ///
+    /// This is for references like `sprites.svg#icon`, where `icon` is a `<view>` element,
+    /// or `sprites.svg#svgView(viewBox(0,0,24,24))`.
+    pub fn lookup_external_view(&self, node_id: &NodeId) -> Option<(Node, ViewSpec)> {
+        match node_id {
+            NodeId::Internal(_) => None,
+            NodeId::External(url, fragment) => self.document.lookup_external_view(url, fragment),
+        }
+    }
+
+    pub fn svg_fonts(&self) -> &Rc<SvgFonts> {
+        self.document.svg_fonts()
+    }
+
     /// Acquires a node.
     /// Nodes acquired by this function must be released in reverse acquiring order.
     pub fn acquire(&mut self, node_id: &NodeId) -> Result<AcquiredNode, AcquireError> {
@@ -548,6 +719,9 @@ impl DocumentBuilder {
         match tree {
             Some(root) if root.is_element() => {
                 if is_element_of_type!(root, Svg) {
+                    let svg_fonts = Rc::new(SvgFonts::new(&root));
+                    let timeline = Timeline::new(&root, &ids);
+
                     let mut document = Document {
                         tree: root,
                         ids,
@@ -555,9 +729,15 @@ impl DocumentBuilder {
                         images: RefCell::new(Images::new()),
                         load_options,
                         stylesheets,
+                        user_stylesheets: Vec::new(),
+                        timeline,
+                        animation_time: Cell::new(None),
+                        web_fonts: None,
+                        font_faces: Vec::new(),
+                        svg_fonts,
                     };
 
-                    document.cascade(&[]);
+                    document.cascade(Vec::new());
 
                     Ok(document)
                 } else {
@@ -572,6 +752,9 @@ impl DocumentBuilder {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::aspect_ratio::AspectRatio;
+    use crate::rect::Rect;
+    use crate::viewbox::ViewBox;
 
     #[test]
     fn parses_node_id() {
@@ -591,6 +774,52 @@ mod tests {
         ));
     }
 
+    #[test]
+    fn parses_node_id_with_view_fragment() {
+        assert_eq!(
+            NodeId::parse("sprites.svg#svgView(viewBox(0,0,24,24))").unwrap(),
+            NodeId::External(
+                "sprites.svg".to_string(),
+                "svgView(viewBox(0,0,24,24))".to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn looks_up_views() {
+        let document = Document::load_from_bytes(
+            br#"<?xml version="1.0" encoding="UTF-8"?>
+<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 48 24">
+  <view id="icon" viewBox="24 0 24 24" preserveAspectRatio="none"/>
+  <rect id="rect" width="10" height="10"/>
+</svg>
+"#,
+        );
+
+        assert_eq!(
+            document.lookup_view("icon"),
+            Some(ViewSpec {
+                vbox: Some(ViewBox::from(Rect::new(24.0, 0.0, 48.0, 24.0))),
+                preserve_aspect_ratio: Some(AspectRatio::parse_str("none").unwrap()),
+            })
+        );
+
+        assert_eq!(
+            document.lookup_view("svgView(viewBox(0,0,24,24))"),
+            Some(ViewSpec {
+                vbox: Some(ViewBox::from(Rect::new(0.0, 0.0, 24.0, 24.0))),
+                preserve_aspect_ratio: None,
+            })
+        );
+
+        assert_eq!(document.lookup_view("rect"), None);
+        assert_eq!(document.lookup_view("nonexistent"), None);
+        assert_eq!(document.lookup_view("svgView(foo)"), None);
+
+        assert!(document.resolve_view("#icon").is_some());
+        assert_eq!(document.resolve_view("rect"), None);
+    }
+
     #[test]
     fn unspecified_mime_type_yields_no_content_type() {
         // Issue #548
@@ -12,7 +12,9 @@ use std::f64::consts::*;
 use std::rc::{Rc, Weak};
 
 use crate::accept_language::UserLanguage;
+use crate::angle::Angle;
 use crate::aspect_ratio::AspectRatio;
+use crate::basic_shape::{ShapeBox, ShapeClip};
 use crate::bbox::BoundingBox;
 use crate::coord_units::CoordUnits;
 use crate::document::{AcquiredNodes, NodeId};
@@ -22,23 +24,30 @@ use crate::error::{AcquireError, ImplementationLimit, RenderingError};
 use crate::filter::FilterValueList;
 use crate::filters::{self, FilterSpec};
 use crate::float_eq_cairo::ApproxEqCairo;
+use crate::font_face::WebFonts;
 use crate::gradient::{GradientVariant, SpreadMethod, UserSpaceGradient};
-use crate::layout::{Image, Shape, StackingContext, Stroke, TextSpan};
+use crate::hatch::UserSpaceHatch;
+use crate::layout::{
+    Decoration, DecorationLine, Image, Shape, StackingContext, Stroke, SvgImage, TextRun, TextSpan,
+};
 use crate::length::*;
 use crate::marker;
+use crate::mesh_gradient::UserSpaceMeshGradient;
 use crate::node::{CascadedValues, Node, NodeBorrow, NodeDraw};
 use crate::paint_server::{PaintSource, UserSpacePaintSource};
 use crate::path_builder::*;
 use crate::pattern::UserSpacePattern;
 use crate::properties::{
-    ClipRule, ComputedValues, FillRule, Filter, MixBlendMode, Opacity, Overflow, PaintTarget,
-    ShapeRendering, StrokeLinecap, StrokeLinejoin, TextRendering,
+    ClipRule, ColorInterpolation, ComputedValues, FillRule, Filter, MaskMode, MaskType,
+    MixBlendMode, Opacity, Overflow, PaintTarget, ShapeRendering, StrokeLinecap, StrokeLinejoin,
+    TextDecorationStyle, TextRendering, VectorEffect,
 };
-use crate::rect::Rect;
+use crate::rect::{IRect, Rect};
 use crate::surface_utils::{
-    shared_surface::ExclusiveImageSurface, shared_surface::SharedImageSurface,
-    shared_surface::SurfaceType,
+    iterators::Pixels, shared_surface::ExclusiveImageSurface, shared_surface::SharedImageSurface,
+    shared_surface::SurfaceType, srgb, ImageSurfaceDataExt,
 };
+use crate::svg_font::{self, SvgFont, SvgFonts};
 use crate::transform::Transform;
 use crate::unit_interval::UnitInterval;
 use crate::viewbox::ViewBox;
@@ -167,20 +176,55 @@ pub struct DrawingCtx {
     cr_stack: Rc<RefCell<Vec<cairo::Context>>>,
     cr: cairo::Context,
 
+    /// Transform from the host coordinate space of `vector-effect` to the surface of `cr`.
+    ///
+    /// The host coordinate space is the one of the toplevel viewport.  Temporary surfaces,
+    /// pattern tiles, etc. get this adjusted for the way they map to the toplevel surface.
+    host_transform: Transform,
+
     user_language: UserLanguage,
 
+    /// Fonts from the document's `@font-face` rules; `None` to use the default font map.
+    web_fonts: Option<Rc<WebFonts>>,
+
+    /// Fonts from the document's `<font>` elements.
+    svg_fonts: Rc<SvgFonts>,
+
     viewport_stack: Rc<RefCell<Vec<Viewport>>>,
 
     drawsub_stack: Vec<Node>,
 
     measuring: bool,
     testing: bool,
+
+    /// Whether to draw text by filling and stroking the outlines of its glyphs.
+    text_as_paths: bool,
+
+    /// Where to collect text runs for `DrawingMode::TextRuns`.
+    text_runs: Option<Rc<RefCell<Vec<TextRun>>>>,
 }
 
 pub enum DrawingMode {
-    LimitToStack { node: Node, root: Node },
+    LimitToStack {
+        node: Node,
+        root: Node,
+    },
 
     OnlyNode(Node),
+
+    /// Like `LimitToStack`, but the text in `node` is only added to the cr's path, as for
+    /// a clipping path, instead of being painted.
+    TextOutline {
+        node: Node,
+        root: Node,
+    },
+
+    /// Like `TextOutline`, but the text in `node` is appended to `runs` instead.
+    TextRuns {
+        node: Node,
+        root: Node,
+        runs: Rc<RefCell<Vec<TextRun>>>,
+    },
 }
 
 /// The toplevel drawing routine.
@@ -194,12 +238,19 @@ pub fn draw_tree(
     dpi: Dpi,
     measuring: bool,
     testing: bool,
+    text_as_paths: bool,
     acquired_nodes: &mut AcquiredNodes<'_>,
 ) -> Result<BoundingBox, RenderingError> {
-    let (drawsub_stack, node) = match mode {
-        DrawingMode::LimitToStack { node, root } => (node.ancestors().collect(), root),
+    let (drawsub_stack, node, clipping, text_runs) = match mode {
+        DrawingMode::LimitToStack { node, root } => (node.ancestors().collect(), root, false, None),
 
-        DrawingMode::OnlyNode(node) => (Vec::new(), node),
+        DrawingMode::OnlyNode(node) => (Vec::new(), node, false, None),
+
+        DrawingMode::TextOutline { node, root } => (node.ancestors().collect(), root, true, None),
+
+        DrawingMode::TextRuns { node, root, runs } => {
+            (node.ancestors().collect(), root, true, Some(runs))
+        }
     };
 
     let cascaded = CascadedValues::new_from_node(&node);
@@ -234,13 +285,17 @@ pub fn draw_tree(
         transform,
         viewport,
         user_language.clone(),
+        acquired_nodes.web_fonts().cloned(),
+        acquired_nodes.svg_fonts().clone(),
         dpi,
         measuring,
         testing,
+        text_as_paths,
+        text_runs,
         drawsub_stack,
     );
 
-    let content_bbox = draw_ctx.draw_node_from_stack(&node, acquired_nodes, &cascaded, false)?;
+    let content_bbox = draw_ctx.draw_node_from_stack(&node, acquired_nodes, &cascaded, clipping)?;
 
     user_bbox.insert(&content_bbox);
 
@@ -274,9 +329,13 @@ impl DrawingCtx {
         transform: Transform,
         viewport: Rect,
         user_language: UserLanguage,
+        web_fonts: Option<Rc<WebFonts>>,
+        svg_fonts: Rc<SvgFonts>,
         dpi: Dpi,
         measuring: bool,
         testing: bool,
+        text_as_paths: bool,
+        text_runs: Option<Rc<RefCell<Vec<TextRun>>>>,
         drawsub_stack: Vec<Node>,
     ) -> DrawingCtx {
         let vbox = ViewBox::from(viewport);
@@ -289,11 +348,16 @@ impl DrawingCtx {
             dpi,
             cr_stack: Rc::new(RefCell::new(Vec::new())),
             cr: cr.clone(),
+            host_transform: transform.pre_translate(viewport.x0, viewport.y0),
             user_language,
+            web_fonts,
+            svg_fonts,
             viewport_stack: Rc::new(RefCell::new(viewport_stack)),
             drawsub_stack,
             measuring,
             testing,
+            text_as_paths,
+            text_runs,
         }
     }
 
@@ -303,7 +367,10 @@ impl DrawingCtx {
     /// would like to use that same state but on a different Cairo surface and context
     /// than the ones being used on `self`.  This function copies the `self` state into a
     /// new `DrawingCtx`, and ties the copied one to the supplied `cr`.
-    fn nested(&self, cr: cairo::Context) -> DrawingCtx {
+    ///
+    /// `surface_to_target` maps the pixels of the surface of `cr` to the ones of the
+    /// surface of `self`; it is used to compute the host transform of the new context.
+    fn nested(&self, cr: cairo::Context, surface_to_target: Transform) -> DrawingCtx {
         let cr_stack = self.cr_stack.clone();
 
         cr_stack.borrow_mut().push(self.cr.clone());
@@ -313,11 +380,16 @@ impl DrawingCtx {
             dpi: self.dpi,
             cr_stack,
             cr,
+            host_transform: self.host_transform_for_surface(surface_to_target),
             user_language: self.user_language.clone(),
+            web_fonts: self.web_fonts.clone(),
+            svg_fonts: self.svg_fonts.clone(),
             viewport_stack: self.viewport_stack.clone(),
             drawsub_stack: Vec::new(),
             measuring: self.measuring,
             testing: self.testing,
+            text_as_paths: self.text_as_paths,
+            text_runs: None,
         }
     }
 
@@ -325,6 +397,20 @@ impl DrawingCtx {
         &self.user_language
     }
 
+    /// Gets the `font-family` value to pass to Pango, where the names of the document's
+    /// `@font-face` fonts are replaced by the ones they are registered with.
+    pub fn pango_font_family<'a>(&self, font_family: &'a str) -> Cow<'a, str> {
+        match self.web_fonts {
+            Some(ref web_fonts) => Cow::Owned(web_fonts.font_family(font_family)),
+            None => Cow::Borrowed(font_family),
+        }
+    }
+
+    /// Gets the SVG font for a `font-family` value, if it names one.
+    pub fn svg_font(&self, font_family: &str) -> Option<&SvgFont> {
+        self.svg_fonts.lookup(font_family)
+    }
+
     pub fn toplevel_viewport(&self) -> Rect {
         *self.initial_viewport.vbox
     }
@@ -333,6 +419,60 @@ impl DrawingCtx {
         self.measuring
     }
 
+    /// Whether text gets collected into runs with `DrawingMode::TextRuns`, instead of drawn.
+    pub fn is_collecting_text_runs(&self) -> bool {
+        self.text_runs.is_some()
+    }
+
+    pub fn add_text_run(&self, run: TextRun) {
+        if let Some(ref runs) = self.text_runs {
+            runs.borrow_mut().push(run);
+        }
+    }
+
+    /// Computes the logical bounds of each character in a text layout, in the coordinate
+    /// system where the document is rendered.
+    ///
+    /// The layout is placed like in `draw_text_span`; each character is returned with its
+    /// byte offset in the layout's text.
+    pub fn text_character_bounds(
+        &self,
+        layout: &pango::Layout,
+        x: f64,
+        y: f64,
+        rotation: f64,
+        inline_scale: f64,
+    ) -> Vec<(usize, Rect)> {
+        let text = match layout.text() {
+            Some(text) => text,
+            None => return Vec::new(),
+        };
+
+        let gravity = layout.context().unwrap().gravity();
+        let transform = self.get_transform().pre_transform(&text_layout_transform(
+            x,
+            y,
+            rotation - gravity.to_rotation(),
+            inline_scale,
+        ));
+
+        let pango_scale = f64::from(pango::SCALE);
+
+        text.char_indices()
+            .map(|(index, _)| {
+                let pos = layout.index_to_pos(index as i32);
+
+                // The width is negative for right-to-left text.
+                let x0 = f64::from(pos.x.min(pos.x + pos.width)) / pango_scale;
+                let x1 = f64::from(pos.x.max(pos.x + pos.width)) / pango_scale;
+                let y0 = f64::from(pos.y) / pango_scale;
+                let y1 = f64::from(pos.y + pos.height) / pango_scale;
+
+                (index, transform.transform_rect(&Rect::new(x0, y0, x1, y1)))
+            })
+            .collect()
+    }
+
     fn get_transform(&self) -> Transform {
         Transform::from(self.cr.matrix())
     }
@@ -547,10 +687,62 @@ impl DrawingCtx {
         Ok(())
     }
 
+    /// Clips to a basic shape from the `clip-path` property.
+    ///
+    /// The shape is resolved against its reference box, which is taken from the
+    /// element's bounding box, or from the current viewport for `view-box`.
+    fn clip_to_shape(
+        &mut self,
+        clip_shape: &Option<ShapeClip>,
+        values: &ComputedValues,
+        bbox: &BoundingBox,
+    ) -> Result<(), RenderingError> {
+        let clip_shape = match *clip_shape {
+            Some(ref clip_shape) => clip_shape,
+            None => return Ok(()),
+        };
+
+        let view_params = self.get_view_params();
+
+        let reference = match clip_shape.reference_box {
+            ShapeBox::Fill => bbox.rect,
+            ShapeBox::Stroke => bbox.ink_rect.or(bbox.rect),
+            ShapeBox::View => Some(*view_params.vbox),
+        };
+
+        if let Some(reference) = reference {
+            let params = NormalizeParams::new(values, &view_params);
+            let (path, fill_rule) =
+                clip_shape
+                    .shape
+                    .to_path(reference.width(), reference.height(), &params);
+
+            let orig_transform = self.get_transform();
+            self.cr.translate(reference.x0, reference.y0);
+
+            // An empty shape, like a circle with zero radius, clips out everything.
+            self.cr.new_path();
+            if !path.is_empty() {
+                path.to_cairo(&self.cr, false)?;
+            }
+
+            self.cr.set_fill_rule(cairo::FillRule::from(fill_rule));
+            self.cr.clip();
+
+            self.cr.set_matrix(orig_transform.into());
+        }
+
+        Ok(())
+    }
+
+    /// Draws the mask for a group into a surface like the group's temporary surface, which
+    /// has `transform` as its user space and is composited with `compositing`.
     fn generate_cairo_mask(
         &mut self,
         mask_node: &Node,
+        mask_mode: MaskMode,
         transform: Transform,
+        compositing: Transform,
         bbox: &BoundingBox,
         acquired_nodes: &mut AcquiredNodes<'_>,
     ) -> Result<Option<cairo::ImageSurface>, RenderingError> {
@@ -628,7 +820,7 @@ impl DrawingCtx {
             // assume the viewport from the DrawingCtx?
             let _params = self.push_coord_units(mask.get_content_units());
 
-            let mut mask_draw_ctx = self.nested(mask_cr);
+            let mut mask_draw_ctx = self.nested(mask_cr, compositing);
 
             let stacking_ctx =
                 StackingContext::new(acquired_nodes, &mask_element, Transform::identity(), values);
@@ -647,9 +839,19 @@ impl DrawingCtx {
 
         let Opacity(opacity) = values.opacity();
 
-        let mask = SharedImageSurface::wrap(mask_content_surface, SurfaceType::SRgb)?
-            .to_mask(opacity)?
-            .into_image_surface()?;
+        let mask_type = match mask_mode {
+            MaskMode::Alpha => MaskType::Alpha,
+            MaskMode::Luminance => MaskType::Luminance,
+            MaskMode::MatchSource => values.mask_type(),
+        };
+
+        let mask_content = SharedImageSurface::wrap(mask_content_surface, SurfaceType::SRgb)?;
+
+        let mask = match mask_type {
+            MaskType::Luminance => mask_content.to_mask(opacity)?,
+            MaskType::Alpha => mask_content.to_alpha_mask(opacity)?,
+        }
+        .into_image_surface()?;
 
         Ok(Some(mask))
     }
@@ -679,6 +881,14 @@ impl DrawingCtx {
 
         self.cr.transform(stacking_ctx.transform.into());
 
+        let vector_effect = &stacking_ctx.vector_effect;
+        if vector_effect.non_scaling_size
+            || vector_effect.non_rotation
+            || vector_effect.fixed_position
+        {
+            self.apply_vector_effect(vector_effect);
+        }
+
         let res = if clipping {
             draw_fn(acquired_nodes, self)
         } else {
@@ -698,14 +908,7 @@ impl DrawingCtx {
                     &self.empty_bbox(),
                 )?;
 
-                let is_opaque = approx_eq!(f64, opacity, 1.0);
-                let needs_temporary_surface = !(is_opaque
-                    && stacking_ctx.filter == Filter::None
-                    && stacking_ctx.mask.is_none()
-                    && stacking_ctx.mix_blend_mode == MixBlendMode::Normal
-                    && stacking_ctx.clip_in_object_space.is_none());
-
-                if needs_temporary_surface {
+                if stacking_ctx.should_isolate() {
                     // Compute our assortment of affines
 
                     let affines = CompositingAffines::new(
@@ -729,7 +932,7 @@ impl DrawingCtx {
                     cr.set_matrix(affines.for_temporary_surface.into());
 
                     let (source_surface, mut res, bbox) = {
-                        let mut temporary_draw_ctx = self.nested(cr);
+                        let mut temporary_draw_ctx = self.nested(cr, affines.compositing);
 
                         // Draw!
 
@@ -813,6 +1016,20 @@ impl DrawingCtx {
                         }
                     };
 
+                    // Switch to linearRGB for compositing if requested
+
+                    let linear_rgb_target =
+                        if stacking_ctx.color_interpolation == ColorInterpolation::LinearRgb {
+                            self.linearize_target(&source_surface, affines.compositing)?
+                        } else {
+                            None
+                        };
+
+                    let source_surface = match linear_rgb_target {
+                        Some(ref target) => target.source.clone(),
+                        None => source_surface,
+                    };
+
                     // Set temporary surface as source
 
                     self.cr.set_matrix(affines.compositing.into());
@@ -822,6 +1039,7 @@ impl DrawingCtx {
 
                     self.cr.set_matrix(affines.outside_temporary_surface.into());
                     self.clip_to_node(&stacking_ctx.clip_in_object_space, acquired_nodes, &bbox)?;
+                    self.clip_to_shape(&stacking_ctx.clip_shape, values, &bbox)?;
 
                     // Mask
 
@@ -829,7 +1047,9 @@ impl DrawingCtx {
                         res = res.and_then(|bbox| {
                             self.generate_cairo_mask(
                                 mask_node,
+                                stacking_ctx.mask_mode,
                                 affines.for_temporary_surface,
+                                affines.compositing,
                                 &bbox,
                                 acquired_nodes,
                             )
@@ -856,6 +1076,10 @@ impl DrawingCtx {
                         }
                     }
 
+                    if let Some(target) = linear_rgb_target {
+                        self.unlinearize_target(target)?;
+                    }
+
                     self.cr.set_matrix(affine_at_start.into());
 
                     res
@@ -876,6 +1100,99 @@ impl DrawingCtx {
         }
     }
 
+    /// Converts the part of the target surface that a group will be composited onto to
+    /// linearRGB, for compositing in that color space, and returns a linearRGB copy of
+    /// the group's `source` surface to composite.
+    ///
+    /// The area is given by the painted pixels of `source`, which will be composited with
+    /// `compositing` as the transform.  The result must be passed to `unlinearize_target()`
+    /// after compositing.  Returns `None` if there is nothing to composite, or if the target
+    /// is not an image surface; in that case compositing happens in sRGB.
+    fn linearize_target(
+        &self,
+        source: &cairo::Surface,
+        compositing: Transform,
+    ) -> Result<Option<LinearizedTarget>, cairo::Error> {
+        let target = match cairo::ImageSurface::try_from(self.cr.target()) {
+            Ok(target) => target,
+            Err(_) => {
+                rsvg_log!("(compositing in sRGB since the target is not an image surface)");
+                return Ok(None);
+            }
+        };
+
+        let source = cairo::ImageSurface::try_from(source.clone())
+            .map_err(|_| cairo::Error::SurfaceTypeMismatch)?;
+        let source = SharedImageSurface::copy_from_surface(&source)?;
+
+        let source_extents = match painted_extents(&source) {
+            Some(extents) => extents,
+            None => return Ok(None),
+        };
+
+        let target_bounds = IRect::from_size(target.width(), target.height());
+
+        let bounds = match IRect::from(compositing.transform_rect(&Rect::from(source_extents)))
+            .intersection(&target_bounds)
+        {
+            Some(bounds) => bounds,
+            None => return Ok(None),
+        };
+
+        let original = copy_surface_area(&target, bounds)?;
+        let linear = srgb::linearize_surface(&original, surface_bounds(&original))?;
+
+        replace_surface_contents(&target, &linear, bounds)?;
+
+        let source = source.to_linear_rgb(source_extents)?.into_image_surface()?;
+        let source: &cairo::Surface = &source; // deref to Surface
+
+        Ok(Some(LinearizedTarget {
+            bounds,
+            original,
+            linear,
+            source: source.clone(),
+        }))
+    }
+
+    /// Converts the area of the target from `linearize_target()` back to sRGB.
+    ///
+    /// Only the pixels that compositing changed are converted; the others get their
+    /// original values back, so that converting to linearRGB and back does not lose
+    /// precision in the parts of the area that the group did not paint.
+    fn unlinearize_target(&self, linearized: LinearizedTarget) -> Result<(), cairo::Error> {
+        let target = cairo::ImageSurface::try_from(self.cr.target())
+            .map_err(|_| cairo::Error::SurfaceTypeMismatch)?;
+
+        let LinearizedTarget {
+            bounds,
+            original,
+            linear,
+            ..
+        } = linearized;
+
+        let composited = copy_surface_area(&target, bounds)?;
+
+        let mut result =
+            ExclusiveImageSurface::new(composited.width(), composited.height(), SurfaceType::SRgb)?;
+        srgb::map_unpremultiplied_components_loop(
+            &composited,
+            &mut result,
+            surface_bounds(&composited),
+            srgb::unlinearize,
+        );
+
+        result.modify(&mut |data, stride| {
+            for (x, y, pixel) in Pixels::new(&composited) {
+                if pixel == linear.get_pixel(x, y) {
+                    data.set_pixel(stride, original.get_pixel(x, y), x, y);
+                }
+            }
+        });
+
+        replace_surface_contents(&target, &result.share()?, bounds)
+    }
+
     fn initial_transform_with_offset(&self) -> Transform {
         let rect = self.toplevel_viewport();
 
@@ -884,6 +1201,61 @@ impl DrawingCtx {
             .pre_translate(rect.x0, rect.y0)
     }
 
+    /// Host transform for a surface whose pixels map to the ones of the current surface
+    /// with `surface_to_target`.
+    fn host_transform_for_surface(&self, surface_to_target: Transform) -> Transform {
+        match surface_to_target.invert() {
+            Some(target_to_surface) => self.host_transform.post_transform(&target_to_surface),
+            None => self.host_transform,
+        }
+    }
+
+    /// Changes the current transform per the `vector-effect` values that constrain the
+    /// mapping from the user space to the host coordinate space.
+    fn apply_vector_effect(&self, vector_effect: &VectorEffect) {
+        let host = self.host_transform;
+
+        if let Some(host_inverse) = host.invert() {
+            let to_host = self.get_transform().post_transform(&host_inverse);
+            let constrained = constrain_for_vector_effect(&to_host, vector_effect);
+
+            self.cr.set_matrix(constrained.post_transform(&host).into());
+        }
+    }
+
+    /// Transform in which to compute the stroke; for `non-scaling-stroke` this is the
+    /// host coordinate space instead of the user space.
+    fn stroke_transform(&self, cr: &cairo::Context, stroke: &Stroke) -> Transform {
+        if stroke.non_scaling {
+            self.host_transform
+        } else {
+            Transform::from(cr.matrix())
+        }
+    }
+
+    /// Width in user space of the stroke as it gets painted, for markers with
+    /// `markerUnits="strokeWidth"`.
+    pub fn stroke_width_in_user_space(&self, stroke: &Stroke) -> f64 {
+        if !stroke.non_scaling {
+            return stroke.width;
+        }
+
+        let to_host = match self.host_transform.invert() {
+            Some(host_inverse) => self.get_transform().post_transform(&host_inverse),
+            None => return stroke.width,
+        };
+
+        let scale = (to_host.xx * to_host.yy - to_host.xy * to_host.yx)
+            .abs()
+            .sqrt();
+
+        if scale > 0.0 {
+            stroke.width / scale
+        } else {
+            stroke.width
+        }
+    }
+
     /// Run the drawing function with the specified opacity
     fn with_alpha(
         &mut self,
@@ -987,19 +1359,117 @@ impl DrawingCtx {
         g.set_matrix(gradient.transform.into());
         g.set_extend(cairo::Extend::from(gradient.spread));
 
+        let is_linear_rgb = gradient.color_interpolation == ColorInterpolation::LinearRgb;
+
         for stop in &gradient.stops {
             let UnitInterval(stop_offset) = stop.offset;
 
+            // For linearRGB, Cairo interpolates between the linearized colors and the
+            // result gets converted back to sRGB in set_source_in_linear_rgb().
+            let rgba = if is_linear_rgb {
+                linearize_rgba(stop.rgba)
+            } else {
+                stop.rgba
+            };
+
             g.add_color_stop_rgba(
                 stop_offset,
-                f64::from(stop.rgba.red_f32()),
-                f64::from(stop.rgba.green_f32()),
-                f64::from(stop.rgba.blue_f32()),
-                f64::from(stop.rgba.alpha_f32()),
+                f64::from(rgba.red_f32()),
+                f64::from(rgba.green_f32()),
+                f64::from(rgba.blue_f32()),
+                f64::from(rgba.alpha_f32()),
             );
         }
 
-        self.cr.set_source(&g)
+        if is_linear_rgb {
+            self.set_source_in_linear_rgb(&g)
+        } else {
+            self.cr.set_source(&g)
+        }
+    }
+
+    /// Sets a pattern with linearRGB colors as the source.
+    ///
+    /// The pattern gets painted to an intermediate surface that covers the current clip
+    /// area, which is then converted to sRGB and used as the source instead.
+    fn set_source_in_linear_rgb(&mut self, pattern: &cairo::Pattern) -> Result<(), cairo::Error> {
+        let transform = self.get_transform();
+
+        let (x0, y0, x1, y1) = self.cr.clip_extents()?;
+        let bounds = IRect::from(transform.transform_rect(&Rect::new(x0, y0, x1, y1)));
+
+        if bounds.is_empty() {
+            return self.cr.set_source(pattern);
+        }
+
+        let mut surface =
+            ExclusiveImageSurface::new(bounds.width(), bounds.height(), SurfaceType::LinearRgb)?;
+
+        surface.draw(&mut |cr| {
+            let offset = transform.post_translate(-f64::from(bounds.x0), -f64::from(bounds.y0));
+            cr.set_matrix(offset.into());
+            cr.set_source(pattern)?;
+            cr.paint()
+        })?;
+
+        let surface = surface
+            .share()?
+            .to_srgb(IRect::from_size(bounds.width(), bounds.height()))?;
+
+        self.cr.set_matrix(Transform::identity().into());
+        let res =
+            surface.set_as_source_surface(&self.cr, f64::from(bounds.x0), f64::from(bounds.y0));
+        self.cr.set_matrix(transform.into());
+
+        res
+    }
+
+    fn set_mesh_gradient(&mut self, gradient: &UserSpaceMeshGradient) -> Result<(), cairo::Error> {
+        let mesh = cairo::Mesh::new();
+
+        for patch in &gradient.patches {
+            let p = &patch.points;
+
+            mesh.begin_patch();
+            mesh.move_to(p[0][0].0, p[0][0].1);
+            mesh.curve_to(
+                p[0][1].0, p[0][1].1, p[0][2].0, p[0][2].1, p[0][3].0, p[0][3].1,
+            );
+            mesh.curve_to(
+                p[1][3].0, p[1][3].1, p[2][3].0, p[2][3].1, p[3][3].0, p[3][3].1,
+            );
+            mesh.curve_to(
+                p[3][2].0, p[3][2].1, p[3][1].0, p[3][1].1, p[3][0].0, p[3][0].1,
+            );
+            mesh.curve_to(
+                p[2][0].0, p[2][0].1, p[1][0].0, p[1][0].1, p[0][0].0, p[0][0].1,
+            );
+
+            let inner = [p[1][1], p[1][2], p[2][2], p[2][1]];
+            let corners = [
+                cairo::MeshCorner::MeshCorner0,
+                cairo::MeshCorner::MeshCorner1,
+                cairo::MeshCorner::MeshCorner2,
+                cairo::MeshCorner::MeshCorner3,
+            ];
+
+            for ((corner, (x, y)), rgba) in corners.iter().zip(&inner).zip(&patch.colors) {
+                mesh.set_control_point(*corner, *x, *y);
+                mesh.set_corner_color_rgba(
+                    *corner,
+                    f64::from(rgba.red_f32()),
+                    f64::from(rgba.green_f32()),
+                    f64::from(rgba.blue_f32()),
+                    f64::from(rgba.alpha_f32()),
+                );
+            }
+
+            mesh.end_patch();
+        }
+
+        mesh.set_matrix(gradient.transform.into());
+
+        self.cr.set_source(&mesh)
     }
 
     fn set_pattern(
@@ -1052,7 +1522,8 @@ impl DrawingCtx {
         // Draw everything
 
         {
-            let mut pattern_draw_ctx = self.nested(cr_pattern);
+            let mut pattern_draw_ctx =
+                self.nested(cr_pattern, affine.post_transform(&self.get_transform()));
 
             pattern_draw_ctx
                 .with_alpha(pattern.opacity, &mut |dc| {
@@ -1101,6 +1572,139 @@ impl DrawingCtx {
         Ok(true)
     }
 
+    fn set_hatch(
+        &mut self,
+        hatch: &UserSpaceHatch,
+        acquired_nodes: &mut AcquiredNodes<'_>,
+    ) -> Result<bool, RenderingError> {
+        let bounds = hatch.bounds;
+
+        if approx_eq!(f64, hatch.pitch, 0.0) || approx_eq!(f64, bounds.height(), 0.0) {
+            return Ok(false);
+        }
+
+        // The tile is one pitch wide, and it covers the painted area in the other direction.
+        let taffine = self.get_transform().pre_transform(&hatch.coord_transform);
+
+        let mut scwscale = (taffine.xx.powi(2) + taffine.xy.powi(2)).sqrt();
+        let mut schscale = (taffine.yx.powi(2) + taffine.yy.powi(2)).sqrt();
+
+        let pw: i32 = (hatch.pitch * scwscale) as i32;
+        let ph: i32 = (bounds.height() * schscale) as i32;
+
+        if pw < 1 || ph < 1 {
+            return Ok(false);
+        }
+
+        scwscale = f64::from(pw) / hatch.pitch;
+        schscale = f64::from(ph) / bounds.height();
+
+        let affine = hatch
+            .coord_transform
+            .pre_translate(0.0, bounds.y0)
+            .pre_scale(1.0 / scwscale, 1.0 / schscale);
+
+        // The hatch paths are specified in the content units, so find out what part of
+        // them covers the tile.
+        let content_bounds = match hatch.content_transform.invert() {
+            Some(t) => t.transform_rect(&bounds),
+            None => return Ok(false),
+        };
+
+        let surface = self
+            .cr
+            .target()
+            .create_similar(cairo::Content::ColorAlpha, pw, ph)?;
+
+        let cr_hatch = cairo::Context::new(&surface)?;
+
+        {
+            let mut hatch_draw_ctx =
+                self.nested(cr_hatch, affine.post_transform(&self.get_transform()));
+
+            hatch_draw_ctx.with_alpha(hatch.opacity, &mut |dc| {
+                let cr = dc.cr.clone();
+
+                for child in hatch.node_with_children.children() {
+                    if !child.is_element() {
+                        continue;
+                    }
+
+                    let elt = child.borrow_element();
+
+                    let hatch_path = match *elt {
+                        Element::HatchPath(ref p) if !elt.is_in_error() => p,
+                        _ => continue,
+                    };
+
+                    let cascaded = CascadedValues::new_from_node(&child);
+                    let values = cascaded.get();
+
+                    if !values.is_visible() {
+                        continue;
+                    }
+
+                    let view_params = dc.get_view_params();
+                    let params = NormalizeParams::new(values, &view_params);
+
+                    let path =
+                        match hatch_path.to_path(&params, content_bounds.y0, content_bounds.y1) {
+                            Some(path) if !path.is_empty() => path,
+                            _ => continue,
+                        };
+
+                    let stroke = Stroke::new(values, &params);
+
+                    let stroke_paint = values.stroke().0.resolve(
+                        acquired_nodes,
+                        values.stroke_opacity().0,
+                        values.color().0,
+                        None,
+                        None,
+                    );
+
+                    // Draw the neighboring copies as well, for strokes that cross the
+                    // sides of the tile.
+                    for i in -1..=1 {
+                        let transform = hatch
+                            .content_transform
+                            .post_translate(f64::from(i) * hatch.pitch, -bounds.y0)
+                            .post_scale(scwscale, schscale);
+
+                        cr.set_matrix(transform.into());
+                        setup_cr_for_stroke(&cr, &stroke);
+                        path.to_cairo(&cr, stroke.line_cap == StrokeLinecap::Square)?;
+
+                        let bbox = compute_stroke_and_fill_box(
+                            &cr,
+                            &stroke,
+                            &stroke_paint,
+                            dc.stroke_transform(&cr, &stroke),
+                        )?;
+                        let stroke_paint = stroke_paint.to_user_space(&bbox, &view_params, values);
+
+                        dc.stroke(&cr, acquired_nodes, &stroke_paint, &stroke)?;
+                        cr.new_path();
+                    }
+                }
+
+                Ok(dc.empty_bbox())
+            })?;
+        }
+
+        // Set the final surface as a Cairo pattern into the Cairo context
+        let pattern = cairo::SurfacePattern::create(&surface);
+
+        if let Some(m) = affine.invert() {
+            pattern.set_matrix(m.into())
+        }
+        pattern.set_extend(cairo::Extend::Repeat);
+        pattern.set_filter(cairo::Filter::Best);
+        self.cr.set_source(&pattern)?;
+
+        Ok(true)
+    }
+
     fn set_color(&self, rgba: cssparser::RGBA) {
         self.cr.clone().set_source_rgba(
             f64::from(rgba.red_f32()),
@@ -1120,6 +1724,20 @@ impl DrawingCtx {
                 self.set_gradient(gradient)?;
                 Ok(true)
             }
+            UserSpacePaintSource::Hatch(ref hatch, c) => {
+                if self.set_hatch(hatch, acquired_nodes)? {
+                    Ok(true)
+                } else if let Some(c) = c {
+                    self.set_color(c);
+                    Ok(true)
+                } else {
+                    Ok(false)
+                }
+            }
+            UserSpacePaintSource::MeshGradient(ref gradient, _c) => {
+                self.set_mesh_gradient(gradient)?;
+                Ok(true)
+            }
             UserSpacePaintSource::Pattern(ref pattern, c) => {
                 if self.set_pattern(pattern, acquired_nodes)? {
                     Ok(true)
@@ -1149,7 +1767,7 @@ impl DrawingCtx {
         let mut surface = ExclusiveImageSurface::new(width, height, SurfaceType::SRgb)?;
 
         surface.draw::<RenderingError>(&mut |cr| {
-            let mut temporary_draw_ctx = self.nested(cr);
+            let mut temporary_draw_ctx = self.nested(cr, Transform::identity());
 
             // FIXME: we are ignoring any error
 
@@ -1170,10 +1788,16 @@ impl DrawingCtx {
         cr: &cairo::Context,
         acquired_nodes: &mut AcquiredNodes<'_>,
         paint_source: &UserSpacePaintSource,
+        stroke: &Stroke,
     ) -> Result<(), RenderingError> {
         let had_paint_server = self.set_paint_source(paint_source, acquired_nodes)?;
         if had_paint_server {
+            // The path and the paint source are already set, so this only changes the
+            // space in which the line width and the dashes apply.
+            let matrix = cr.matrix();
+            cr.set_matrix(self.stroke_transform(cr, stroke).into());
             cr.stroke_preserve()?;
+            cr.set_matrix(matrix);
         }
 
         Ok(())
@@ -1233,7 +1857,12 @@ impl DrawingCtx {
                 cr.set_fill_rule(cairo::FillRule::from(shape.fill_rule));
 
                 path_helper.set()?;
-                let bbox = compute_stroke_and_fill_box(&cr, &shape.stroke, &shape.stroke_paint)?;
+                let bbox = compute_stroke_and_fill_box(
+                    &cr,
+                    &shape.stroke,
+                    &shape.stroke_paint,
+                    dc.stroke_transform(&cr, &shape.stroke),
+                )?;
 
                 let stroke_paint = shape.stroke_paint.to_user_space(&bbox, view_params, values);
                 let fill_paint = shape.fill_paint.to_user_space(&bbox, view_params, values);
@@ -1250,7 +1879,7 @@ impl DrawingCtx {
 
                             PaintTarget::Stroke => {
                                 path_helper.set()?;
-                                dc.stroke(&cr, an, &stroke_paint)?;
+                                dc.stroke(&cr, an, &stroke_paint, &shape.stroke)?;
                             }
 
                             PaintTarget::Markers => {
@@ -1347,6 +1976,124 @@ impl DrawingCtx {
         }
     }
 
+    pub fn draw_svg_image(
+        &mut self,
+        image: &SvgImage,
+        stacking_ctx: &StackingContext,
+        acquired_nodes: &mut AcquiredNodes<'_>,
+        values: &ComputedValues,
+        clipping: bool,
+    ) -> Result<BoundingBox, RenderingError> {
+        if clipping || image.rect.is_empty() {
+            return Ok(self.empty_bbox());
+        }
+
+        // The referenced document may in turn reference the one being rendered, so
+        // acquire its root to catch circular references.
+        let _root_acquired = match acquired_nodes.acquire_ref(&image.root) {
+            Ok(n) => n,
+
+            Err(AcquireError::CircularReference(node)) => {
+                rsvg_log!("circular reference in element {}", node);
+                return Ok(self.empty_bbox());
+            }
+
+            _ => unreachable!(),
+        };
+
+        let vbox = borrow_element_as!(image.root, Svg)
+            .resolve_view(Some(image.view))
+            .0;
+
+        // The root's own preserveAspectRatio is ignored in favor of the <image>'s, but a
+        // view can still override it.
+        let aspect = image.view.preserve_aspect_ratio.unwrap_or(image.aspect);
+
+        // Unlike a raster image, the document's contents can extend beyond its viewBox.
+        let clip_mode =
+            if !(image.overflow == Overflow::Auto || image.overflow == Overflow::Visible) {
+                Some(ClipMode::ClipToViewport)
+            } else {
+                None
+            };
+
+        let bounds = self.empty_bbox().with_rect(image.rect);
+
+        if image.is_visible {
+            self.with_discrete_layer(
+                stacking_ctx,
+                acquired_nodes,
+                values,
+                clipping,
+                None,
+                &mut |an, dc| {
+                    with_saved_cr(&dc.cr.clone(), || {
+                        if let Some(_params) =
+                            dc.push_new_viewport(vbox, image.rect, aspect, clip_mode)
+                        {
+                            image.root.draw_children(
+                                an,
+                                &CascadedValues::new_from_node(&image.root),
+                                dc,
+                                clipping,
+                            )?;
+                        }
+
+                        Ok(bounds)
+                    })
+                },
+            )
+        } else {
+            Ok(bounds)
+        }
+    }
+
+    /// Fills the decorations of a text span with their colors.
+    fn draw_text_decorations(
+        &self,
+        span: &TextSpan,
+        decorations: &[&Decoration],
+        rotation: Option<f64>,
+        scale: Option<f64>,
+    ) -> Result<BoundingBox, RenderingError> {
+        let mut bbox = self.empty_bbox();
+
+        if decorations.is_empty() {
+            return Ok(bbox);
+        }
+
+        let transform = self.get_transform();
+
+        let matrix = self.cr.matrix();
+        self.cr.translate(span.x, span.y);
+        if let Some(rot) = rotation {
+            self.cr.rotate(rot);
+        }
+        if let Some(scale) = scale {
+            self.cr.scale(scale, 1.0);
+        }
+
+        for decoration in decorations {
+            self.cr.new_path();
+            decoration_path(&self.cr, &span.layout, decoration);
+
+            let (x0, y0, x1, y1) = self.cr.fill_extents()?;
+            let r = Rect::new(x0, y0, x1, y1);
+            bbox.insert(
+                &BoundingBox::new()
+                    .with_transform(transform)
+                    .with_ink_rect(r),
+            );
+
+            self.set_color(decoration.color);
+            self.cr.fill()?;
+        }
+
+        self.cr.set_matrix(matrix);
+
+        Ok(bbox)
+    }
+
     pub fn draw_text_span(
         &mut self,
         view_params: &ViewParams,
@@ -1359,14 +2106,25 @@ impl DrawingCtx {
 
         let paint_order = values.paint_order();
 
+        // Vertical text is laid out like horizontal text, and rotated per the gravity.
         let gravity = span.layout.context().unwrap().gravity();
+        let total_rotation = span.rotation - gravity.to_rotation();
+
+        let bbox = compute_text_box(
+            &span.layout,
+            span.x,
+            span.y,
+            total_rotation,
+            span.inline_scale,
+            transform,
+        );
 
-        let bbox = compute_text_box(&span.layout, span.x, span.y, transform, gravity);
-        if bbox.is_none() {
+        // A span with just spaces has no ink, but it may still have decorations.
+        if bbox.is_none() && span.decorations.is_empty() {
             return Ok(self.empty_bbox());
         }
 
-        let mut bbox = bbox.unwrap();
+        let mut bbox = bbox.unwrap_or_else(|| self.empty_bbox());
 
         with_saved_cr(&self.cr.clone(), || {
             self.cr
@@ -1374,9 +2132,14 @@ impl DrawingCtx {
 
             setup_cr_for_stroke(&self.cr, &span.stroke);
 
-            let rotation_from_gravity = gravity.to_rotation();
-            let rotation = if !rotation_from_gravity.approx_eq_cairo(0.0) {
-                Some(-rotation_from_gravity)
+            let rotation = if !total_rotation.approx_eq_cairo(0.0) {
+                Some(total_rotation)
+            } else {
+                None
+            };
+
+            let scale = if !span.inline_scale.approx_eq_cairo(1.0) {
+                Some(span.inline_scale)
             } else {
                 None
             };
@@ -1388,61 +2151,129 @@ impl DrawingCtx {
                 if let Some(rot) = rotation {
                     self.cr.rotate(rot);
                 }
+                if let Some(scale) = scale {
+                    self.cr.scale(scale, 1.0);
+                }
 
                 pangocairo::functions::update_layout(&self.cr, &span.layout);
                 pangocairo::functions::layout_path(&self.cr, &span.layout);
 
                 self.cr.set_matrix(matrix);
 
+                if !span.decorations.is_empty() {
+                    self.cr.translate(span.x, span.y);
+                    if let Some(rot) = rotation {
+                        self.cr.rotate(rot);
+                    }
+                    if let Some(scale) = scale {
+                        self.cr.scale(scale, 1.0);
+                    }
+
+                    for decoration in &span.decorations {
+                        decoration_path(&self.cr, &span.layout, decoration);
+                    }
+
+                    self.cr.set_matrix(matrix);
+                }
+
                 return Ok(self.empty_bbox());
             }
 
             if span.is_visible {
+                // Underlines and overlines go below the text, and line-throughs above it.
+                let (over, under): (Vec<&Decoration>, Vec<&Decoration>) = span
+                    .decorations
+                    .iter()
+                    .partition(|d| d.line == DecorationLine::LineThrough);
+
+                let decorations_bbox = self.draw_text_decorations(span, &under, rotation, scale)?;
+                bbox.insert(&decorations_bbox);
+
                 for &target in &paint_order.targets {
                     match target {
                         PaintTarget::Fill => {
+                            // The paint source is in user space, not in the rotated one.
+                            let fill_paint =
+                                span.fill_paint.to_user_space(&bbox, view_params, values);
+                            let had_paint_server =
+                                self.set_paint_source(&fill_paint, acquired_nodes)?;
+
                             self.cr.move_to(span.x, span.y);
 
                             let matrix = self.cr.matrix();
                             if let Some(rot) = rotation {
                                 self.cr.rotate(rot);
                             }
+                            if let Some(scale) = scale {
+                                self.cr.scale(scale, 1.0);
+                            }
 
-                            let fill_paint =
-                                span.fill_paint.to_user_space(&bbox, view_params, values);
-                            let had_paint_server =
-                                self.set_paint_source(&fill_paint, acquired_nodes)?;
                             if had_paint_server {
                                 pangocairo::functions::update_layout(&self.cr, &span.layout);
-                                pangocairo::functions::show_layout(&self.cr, &span.layout);
+
+                                if self.text_as_paths {
+                                    pangocairo::functions::layout_path(&self.cr, &span.layout);
+                                    self.cr.set_fill_rule(cairo::FillRule::Winding);
+                                    self.cr.fill()?;
+                                } else {
+                                    pangocairo::functions::show_layout(&self.cr, &span.layout);
+                                }
                             }
 
                             self.cr.set_matrix(matrix);
                         }
 
                         PaintTarget::Stroke => {
+                            if let PaintSource::None = span.stroke_paint {
+                                continue;
+                            }
+
                             self.cr.move_to(span.x, span.y);
 
                             let matrix = self.cr.matrix();
                             if let Some(rot) = rotation {
                                 self.cr.rotate(rot);
                             }
+                            if let Some(scale) = scale {
+                                self.cr.scale(scale, 1.0);
+                            }
+
+                            pangocairo::functions::update_layout(&self.cr, &span.layout);
+                            pangocairo::functions::layout_path(&self.cr, &span.layout);
+
+                            let stroke_transform = if span.stroke.non_scaling {
+                                self.cr.set_matrix(self.host_transform.into());
+                                self.host_transform
+                            } else {
+                                transform
+                            };
+                            let stroke_matrix = self.cr.matrix();
+
+                            let (x0, y0, x1, y1) = self.cr.stroke_extents()?;
+                            let r = Rect::new(x0, y0, x1, y1);
+                            let ib = BoundingBox::new()
+                                .with_transform(stroke_transform)
+                                .with_ink_rect(r);
+
+                            // The paint source is in user space, not in the rotated one.
+                            // Its bounding box includes the stroke, which hatches must cover.
+                            self.cr.set_matrix(matrix);
+
+                            let mut paint_bbox = bbox;
+                            paint_bbox.insert(&ib);
 
                             let stroke_paint =
-                                span.stroke_paint.to_user_space(&bbox, view_params, values);
+                                span.stroke_paint
+                                    .to_user_space(&paint_bbox, view_params, values);
                             let had_paint_server =
                                 self.set_paint_source(&stroke_paint, acquired_nodes)?;
-                            if had_paint_server {
-                                pangocairo::functions::update_layout(&self.cr, &span.layout);
-                                pangocairo::functions::layout_path(&self.cr, &span.layout);
 
-                                let (x0, y0, x1, y1) = self.cr.stroke_extents()?;
-                                let r = Rect::new(x0, y0, x1, y1);
-                                let ib = BoundingBox::new()
-                                    .with_transform(transform)
-                                    .with_ink_rect(r);
+                            if had_paint_server {
+                                self.cr.set_matrix(stroke_matrix);
                                 bbox.insert(&ib);
                                 self.cr.stroke()?;
+                            } else {
+                                self.cr.new_path();
                             }
 
                             self.cr.set_matrix(matrix);
@@ -1451,6 +2282,9 @@ impl DrawingCtx {
                         PaintTarget::Markers => {}
                     }
                 }
+
+                let decorations_bbox = self.draw_text_decorations(span, &over, rotation, scale)?;
+                bbox.insert(&decorations_bbox);
             }
 
             Ok(bbox)
@@ -1515,12 +2349,14 @@ impl DrawingCtx {
 
         let save_initial_viewport = self.initial_viewport;
         let save_cr = self.cr.clone();
+        let save_host_transform = self.host_transform;
 
         {
             let cr = cairo::Context::new(&surface)?;
             cr.set_matrix(affine.into());
 
             self.cr = cr;
+            self.host_transform = affine;
             self.initial_viewport = Viewport {
                 transform: affine,
                 vbox: ViewBox::from(Rect::from_size(f64::from(width), f64::from(height))),
@@ -1530,6 +2366,7 @@ impl DrawingCtx {
         }
 
         self.cr = save_cr;
+        self.host_transform = save_host_transform;
         self.initial_viewport = save_initial_viewport;
 
         Ok(SharedImageSurface::wrap(surface, SurfaceType::SRgb)?)
@@ -1592,7 +2429,16 @@ impl DrawingCtx {
             _ => unreachable!(),
         };
 
-        let acquired = match acquired_nodes.acquire(link) {
+        // A reference to a view of another document, like "sprites.svg#icon", gets that
+        // document's root element.
+        let external_view = acquired_nodes.lookup_external_view(link);
+
+        let acquired = match external_view {
+            Some((ref root, _)) => acquired_nodes.acquire_ref(root),
+            None => acquired_nodes.acquire(link),
+        };
+
+        let acquired = match acquired {
             Ok(acquired) => acquired,
 
             Err(AcquireError::CircularReference(node)) => {
@@ -1632,7 +2478,48 @@ impl DrawingCtx {
 
         let use_element = node.borrow_element();
 
-        let res = if is_element_of_type!(child, Symbol) {
+        let res = if let Some((_, view)) = external_view {
+            // if the <use> references a view, the root <svg> gets rendered into the <use>'s
+            // rectangle with the view's viewBox and preserveAspectRatio
+
+            let (vbox, preserve_aspect_ratio) =
+                borrow_element_as!(child, Svg).resolve_view(Some(view));
+
+            // The root is not the outermost <svg> anymore, so it gets the UA stylesheet's
+            // overflow: hidden unless it specifies otherwise.
+            let clip_mode = if child.borrow_element().get_specified_values().is_overflow() {
+                None
+            } else {
+                Some(ClipMode::ClipToViewport)
+            };
+
+            let stacking_ctx =
+                StackingContext::new(acquired_nodes, &use_element, Transform::identity(), values);
+
+            self.with_discrete_layer(
+                &stacking_ctx,
+                acquired_nodes,
+                values,
+                clipping,
+                None,
+                &mut |an, dc| {
+                    let _params =
+                        dc.push_new_viewport(vbox, use_rect, preserve_aspect_ratio, clip_mode);
+
+                    child.draw_children(
+                        an,
+                        &CascadedValues::new_from_values(
+                            child,
+                            values,
+                            Some(fill_paint.clone()),
+                            Some(stroke_paint.clone()),
+                        ),
+                        dc,
+                        clipping,
+                    )
+                },
+            )
+        } else if is_element_of_type!(child, Symbol) {
             // if the <use> references a <symbol>, it gets handled specially
 
             let elt = child.borrow_element();
@@ -1802,10 +2689,51 @@ impl CompositingAffines {
     }
 }
 
+/// Constrains a transform from user space to the host coordinate space per the values of
+/// `vector-effect` other than `non-scaling-stroke`.
+///
+/// `non-scaling-size` removes the scale, `non-rotation` removes rotation and skew while
+/// keeping the scale, and `fixed-position` puts the origin of the user space at the
+/// origin of the host coordinate space.
+fn constrain_for_vector_effect(transform: &Transform, vector_effect: &VectorEffect) -> Transform {
+    let Transform {
+        mut xx,
+        mut yx,
+        mut xy,
+        mut yy,
+        mut x0,
+        mut y0,
+    } = *transform;
+
+    let scale = (xx * yy - xy * yx).abs().sqrt();
+
+    if vector_effect.non_rotation {
+        xx = scale;
+        yx = 0.0;
+        xy = 0.0;
+        yy = scale;
+    }
+
+    if vector_effect.non_scaling_size && scale > 0.0 {
+        xx /= scale;
+        yx /= scale;
+        xy /= scale;
+        yy /= scale;
+    }
+
+    if vector_effect.fixed_position {
+        x0 = 0.0;
+        y0 = 0.0;
+    }
+
+    Transform::new_unchecked(xx, yx, xy, yy, x0, y0)
+}
+
 fn compute_stroke_and_fill_box(
     cr: &cairo::Context,
     stroke: &Stroke,
     stroke_paint_source: &PaintSource,
+    stroke_transform: Transform,
 ) -> Result<BoundingBox, RenderingError> {
     let affine = Transform::from(cr.matrix());
 
@@ -1843,9 +2771,12 @@ fn compute_stroke_and_fill_box(
     // bounding box if so.
 
     if !stroke.width.approx_eq_cairo(0.0) && !matches!(stroke_paint_source, PaintSource::None) {
+        cr.set_matrix(stroke_transform.into());
         let (x0, y0, x1, y1) = cr.stroke_extents()?;
+        cr.set_matrix(affine.into());
+
         let sb = BoundingBox::new()
-            .with_transform(affine)
+            .with_transform(stroke_transform)
             .with_ink_rect(Rect::new(x0, y0, x1, y1));
         bbox.insert(&sb);
     }
@@ -1865,43 +2796,154 @@ fn compute_stroke_and_fill_box(
     Ok(bbox)
 }
 
+/// Adds the shape of a text decoration to the current path.
+///
+/// The current transformation must have its origin at the top-left corner of the
+/// span's layout.  Unless the decoration specifies them, the position and thickness
+/// of the line come from the font's metrics.
+fn decoration_path(cr: &cairo::Context, layout: &pango::Layout, decoration: &Decoration) {
+    let metrics = match layout
+        .context()
+        .and_then(|c| c.metrics(layout.font_description().as_ref(), None))
+    {
+        Some(metrics) => metrics,
+        None => return,
+    };
+
+    let pango_scale = f64::from(pango::SCALE);
+    let width = f64::from(layout.size().0) / pango_scale;
+    let baseline = f64::from(layout.baseline()) / pango_scale;
+
+    // Pango gives the positions of the tops of the lines, measured upwards.
+    let (font_position, font_thickness) = match decoration.line {
+        DecorationLine::LineThrough => (
+            metrics.strikethrough_position(),
+            metrics.strikethrough_thickness(),
+        ),
+        DecorationLine::Underline => (metrics.underline_position(), metrics.underline_thickness()),
+        DecorationLine::Overline => (metrics.ascent(), metrics.underline_thickness()),
+    };
+
+    let font_top = baseline - f64::from(font_position) / pango_scale;
+    let font_thickness = f64::from(font_thickness) / pango_scale;
+    let thickness = decoration.thickness.unwrap_or(font_thickness);
+
+    if width <= 0.0 || thickness <= 0.0 {
+        return;
+    }
+
+    let top = match decoration.line {
+        DecorationLine::Underline => decoration
+            .underline_offset
+            .map_or(font_top, |offset| baseline + offset),
+
+        // Keep a thicker or thinner line-through centered on the font's one.
+        DecorationLine::LineThrough => font_top + (font_thickness - thickness) / 2.0,
+
+        DecorationLine::Overline => font_top,
+    };
+
+    // Dots, dashes and waves are sized proportionally to the thickness; a line with
+    // too many of them just gets drawn as a solid one.
+    let style = match decoration.style {
+        TextDecorationStyle::Dotted | TextDecorationStyle::Dashed | TextDecorationStyle::Wavy
+            if width / thickness > 100_000.0 =>
+        {
+            TextDecorationStyle::Solid
+        }
+        style => style,
+    };
+
+    match style {
+        TextDecorationStyle::Solid => cr.rectangle(0.0, top, width, thickness),
+
+        TextDecorationStyle::Double => {
+            // The second line goes away from the text, with a gap as thick as the lines.
+            let (first, second) = match decoration.line {
+                DecorationLine::Underline => (top, top + 2.0 * thickness),
+                DecorationLine::Overline => (top, top - 2.0 * thickness),
+                DecorationLine::LineThrough => (top - thickness, top + thickness),
+            };
+
+            cr.rectangle(0.0, first, width, thickness);
+            cr.rectangle(0.0, second, width, thickness);
+        }
+
+        TextDecorationStyle::Dotted => {
+            let radius = thickness / 2.0;
+            let mut x = radius;
+
+            while x < width {
+                cr.new_sub_path();
+                cr.arc(x, top + radius, radius, 0.0, 2.0 * PI);
+                x += 2.0 * thickness;
+            }
+        }
+
+        TextDecorationStyle::Dashed => {
+            let dash = 3.0 * thickness;
+            let mut x = 0.0;
+
+            while x < width {
+                cr.rectangle(x, top, dash.min(width - x), thickness);
+                x += 2.0 * dash;
+            }
+        }
+
+        TextDecorationStyle::Wavy => {
+            let amplitude = thickness;
+            let wavelength = 6.0 * thickness;
+            let center = top + thickness / 2.0;
+
+            let steps = (16.0 * width / wavelength).ceil() as usize;
+            let points: Vec<(f64, f64)> = (0..=steps)
+                .map(|i| {
+                    let x = width * i as f64 / steps as f64;
+                    (x, center + amplitude * (2.0 * PI * x / wavelength).sin())
+                })
+                .collect();
+
+            // An outline of the wave, thickness units tall.
+            cr.new_sub_path();
+            for &(x, y) in &points {
+                cr.line_to(x, y - thickness / 2.0);
+            }
+            for &(x, y) in points.iter().rev() {
+                cr.line_to(x, y + thickness / 2.0);
+            }
+            cr.close_path();
+        }
+    }
+}
+
 fn compute_text_box(
     layout: &pango::Layout,
     x: f64,
     y: f64,
+    rotation: f64,
+    inline_scale: f64,
     transform: Transform,
-    gravity: pango::Gravity,
 ) -> Option<BoundingBox> {
-    #![allow(clippy::many_single_char_names)]
-
     let (ink, _) = layout.extents();
     if ink.width == 0 || ink.height == 0 {
         return None;
     }
 
-    let ink_x = f64::from(ink.x);
-    let ink_y = f64::from(ink.y);
-    let ink_width = f64::from(ink.width);
-    let ink_height = f64::from(ink.height);
-    let pango_scale = f64::from(pango::SCALE);
+    // For vertical text, the rotation includes the one for the layout's gravity.
+    let layout_to_user = text_layout_transform(x, y, rotation, inline_scale);
 
-    let (x, y, w, h) = if gravity_is_vertical(gravity) {
-        (
-            x + (ink_x - ink_height) / pango_scale,
-            y + ink_y / pango_scale,
-            ink_height / pango_scale,
-            ink_width / pango_scale,
-        )
-    } else {
-        (
-            x + ink_x / pango_scale,
-            y + ink_y / pango_scale,
-            ink_width / pango_scale,
-            ink_height / pango_scale,
-        )
-    };
-
-    let r = Rect::new(x, y, x + w, y + h);
+    let pango_scale = f64::from(pango::SCALE);
+    let ink_x = f64::from(ink.x) / pango_scale;
+    let ink_y = f64::from(ink.y) / pango_scale;
+    let ink_width = f64::from(ink.width) / pango_scale;
+    let ink_height = f64::from(ink.height) / pango_scale;
+
+    let r = layout_to_user.transform_rect(&Rect::new(
+        ink_x,
+        ink_y,
+        ink_x + ink_width,
+        ink_y + ink_height,
+    ));
     let bbox = BoundingBox::new()
         .with_transform(transform)
         .with_rect(r)
@@ -1910,6 +2952,14 @@ fn compute_text_box(
     Some(bbox)
 }
 
+/// Transform from the coordinate system of a text layout to user space, for a layout drawn
+/// with its origin at `(x, y)`, rotated by `rotation` radians and scaled along its lines.
+fn text_layout_transform(x: f64, y: f64, rotation: f64, inline_scale: f64) -> Transform {
+    Transform::new_translate(x, y)
+        .pre_rotate(Angle::new(rotation))
+        .pre_scale(inline_scale, 1.0)
+}
+
 fn setup_cr_for_stroke(cr: &cairo::Context, stroke: &Stroke) {
     cr.set_line_width(stroke.width);
     cr.set_miter_limit(stroke.miter_limit.0);
@@ -1925,11 +2975,6 @@ fn setup_cr_for_stroke(cr: &cairo::Context, stroke: &Stroke) {
     }
 }
 
-// FIXME: should the pango crate provide this like PANGO_GRAVITY_IS_VERTICAL() ?
-fn gravity_is_vertical(gravity: pango::Gravity) -> bool {
-    matches!(gravity, pango::Gravity::East | pango::Gravity::West)
-}
-
 /// escape quotes and backslashes with backslash
 fn escape_link_target(value: &str) -> Cow<'_, str> {
     static REGEX: Lazy<Regex> = Lazy::new(|| Regex::new(r"['\\]").unwrap());
@@ -1943,6 +2988,79 @@ fn escape_link_target(value: &str) -> Cow<'_, str> {
     })
 }
 
+/// Converts the color of a gradient stop to linearRGB; the alpha stays the same.
+fn linearize_rgba(rgba: RGBA) -> RGBA {
+    RGBA {
+        red: srgb::linearize(rgba.red),
+        green: srgb::linearize(rgba.green),
+        blue: srgb::linearize(rgba.blue),
+        alpha: rgba.alpha,
+    }
+}
+
+/// Area of a target surface that a group gets composited onto in linearRGB, see
+/// `DrawingCtx::linearize_target()`.
+struct LinearizedTarget {
+    /// The area, in the target's pixels.
+    bounds: IRect,
+
+    /// Original contents of the area.
+    original: SharedImageSurface,
+
+    /// Contents of the area converted to linearRGB, as they are before compositing.
+    linear: SharedImageSurface,
+
+    /// The group's surface converted to linearRGB.
+    source: cairo::Surface,
+}
+
+/// Returns the smallest rectangle that contains all the non-transparent pixels of `surface`.
+fn painted_extents(surface: &SharedImageSurface) -> Option<IRect> {
+    Pixels::new(surface)
+        .filter(|(_, _, pixel)| pixel.a != 0)
+        .map(|(x, y, _)| IRect::new(x as i32, y as i32, x as i32 + 1, y as i32 + 1))
+        .reduce(|a, b| a.union(&b))
+}
+
+fn surface_bounds(surface: &SharedImageSurface) -> IRect {
+    IRect::from_size(surface.width(), surface.height())
+}
+
+/// Returns a copy of the pixels of `surface` within `bounds`.
+fn copy_surface_area(
+    surface: &cairo::ImageSurface,
+    bounds: IRect,
+) -> Result<SharedImageSurface, cairo::Error> {
+    let copy = cairo::ImageSurface::create(cairo::Format::ARgb32, bounds.width(), bounds.height())?;
+
+    {
+        let cr = cairo::Context::new(&copy)?;
+        cr.set_source_surface(surface, -f64::from(bounds.x0), -f64::from(bounds.y0))?;
+        cr.paint()?;
+    }
+
+    SharedImageSurface::wrap(copy, SurfaceType::SRgb)
+}
+
+/// Overwrites the pixels of `target` within `bounds` with those of `contents`, which
+/// has the size of `bounds`.
+fn replace_surface_contents(
+    target: &cairo::ImageSurface,
+    contents: &SharedImageSurface,
+    bounds: IRect,
+) -> Result<(), cairo::Error> {
+    let cr = cairo::Context::new(target)?;
+    cr.set_operator(cairo::Operator::Source);
+    contents.set_as_source_surface(&cr, f64::from(bounds.x0), f64::from(bounds.y0))?;
+    cr.rectangle(
+        f64::from(bounds.x0),
+        f64::from(bounds.y0),
+        f64::from(bounds.width()),
+        f64::from(bounds.height()),
+    );
+    cr.fill()
+}
+
 fn clip_to_rectangle(cr: &cairo::Context, r: &Rect) {
     cr.rectangle(r.x0, r.y0, r.width(), r.height());
     cr.clip();
@@ -2055,11 +3173,17 @@ impl From<&DrawingCtx> for pango::Context {
 
         cr.set_font_options(&options);
 
-        let font_map = pangocairo::FontMap::default().unwrap();
+        let font_map = draw_ctx
+            .web_fonts
+            .as_ref()
+            .map(|web_fonts| web_fonts.font_map().clone())
+            .unwrap_or_else(|| pangocairo::FontMap::default().unwrap());
         let context = font_map.create_context().unwrap();
 
         context.set_round_glyph_positions(false);
 
+        svg_font::set_shape_renderer(&context);
+
         pangocairo::functions::update_context(&cr, &context);
 
         // Pango says this about pango_cairo_context_set_resolution():
@@ -7,6 +7,7 @@ use std::fmt;
 use std::ops::Deref;
 
 use crate::accept_language::UserLanguage;
+use crate::animation::{Animate, AnimateMotion, AnimateTransform, AnimationElement, MPath, Set};
 use crate::bbox::BoundingBox;
 use crate::cond::{RequiredExtensions, RequiredFeatures, SystemLanguage};
 use crate::css::{Declaration, Origin};
@@ -33,15 +34,18 @@ use crate::filters::{
     FilterEffect,
 };
 use crate::gradient::{LinearGradient, RadialGradient, Stop};
+use crate::hatch::{Hatch, HatchPath};
 use crate::image::Image;
 use crate::marker::Marker;
+use crate::mesh_gradient::{MeshGradient, MeshPatch, MeshRow};
 use crate::node::*;
 use crate::pattern::Pattern;
 use crate::properties::{ComputedValues, SpecifiedValues};
 use crate::shapes::{Circle, Ellipse, Line, Path, Polygon, Polyline, Rect};
-use crate::structure::{ClipPath, Group, Link, Mask, NonRendering, Svg, Switch, Symbol, Use};
+use crate::structure::{ClipPath, Group, Link, Mask, NonRendering, Svg, Switch, Symbol, Use, View};
 use crate::style::Style;
-use crate::text::{TRef, TSpan, Text};
+use crate::svg_font::{Font, FontFace, Glyph, HKern, MissingGlyph};
+use crate::text::{TRef, TSpan, Text, TextPath};
 use crate::xml::Attributes;
 
 // After creating/parsing a Element, it will be in a success or an error state.
@@ -249,6 +253,17 @@ impl<T: SetAttributes + Draw> ElementInner<T> {
         }
     }
 
+    /// Applies the values of properties from animations, which override the ones from
+    /// the style attribute.
+    fn apply_animated_properties(&mut self, declarations: &str) {
+        // Invalid values just get ignored, so this cannot fail.
+        let _ = self.specified_values.parse_style_declarations(
+            declarations,
+            Origin::Author,
+            &mut self.important_styles,
+        );
+    }
+
     fn set_error(&mut self, error: ElementError) {
         rsvg_log!("setting node {} in error: {}", self, error);
         self.result = Err(error);
@@ -307,17 +322,31 @@ impl<T: SetAttributes + Draw> Deref for ElementInner<T> {
 /// the size of a pointer.
 
 pub enum Element {
+    Animate(Box<ElementInner<Animate>>),
+    AnimateMotion(Box<ElementInner<AnimateMotion>>),
+    AnimateTransform(Box<ElementInner<AnimateTransform>>),
     Circle(Box<ElementInner<Circle>>),
     ClipPath(Box<ElementInner<ClipPath>>),
     Ellipse(Box<ElementInner<Ellipse>>),
     Filter(Box<ElementInner<Filter>>),
+    Font(Box<ElementInner<Font>>),
+    FontFace(Box<ElementInner<FontFace>>),
+    Glyph(Box<ElementInner<Glyph>>),
     Group(Box<ElementInner<Group>>),
+    Hatch(Box<ElementInner<Hatch>>),
+    HatchPath(Box<ElementInner<HatchPath>>),
+    HKern(Box<ElementInner<HKern>>),
     Image(Box<ElementInner<Image>>),
     Line(Box<ElementInner<Line>>),
     LinearGradient(Box<ElementInner<LinearGradient>>),
     Link(Box<ElementInner<Link>>),
     Marker(Box<ElementInner<Marker>>),
     Mask(Box<ElementInner<Mask>>),
+    MeshGradient(Box<ElementInner<MeshGradient>>),
+    MeshPatch(Box<ElementInner<MeshPatch>>),
+    MeshRow(Box<ElementInner<MeshRow>>),
+    MissingGlyph(Box<ElementInner<MissingGlyph>>),
+    MPath(Box<ElementInner<MPath>>),
     NonRendering(Box<ElementInner<NonRendering>>),
     Path(Box<ElementInner<Path>>),
     Pattern(Box<ElementInner<Pattern>>),
@@ -325,15 +354,18 @@ pub enum Element {
     Polyline(Box<ElementInner<Polyline>>),
     RadialGradient(Box<ElementInner<RadialGradient>>),
     Rect(Box<ElementInner<Rect>>),
+    Set(Box<ElementInner<Set>>),
     Stop(Box<ElementInner<Stop>>),
     Style(Box<ElementInner<Style>>),
     Svg(Box<ElementInner<Svg>>),
     Switch(Box<ElementInner<Switch>>),
     Symbol(Box<ElementInner<Symbol>>),
     Text(Box<ElementInner<Text>>),
+    TextPath(Box<ElementInner<TextPath>>),
     TRef(Box<ElementInner<TRef>>),
     TSpan(Box<ElementInner<TSpan>>),
     Use(Box<ElementInner<Use>>),
+    View(Box<ElementInner<View>>),
 
     // Filter primitives, these start with "Fe" as element names are e.g. "feBlend"
     FeBlend(Box<ElementInner<FeBlend>>),
@@ -366,17 +398,31 @@ macro_rules! call_inner {
     // end recursion, call the method
     ($element:ident, $method:ident [$($args:expr),*]) => {
         match $element {
+            Element::Animate(i) => i.$method($($args),*),
+            Element::AnimateMotion(i) => i.$method($($args),*),
+            Element::AnimateTransform(i) => i.$method($($args),*),
             Element::Circle(i) => i.$method($($args),*),
             Element::ClipPath(i) => i.$method($($args),*),
             Element::Ellipse(i) => i.$method($($args),*),
             Element::Filter(i) => i.$method($($args),*),
+            Element::Font(i) => i.$method($($args),*),
+            Element::FontFace(i) => i.$method($($args),*),
+            Element::Glyph(i) => i.$method($($args),*),
             Element::Group(i) => i.$method($($args),*),
+            Element::Hatch(i) => i.$method($($args),*),
+            Element::HatchPath(i) => i.$method($($args),*),
+            Element::HKern(i) => i.$method($($args),*),
             Element::Image(i) => i.$method($($args),*),
             Element::Line(i) => i.$method($($args),*),
             Element::LinearGradient(i) => i.$method($($args),*),
             Element::Link(i) => i.$method($($args),*),
             Element::Marker(i) => i.$method($($args),*),
             Element::Mask(i) => i.$method($($args),*),
+            Element::MeshGradient(i) => i.$method($($args),*),
+            Element::MeshPatch(i) => i.$method($($args),*),
+            Element::MeshRow(i) => i.$method($($args),*),
+            Element::MissingGlyph(i) => i.$method($($args),*),
+            Element::MPath(i) => i.$method($($args),*),
             Element::NonRendering(i) => i.$method($($args),*),
             Element::Path(i) => i.$method($($args),*),
             Element::Pattern(i) => i.$method($($args),*),
@@ -384,15 +430,18 @@ macro_rules! call_inner {
             Element::Polyline(i) => i.$method($($args),*),
             Element::RadialGradient(i) => i.$method($($args),*),
             Element::Rect(i) => i.$method($($args),*),
+            Element::Set(i) => i.$method($($args),*),
             Element::Stop(i) => i.$method($($args),*),
             Element::Style(i) => i.$method($($args),*),
             Element::Svg(i) => i.$method($($args),*),
             Element::Switch(i) => i.$method($($args),*),
             Element::Symbol(i) => i.$method($($args),*),
             Element::Text(i) => i.$method($($args),*),
+            Element::TextPath(i) => i.$method($($args),*),
             Element::TRef(i) => i.$method($($args),*),
             Element::TSpan(i) => i.$method($($args),*),
             Element::Use(i) => i.$method($($args),*),
+            Element::View(i) => i.$method($($args),*),
             Element::FeBlend(i) => i.$method($($args),*),
             Element::FeColorMatrix(i) => i.$method($($args),*),
             Element::FeComponentTransfer(i) => i.$method($($args),*),
@@ -521,10 +570,24 @@ impl Element {
         call_inner!(self, set_style_attribute);
     }
 
+    pub fn apply_animated_properties(&mut self, declarations: &str) {
+        call_inner!(self, apply_animated_properties, declarations);
+    }
+
     pub fn is_in_error(&self) -> bool {
         call_inner!(self, is_in_error)
     }
 
+    pub fn as_animation(&self) -> Option<&dyn AnimationElement> {
+        match self {
+            Element::Animate(ref a) => Some(&a.element_impl),
+            Element::AnimateMotion(ref a) => Some(&a.element_impl),
+            Element::AnimateTransform(ref a) => Some(&a.element_impl),
+            Element::Set(ref a) => Some(&a.element_impl),
+            _ => None,
+        }
+    }
+
     pub fn as_filter_effect(&self) -> Option<&dyn FilterEffect> {
         match self {
             Element::FeBlend(ref fe) => Some(&fe.element_impl),
@@ -555,9 +618,11 @@ impl Element {
             self,
             Element::ClipPath(_)
                 | Element::Filter(_)
+                | Element::Hatch(_)
                 | Element::LinearGradient(_)
                 | Element::Marker(_)
                 | Element::Mask(_)
+                | Element::MeshGradient(_)
                 | Element::Pattern(_)
                 | Element::RadialGradient(_)
         )
@@ -621,6 +686,9 @@ macro_rules! e {
 mod creators {
     use super::*;
 
+    e!(create_animate,                  Animate);
+    e!(create_animate_motion,           AnimateMotion);
+    e!(create_animate_transform,        AnimateTransform);
     e!(create_circle,                   Circle);
     e!(create_clip_path,                ClipPath);
     e!(create_defs,                     NonRendering);
@@ -650,13 +718,24 @@ mod creators {
     e!(create_fe_tile,                  FeTile);
     e!(create_fe_turbulence,            FeTurbulence);
     e!(create_filter,                   Filter);
+    e!(create_font,                     Font);
+    e!(create_font_face,                FontFace);
+    e!(create_glyph,                    Glyph);
     e!(create_group,                    Group);
+    e!(create_hatch,                    Hatch);
+    e!(create_hatch_path,               HatchPath);
+    e!(create_hkern,                    HKern);
     e!(create_image,                    Image);
     e!(create_line,                     Line);
     e!(create_linear_gradient,          LinearGradient);
     e!(create_link,                     Link);
     e!(create_marker,                   Marker);
     e!(create_mask,                     Mask);
+    e!(create_mesh_gradient,            MeshGradient);
+    e!(create_mesh_patch,               MeshPatch);
+    e!(create_mesh_row,                 MeshRow);
+    e!(create_missing_glyph,            MissingGlyph);
+    e!(create_mpath,                    MPath);
     e!(create_non_rendering,            NonRendering);
     e!(create_path,                     Path);
     e!(create_pattern,                  Pattern);
@@ -664,15 +743,18 @@ mod creators {
     e!(create_polyline,                 Polyline);
     e!(create_radial_gradient,          RadialGradient);
     e!(create_rect,                     Rect);
+    e!(create_set,                      Set);
     e!(create_stop,                     Stop);
     e!(create_style,                    Style);
     e!(create_svg,                      Svg);
     e!(create_switch,                   Switch);
     e!(create_symbol,                   Symbol);
     e!(create_text,                     Text);
+    e!(create_text_path,                TextPath);
     e!(create_tref,                     TRef);
     e!(create_tspan,                    TSpan);
     e!(create_use,                      Use);
+    e!(create_view,                     View);
 
     /* Hack to make multiImage sort-of work
      *
@@ -714,10 +796,10 @@ static ELEMENT_CREATORS: Lazy<HashMap<&'static str, (ElementCreateFn, ElementCre
         /* ("altGlyph",         ), */
         /* ("altGlyphDef",      ), */
         /* ("altGlyphItem",     ), */
-        /* ("animate",          ), */
+        ("animate",             create_animate,               Default),
         /* ("animateColor",     ), */
-        /* ("animateMotion",    ), */
-        /* ("animateTransform", ), */
+        ("animateMotion",       create_animate_motion,        Default),
+        ("animateTransform",    create_animate_transform,     Default),
         ("circle",              create_circle,                Default),
         ("clipPath",            create_clip_path,             Default),
         /* ("color-profile",    ), */
@@ -750,25 +832,30 @@ static ELEMENT_CREATORS: Lazy<HashMap<&'static str, (ElementCreateFn, ElementCre
         ("feTile",              create_fe_tile,               Default),
         ("feTurbulence",        create_fe_turbulence,         Default),
         ("filter",              create_filter,                Default),
-        /* ("font",             ), */
-        /* ("font-face",        ), */
+        ("font",                create_font,                  Default),
+        ("font-face",           create_font_face,             Default),
         /* ("font-face-format", ), */
         /* ("font-face-name",   ), */
         /* ("font-face-src",    ), */
         /* ("font-face-uri",    ), */
         /* ("foreignObject",    ), */
         ("g",                   create_group,                 Default),
-        /* ("glyph",            ), */
+        ("glyph",               create_glyph,                 Default),
         /* ("glyphRef",         ), */
-        /* ("hkern",            ), */
+        ("hatch",               create_hatch,                 Default),
+        ("hatchpath",           create_hatch_path,            Default),
+        ("hkern",               create_hkern,                 Default),
         ("image",               create_image,                 Default),
         ("line",                create_line,                  Default),
         ("linearGradient",      create_linear_gradient,       Default),
         ("marker",              create_marker,                Default),
         ("mask",                create_mask,                  Default),
+        ("meshgradient",        create_mesh_gradient,         Default),
+        ("meshpatch",           create_mesh_patch,            Default),
+        ("meshrow",             create_mesh_row,              Default),
         /* ("metadata",         ), */
-        /* ("missing-glyph",    ), */
-        /* ("mpath",            ), */
+        ("missing-glyph",       create_missing_glyph,         Default),
+        ("mpath",               create_mpath,                 Default),
         /* ("multiImage",       ), */
         ("path",                create_path,                  Default),
         ("pattern",             create_pattern,               Default),
@@ -777,7 +864,7 @@ static ELEMENT_CREATORS: Lazy<HashMap<&'static str, (ElementCreateFn, ElementCre
         ("radialGradient",      create_radial_gradient,       Default),
         ("rect",                create_rect,                  Default),
         /* ("script",           ), */
-        /* ("set",              ), */
+        ("set",                 create_set,                   Default),
         ("stop",                create_stop,                  Default),
         ("style",               create_style,                 IgnoreClass),
         /* ("subImage",         ), */
@@ -786,12 +873,12 @@ static ELEMENT_CREATORS: Lazy<HashMap<&'static str, (ElementCreateFn, ElementCre
         ("switch",              create_switch,                Default),
         ("symbol",              create_symbol,                Default),
         ("text",                create_text,                  Default),
-        /* ("textPath",         ), */
+        ("textPath",            create_text_path,             Default),
         /* ("title",            ), */
         ("tref",                create_tref,                  Default),
         ("tspan",               create_tspan,                 Default),
         ("use",                 create_use,                   Default),
-        /* ("view",             ), */
+        ("view",                create_view,                  Default),
         /* ("vkern",            ), */
     ];
 
@@ -820,6 +907,9 @@ mod sizes {
         print_size!(ComputedValues);
         print_size!(ParsedProperty);
 
+        print_size!(Animate);
+        print_size!(AnimateMotion);
+        print_size!(AnimateTransform);
         print_size!(Circle);
         print_size!(ClipPath);
         print_size!(NonRendering);
@@ -849,13 +939,24 @@ mod sizes {
         print_size!(FeTile);
         print_size!(FeTurbulence);
         print_size!(Filter);
+        print_size!(Font);
+        print_size!(FontFace);
+        print_size!(Glyph);
         print_size!(Group);
+        print_size!(Hatch);
+        print_size!(HatchPath);
+        print_size!(HKern);
         print_size!(Image);
         print_size!(Line);
         print_size!(LinearGradient);
         print_size!(Link);
         print_size!(Marker);
         print_size!(Mask);
+        print_size!(MeshGradient);
+        print_size!(MeshPatch);
+        print_size!(MeshRow);
+        print_size!(MissingGlyph);
+        print_size!(MPath);
         print_size!(NonRendering);
         print_size!(Path);
         print_size!(Pattern);
@@ -863,14 +964,17 @@ mod sizes {
         print_size!(Polyline);
         print_size!(RadialGradient);
         print_size!(Rect);
+        print_size!(Set);
         print_size!(Stop);
         print_size!(Style);
         print_size!(Svg);
         print_size!(Switch);
         print_size!(Symbol);
         print_size!(Text);
+        print_size!(TextPath);
         print_size!(TRef);
         print_size!(TSpan);
         print_size!(Use);
+        print_size!(View);
     }
 }
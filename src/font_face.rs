@@ -0,0 +1,641 @@
+//! Web fonts declared with CSS `@font-face` rules.
+//!
+//! The `@font-face` rules from the document's stylesheets get turned into [`FontFace`]
+//! values while parsing CSS.  Later, [`WebFonts::load`] fetches the font data and
+//! registers it in a Pango font map that is separate from the application's one.  That
+//! font map is shared by all the documents in a thread, so that the system fonts only
+//! get loaded once; each document's fonts get registered under private family names, so
+//! that they are not visible to other documents.
+
+use cssparser::Parser;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::error::*;
+use crate::io::{self, BinaryData};
+use crate::parsers::Parse;
+use crate::properties::{FontFamily, FontStyle, FontWeight};
+use crate::url_resolver::{AllowedUrl, UrlResolver};
+
+/// One of the values in the `src` descriptor of an `@font-face` rule.
+///
+/// https://www.w3.org/TR/css-fonts-3/#src-desc
+#[derive(Debug, Clone, PartialEq)]
+pub enum FontFaceSource {
+    /// `url("foo.woff") format("woff")`
+    ///
+    /// The format hint is not stored, since FreeType detects the format from the font
+    /// data by itself.
+    Url(String),
+
+    /// `local("Font Name")`
+    Local(String),
+}
+
+impl Parse for FontFaceSource {
+    fn parse<'i>(parser: &mut Parser<'i, '_>) -> Result<FontFaceSource, ParseError<'i>> {
+        if let Ok(url) = parser.try_parse(|p| p.expect_url().map(|s| s.to_string())) {
+            let _: Result<_, ParseError<'_>> = parser.try_parse(|p| {
+                p.expect_function_matching("format")?;
+                p.parse_nested_block(|p| {
+                    p.parse_comma_separated(|p| Ok(p.expect_string()?.to_string()))
+                })
+            });
+
+            return Ok(FontFaceSource::Url(url));
+        }
+
+        parser.expect_function_matching("local")?;
+        let FontFamily(name) = parser.parse_nested_block(FontFamily::parse)?;
+
+        Ok(FontFaceSource::Local(name))
+    }
+}
+
+/// A descriptor inside an `@font-face` rule.
+///
+/// Other descriptors like `unicode-range` are ignored.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FontFaceDescriptor {
+    FontFamily(String),
+    Src(Vec<FontFaceSource>),
+    FontStyle(FontStyle),
+    FontWeight(FontWeight),
+}
+
+impl FontFaceDescriptor {
+    /// Parses the value of the descriptor called `name`.
+    pub fn parse<'i>(
+        name: &str,
+        parser: &mut Parser<'i, '_>,
+    ) -> Result<FontFaceDescriptor, ParseError<'i>> {
+        let loc = parser.current_source_location();
+
+        match name {
+            "font-family" => {
+                let FontFamily(family) = FontFamily::parse(parser)?;
+
+                if family.contains(',') {
+                    Err(loc.new_custom_error(ValueErrorKind::value_error(
+                        "@font-face must specify a single font family",
+                    )))
+                } else {
+                    Ok(FontFaceDescriptor::FontFamily(family))
+                }
+            }
+
+            "src" => Ok(FontFaceDescriptor::Src(
+                parser.parse_comma_separated(FontFaceSource::parse)?,
+            )),
+
+            "font-style" => Ok(FontFaceDescriptor::FontStyle(FontStyle::parse(parser)?)),
+
+            "font-weight" => Ok(FontFaceDescriptor::FontWeight(FontWeight::parse(parser)?)),
+
+            _ => Err(loc.new_custom_error(ValueErrorKind::UnknownProperty)),
+        }
+    }
+}
+
+/// A font declared with an `@font-face` rule.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FontFace {
+    family: String,
+    sources: Vec<AllowedUrl>,
+    style: Option<FontStyle>,
+    weight: Option<FontWeight>,
+}
+
+impl FontFace {
+    /// Creates a `FontFace` from the descriptors in an `@font-face` rule.
+    ///
+    /// The `url_resolver` must be the one for the stylesheet that contains the rule, so
+    /// that relative URLs get resolved with respect to it.  Sources that are not allowed
+    /// by the resolver's security policy are dropped.
+    ///
+    /// Returns `None` if the rule does not have the required `font-family` and `src`
+    /// descriptors.
+    pub fn new(
+        descriptors: Vec<FontFaceDescriptor>,
+        url_resolver: &UrlResolver,
+    ) -> Option<FontFace> {
+        let mut family = None;
+        let mut sources = Vec::new();
+        let mut style = None;
+        let mut weight = None;
+
+        for descriptor in descriptors {
+            match descriptor {
+                FontFaceDescriptor::FontFamily(f) => family = Some(f),
+                FontFaceDescriptor::Src(s) => sources = s,
+                FontFaceDescriptor::FontStyle(s) => style = Some(s),
+                FontFaceDescriptor::FontWeight(w) => weight = Some(w),
+            }
+        }
+
+        let family = family?;
+
+        let sources: Vec<AllowedUrl> = sources
+            .into_iter()
+            .filter_map(|source| match source {
+                FontFaceSource::Url(href) => match url_resolver.resolve_href(&href) {
+                    Ok(aurl) => Some(aurl),
+                    Err(e) => {
+                        rsvg_log!("not loading font \"{}\" for @font-face: {}", href, e);
+                        None
+                    }
+                },
+
+                // FIXME: we should alias the family to the installed font
+                FontFaceSource::Local(name) => {
+                    rsvg_log!("local(\"{}\") is not supported in @font-face", name);
+                    None
+                }
+            })
+            .collect();
+
+        if sources.is_empty() {
+            None
+        } else {
+            Some(FontFace {
+                family,
+                sources,
+                style,
+                weight,
+            })
+        }
+    }
+}
+
+/// The fonts from a document's `@font-face` rules.
+///
+/// Fontconfig can only load fonts from files, so the font data gets written to a
+/// temporary directory, which is removed when this is dropped.
+pub struct WebFonts {
+    font_map: pango::FontMap,
+
+    /// Pairs of the family names from the `@font-face` rules, and the private names
+    /// that the fonts are registered with in `font_map`.
+    families: Vec<(String, String)>,
+
+    dir: PathBuf,
+}
+
+impl WebFonts {
+    /// Loads the fonts and adds them to the shared font map with the system fonts.
+    ///
+    /// Returns `None` if none of the fonts could be loaded.
+    pub fn load(faces: &[FontFace]) -> Option<WebFonts> {
+        static NEXT_FAMILY_ID: AtomicUsize = AtomicUsize::new(0);
+
+        if faces.is_empty() {
+            return None;
+        }
+
+        let dir = create_private_dir()?;
+
+        let mut families: Vec<(String, String)> = Vec::new();
+
+        let font_map = fontconfig::add_fonts(|config| {
+            for (i, face) in faces.iter().enumerate() {
+                let private_family = families
+                    .iter()
+                    .find(|(family, _)| family.eq_ignore_ascii_case(&face.family))
+                    .map(|(_, private_family)| private_family.clone())
+                    .unwrap_or_else(|| {
+                        let id = NEXT_FAMILY_ID.fetch_add(1, Ordering::Relaxed);
+                        format!("librsvg-web-font-{}", id)
+                    });
+
+                let path = dir.join(format!("font-{}", i));
+
+                if load_font_face(config, face, &private_family, &path)
+                    && !families.iter().any(|(_, f)| *f == private_family)
+                {
+                    families.push((face.family.clone(), private_family));
+                }
+            }
+
+            !families.is_empty()
+        });
+
+        match font_map {
+            Some(font_map) => Some(WebFonts {
+                font_map,
+                families,
+                dir,
+            }),
+            None => {
+                let _ = std::fs::remove_dir_all(&dir);
+                None
+            }
+        }
+    }
+
+    pub fn font_map(&self) -> &pango::FontMap {
+        &self.font_map
+    }
+
+    /// Replaces the names in a `font-family` list that refer to the web fonts with the
+    /// private names that the fonts are registered with in the font map.
+    pub fn font_family(&self, font_family: &str) -> String {
+        font_family
+            .split(',')
+            .map(|name| {
+                self.families
+                    .iter()
+                    .find(|(family, _)| family.eq_ignore_ascii_case(name.trim()))
+                    .map_or(name, |(_, private_family)| private_family)
+            })
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+}
+
+impl Drop for WebFonts {
+    fn drop(&mut self) {
+        fontconfig::release_fonts();
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+/// Creates a new temporary directory that only the current user can access.
+//
+// We don't use glib::mkdtemp() since its binding frees the returned path twice.
+fn create_private_dir() -> Option<PathBuf> {
+    let mut builder = std::fs::DirBuilder::new();
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::DirBuilderExt;
+        builder.mode(0o700);
+    }
+
+    for _ in 0..100 {
+        let dir = std::env::temp_dir().join(format!("librsvg-fonts-{:08x}", glib::random_int()));
+
+        match builder.create(&dir) {
+            Ok(()) => return Some(dir),
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+            Err(e) => {
+                rsvg_log!("could not create a temporary directory for fonts: {}", e);
+                return None;
+            }
+        }
+    }
+
+    None
+}
+
+/// Loads the first source of a font face that works, and registers it as `family`.
+fn load_font_face(
+    config: &mut fontconfig::Config,
+    face: &FontFace,
+    family: &str,
+    path: &Path,
+) -> bool {
+    for aurl in &face.sources {
+        let data = match io::acquire_data(aurl, None) {
+            Ok(BinaryData { data, .. }) => data,
+            Err(e) => {
+                rsvg_log!("could not load font \"{}\": {}", aurl, e);
+                continue;
+            }
+        };
+
+        if let Err(e) = std::fs::write(path, &data) {
+            rsvg_log!(
+                "could not write font \"{}\" to {}: {}",
+                aurl,
+                path.display(),
+                e
+            );
+            return false;
+        }
+
+        if config.add_font_file(path, family, face.style, face.weight) {
+            return true;
+        }
+
+        rsvg_log!("\"{}\" does not contain a supported font", aurl);
+    }
+
+    false
+}
+
+#[cfg(system_deps_have_pangoft2)]
+mod fontconfig {
+    use glib::translate::*;
+    use libc::{c_char, c_int, c_uchar, c_void};
+    use std::cell::RefCell;
+    use std::ffi::{CStr, CString};
+    use std::path::Path;
+
+    use crate::properties::{FontStyle, FontWeight};
+
+    // Fontconfig and PangoFcFontMap are not bound in gtk-rs, so we bind the few
+    // functions we need by hand.
+
+    #[repr(C)]
+    struct FcFontSet {
+        nfont: c_int,
+        sfont: c_int,
+        fonts: *mut *mut c_void,
+    }
+
+    const FC_SET_APPLICATION: c_int = 1;
+    const FC_RESULT_MATCH: c_int = 0;
+
+    const FC_SLANT_ROMAN: c_int = 0;
+    const FC_SLANT_ITALIC: c_int = 100;
+    const FC_SLANT_OBLIQUE: c_int = 110;
+
+    extern "C" {
+        fn FcInitLoadConfigAndFonts() -> *mut c_void;
+        fn FcConfigDestroy(config: *mut c_void);
+        fn FcConfigAppFontAddFile(config: *mut c_void, file: *const c_uchar) -> c_int;
+        fn FcConfigAppFontClear(config: *mut c_void);
+        fn FcConfigGetFonts(config: *mut c_void, set: c_int) -> *mut FcFontSet;
+        fn FcPatternGetString(
+            p: *mut c_void,
+            object: *const c_char,
+            n: c_int,
+            s: *mut *mut c_uchar,
+        ) -> c_int;
+        fn FcPatternAddString(p: *mut c_void, object: *const c_char, s: *const c_uchar) -> c_int;
+        fn FcPatternAddInteger(p: *mut c_void, object: *const c_char, i: c_int) -> c_int;
+        fn FcPatternDel(p: *mut c_void, object: *const c_char) -> c_int;
+        fn FcWeightFromOpenType(ot_weight: c_int) -> c_int;
+
+        fn pango_fc_font_map_set_config(font_map: *mut c_void, config: *mut c_void);
+        fn pango_fc_font_map_config_changed(font_map: *mut c_void);
+    }
+
+    /// A fontconfig configuration with the system fonts.
+    pub struct Config(*mut c_void);
+
+    impl Config {
+        /// Adds the fonts in a file, and makes them available under `family` instead of
+        /// the family names in the file itself.
+        pub fn add_font_file(
+            &mut self,
+            path: &Path,
+            family: &str,
+            style: Option<FontStyle>,
+            weight: Option<FontWeight>,
+        ) -> bool {
+            let path = match path.to_str().and_then(|p| CString::new(p).ok()) {
+                Some(p) => p,
+                None => return false,
+            };
+
+            let family = match CString::new(family) {
+                Ok(f) => f,
+                Err(_) => return false,
+            };
+
+            unsafe {
+                if FcConfigAppFontAddFile(self.0, path.as_ptr() as *const _) == 0 {
+                    return false;
+                }
+
+                let set = FcConfigGetFonts(self.0, FC_SET_APPLICATION);
+                if set.is_null() {
+                    return false;
+                }
+
+                let set = &*set;
+
+                for i in 0..set.nfont as usize {
+                    let pattern = *set.fonts.add(i);
+
+                    let mut file = std::ptr::null_mut();
+                    if FcPatternGetString(pattern, b"file\0".as_ptr() as *const _, 0, &mut file)
+                        != FC_RESULT_MATCH
+                        || CStr::from_ptr(file as *const c_char) != path.as_c_str()
+                    {
+                        continue;
+                    }
+
+                    FcPatternDel(pattern, b"family\0".as_ptr() as *const _);
+                    FcPatternDel(pattern, b"familylang\0".as_ptr() as *const _);
+                    FcPatternAddString(
+                        pattern,
+                        b"family\0".as_ptr() as *const _,
+                        family.as_ptr() as *const _,
+                    );
+
+                    if let Some(style) = style {
+                        let slant = match style {
+                            FontStyle::Normal => FC_SLANT_ROMAN,
+                            FontStyle::Italic => FC_SLANT_ITALIC,
+                            FontStyle::Oblique => FC_SLANT_OBLIQUE,
+                        };
+
+                        FcPatternDel(pattern, b"slant\0".as_ptr() as *const _);
+                        FcPatternAddInteger(pattern, b"slant\0".as_ptr() as *const _, slant);
+                    }
+
+                    if let Some(weight) = weight {
+                        // Relative weights don't make sense in @font-face.
+                        if let FontWeight::Normal | FontWeight::Bold | FontWeight::Weight(_) =
+                            weight
+                        {
+                            let w = FcWeightFromOpenType(c_int::from(weight.numeric_weight()));
+                            FcPatternDel(pattern, b"weight\0".as_ptr() as *const _);
+                            FcPatternAddInteger(pattern, b"weight\0".as_ptr() as *const _, w);
+                        }
+                    }
+                }
+            }
+
+            true
+        }
+    }
+
+    /// The configuration with the system fonts that all the documents in a thread add
+    /// their fonts to, and the font map that uses it.
+    struct Shared {
+        config: Config,
+        font_map: pango::FontMap,
+
+        /// Number of `WebFonts` that have added fonts to `config`.
+        num_users: usize,
+    }
+
+    impl Shared {
+        fn new() -> Option<Shared> {
+            let config = unsafe { FcInitLoadConfigAndFonts() };
+            if config.is_null() {
+                return None;
+            }
+
+            let config = Config(config);
+
+            let font_map = pangocairo::FontMap::for_font_type(cairo::FontType::FontTypeFt)?;
+
+            unsafe {
+                pango_fc_font_map_set_config(raw_font_map(&font_map), config.0);
+            }
+
+            Some(Shared {
+                config,
+                font_map,
+                num_users: 0,
+            })
+        }
+
+        /// Makes the font map pick up the fonts that were added to or removed from the
+        /// configuration.
+        fn config_changed(&self) {
+            unsafe {
+                pango_fc_font_map_config_changed(raw_font_map(&self.font_map));
+            }
+        }
+    }
+
+    fn raw_font_map(font_map: &pango::FontMap) -> *mut c_void {
+        let raw_font_map: *mut pango::ffi::PangoFontMap = font_map.to_glib_none().0;
+        raw_font_map as *mut _
+    }
+
+    thread_local! {
+        static SHARED: RefCell<Option<Shared>> = RefCell::new(Shared::new());
+    }
+
+    /// Adds fonts to the thread's font map with the system fonts, and returns that font map.
+    ///
+    /// Returns `None` if `add_fonts` returns false.  Otherwise, `release_fonts()` must be
+    /// called once the fonts are no longer needed.
+    pub fn add_fonts<F>(add_fonts: F) -> Option<pango::FontMap>
+    where
+        F: FnOnce(&mut Config) -> bool,
+    {
+        SHARED.with(|shared| {
+            let mut shared = shared.borrow_mut();
+            let shared = shared.as_mut()?;
+
+            if !add_fonts(&mut shared.config) {
+                return None;
+            }
+
+            shared.num_users += 1;
+            shared.config_changed();
+
+            Some(shared.font_map.clone())
+        })
+    }
+
+    /// Releases the fonts added with `add_fonts()`.
+    ///
+    /// Fontconfig cannot remove individual fonts, so all of them get removed once no
+    /// document uses them anymore.
+    pub fn release_fonts() {
+        // The thread-local may already be gone if this happens while the thread exits.
+        let _ = SHARED.try_with(|shared| {
+            if let Some(ref mut shared) = *shared.borrow_mut() {
+                shared.num_users -= 1;
+
+                if shared.num_users == 0 {
+                    unsafe {
+                        FcConfigAppFontClear(shared.config.0);
+                    }
+
+                    shared.config_changed();
+                }
+            }
+        });
+    }
+
+    impl Drop for Config {
+        fn drop(&mut self) {
+            // The font map keeps its own reference to the configuration.
+            unsafe {
+                FcConfigDestroy(self.0);
+            }
+        }
+    }
+}
+
+#[cfg(not(system_deps_have_pangoft2))]
+mod fontconfig {
+    use std::path::Path;
+
+    use crate::properties::{FontStyle, FontWeight};
+
+    pub struct Config;
+
+    impl Config {
+        pub fn add_font_file(
+            &mut self,
+            _path: &Path,
+            _family: &str,
+            _style: Option<FontStyle>,
+            _weight: Option<FontWeight>,
+        ) -> bool {
+            false
+        }
+    }
+
+    pub fn add_fonts<F>(_add_fonts: F) -> Option<pango::FontMap>
+    where
+        F: FnOnce(&mut Config) -> bool,
+    {
+        rsvg_log!("@font-face is only supported with fontconfig");
+        None
+    }
+
+    pub fn release_fonts() {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_font_face_sources() {
+        let mut input = cssparser::ParserInput::new(
+            "url(\"foo.woff2\") format(\"woff2\"), url(foo.ttf), local(\"Foo Bold\"), local(Foo)",
+        );
+        let mut parser = Parser::new(&mut input);
+
+        assert_eq!(
+            FontFaceDescriptor::parse("src", &mut parser).unwrap(),
+            FontFaceDescriptor::Src(vec![
+                FontFaceSource::Url(String::from("foo.woff2")),
+                FontFaceSource::Url(String::from("foo.ttf")),
+                FontFaceSource::Local(String::from("Foo Bold")),
+                FontFaceSource::Local(String::from("Foo")),
+            ])
+        );
+    }
+
+    #[test]
+    fn creates_font_face_from_descriptors() {
+        let url_resolver = UrlResolver::new(None);
+
+        let face = FontFace::new(
+            vec![
+                FontFaceDescriptor::FontFamily(String::from("Foo")),
+                FontFaceDescriptor::Src(vec![
+                    FontFaceSource::Local(String::from("Foo")),
+                    FontFaceSource::Url(String::from("foo.ttf")),
+                    FontFaceSource::Url(String::from("data:font/ttf;base64,AAAA")),
+                ]),
+                FontFaceDescriptor::FontWeight(FontWeight::Bold),
+            ],
+            &url_resolver,
+        )
+        .unwrap();
+
+        assert_eq!(face.family, "Foo");
+        assert_eq!(face.sources.len(), 1);
+        assert_eq!(face.weight, Some(FontWeight::Bold));
+
+        assert!(FontFace::new(
+            vec![FontFaceDescriptor::FontFamily(String::from("Foo"))],
+            &url_resolver
+        )
+        .is_none());
+    }
+}
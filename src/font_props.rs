@@ -4,7 +4,7 @@
 //! which re-exports things from here.
 
 use cast::{f64, u16};
-use cssparser::{Parser, Token};
+use cssparser::{_cssparser_internal_to_lowercase, match_ignore_ascii_case, Parser, Token};
 
 use crate::error::*;
 use crate::length::*;
@@ -413,6 +413,53 @@ impl Parse for LetterSpacing {
     }
 }
 
+/// `word-spacing` property.
+///
+/// https://www.w3.org/TR/SVG/text.html#WordSpacingProperty
+///
+/// https://www.w3.org/TR/css-text-3/#word-spacing-property
+#[derive(Debug, Clone, PartialEq)]
+pub enum WordSpacing {
+    Normal,
+    Value(Length<Horizontal>),
+}
+
+impl WordSpacing {
+    pub fn value(&self) -> Length<Horizontal> {
+        match self {
+            WordSpacing::Value(s) => *s,
+            _ => unreachable!(),
+        }
+    }
+
+    pub fn compute(&self) -> Self {
+        let spacing = match self {
+            WordSpacing::Normal => Length::<Horizontal>::new(0.0, LengthUnit::Px),
+            WordSpacing::Value(s) => *s,
+        };
+
+        WordSpacing::Value(spacing)
+    }
+
+    pub fn to_user(&self, params: &NormalizeParams) -> f64 {
+        self.value().to_user(params)
+    }
+}
+
+impl Parse for WordSpacing {
+    fn parse<'i>(parser: &mut Parser<'i, '_>) -> Result<WordSpacing, ParseError<'i>> {
+        parser
+            .try_parse(|p| Length::<Horizontal>::parse(p))
+            .map(WordSpacing::Value)
+            .or_else(|_| {
+                Ok(parse_identifiers!(
+                    parser,
+                    "normal" => WordSpacing::Normal,
+                )?)
+            })
+    }
+}
+
 /// `line-height` property.
 ///
 /// https://drafts.csswg.org/css2/visudet.html#propdef-line-height
@@ -427,29 +474,33 @@ pub enum LineHeight {
 }
 
 impl LineHeight {
-    pub fn value(&self) -> Length<Both> {
-        match self {
-            LineHeight::Length(l) => *l,
-            _ => unreachable!(),
-        }
-    }
-
     pub fn compute(&self, values: &ComputedValues) -> Self {
         let font_size = values.font_size().value();
 
+        // Unitless numbers and `normal` inherit as they are, so that they are relative to
+        // the font size of each descendant; only percentages become an absolute length.
         match *self {
-            LineHeight::Normal => LineHeight::Length(font_size),
-
-            LineHeight::Number(f) | LineHeight::Percentage(f) => {
+            LineHeight::Percentage(f) => {
                 LineHeight::Length(Length::new(font_size.length * f64(f), font_size.unit))
             }
 
-            LineHeight::Length(l) => LineHeight::Length(l),
+            ref line_height => line_height.clone(),
         }
     }
 
-    pub fn to_user(&self, params: &NormalizeParams) -> f64 {
-        self.value().to_user(params)
+    /// Normalizes the line height to user units, relative to the font size in `params`.
+    ///
+    /// Returns `None` for `normal`, since that depends on the metrics of the font.
+    pub fn to_user(&self, params: &NormalizeParams) -> Option<f64> {
+        match *self {
+            LineHeight::Normal => None,
+
+            LineHeight::Number(f) | LineHeight::Percentage(f) => {
+                Some(Length::<Both>::new(f64(f), LengthUnit::Em).to_user(params))
+            }
+
+            LineHeight::Length(l) => Some(l.to_user(params)),
+        }
     }
 }
 
@@ -532,6 +583,317 @@ impl FontFamily {
     }
 }
 
+/// Returns an error for a keyword that was already given in a multi-keyword property.
+fn duplicate_keyword<'i>(loc: cssparser::SourceLocation) -> ParseError<'i> {
+    loc.new_custom_error(ValueErrorKind::parse_error(
+        "repeated or conflicting keyword",
+    ))
+}
+
+/// Sets a field of a multi-keyword property, or fails if it was already set.
+fn set_keyword<'i, T>(
+    field: &mut Option<T>,
+    value: T,
+    loc: cssparser::SourceLocation,
+) -> Result<(), ParseError<'i>> {
+    if field.is_some() {
+        Err(duplicate_keyword(loc))
+    } else {
+        *field = Some(value);
+        Ok(())
+    }
+}
+
+/// `font-variant-ligatures` property.
+///
+/// https://www.w3.org/TR/css-fonts-3/#font-variant-ligatures-prop
+///
+/// Each field is `None` if the font's default is used for that kind of ligatures,
+/// or whether they are turned on or off otherwise.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FontVariantLigatures {
+    pub common: Option<bool>,
+    pub discretionary: Option<bool>,
+    pub historical: Option<bool>,
+    pub contextual: Option<bool>,
+}
+
+impl Parse for FontVariantLigatures {
+    fn parse<'i>(parser: &mut Parser<'i, '_>) -> Result<FontVariantLigatures, ParseError<'i>> {
+        if parser
+            .try_parse(|p| p.expect_ident_matching("normal"))
+            .is_ok()
+        {
+            return Ok(FontVariantLigatures::default());
+        }
+
+        if parser
+            .try_parse(|p| p.expect_ident_matching("none"))
+            .is_ok()
+        {
+            return Ok(FontVariantLigatures {
+                common: Some(false),
+                discretionary: Some(false),
+                historical: Some(false),
+                contextual: Some(false),
+            });
+        }
+
+        let mut ligatures = FontVariantLigatures::default();
+
+        loop {
+            let loc = parser.current_source_location();
+            let ident = parser.expect_ident()?;
+
+            let (field, value) = match_ignore_ascii_case! { ident,
+                "common-ligatures" => (&mut ligatures.common, true),
+                "no-common-ligatures" => (&mut ligatures.common, false),
+                "discretionary-ligatures" => (&mut ligatures.discretionary, true),
+                "no-discretionary-ligatures" => (&mut ligatures.discretionary, false),
+                "historical-ligatures" => (&mut ligatures.historical, true),
+                "no-historical-ligatures" => (&mut ligatures.historical, false),
+                "contextual" => (&mut ligatures.contextual, true),
+                "no-contextual" => (&mut ligatures.contextual, false),
+                _ => return Err(loc.new_basic_unexpected_token_error(Token::Ident(ident.clone())).into()),
+            };
+
+            set_keyword(field, value, loc)?;
+
+            if parser.is_exhausted() {
+                break;
+            }
+        }
+
+        Ok(ligatures)
+    }
+}
+
+/// Values for the figures in `font-variant-numeric`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum NumericFigure {
+    Lining,
+    Oldstyle,
+}
+
+/// Values for the spacing of figures in `font-variant-numeric`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum NumericSpacing {
+    Proportional,
+    Tabular,
+}
+
+/// Values for fractions in `font-variant-numeric`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum NumericFraction {
+    Diagonal,
+    Stacked,
+}
+
+/// `font-variant-numeric` property.
+///
+/// https://www.w3.org/TR/css-fonts-3/#font-variant-numeric-prop
+#[derive(Debug, Clone, PartialEq)]
+pub struct FontVariantNumeric {
+    pub figure: Option<NumericFigure>,
+    pub spacing: Option<NumericSpacing>,
+    pub fraction: Option<NumericFraction>,
+    pub ordinal: bool,
+    pub slashed_zero: bool,
+}
+
+impl Parse for FontVariantNumeric {
+    fn parse<'i>(parser: &mut Parser<'i, '_>) -> Result<FontVariantNumeric, ParseError<'i>> {
+        if parser
+            .try_parse(|p| p.expect_ident_matching("normal"))
+            .is_ok()
+        {
+            return Ok(FontVariantNumeric::default());
+        }
+
+        let mut numeric = FontVariantNumeric::default();
+
+        loop {
+            let loc = parser.current_source_location();
+            let ident = parser.expect_ident()?.clone();
+
+            match_ignore_ascii_case! { &ident,
+                "lining-nums" => set_keyword(&mut numeric.figure, NumericFigure::Lining, loc)?,
+                "oldstyle-nums" => set_keyword(&mut numeric.figure, NumericFigure::Oldstyle, loc)?,
+                "proportional-nums" => set_keyword(&mut numeric.spacing, NumericSpacing::Proportional, loc)?,
+                "tabular-nums" => set_keyword(&mut numeric.spacing, NumericSpacing::Tabular, loc)?,
+                "diagonal-fractions" => set_keyword(&mut numeric.fraction, NumericFraction::Diagonal, loc)?,
+                "stacked-fractions" => set_keyword(&mut numeric.fraction, NumericFraction::Stacked, loc)?,
+
+                "ordinal" if !numeric.ordinal => numeric.ordinal = true,
+                "slashed-zero" if !numeric.slashed_zero => numeric.slashed_zero = true,
+                "ordinal" | "slashed-zero" => return Err(duplicate_keyword(loc)),
+
+                _ => return Err(loc.new_basic_unexpected_token_error(Token::Ident(ident)).into()),
+            }
+
+            if parser.is_exhausted() {
+                break;
+            }
+        }
+
+        Ok(numeric)
+    }
+}
+
+/// Values for the glyph variants in `font-variant-east-asian`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum EastAsianVariant {
+    Jis78,
+    Jis83,
+    Jis90,
+    Jis04,
+    Simplified,
+    Traditional,
+}
+
+/// Values for the width of glyphs in `font-variant-east-asian`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum EastAsianWidth {
+    Full,
+    Proportional,
+}
+
+/// `font-variant-east-asian` property.
+///
+/// https://www.w3.org/TR/css-fonts-3/#font-variant-east-asian-prop
+#[derive(Debug, Clone, PartialEq)]
+pub struct FontVariantEastAsian {
+    pub variant: Option<EastAsianVariant>,
+    pub width: Option<EastAsianWidth>,
+    pub ruby: bool,
+}
+
+impl Parse for FontVariantEastAsian {
+    fn parse<'i>(parser: &mut Parser<'i, '_>) -> Result<FontVariantEastAsian, ParseError<'i>> {
+        if parser
+            .try_parse(|p| p.expect_ident_matching("normal"))
+            .is_ok()
+        {
+            return Ok(FontVariantEastAsian::default());
+        }
+
+        let mut east_asian = FontVariantEastAsian::default();
+
+        loop {
+            let loc = parser.current_source_location();
+            let ident = parser.expect_ident()?.clone();
+
+            match_ignore_ascii_case! { &ident,
+                "jis78" => set_keyword(&mut east_asian.variant, EastAsianVariant::Jis78, loc)?,
+                "jis83" => set_keyword(&mut east_asian.variant, EastAsianVariant::Jis83, loc)?,
+                "jis90" => set_keyword(&mut east_asian.variant, EastAsianVariant::Jis90, loc)?,
+                "jis04" => set_keyword(&mut east_asian.variant, EastAsianVariant::Jis04, loc)?,
+                "simplified" => set_keyword(&mut east_asian.variant, EastAsianVariant::Simplified, loc)?,
+                "traditional" => set_keyword(&mut east_asian.variant, EastAsianVariant::Traditional, loc)?,
+                "full-width" => set_keyword(&mut east_asian.width, EastAsianWidth::Full, loc)?,
+                "proportional-width" => set_keyword(&mut east_asian.width, EastAsianWidth::Proportional, loc)?,
+
+                "ruby" if !east_asian.ruby => east_asian.ruby = true,
+                "ruby" => return Err(duplicate_keyword(loc)),
+
+                _ => return Err(loc.new_basic_unexpected_token_error(Token::Ident(ident)).into()),
+            }
+
+            if parser.is_exhausted() {
+                break;
+            }
+        }
+
+        Ok(east_asian)
+    }
+}
+
+/// Parses a quoted OpenType tag for a feature or a variation axis, like `"liga"` or `"wght"`.
+fn parse_opentype_tag<'i>(parser: &mut Parser<'i, '_>) -> Result<String, ParseError<'i>> {
+    let loc = parser.current_source_location();
+    let tag = parser.expect_string()?.to_string();
+
+    if tag.len() == 4 && tag.chars().all(|c| (' '..='~').contains(&c)) {
+        Ok(tag)
+    } else {
+        Err(loc.new_custom_error(ValueErrorKind::value_error(
+            "OpenType tags must have four ASCII characters",
+        )))
+    }
+}
+
+/// `font-feature-settings` property.
+///
+/// https://www.w3.org/TR/css-fonts-3/#font-feature-settings-prop
+///
+/// This is a list of OpenType feature tags and their values; it is empty for `normal`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FontFeatureSettings(pub Vec<(String, u32)>);
+
+impl Parse for FontFeatureSettings {
+    fn parse<'i>(parser: &mut Parser<'i, '_>) -> Result<FontFeatureSettings, ParseError<'i>> {
+        if parser
+            .try_parse(|p| p.expect_ident_matching("normal"))
+            .is_ok()
+        {
+            return Ok(FontFeatureSettings::default());
+        }
+
+        let features = parser.parse_comma_separated(|parser| {
+            let loc = parser.current_source_location();
+            let tag = parse_opentype_tag(parser)?;
+
+            let value = if let Ok(v) = parser.try_parse(|p| p.expect_integer()) {
+                if v < 0 {
+                    return Err(loc.new_custom_error(ValueErrorKind::value_error(
+                        "feature values must not be negative",
+                    )));
+                }
+
+                v as u32
+            } else if parser.try_parse(|p| p.expect_ident_matching("off")).is_ok() {
+                0
+            } else {
+                // "on" is the same as not specifying a value.
+                let _ = parser.try_parse(|p| p.expect_ident_matching("on"));
+                1
+            };
+
+            Ok((tag, value))
+        })?;
+
+        Ok(FontFeatureSettings(features))
+    }
+}
+
+/// `font-variation-settings` property.
+///
+/// https://www.w3.org/TR/css-fonts-4/#font-variation-settings-def
+///
+/// This is a list of variation axis tags and their values; it is empty for `normal`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FontVariationSettings(pub Vec<(String, f64)>);
+
+impl Parse for FontVariationSettings {
+    fn parse<'i>(parser: &mut Parser<'i, '_>) -> Result<FontVariationSettings, ParseError<'i>> {
+        if parser
+            .try_parse(|p| p.expect_ident_matching("normal"))
+            .is_ok()
+        {
+            return Ok(FontVariationSettings::default());
+        }
+
+        let axes = parser.parse_comma_separated(|parser| {
+            let tag = parse_opentype_tag(parser)?;
+            let value = f64::parse(parser)?;
+
+            Ok((tag, value))
+        })?;
+
+        Ok(FontVariationSettings(axes))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -678,6 +1040,35 @@ mod tests {
         assert!(LetterSpacing::parse_str("furlong").is_err());
     }
 
+    #[test]
+    fn parses_word_spacing() {
+        assert_eq!(
+            <WordSpacing as Parse>::parse_str("normal").unwrap(),
+            WordSpacing::Normal
+        );
+        assert_eq!(
+            <WordSpacing as Parse>::parse_str("-0.5em").unwrap(),
+            WordSpacing::Value(Length::<Horizontal>::new(-0.5, LengthUnit::Em))
+        );
+        assert!(WordSpacing::parse_str("wide").is_err());
+    }
+
+    #[test]
+    fn computes_word_spacing() {
+        assert_eq!(
+            <WordSpacing as Parse>::parse_str("normal")
+                .map(|s| s.compute())
+                .unwrap(),
+            WordSpacing::Value(Length::<Horizontal>::new(0.0, LengthUnit::Px))
+        );
+        assert_eq!(
+            <WordSpacing as Parse>::parse_str("4px")
+                .map(|s| s.compute())
+                .unwrap(),
+            WordSpacing::Value(Length::<Horizontal>::new(4.0, LengthUnit::Px))
+        );
+    }
+
     #[test]
     fn parses_font_family() {
         assert_eq!(
@@ -762,14 +1153,11 @@ mod tests {
         let mut values = ComputedValues::default();
         specified.to_computed_values(&mut values);
 
-        assert_eq!(
-            LineHeight::Normal.compute(&values),
-            LineHeight::Length(Length::new(10.0, LengthUnit::Px)),
-        );
+        assert_eq!(LineHeight::Normal.compute(&values), LineHeight::Normal);
 
         assert_eq!(
             LineHeight::Number(2.0).compute(&values),
-            LineHeight::Length(Length::new(20.0, LengthUnit::Px)),
+            LineHeight::Number(2.0),
         );
 
         assert_eq!(
@@ -782,4 +1170,111 @@ mod tests {
             LineHeight::Length(Length::new(15.0, LengthUnit::Px)),
         );
     }
+
+    #[test]
+    fn parses_font_variant_ligatures() {
+        assert_eq!(
+            FontVariantLigatures::parse_str("normal").unwrap(),
+            FontVariantLigatures::default()
+        );
+
+        assert_eq!(
+            FontVariantLigatures::parse_str("none").unwrap(),
+            FontVariantLigatures {
+                common: Some(false),
+                discretionary: Some(false),
+                historical: Some(false),
+                contextual: Some(false),
+            }
+        );
+
+        assert_eq!(
+            FontVariantLigatures::parse_str("discretionary-ligatures no-contextual").unwrap(),
+            FontVariantLigatures {
+                common: None,
+                discretionary: Some(true),
+                historical: None,
+                contextual: Some(false),
+            }
+        );
+
+        assert!(FontVariantLigatures::parse_str("common-ligatures no-common-ligatures").is_err());
+        assert!(FontVariantLigatures::parse_str("none contextual").is_err());
+        assert!(FontVariantLigatures::parse_str("ligatures").is_err());
+    }
+
+    #[test]
+    fn parses_font_variant_numeric() {
+        assert_eq!(
+            FontVariantNumeric::parse_str("tabular-nums slashed-zero oldstyle-nums").unwrap(),
+            FontVariantNumeric {
+                figure: Some(NumericFigure::Oldstyle),
+                spacing: Some(NumericSpacing::Tabular),
+                fraction: None,
+                ordinal: false,
+                slashed_zero: true,
+            }
+        );
+
+        assert!(FontVariantNumeric::parse_str("tabular-nums proportional-nums").is_err());
+        assert!(FontVariantNumeric::parse_str("ordinal ordinal").is_err());
+        assert!(FontVariantNumeric::parse_str("").is_err());
+    }
+
+    #[test]
+    fn parses_font_variant_east_asian() {
+        assert_eq!(
+            FontVariantEastAsian::parse_str("ruby jis04 full-width").unwrap(),
+            FontVariantEastAsian {
+                variant: Some(EastAsianVariant::Jis04),
+                width: Some(EastAsianWidth::Full),
+                ruby: true,
+            }
+        );
+
+        assert!(FontVariantEastAsian::parse_str("simplified traditional").is_err());
+    }
+
+    #[test]
+    fn parses_font_feature_settings() {
+        assert_eq!(
+            FontFeatureSettings::parse_str("normal").unwrap(),
+            FontFeatureSettings(Vec::new())
+        );
+
+        assert_eq!(
+            FontFeatureSettings::parse_str("\"tnum\", 'ss01' 2, \"liga\" off, \"dlig\" on")
+                .unwrap(),
+            FontFeatureSettings(vec![
+                (String::from("tnum"), 1),
+                (String::from("ss01"), 2),
+                (String::from("liga"), 0),
+                (String::from("dlig"), 1),
+            ])
+        );
+
+        assert!(FontFeatureSettings::parse_str("\"toolong\"").is_err());
+        assert!(FontFeatureSettings::parse_str("\"liga\" -1").is_err());
+        assert!(FontFeatureSettings::parse_str("liga").is_err());
+    }
+
+    #[test]
+    fn parses_font_variation_settings() {
+        assert_eq!(
+            FontVariationSettings::parse_str("normal").unwrap(),
+            FontVariationSettings(Vec::new())
+        );
+
+        assert_eq!(
+            FontVariationSettings::parse_str("\"wght\" 450, 'GRAD' -25.5").unwrap(),
+            FontVariationSettings(vec![
+                (String::from("wght"), 450.0),
+                (String::from("GRAD"), -25.5),
+            ])
+        );
+
+        assert!(FontVariationSettings::parse_str("\"wght\"").is_err());
+        assert!(FontVariationSettings::parse_str("\"wdth\" on").is_err());
+        assert!(FontVariationSettings::parse_str("\"wdth\" 100 \"wght\" 400").is_err());
+    }
 }
@@ -13,10 +13,11 @@ use crate::element::{Draw, Element, ElementResult, SetAttributes};
 use crate::error::*;
 use crate::href::{is_href, set_href};
 use crate::length::*;
+use crate::mesh_gradient::MeshSide;
 use crate::node::{CascadedValues, Node, NodeBorrow};
 use crate::paint_server::resolve_color;
 use crate::parsers::{Parse, ParseValue};
-use crate::properties::ComputedValues;
+use crate::properties::{ColorInterpolation, ComputedValues};
 use crate::transform::{Transform, TransformAttribute};
 use crate::unit_interval::UnitInterval;
 use crate::xml::Attributes;
@@ -76,16 +77,29 @@ impl Parse for StopOffset {
 pub struct Stop {
     /// <stop offset="..."/>
     offset: UnitInterval,
+
+    /// <stop path="..."/>, for stops inside a mesh gradient's patches
+    path: Option<MeshSide>,
     /* stop-color and stop-opacity are not attributes; they are properties, so
      * they go into property_defs.rs */
 }
 
+impl Stop {
+    pub fn get_mesh_side(&self) -> Option<MeshSide> {
+        self.path
+    }
+}
+
 impl SetAttributes for Stop {
     fn set_attributes(&mut self, attrs: &Attributes) -> ElementResult {
         for (attr, value) in attrs.iter() {
-            if let expanded_name!("", "offset") = attr.expanded() {
-                let StopOffset(o) = attr.parse(value)?;
-                self.offset = o;
+            match attr.expanded() {
+                expanded_name!("", "offset") => {
+                    let StopOffset(o) = attr.parse(value)?;
+                    self.offset = o;
+                }
+                expanded_name!("", "path") => self.path = attr.parse(value)?,
+                _ => (),
             }
         }
 
@@ -353,6 +367,7 @@ pub struct ResolvedGradient {
     transform: Transform,
     spread: SpreadMethod,
     stops: Vec<ColorStop>,
+    color_interpolation: ColorInterpolation,
 
     variant: ResolvedGradientVariant,
 }
@@ -363,11 +378,14 @@ pub struct UserSpaceGradient {
     pub spread: SpreadMethod,
     pub stops: Vec<ColorStop>,
 
+    /// Color space in which to interpolate between the stops.
+    pub color_interpolation: ColorInterpolation,
+
     pub variant: GradientVariant,
 }
 
 impl UnresolvedGradient {
-    fn into_resolved(self) -> ResolvedGradient {
+    fn into_resolved(self, color_interpolation: ColorInterpolation) -> ResolvedGradient {
         assert!(self.is_resolved());
 
         let UnresolvedGradient {
@@ -384,6 +402,7 @@ impl UnresolvedGradient {
                 transform: transform.unwrap(),
                 spread: spread.unwrap(),
                 stops: stops.unwrap(),
+                color_interpolation,
 
                 variant: variant.into_resolved(),
             },
@@ -393,6 +412,7 @@ impl UnresolvedGradient {
                 transform: transform.unwrap(),
                 spread: spread.unwrap(),
                 stops: stops.unwrap(),
+                color_interpolation,
 
                 variant: variant.into_resolved(),
             },
@@ -639,7 +659,12 @@ macro_rules! impl_gradient {
                     }
                 }
 
-                Ok(gradient.into_resolved())
+                // Like other properties, color-interpolation comes from the
+                // referenced gradient element, not from its href fallbacks.
+                let cascaded = CascadedValues::new_from_node(node);
+                let color_interpolation = cascaded.get().color_interpolation();
+
+                Ok(gradient.into_resolved(color_interpolation))
             }
         }
     };
@@ -729,6 +754,7 @@ impl ResolvedGradient {
             transform,
             spread: self.spread,
             stops: self.stops.clone(),
+            color_interpolation: self.color_interpolation,
             variant,
         })
     }
@@ -2,17 +2,21 @@
 //!
 //! This module provides the primitives on which the public APIs are implemented.
 
+use std::cell::RefCell;
+use std::rc::Rc;
+
 use crate::accept_language::UserLanguage;
 use crate::bbox::BoundingBox;
 use crate::css::{Origin, Stylesheet};
 use crate::document::{AcquiredNodes, Document, NodeId};
 use crate::dpi::Dpi;
-use crate::drawing_ctx::{draw_tree, with_saved_cr, DrawingMode, ViewParams};
+use crate::drawing_ctx::{draw_tree, with_saved_cr, DrawingMode};
+use crate::element::Element;
 use crate::error::{DefsLookupErrorKind, LoadingError, RenderingError};
-use crate::length::*;
+use crate::layout::TextRun;
 use crate::node::{CascadedValues, Node, NodeBorrow};
 use crate::rect::Rect;
-use crate::structure::IntrinsicDimensions;
+use crate::structure::{IntrinsicDimensions, ViewSpec};
 use crate::url_resolver::{AllowedUrl, UrlResolver};
 
 /// Loading options for SVG documents.
@@ -82,6 +86,16 @@ pub struct Handle {
     document: Document,
 }
 
+/// The outlines of the glyphs in a `<text>` element.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextOutline {
+    /// `id` attribute of the `<text>` element, if present
+    pub id: Option<String>,
+
+    /// The outlines as SVG path data, suitable for the `d` attribute of a `<path>`
+    pub path_data: String,
+}
+
 impl Handle {
     /// Loads an SVG document into a `Handle`.
     pub fn from_stream(
@@ -113,32 +127,18 @@ impl Handle {
     ///
     /// If any of the width/height are percentages, we cannot compute the size here.  Here
     /// just normalize lengths with physical units, or units based on the font size.
-    pub fn get_intrinsic_size_in_pixels(&self, dpi: Dpi) -> Option<(f64, f64)> {
-        let dimensions = self.get_intrinsic_dimensions();
-
-        if dimensions.width.is_none() || dimensions.height.is_none() {
-            // If either of width/height don't exist, the spec says they should default to 100%,
-            // which is a percentage-based unit - which we can't resolve here.
-            return None;
-        }
-
-        let w = dimensions.width.unwrap();
-        let h = dimensions.height.unwrap();
-
-        use crate::length::LengthUnit::*;
-
-        if w.unit == Percent || h.unit == Percent {
-            return None;
-        }
+    pub fn get_intrinsic_size_in_pixels(
+        &self,
+        dpi: Dpi,
+        time: Option<f64>,
+        view: Option<ViewSpec>,
+    ) -> Option<(f64, f64)> {
+        let dimensions = self.get_intrinsic_dimensions(time, view);
 
-        let view_params = ViewParams::new(dpi, 0.0, 0.0);
         let root = self.document.root();
         let cascaded = CascadedValues::new_from_node(&root);
-        let values = cascaded.get();
-
-        let params = NormalizeParams::new(values, &view_params);
 
-        Some((w.to_user(&params), h.to_user(&params)))
+        dimensions.size_in_pixels(cascaded.get(), dpi)
     }
 
     fn get_node_or_root(&self, id: Option<&str>) -> Result<Node, RenderingError> {
@@ -156,6 +156,7 @@ impl Handle {
         user_language: &UserLanguage,
         dpi: Dpi,
         is_testing: bool,
+        view: Option<ViewSpec>,
     ) -> Result<(Rect, Rect), RenderingError> {
         let root = self.document.root();
 
@@ -170,7 +171,8 @@ impl Handle {
             dpi,
             true,
             is_testing,
-            &mut AcquiredNodes::new(&self.document),
+            false,
+            &mut AcquiredNodes::new(&self.document).with_view(view),
         )?;
 
         let ink_rect = bbox.ink_rect.unwrap_or_default();
@@ -186,12 +188,16 @@ impl Handle {
         user_language: &UserLanguage,
         dpi: Dpi,
         is_testing: bool,
+        time: Option<f64>,
+        view: Option<ViewSpec>,
     ) -> Result<(cairo::Rectangle, cairo::Rectangle), RenderingError> {
+        self.document.set_time(time);
+
         let viewport = Rect::from(*viewport);
         let node = self.get_node_or_root(id)?;
 
         let (ink_rect, logical_rect) =
-            self.geometry_for_layer(node, viewport, user_language, dpi, is_testing)?;
+            self.geometry_for_layer(node, viewport, user_language, dpi, is_testing, view)?;
 
         Ok((
             cairo::Rectangle::from(ink_rect),
@@ -233,8 +239,21 @@ impl Handle {
         user_language: &UserLanguage,
         dpi: Dpi,
         is_testing: bool,
+        text_as_paths: bool,
+        time: Option<f64>,
+        view: Option<ViewSpec>,
     ) -> Result<(), RenderingError> {
-        self.render_layer(cr, None, viewport, user_language, dpi, is_testing)
+        self.render_layer(
+            cr,
+            None,
+            viewport,
+            user_language,
+            dpi,
+            is_testing,
+            text_as_paths,
+            time,
+            view,
+        )
     }
 
     pub fn render_layer(
@@ -245,9 +264,14 @@ impl Handle {
         user_language: &UserLanguage,
         dpi: Dpi,
         is_testing: bool,
+        text_as_paths: bool,
+        time: Option<f64>,
+        view: Option<ViewSpec>,
     ) -> Result<(), RenderingError> {
         cr.status()?;
 
+        self.document.set_time(time);
+
         let node = self.get_node_or_root(id)?;
         let root = self.document.root();
 
@@ -262,7 +286,8 @@ impl Handle {
                 dpi,
                 false,
                 is_testing,
-                &mut AcquiredNodes::new(&self.document),
+                text_as_paths,
+                &mut AcquiredNodes::new(&self.document).with_view(view),
             )
             .map(|_bbox| ())
         })
@@ -274,6 +299,7 @@ impl Handle {
         user_language: &UserLanguage,
         dpi: Dpi,
         is_testing: bool,
+        view: Option<ViewSpec>,
     ) -> Result<BoundingBox, RenderingError> {
         let target = cairo::ImageSurface::create(cairo::Format::Rgb24, 1, 1)?;
         let cr = cairo::Context::new(&target)?;
@@ -288,7 +314,8 @@ impl Handle {
             dpi,
             true,
             is_testing,
-            &mut AcquiredNodes::new(&self.document),
+            false,
+            &mut AcquiredNodes::new(&self.document).with_view(view),
         )
     }
 
@@ -299,10 +326,14 @@ impl Handle {
         user_language: &UserLanguage,
         dpi: Dpi,
         is_testing: bool,
+        time: Option<f64>,
+        view: Option<ViewSpec>,
     ) -> Result<(cairo::Rectangle, cairo::Rectangle), RenderingError> {
+        self.document.set_time(time);
+
         let node = self.get_node_or_root(id)?;
 
-        let bbox = self.get_bbox_for_element(&node, user_language, dpi, is_testing)?;
+        let bbox = self.get_bbox_for_element(&node, user_language, dpi, is_testing, view)?;
 
         let ink_rect = bbox.ink_rect.unwrap_or_default();
         let logical_rect = bbox.rect.unwrap_or_default();
@@ -324,12 +355,17 @@ impl Handle {
         user_language: &UserLanguage,
         dpi: Dpi,
         is_testing: bool,
+        text_as_paths: bool,
+        time: Option<f64>,
+        view: Option<ViewSpec>,
     ) -> Result<(), RenderingError> {
         cr.status()?;
 
+        self.document.set_time(time);
+
         let node = self.get_node_or_root(id)?;
 
-        let bbox = self.get_bbox_for_element(&node, user_language, dpi, is_testing)?;
+        let bbox = self.get_bbox_for_element(&node, user_language, dpi, is_testing, view)?;
 
         if bbox.ink_rect.is_none() || bbox.rect.is_none() {
             // Nothing to draw
@@ -360,24 +396,172 @@ impl Handle {
                 dpi,
                 false,
                 is_testing,
-                &mut AcquiredNodes::new(&self.document),
+                text_as_paths,
+                &mut AcquiredNodes::new(&self.document).with_view(view),
             )
             .map(|_bbox| ())
         })
     }
 
-    pub fn get_intrinsic_dimensions(&self) -> IntrinsicDimensions {
-        borrow_element_as!(self.document.root(), Svg).get_intrinsic_dimensions()
+    /// Returns the glyph outlines of each `<text>` element in the document, as if the
+    /// document were rendered to a specific viewport.
+    ///
+    /// The outlines are in the viewport's coordinate system.  Elements with no text to
+    /// render, like those within a `<defs>`, are omitted.
+    pub fn get_text_outlines(
+        &self,
+        viewport: &cairo::Rectangle,
+        user_language: &UserLanguage,
+        dpi: Dpi,
+        is_testing: bool,
+        time: Option<f64>,
+        view: Option<ViewSpec>,
+    ) -> Result<Vec<TextOutline>, RenderingError> {
+        self.document.set_time(time);
+
+        let root = self.document.root();
+        let viewport = Rect::from(*viewport);
+
+        let target = cairo::ImageSurface::create(cairo::Format::Rgb24, 1, 1)?;
+
+        let mut outlines = Vec::new();
+
+        for node in self.text_elements() {
+            let cr = cairo::Context::new(&target)?;
+
+            draw_tree(
+                DrawingMode::TextOutline {
+                    node: node.clone(),
+                    root: root.clone(),
+                },
+                &cr,
+                viewport,
+                user_language,
+                dpi,
+                false,
+                is_testing,
+                false,
+                &mut AcquiredNodes::new(&self.document).with_view(view),
+            )?;
+
+            // The path is copied in user space, so go back to the viewport's coordinates.
+            cr.identity_matrix();
+            let path = cr.copy_path()?;
+
+            let path_data = path_data(&path);
+            if !path_data.is_empty() {
+                outlines.push(TextOutline {
+                    id: node.borrow_element().get_id().map(String::from),
+                    path_data,
+                });
+            }
+        }
+
+        Ok(outlines)
+    }
+
+    /// Returns the runs of text that get rendered in the document, as if it were rendered
+    /// to a specific viewport.
+    ///
+    /// The runs are in document order, with positions in the viewport's coordinate system.
+    pub fn get_text_runs(
+        &self,
+        viewport: &cairo::Rectangle,
+        user_language: &UserLanguage,
+        dpi: Dpi,
+        is_testing: bool,
+        time: Option<f64>,
+        view: Option<ViewSpec>,
+    ) -> Result<Vec<TextRun>, RenderingError> {
+        self.document.set_time(time);
+
+        let root = self.document.root();
+        let viewport = Rect::from(*viewport);
+
+        let target = cairo::ImageSurface::create(cairo::Format::Rgb24, 1, 1)?;
+
+        let runs = Rc::new(RefCell::new(Vec::new()));
+
+        for node in self.text_elements() {
+            let cr = cairo::Context::new(&target)?;
+
+            draw_tree(
+                DrawingMode::TextRuns {
+                    node,
+                    root: root.clone(),
+                    runs: runs.clone(),
+                },
+                &cr,
+                viewport,
+                user_language,
+                dpi,
+                false,
+                is_testing,
+                false,
+                &mut AcquiredNodes::new(&self.document).with_view(view),
+            )?;
+        }
+
+        let runs = runs.replace(Vec::new());
+        Ok(runs)
+    }
+
+    /// Returns the `<text>` elements in the document, in document order.
+    fn text_elements(&self) -> impl Iterator<Item = Node> {
+        self.document
+            .root()
+            .descendants()
+            .filter(|node| node.is_element() && matches!(*node.borrow_element(), Element::Text(_)))
+    }
+
+    pub fn get_intrinsic_dimensions(
+        &self,
+        time: Option<f64>,
+        view: Option<ViewSpec>,
+    ) -> IntrinsicDimensions {
+        self.document.set_time(time);
+
+        borrow_element_as!(self.document.root(), Svg).get_intrinsic_dimensions(view)
     }
 
     pub fn set_stylesheet(&mut self, css: &str) -> Result<(), LoadingError> {
         let mut stylesheet = Stylesheet::new(Origin::User);
         stylesheet.parse(css, &UrlResolver::new(None))?;
-        self.document.cascade(&[stylesheet]);
+        self.document.cascade(vec![stylesheet]);
         Ok(())
     }
+
+    pub fn resolve_view(&self, fragment: &str) -> Option<ViewSpec> {
+        self.document.resolve_view(fragment)
+    }
 }
 
 fn unit_rectangle() -> Rect {
     Rect::from_size(1.0, 1.0)
 }
+
+/// Formats a Cairo path as SVG path data.
+///
+/// Cairo puts a move-to after each close-path, and text spans start with a move-to to
+/// their position; move-tos without anything drawn after them are omitted.
+fn path_data(path: &cairo::Path) -> String {
+    let segments: Vec<cairo::PathSegment> = path.iter().collect();
+
+    let commands: Vec<String> = segments
+        .iter()
+        .enumerate()
+        .filter_map(|(i, segment)| match *segment {
+            cairo::PathSegment::MoveTo((x, y)) => match segments.get(i + 1) {
+                None | Some(cairo::PathSegment::MoveTo(_)) => None,
+                _ => Some(format!("M {} {}", x, y)),
+            },
+            cairo::PathSegment::LineTo((x, y)) => Some(format!("L {} {}", x, y)),
+            cairo::PathSegment::CurveTo((x1, y1), (x2, y2), (x3, y3)) => {
+                Some(format!("C {} {} {} {} {} {}", x1, y1, x2, y2, x3, y3))
+            }
+            cairo::PathSegment::ClosePath => Some(String::from("Z")),
+        })
+        .collect();
+
+    commands.join(" ")
+}
@@ -0,0 +1,520 @@
+//! The `hatch` and `hatchpath` elements.
+//!
+//! A hatch is a paint server that fills an area with parallel lines.  The lines are
+//! defined by the `<hatchpath>` children of the hatch, and they are repeated every
+//! `pitch` units along the hatch's x axis.  Each `<hatchpath>` is stroked with its own
+//! stroke properties.
+//!
+//! See https://www.w3.org/TR/2016/CR-SVG2-20160915/pservers.html#Hatches
+
+use markup5ever::{expanded_name, local_name, namespace_url, ns};
+use std::rc::Rc;
+
+use crate::angle::Angle;
+use crate::bbox::BoundingBox;
+use crate::coord_units::CoordUnits;
+use crate::document::{AcquiredNodes, NodeId, NodeStack};
+use crate::drawing_ctx::ViewParams;
+use crate::element::{Draw, Element, ElementResult, SetAttributes};
+use crate::error::*;
+use crate::href::{is_href, set_href};
+use crate::length::*;
+use crate::node::{Node, NodeBorrow};
+use crate::parsers::ParseValue;
+use crate::path_builder::{Path as SvgPath, PathBuilder, PathCommand};
+use crate::pattern::{Children, UnresolvedChildren};
+use crate::properties::ComputedValues;
+use crate::rect::Rect;
+use crate::transform::{Transform, TransformAttribute};
+use crate::unit_interval::UnitInterval;
+use crate::xml::Attributes;
+
+coord_units!(HatchUnits, CoordUnits::ObjectBoundingBox);
+coord_units!(HatchContentUnits, CoordUnits::UserSpaceOnUse);
+
+/// Maximum number of copies of a `<hatchpath>`'s path data to cover a hatched area.
+const MAX_HATCH_PATH_REPEATS: i64 = 10_000;
+
+#[derive(Clone, Default)]
+struct Common {
+    units: Option<HatchUnits>,
+    content_units: Option<HatchContentUnits>,
+    transform: Option<Transform>,
+    x: Option<Length<Horizontal>>,
+    y: Option<Length<Vertical>>,
+    pitch: Option<ULength<Horizontal>>,
+    rotate: Option<Angle>,
+}
+
+/// Node for the `<hatch>` element
+#[derive(Default)]
+pub struct Hatch {
+    common: Common,
+    fallback: Option<NodeId>,
+}
+
+/// Node for the `<hatchpath>` element
+#[derive(Default)]
+pub struct HatchPath {
+    d: Option<Rc<SvgPath>>,
+    offset: Length<Horizontal>,
+}
+
+/// Hatch during resolution of its `href` fallbacks.  Fields in `common` that are `None`
+/// were not specified.
+struct UnresolvedHatch {
+    common: Common,
+    children: UnresolvedChildren,
+}
+
+#[derive(Clone)]
+pub struct ResolvedHatch {
+    units: HatchUnits,
+    content_units: HatchContentUnits,
+    transform: Transform,
+    x: Length<Horizontal>,
+    y: Length<Vertical>,
+    pitch: ULength<Horizontal>,
+    rotate: Angle,
+    opacity: UnitInterval,
+
+    // Link to the node whose children are the hatch's resolved children.
+    children: Children,
+}
+
+/// Hatch normalized to user-space units.
+pub struct UserSpaceHatch {
+    /// Distance between repetitions of the hatch paths, in hatch space.
+    pub pitch: f64,
+
+    /// Area to be painted, including any stroke, in hatch space.
+    pub bounds: Rect,
+
+    /// Transform from hatch space to user space.
+    pub coord_transform: Transform,
+
+    /// Transform from the hatch paths' coordinates to hatch space.
+    pub content_transform: Transform,
+
+    pub opacity: UnitInterval,
+    pub node_with_children: Node,
+}
+
+impl SetAttributes for Hatch {
+    fn set_attributes(&mut self, attrs: &Attributes) -> ElementResult {
+        for (attr, value) in attrs.iter() {
+            match attr.expanded() {
+                ref a if *a.ns == ns!() && &**a.local == "hatchUnits" => {
+                    self.common.units = attr.parse(value)?
+                }
+                ref a if *a.ns == ns!() && &**a.local == "hatchContentUnits" => {
+                    self.common.content_units = attr.parse(value)?
+                }
+                expanded_name!("", "transform") => {
+                    let transform_attr: TransformAttribute = attr.parse(value)?;
+                    self.common.transform = Some(transform_attr.to_transform());
+                }
+                ref a if is_href(a) => {
+                    set_href(
+                        a,
+                        &mut self.fallback,
+                        NodeId::parse(value).attribute(attr.clone())?,
+                    );
+                }
+                expanded_name!("", "x") => self.common.x = attr.parse(value)?,
+                expanded_name!("", "y") => self.common.y = attr.parse(value)?,
+                ref a if *a.ns == ns!() && &**a.local == "pitch" => {
+                    self.common.pitch = attr.parse(value)?
+                }
+                expanded_name!("", "rotate") => self.common.rotate = attr.parse(value)?,
+                _ => (),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Draw for Hatch {}
+
+impl SetAttributes for HatchPath {
+    fn set_attributes(&mut self, attrs: &Attributes) -> ElementResult {
+        for (attr, value) in attrs.iter() {
+            match attr.expanded() {
+                expanded_name!("", "d") => {
+                    let mut builder = PathBuilder::default();
+                    if let Err(e) = builder.parse(value) {
+                        // Like for the path element, a partial path is OK per the spec
+                        rsvg_log!("could not parse hatch path: {}", e);
+                    }
+                    self.d = Some(Rc::new(builder.into_path()));
+                }
+                expanded_name!("", "offset") => self.offset = attr.parse(value)?,
+                _ => (),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Draw for HatchPath {}
+
+impl HatchPath {
+    /// Builds the path to be stroked for the hatch line, in the hatch content's coordinates.
+    ///
+    /// Without path data, the hatch line is a straight line along the y axis.  Path data
+    /// is repeated along the y axis by the distance between its start and end points, so
+    /// it must advance in the y direction.  The result covers the vertical range `y0..y1`.
+    pub fn to_path(&self, params: &NormalizeParams, y0: f64, y1: f64) -> Option<SvgPath> {
+        let offset = self.offset.to_user(params);
+
+        let mut builder = PathBuilder::default();
+
+        match self.d {
+            None => {
+                builder.move_to(offset, y0);
+                builder.line_to(offset, y1);
+            }
+
+            Some(ref path) => {
+                let (start, end) = path_endpoints(path)?;
+                let advance = end.1 - start.1;
+
+                if advance <= 0.0 {
+                    rsvg_log!("(not using hatch path because it does not advance along y)");
+                    return None;
+                }
+
+                let first = ((y0 - end.1) / advance).floor() as i64;
+                let last = ((y1 - start.1) / advance).ceil() as i64;
+
+                if last - first > MAX_HATCH_PATH_REPEATS {
+                    rsvg_log!("(not using hatch path because it needs too many repetitions)");
+                    return None;
+                }
+
+                for i in first..=last {
+                    push_translated(&mut builder, path, offset, advance * i as f64);
+                }
+            }
+        }
+
+        Some(builder.into_path())
+    }
+}
+
+/// Gets the start and end points of a path.
+fn path_endpoints(path: &SvgPath) -> Option<((f64, f64), (f64, f64))> {
+    let mut start = None;
+    let mut subpath_start = (0.0, 0.0);
+    let mut end = (0.0, 0.0);
+
+    for cmd in path.iter() {
+        end = match cmd {
+            PathCommand::MoveTo(x, y) => {
+                subpath_start = (x, y);
+                (x, y)
+            }
+            PathCommand::LineTo(x, y) => (x, y),
+            PathCommand::CurveTo(curve) => curve.to,
+            PathCommand::Arc(arc) => arc.to,
+            PathCommand::ClosePath => subpath_start,
+        };
+
+        start = start.or(Some(end));
+    }
+
+    start.map(|start| (start, end))
+}
+
+/// Appends a copy of a path, translated by `(dx, dy)`.
+fn push_translated(builder: &mut PathBuilder, path: &SvgPath, dx: f64, dy: f64) {
+    for cmd in path.iter() {
+        match cmd {
+            PathCommand::MoveTo(x, y) => builder.move_to(x + dx, y + dy),
+            PathCommand::LineTo(x, y) => builder.line_to(x + dx, y + dy),
+            PathCommand::CurveTo(curve) => builder.curve_to(
+                curve.pt1.0 + dx,
+                curve.pt1.1 + dy,
+                curve.pt2.0 + dx,
+                curve.pt2.1 + dy,
+                curve.to.0 + dx,
+                curve.to.1 + dy,
+            ),
+            PathCommand::Arc(arc) => builder.arc(
+                arc.from.0 + dx,
+                arc.from.1 + dy,
+                arc.r.0,
+                arc.r.1,
+                arc.x_axis_rotation,
+                arc.large_arc,
+                arc.sweep,
+                arc.to.0 + dx,
+                arc.to.1 + dy,
+            ),
+            PathCommand::ClosePath => builder.close_path(),
+        }
+    }
+}
+
+impl UnresolvedHatch {
+    fn into_resolved(self, opacity: UnitInterval) -> ResolvedHatch {
+        assert!(self.is_resolved());
+
+        ResolvedHatch {
+            units: self.common.units.unwrap(),
+            content_units: self.common.content_units.unwrap(),
+            transform: self.common.transform.unwrap(),
+            x: self.common.x.unwrap(),
+            y: self.common.y.unwrap(),
+            pitch: self.common.pitch.unwrap(),
+            rotate: self.common.rotate.unwrap(),
+            opacity,
+
+            children: self.children.to_resolved(),
+        }
+    }
+
+    fn is_resolved(&self) -> bool {
+        self.common.units.is_some()
+            && self.common.content_units.is_some()
+            && self.common.transform.is_some()
+            && self.common.x.is_some()
+            && self.common.y.is_some()
+            && self.common.pitch.is_some()
+            && self.common.rotate.is_some()
+            && self.children.is_resolved()
+    }
+
+    fn resolve_from_fallback(&self, fallback: &UnresolvedHatch) -> UnresolvedHatch {
+        UnresolvedHatch {
+            common: Common {
+                units: self.common.units.or(fallback.common.units),
+                content_units: self.common.content_units.or(fallback.common.content_units),
+                transform: self.common.transform.or(fallback.common.transform),
+                x: self.common.x.or(fallback.common.x),
+                y: self.common.y.or(fallback.common.y),
+                pitch: self.common.pitch.or(fallback.common.pitch),
+                rotate: self.common.rotate.or(fallback.common.rotate),
+            },
+            children: self.children.resolve_from_fallback(&fallback.children),
+        }
+    }
+
+    fn resolve_from_defaults(&self) -> UnresolvedHatch {
+        UnresolvedHatch {
+            common: Common {
+                units: self.common.units.or_else(|| Some(HatchUnits::default())),
+                content_units: self
+                    .common
+                    .content_units
+                    .or_else(|| Some(HatchContentUnits::default())),
+                transform: self.common.transform.or_else(|| Some(Transform::default())),
+                x: self.common.x.or_else(|| Some(Default::default())),
+                y: self.common.y.or_else(|| Some(Default::default())),
+                pitch: self.common.pitch.or_else(|| Some(Default::default())),
+                rotate: self.common.rotate.or_else(|| Some(Angle::new(0.0))),
+            },
+            children: self.children.resolve_from_defaults(),
+        }
+    }
+}
+
+impl ResolvedHatch {
+    fn node_with_children(&self) -> Option<Node> {
+        match self.children {
+            // This means we didn't find any children among the fallbacks,
+            // so there is nothing to render.
+            Children::Empty => None,
+
+            Children::WithChildren(ref wc) => Some(wc.upgrade().unwrap()),
+        }
+    }
+
+    pub fn to_user_space(
+        &self,
+        bbox: &BoundingBox,
+        current_params: &ViewParams,
+        values: &ComputedValues,
+    ) -> Option<UserSpaceHatch> {
+        let node_with_children = self.node_with_children()?;
+
+        let view_params = current_params.with_units(self.units.0);
+        let params = NormalizeParams::new(values, &view_params);
+
+        let x = self.x.to_user(&params);
+        let y = self.y.to_user(&params);
+        let pitch = self.pitch.to_user(&params);
+
+        let bbrect = bbox.rect?;
+
+        // Create the hatch coordinate system
+        let (x, y, pitch) = match self.units {
+            HatchUnits(CoordUnits::ObjectBoundingBox) => (
+                bbrect.x0 + x * bbrect.width(),
+                bbrect.y0 + y * bbrect.height(),
+                pitch * bbrect.width(),
+            ),
+            HatchUnits(CoordUnits::UserSpaceOnUse) => (x, y, pitch),
+        };
+
+        let coord_transform = Transform::new_rotate(self.rotate)
+            .post_translate(x, y)
+            .post_transform(&self.transform);
+
+        // The hatch lines are infinite along the hatch's y axis, so they only need to
+        // cover the painted area.  That is the ink box, since for a stroke it extends
+        // past the object's bounding box; the hatch tile does not repeat seamlessly along
+        // the y axis, so it must not need to repeat within the painted area.
+        let painted = bbox.ink_rect.unwrap_or(bbrect);
+        let bounds = coord_transform.invert()?.transform_rect(&painted);
+
+        let content_transform = match self.content_units {
+            HatchContentUnits(CoordUnits::ObjectBoundingBox) => {
+                Transform::new_scale(bbrect.width(), bbrect.height())
+            }
+            HatchContentUnits(CoordUnits::UserSpaceOnUse) => Transform::identity(),
+        };
+
+        Some(UserSpaceHatch {
+            pitch,
+            bounds,
+            coord_transform,
+            content_transform,
+            opacity: self.opacity,
+            node_with_children,
+        })
+    }
+}
+
+impl Hatch {
+    fn get_unresolved(&self, node: &Node) -> UnresolvedHatch {
+        UnresolvedHatch {
+            common: self.common.clone(),
+            children: UnresolvedChildren::from_node(node),
+        }
+    }
+
+    pub fn resolve(
+        &self,
+        node: &Node,
+        acquired_nodes: &mut AcquiredNodes<'_>,
+        opacity: UnitInterval,
+    ) -> Result<ResolvedHatch, AcquireError> {
+        let mut hatch = self.get_unresolved(node);
+        let mut fallback = self.fallback.clone();
+
+        let mut stack = NodeStack::new();
+
+        while !hatch.is_resolved() {
+            if let Some(ref node_id) = fallback {
+                match acquired_nodes.acquire(node_id) {
+                    Ok(acquired) => {
+                        let acquired_node = acquired.get();
+
+                        if stack.contains(acquired_node) {
+                            return Err(AcquireError::CircularReference(acquired_node.clone()));
+                        }
+
+                        match *acquired_node.borrow_element() {
+                            Element::Hatch(ref h) => {
+                                hatch =
+                                    hatch.resolve_from_fallback(&h.get_unresolved(acquired_node));
+                                fallback = h.fallback.clone();
+
+                                stack.push(acquired_node);
+                            }
+                            _ => return Err(AcquireError::InvalidLinkType(node_id.clone())),
+                        }
+                    }
+
+                    Err(AcquireError::MaxReferencesExceeded) => {
+                        return Err(AcquireError::MaxReferencesExceeded)
+                    }
+
+                    Err(e) => {
+                        rsvg_log!("Stopping hatch resolution: {}", e);
+                        hatch = hatch.resolve_from_defaults();
+                        break;
+                    }
+                }
+            } else {
+                hatch = hatch.resolve_from_defaults();
+                break;
+            }
+        }
+
+        Ok(hatch.into_resolved(opacity))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dpi::Dpi;
+    use crate::node::NodeData;
+    use crate::parsers::Parse;
+    use markup5ever::{LocalName, QualName};
+
+    #[test]
+    fn hatch_resolved_from_defaults_is_really_resolved() {
+        let node = Node::new(NodeData::new_element(
+            &QualName::new(None, ns!(svg), LocalName::from("hatch")),
+            Attributes::new(),
+        ));
+
+        let unresolved = borrow_element_as!(node, Hatch).get_unresolved(&node);
+        let hatch = unresolved.resolve_from_defaults();
+        assert!(hatch.is_resolved());
+    }
+
+    fn hatch_path(d: Option<&str>) -> HatchPath {
+        HatchPath {
+            d: d.map(|d| {
+                let mut builder = PathBuilder::default();
+                builder.parse(d).unwrap();
+                Rc::new(builder.into_path())
+            }),
+            offset: Length::<Horizontal>::parse_str("5").unwrap(),
+        }
+    }
+
+    fn to_path(hatch_path: &HatchPath, y0: f64, y1: f64) -> Option<Vec<PathCommand>> {
+        let view_params = ViewParams::new(Dpi::new(96.0, 96.0), 100.0, 100.0);
+        let values = ComputedValues::default();
+        let params = NormalizeParams::new(&values, &view_params);
+
+        hatch_path
+            .to_path(&params, y0, y1)
+            .map(|path| path.iter().collect())
+    }
+
+    #[test]
+    fn hatch_path_without_data_is_a_line() {
+        assert_eq!(
+            to_path(&hatch_path(None), -10.0, 20.0).unwrap(),
+            vec![
+                PathCommand::MoveTo(5.0, -10.0),
+                PathCommand::LineTo(5.0, 20.0)
+            ]
+        );
+    }
+
+    #[test]
+    fn hatch_path_data_is_repeated_along_y() {
+        let commands = to_path(&hatch_path(Some("M 0,0 L 2,10")), 0.0, 20.0).unwrap();
+
+        assert_eq!(commands.len(), 8);
+        assert_eq!(commands[0], PathCommand::MoveTo(5.0, -10.0));
+        assert_eq!(commands[1], PathCommand::LineTo(7.0, 0.0));
+        assert_eq!(commands[2], PathCommand::MoveTo(5.0, 0.0));
+        assert_eq!(commands[7], PathCommand::LineTo(7.0, 30.0));
+    }
+
+    #[test]
+    fn hatch_path_must_advance_along_y() {
+        assert!(to_path(&hatch_path(Some("M 0,0 L 10,0")), 0.0, 20.0).is_none());
+        assert!(to_path(&hatch_path(Some("M 0,10 L 0,0")), 0.0, 20.0).is_none());
+    }
+}
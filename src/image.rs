@@ -4,7 +4,8 @@ use markup5ever::{expanded_name, local_name, namespace_url, ns};
 
 use crate::aspect_ratio::AspectRatio;
 use crate::bbox::BoundingBox;
-use crate::document::AcquiredNodes;
+use crate::document::{AcquiredNodes, NodeId};
+use crate::dpi::Dpi;
 use crate::drawing_ctx::DrawingCtx;
 use crate::element::{Draw, ElementResult, SetAttributes};
 use crate::error::*;
@@ -14,6 +15,8 @@ use crate::length::*;
 use crate::node::{CascadedValues, Node, NodeBorrow};
 use crate::parsers::ParseValue;
 use crate::rect::Rect;
+use crate::structure::ViewSpec;
+use crate::surface_utils::shared_surface::SharedImageSurface;
 use crate::xml::Attributes;
 
 #[derive(Default)]
@@ -58,17 +61,30 @@ impl Draw for Image {
         draw_ctx: &mut DrawingCtx,
         clipping: bool,
     ) -> Result<BoundingBox, RenderingError> {
-        let surface = match self.href {
-            Some(ref url) => match acquired_nodes.lookup_image(url) {
-                Ok(surf) => surf,
+        let url = match self.href {
+            Some(ref url) => url,
+            None => return Ok(draw_ctx.empty_bbox()),
+        };
+
+        // A view of an SVG document, like "sprites.svg#icon", gets rendered from the
+        // document itself; anything else is loaded as a raster image.
+        let view = NodeId::parse(url)
+            .ok()
+            .and_then(|node_id| acquired_nodes.lookup_external_view(&node_id));
+
+        let source = match view {
+            Some((root, view)) => Source::Svg(root, view),
+            None => match acquired_nodes.lookup_image(url) {
+                Ok(surf) => Source::Raster(surf),
                 Err(e) => {
                     rsvg_log!("could not load image \"{}\": {}", url, e);
                     return Ok(draw_ctx.empty_bbox());
                 }
             },
-            None => return Ok(draw_ctx.empty_bbox()),
         };
 
+        let (intrinsic_width, intrinsic_height) = source.size(draw_ctx.get_view_params().dpi);
+
         let values = cascaded.get();
 
         let view_params = draw_ctx.get_view_params();
@@ -79,11 +95,11 @@ impl Draw for Image {
 
         let w = match self.width {
             LengthOrAuto::Length(l) => l.to_user(&params),
-            LengthOrAuto::Auto => surface.width() as f64,
+            LengthOrAuto::Auto => intrinsic_width,
         };
         let h = match self.height {
             LengthOrAuto::Length(l) => l.to_user(&params),
-            LengthOrAuto::Auto => surface.height() as f64,
+            LengthOrAuto::Auto => intrinsic_height,
         };
 
         let is_visible = values.is_visible();
@@ -92,17 +108,71 @@ impl Draw for Image {
 
         let overflow = values.overflow();
 
-        let image = layout::Image {
-            surface,
-            is_visible,
-            rect,
-            aspect: self.aspect,
-            overflow,
-        };
-
         let elt = node.borrow_element();
         let stacking_ctx = StackingContext::new(acquired_nodes, &elt, values.transform(), values);
 
-        draw_ctx.draw_image(&image, &stacking_ctx, acquired_nodes, values, clipping)
+        match source {
+            Source::Raster(surface) => {
+                let image = layout::Image {
+                    surface,
+                    is_visible,
+                    rect,
+                    aspect: self.aspect,
+                    overflow,
+                };
+
+                draw_ctx.draw_image(&image, &stacking_ctx, acquired_nodes, values, clipping)
+            }
+
+            Source::Svg(root, view) => {
+                let image = layout::SvgImage {
+                    root,
+                    view,
+                    is_visible,
+                    rect,
+                    aspect: self.aspect,
+                    overflow,
+                };
+
+                draw_ctx.draw_svg_image(&image, &stacking_ctx, acquired_nodes, values, clipping)
+            }
+        }
+    }
+}
+
+/// What an `<image>` element references.
+enum Source {
+    Raster(SharedImageSurface),
+
+    /// Root element of an SVG document, and the view with which to render it.
+    Svg(Node, ViewSpec),
+}
+
+impl Source {
+    /// Size to use when the `<image>` has `width` or `height` set to `auto`.
+    ///
+    /// For an SVG document this is the size of its `viewBox` as overridden by the view, or
+    /// else its intrinsic size in pixels, like for the handle.
+    fn size(&self, dpi: Dpi) -> (f64, f64) {
+        match *self {
+            Source::Raster(ref surface) => {
+                (f64::from(surface.width()), f64::from(surface.height()))
+            }
+
+            Source::Svg(ref root, view) => {
+                let dimensions = borrow_element_as!(root, Svg).get_intrinsic_dimensions(Some(view));
+
+                match dimensions.vbox {
+                    Some(vbox) => (vbox.width(), vbox.height()),
+                    None => {
+                        let cascaded = CascadedValues::new_from_node(root);
+
+                        dimensions
+                            .size_in_pixels(cascaded.get(), dpi)
+                            .unwrap_or((0.0, 0.0))
+                    }
+                }
+            }
+        }
     }
 }
@@ -0,0 +1,1548 @@
+//! CSS animations with `@keyframes` rules.
+//!
+//! The `@keyframes` rules from the document's stylesheets get turned into [`Keyframes`]
+//! values while parsing CSS, and the `animation-*` properties say which of them run on an
+//! element, and how.  When a document is rendered at a certain time, the cascade calls
+//! [`CssAnimations::apply`] for each element after computing its values; this replaces
+//! the computed values of the animated properties with ones interpolated between keyframes.
+//!
+//! Colors, lengths, numbers, opacities and transforms get interpolated; other properties
+//! switch from one keyframe's value to the next one halfway between them.
+
+use cssparser::{Parser, Token};
+use markup5ever::QualName;
+use std::f64::consts::PI;
+use std::sync::Arc;
+
+use crate::angle::Angle;
+use crate::animation::KeySpline;
+use crate::css::Declaration;
+use crate::error::*;
+use crate::length::*;
+use crate::paint_server::PaintServer;
+use crate::parsers::{CustomIdent, Parse};
+use crate::properties::{
+    BaselineShift, Color, ComputedValues, Fill, FillOpacity, FloodColor, FloodOpacity, FontSize,
+    LetterSpacing, LightingColor, Opacity, ParsedProperty, SpecifiedValue, SpecifiedValues,
+    StopColor, StopOpacity, Stroke, StrokeDashoffset, StrokeMiterlimit, StrokeOpacity, StrokeWidth,
+    TextDecorationColor, WordSpacing,
+};
+use crate::transform::{Transform, TransformFunction, TransformProperty};
+use crate::unit_interval::UnitInterval;
+use crate::util;
+
+/// Parses a `<time>` value, and returns it in seconds.
+///
+/// https://www.w3.org/TR/css-values-3/#time
+fn parse_time<'i>(parser: &mut Parser<'i, '_>) -> Result<f64, ParseError<'i>> {
+    let loc = parser.current_source_location();
+    let token = parser.next()?;
+
+    match *token {
+        Token::Dimension {
+            value, ref unit, ..
+        } if unit.eq_ignore_ascii_case("s") => Ok(f64::from(value)),
+
+        Token::Dimension {
+            value, ref unit, ..
+        } if unit.eq_ignore_ascii_case("ms") => Ok(f64::from(value) / 1000.0),
+
+        _ => Err(loc.new_basic_unexpected_token_error(token.clone()).into()),
+    }
+}
+
+/// Parses a `<time>` value for the duration of an animation, which cannot be negative.
+fn parse_duration<'i>(parser: &mut Parser<'i, '_>) -> Result<f64, ParseError<'i>> {
+    let loc = parser.current_source_location();
+    let duration = parse_time(parser)?;
+
+    if duration >= 0.0 {
+        Ok(duration)
+    } else {
+        Err(loc.new_custom_error(ValueErrorKind::value_error(
+            "animation durations must not be negative",
+        )))
+    }
+}
+
+/// Parses an iteration count; `infinite` is returned as infinity.
+fn parse_iteration_count<'i>(parser: &mut Parser<'i, '_>) -> Result<f64, ParseError<'i>> {
+    if parser
+        .try_parse(|p| p.expect_ident_matching("infinite"))
+        .is_ok()
+    {
+        return Ok(f64::INFINITY);
+    }
+
+    let loc = parser.current_source_location();
+    let count = f64::parse(parser)?;
+
+    if count >= 0.0 {
+        Ok(count)
+    } else {
+        Err(loc.new_custom_error(ValueErrorKind::value_error(
+            "iteration counts must not be negative",
+        )))
+    }
+}
+
+/// Parses the name of an animation; `none` is returned as `None`.
+fn parse_animation_name<'i>(parser: &mut Parser<'i, '_>) -> Result<Option<String>, ParseError<'i>> {
+    if parser
+        .try_parse(|p| p.expect_ident_matching("none"))
+        .is_ok()
+    {
+        return Ok(None);
+    }
+
+    if let Ok(name) = parser.try_parse(|p| p.expect_string().map(|s| s.to_string())) {
+        return Ok(Some(name));
+    }
+
+    let CustomIdent(name) = CustomIdent::parse(parser)?;
+    Ok(Some(name))
+}
+
+/// Parses one of the selectors in the prelude of a keyframe, like `from` or `50%`, and
+/// returns it as a fraction of the animation's duration.
+///
+/// https://www.w3.org/TR/css-animations-1/#typedef-keyframe-selector
+pub fn parse_keyframe_selector<'i>(parser: &mut Parser<'i, '_>) -> Result<f64, ParseError<'i>> {
+    let loc = parser.current_source_location();
+    let token = parser.next()?;
+
+    match *token {
+        Token::Ident(ref cow) if cow.eq_ignore_ascii_case("from") => Ok(0.0),
+        Token::Ident(ref cow) if cow.eq_ignore_ascii_case("to") => Ok(1.0),
+        Token::Percentage { unit_value, .. } if (0.0..=1.0).contains(&unit_value) => {
+            Ok(f64::from(unit_value))
+        }
+
+        _ => Err(loc.new_basic_unexpected_token_error(token.clone()).into()),
+    }
+}
+
+/// Position of the jumps in a `steps()` timing function.
+///
+/// https://www.w3.org/TR/css-easing-1/#step-position
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum StepPosition {
+    Start,
+    End,
+    None,
+    Both,
+}
+
+impl Parse for StepPosition {
+    fn parse<'i>(parser: &mut Parser<'i, '_>) -> Result<StepPosition, ParseError<'i>> {
+        Ok(parse_identifiers!(
+            parser,
+            "jump-start" => StepPosition::Start,
+            "jump-end" => StepPosition::End,
+            "jump-none" => StepPosition::None,
+            "jump-both" => StepPosition::Both,
+            "start" => StepPosition::Start,
+            "end" => StepPosition::End,
+        )?)
+    }
+}
+
+/// A value of the `animation-timing-function` property.
+///
+/// https://www.w3.org/TR/css-easing-1/#easing-functions
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum TimingFunction {
+    Linear,
+    CubicBezier(KeySpline),
+    Steps(u32, StepPosition),
+}
+
+impl Default for TimingFunction {
+    fn default() -> TimingFunction {
+        TimingFunction::CubicBezier(KeySpline::new(0.25, 0.1, 0.25, 1.0))
+    }
+}
+
+impl Parse for TimingFunction {
+    fn parse<'i>(parser: &mut Parser<'i, '_>) -> Result<TimingFunction, ParseError<'i>> {
+        if let Ok(f) = parser.try_parse(|p| {
+            parse_identifiers!(
+                p,
+                "linear" => TimingFunction::Linear,
+                "ease" => TimingFunction::default(),
+                "ease-in" => TimingFunction::CubicBezier(KeySpline::new(0.42, 0.0, 1.0, 1.0)),
+                "ease-out" => TimingFunction::CubicBezier(KeySpline::new(0.0, 0.0, 0.58, 1.0)),
+                "ease-in-out" => TimingFunction::CubicBezier(KeySpline::new(0.42, 0.0, 0.58, 1.0)),
+                "step-start" => TimingFunction::Steps(1, StepPosition::Start),
+                "step-end" => TimingFunction::Steps(1, StepPosition::End),
+            )
+        }) {
+            return Ok(f);
+        }
+
+        let loc = parser.current_source_location();
+        let name = parser.expect_function()?.clone();
+
+        if name.eq_ignore_ascii_case("cubic-bezier") {
+            parser.parse_nested_block(|p| {
+                let mut values = [0.0; 4];
+
+                for (i, value) in values.iter_mut().enumerate() {
+                    if i > 0 {
+                        p.expect_comma()?;
+                    }
+
+                    let loc = p.current_source_location();
+                    *value = f64::parse(p)?;
+
+                    // Only the x coordinates need to be in range, so that the curve is a
+                    // function of time.
+                    if i % 2 == 0 && !(0.0..=1.0).contains(value) {
+                        return Err(loc.new_custom_error(ValueErrorKind::value_error(
+                            "x coordinates in cubic-bezier() must be between 0 and 1",
+                        )));
+                    }
+                }
+
+                let [x1, y1, x2, y2] = values;
+                Ok(TimingFunction::CubicBezier(KeySpline::new(x1, y1, x2, y2)))
+            })
+        } else if name.eq_ignore_ascii_case("steps") {
+            parser.parse_nested_block(|p| {
+                let loc = p.current_source_location();
+                let steps = p.expect_integer()?;
+
+                let position = p
+                    .try_parse(|p| {
+                        p.expect_comma()?;
+                        StepPosition::parse(p)
+                    })
+                    .unwrap_or(StepPosition::End);
+
+                let min_steps = if position == StepPosition::None { 2 } else { 1 };
+
+                if steps < min_steps {
+                    return Err(loc.new_custom_error(ValueErrorKind::value_error(
+                        "not enough steps in steps()",
+                    )));
+                }
+
+                Ok(TimingFunction::Steps(steps as u32, position))
+            })
+        } else {
+            Err(loc.new_unexpected_token_error(Token::Function(name)))
+        }
+    }
+}
+
+impl TimingFunction {
+    /// Maps the fraction of time elapsed between two keyframes to the fraction of the
+    /// change between their values.
+    pub fn apply(&self, t: f64) -> f64 {
+        match *self {
+            TimingFunction::Linear => t,
+
+            TimingFunction::CubicBezier(ref spline) => spline.apply(t),
+
+            TimingFunction::Steps(steps, position) => {
+                let steps = f64::from(steps);
+
+                let jumps = match position {
+                    StepPosition::Start | StepPosition::End => steps,
+                    StepPosition::None => steps - 1.0,
+                    StepPosition::Both => steps + 1.0,
+                };
+
+                let mut step = (t * steps).floor();
+
+                if let StepPosition::Start | StepPosition::Both = position {
+                    step += 1.0;
+                }
+
+                util::clamp(step, 0.0, jumps) / jumps
+            }
+        }
+    }
+}
+
+/// A value of the `animation-direction` property.
+///
+/// https://www.w3.org/TR/css-animations-1/#animation-direction
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum SingleAnimationDirection {
+    Normal,
+    Reverse,
+    Alternate,
+    AlternateReverse,
+}
+
+impl Parse for SingleAnimationDirection {
+    fn parse<'i>(parser: &mut Parser<'i, '_>) -> Result<SingleAnimationDirection, ParseError<'i>> {
+        Ok(parse_identifiers!(
+            parser,
+            "normal" => SingleAnimationDirection::Normal,
+            "reverse" => SingleAnimationDirection::Reverse,
+            "alternate" => SingleAnimationDirection::Alternate,
+            "alternate-reverse" => SingleAnimationDirection::AlternateReverse,
+        )?)
+    }
+}
+
+/// A value of the `animation-fill-mode` property.
+///
+/// https://www.w3.org/TR/css-animations-1/#animation-fill-mode
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum SingleAnimationFillMode {
+    None,
+    Forwards,
+    Backwards,
+    Both,
+}
+
+impl Parse for SingleAnimationFillMode {
+    fn parse<'i>(parser: &mut Parser<'i, '_>) -> Result<SingleAnimationFillMode, ParseError<'i>> {
+        Ok(parse_identifiers!(
+            parser,
+            "none" => SingleAnimationFillMode::None,
+            "forwards" => SingleAnimationFillMode::Forwards,
+            "backwards" => SingleAnimationFillMode::Backwards,
+            "both" => SingleAnimationFillMode::Both,
+        )?)
+    }
+}
+
+/// A value of the `animation-play-state` property.
+///
+/// https://www.w3.org/TR/css-animations-1/#animation-play-state
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum SingleAnimationPlayState {
+    Running,
+    Paused,
+}
+
+impl Parse for SingleAnimationPlayState {
+    fn parse<'i>(parser: &mut Parser<'i, '_>) -> Result<SingleAnimationPlayState, ParseError<'i>> {
+        Ok(parse_identifiers!(
+            parser,
+            "running" => SingleAnimationPlayState::Running,
+            "paused" => SingleAnimationPlayState::Paused,
+        )?)
+    }
+}
+
+/// `animation-name` property.
+///
+/// https://www.w3.org/TR/css-animations-1/#animation-name
+///
+/// Like the other `animation-*` properties, this is a comma-separated list with one value
+/// for each of the animations that run on the element.  A `none` is stored as `None`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnimationName(pub Vec<Option<String>>);
+
+impl Parse for AnimationName {
+    fn parse<'i>(parser: &mut Parser<'i, '_>) -> Result<AnimationName, ParseError<'i>> {
+        Ok(AnimationName(
+            parser.parse_comma_separated(parse_animation_name)?,
+        ))
+    }
+}
+
+/// `animation-duration` property, in seconds.
+///
+/// https://www.w3.org/TR/css-animations-1/#animation-duration
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnimationDuration(pub Vec<f64>);
+
+impl Parse for AnimationDuration {
+    fn parse<'i>(parser: &mut Parser<'i, '_>) -> Result<AnimationDuration, ParseError<'i>> {
+        Ok(AnimationDuration(
+            parser.parse_comma_separated(parse_duration)?,
+        ))
+    }
+}
+
+/// `animation-delay` property, in seconds.
+///
+/// https://www.w3.org/TR/css-animations-1/#animation-delay
+///
+/// A negative delay makes an animation start partway through its cycle.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnimationDelay(pub Vec<f64>);
+
+impl Parse for AnimationDelay {
+    fn parse<'i>(parser: &mut Parser<'i, '_>) -> Result<AnimationDelay, ParseError<'i>> {
+        Ok(AnimationDelay(parser.parse_comma_separated(parse_time)?))
+    }
+}
+
+/// `animation-iteration-count` property.
+///
+/// https://www.w3.org/TR/css-animations-1/#animation-iteration-count
+///
+/// An `infinite` count is stored as infinity.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnimationIterationCount(pub Vec<f64>);
+
+impl Parse for AnimationIterationCount {
+    fn parse<'i>(parser: &mut Parser<'i, '_>) -> Result<AnimationIterationCount, ParseError<'i>> {
+        Ok(AnimationIterationCount(
+            parser.parse_comma_separated(parse_iteration_count)?,
+        ))
+    }
+}
+
+/// `animation-direction` property.
+///
+/// https://www.w3.org/TR/css-animations-1/#animation-direction
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnimationDirection(pub Vec<SingleAnimationDirection>);
+
+impl Parse for AnimationDirection {
+    fn parse<'i>(parser: &mut Parser<'i, '_>) -> Result<AnimationDirection, ParseError<'i>> {
+        Ok(AnimationDirection(
+            parser.parse_comma_separated(SingleAnimationDirection::parse)?,
+        ))
+    }
+}
+
+/// `animation-fill-mode` property.
+///
+/// https://www.w3.org/TR/css-animations-1/#animation-fill-mode
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnimationFillMode(pub Vec<SingleAnimationFillMode>);
+
+impl Parse for AnimationFillMode {
+    fn parse<'i>(parser: &mut Parser<'i, '_>) -> Result<AnimationFillMode, ParseError<'i>> {
+        Ok(AnimationFillMode(
+            parser.parse_comma_separated(SingleAnimationFillMode::parse)?,
+        ))
+    }
+}
+
+/// `animation-play-state` property.
+///
+/// https://www.w3.org/TR/css-animations-1/#animation-play-state
+///
+/// Since librsvg renders a single point in time, a paused animation is rendered as it
+/// was when the document started.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnimationPlayState(pub Vec<SingleAnimationPlayState>);
+
+impl Parse for AnimationPlayState {
+    fn parse<'i>(parser: &mut Parser<'i, '_>) -> Result<AnimationPlayState, ParseError<'i>> {
+        Ok(AnimationPlayState(
+            parser.parse_comma_separated(SingleAnimationPlayState::parse)?,
+        ))
+    }
+}
+
+/// `animation-timing-function` property.
+///
+/// https://www.w3.org/TR/css-animations-1/#animation-timing-function
+///
+/// The timing function applies between each pair of keyframes, not to the whole
+/// animation.  A keyframe can also specify the timing function to the next keyframe.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnimationTimingFunction(pub Vec<TimingFunction>);
+
+impl Parse for AnimationTimingFunction {
+    fn parse<'i>(parser: &mut Parser<'i, '_>) -> Result<AnimationTimingFunction, ParseError<'i>> {
+        Ok(AnimationTimingFunction(
+            parser.parse_comma_separated(TimingFunction::parse)?,
+        ))
+    }
+}
+
+/// One of the animations in the `animation` shorthand property.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SingleAnimation {
+    pub name: Option<String>,
+    pub duration: f64,
+    pub timing_function: TimingFunction,
+    pub delay: f64,
+    pub iteration_count: f64,
+    pub direction: SingleAnimationDirection,
+    pub fill_mode: SingleAnimationFillMode,
+    pub play_state: SingleAnimationPlayState,
+}
+
+impl Default for SingleAnimation {
+    fn default() -> SingleAnimation {
+        SingleAnimation {
+            name: None,
+            duration: 0.0,
+            timing_function: TimingFunction::default(),
+            delay: 0.0,
+            iteration_count: 1.0,
+            direction: SingleAnimationDirection::Normal,
+            fill_mode: SingleAnimationFillMode::None,
+            play_state: SingleAnimationPlayState::Running,
+        }
+    }
+}
+
+impl Parse for SingleAnimation {
+    fn parse<'i>(parser: &mut Parser<'i, '_>) -> Result<SingleAnimation, ParseError<'i>> {
+        let loc = parser.current_source_location();
+
+        let mut name = None;
+        let mut duration = None;
+        let mut timing_function = None;
+        let mut delay = None;
+        let mut iteration_count = None;
+        let mut direction = None;
+        let mut fill_mode = None;
+        let mut play_state = None;
+
+        // The values can come in any order, except that the first time is the duration
+        // and the second one is the delay.  Keywords are tried before the name, so an
+        // animation cannot be called e.g. "infinite" in the shorthand.
+        loop {
+            if duration.is_none() {
+                if let Ok(d) = parser.try_parse(parse_duration) {
+                    duration = Some(d);
+                    continue;
+                }
+            } else if delay.is_none() {
+                if let Ok(d) = parser.try_parse(parse_time) {
+                    delay = Some(d);
+                    continue;
+                }
+            }
+
+            if timing_function.is_none() {
+                if let Ok(t) = parser.try_parse(TimingFunction::parse) {
+                    timing_function = Some(t);
+                    continue;
+                }
+            }
+
+            if iteration_count.is_none() {
+                if let Ok(c) = parser.try_parse(parse_iteration_count) {
+                    iteration_count = Some(c);
+                    continue;
+                }
+            }
+
+            if direction.is_none() {
+                if let Ok(d) = parser.try_parse(SingleAnimationDirection::parse) {
+                    direction = Some(d);
+                    continue;
+                }
+            }
+
+            if fill_mode.is_none() {
+                if let Ok(f) = parser.try_parse(SingleAnimationFillMode::parse) {
+                    fill_mode = Some(f);
+                    continue;
+                }
+            }
+
+            if play_state.is_none() {
+                if let Ok(p) = parser.try_parse(SingleAnimationPlayState::parse) {
+                    play_state = Some(p);
+                    continue;
+                }
+            }
+
+            if name.is_none() {
+                if let Ok(n) = parser.try_parse(parse_animation_name) {
+                    name = Some(n);
+                    continue;
+                }
+            }
+
+            break;
+        }
+
+        if name.is_none()
+            && duration.is_none()
+            && timing_function.is_none()
+            && iteration_count.is_none()
+            && direction.is_none()
+            && fill_mode.is_none()
+            && play_state.is_none()
+        {
+            return Err(loc.new_custom_error(ValueErrorKind::parse_error("invalid animation")));
+        }
+
+        let default = SingleAnimation::default();
+
+        Ok(SingleAnimation {
+            name: name.unwrap_or(default.name),
+            duration: duration.unwrap_or(default.duration),
+            timing_function: timing_function.unwrap_or(default.timing_function),
+            delay: delay.unwrap_or(default.delay),
+            iteration_count: iteration_count.unwrap_or(default.iteration_count),
+            direction: direction.unwrap_or(default.direction),
+            fill_mode: fill_mode.unwrap_or(default.fill_mode),
+            play_state: play_state.unwrap_or(default.play_state),
+        })
+    }
+}
+
+/// `animation` shorthand property.
+///
+/// https://www.w3.org/TR/css-animations-1/#animation
+///
+/// This is a shorthand, which expands to all the `animation-*` longhand properties.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Animation(pub Vec<SingleAnimation>);
+
+impl Parse for Animation {
+    fn parse<'i>(parser: &mut Parser<'i, '_>) -> Result<Animation, ParseError<'i>> {
+        Ok(Animation(
+            parser.parse_comma_separated(SingleAnimation::parse)?,
+        ))
+    }
+}
+
+/// One of the keyframes in a `@keyframes` rule, for a single offset.
+struct Keyframe {
+    /// Fraction of the animation's duration where the keyframe is.
+    offset: f64,
+
+    /// Timing function from this keyframe to the next one, if the keyframe specifies one.
+    timing_function: Option<TimingFunction>,
+
+    declarations: Arc<Vec<Declaration>>,
+}
+
+impl Keyframe {
+    fn declaration(&self, prop_name: &QualName) -> Option<&Declaration> {
+        self.declarations
+            .iter()
+            .rev()
+            .find(|decl| decl.prop_name == *prop_name)
+    }
+
+    /// Computes the values of an element with the declarations in this keyframe.
+    fn compute(&self, specified: &SpecifiedValues, parent: &ComputedValues) -> ComputedValues {
+        let mut specified = specified.clone();
+
+        for decl in self.declarations.iter() {
+            specified.set_parsed_property(&decl.property);
+        }
+
+        let mut values = parent.clone();
+        specified.to_computed_values(&mut values);
+        values
+    }
+}
+
+/// A `@keyframes` rule.
+///
+/// https://www.w3.org/TR/css-animations-1/#keyframes
+pub struct Keyframes {
+    name: String,
+
+    /// Sorted by offset.
+    keyframes: Vec<Keyframe>,
+}
+
+impl Keyframes {
+    /// Creates a `@keyframes` rule from its name and the rules in its block.
+    ///
+    /// Each rule has the offsets from its keyframe selectors, like `0%, 50%`, and its
+    /// declarations.
+    pub fn new(name: String, rules: Vec<(Vec<f64>, Vec<Declaration>)>) -> Keyframes {
+        let mut keyframes = Vec::new();
+
+        for (offsets, declarations) in rules {
+            let mut timing_function = None;
+
+            // Declarations with !important are ignored in keyframes, and so are the
+            // animation properties, except for the timing function to the next keyframe.
+            let declarations: Vec<Declaration> = declarations
+                .into_iter()
+                .filter(|decl| match decl.property {
+                    _ if decl.important => {
+                        rsvg_log!("ignoring !important declaration in @keyframes");
+                        false
+                    }
+
+                    ParsedProperty::AnimationTimingFunction(SpecifiedValue::Specified(
+                        AnimationTimingFunction(ref functions),
+                    )) => {
+                        timing_function = functions.first().cloned();
+                        false
+                    }
+
+                    ParsedProperty::Animation(_)
+                    | ParsedProperty::AnimationDelay(_)
+                    | ParsedProperty::AnimationDirection(_)
+                    | ParsedProperty::AnimationDuration(_)
+                    | ParsedProperty::AnimationFillMode(_)
+                    | ParsedProperty::AnimationIterationCount(_)
+                    | ParsedProperty::AnimationName(_)
+                    | ParsedProperty::AnimationPlayState(_)
+                    | ParsedProperty::AnimationTimingFunction(_) => false,
+
+                    _ => true,
+                })
+                .collect();
+
+            let declarations = Arc::new(declarations);
+
+            for offset in offsets {
+                keyframes.push(Keyframe {
+                    offset,
+                    timing_function,
+                    declarations: declarations.clone(),
+                });
+            }
+        }
+
+        // The sort is stable, so among keyframes with the same offset the later
+        // ones take precedence.
+        keyframes.sort_by(|a, b| util::total_cmp(a.offset, b.offset));
+
+        Keyframes { name, keyframes }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Replaces the computed `values` of the properties in the keyframes with the ones
+    /// at `progress` through the animation.
+    fn apply(
+        &self,
+        progress: f64,
+        timing_function: &TimingFunction,
+        specified: &SpecifiedValues,
+        parent: &ComputedValues,
+        values: &mut ComputedValues,
+    ) {
+        let underlying = values.clone();
+        let mut computed: Vec<Option<ComputedValues>> = vec![None; self.keyframes.len()];
+
+        let mut prop_names: Vec<&QualName> = Vec::new();
+        for keyframe in &self.keyframes {
+            for decl in keyframe.declarations.iter() {
+                if !prop_names.contains(&&decl.prop_name) {
+                    prop_names.push(&decl.prop_name);
+                }
+            }
+        }
+
+        for prop_name in prop_names {
+            // The keyframes that specify the property, plus the implicit ones at 0% and
+            // 100% with the element's own value, which are represented with None.
+            let mut frames: Vec<(f64, Option<usize>)> = Vec::new();
+
+            for (i, keyframe) in self.keyframes.iter().enumerate() {
+                if keyframe.declaration(prop_name).is_some() {
+                    match frames.last_mut() {
+                        Some(last) if last.0 == keyframe.offset => last.1 = Some(i),
+                        _ => frames.push((keyframe.offset, Some(i))),
+                    }
+                }
+            }
+
+            let property = match frames.first() {
+                Some(&(_, Some(i))) => &self.keyframes[i].declaration(prop_name).unwrap().property,
+                _ => continue,
+            };
+
+            if frames[0].0 > 0.0 {
+                frames.insert(0, (0.0, None));
+            }
+
+            if frames[frames.len() - 1].0 < 1.0 {
+                frames.push((1.0, None));
+            }
+
+            let start = frames.iter().rposition(|f| f.0 <= progress).unwrap_or(0);
+            let (from_offset, from) = frames[start];
+            let (to_offset, to) = frames.get(start + 1).cloned().unwrap_or(frames[start]);
+
+            let t = if to_offset > from_offset {
+                (progress - from_offset) / (to_offset - from_offset)
+            } else {
+                0.0
+            };
+
+            let t = from
+                .and_then(|i| self.keyframes[i].timing_function)
+                .unwrap_or(*timing_function)
+                .apply(t);
+
+            for i in [from, to].iter().flatten() {
+                if computed[*i].is_none() {
+                    computed[*i] = Some(self.keyframes[*i].compute(specified, parent));
+                }
+            }
+
+            let values_at = |k: Option<usize>| match k {
+                Some(i) => computed[i].as_ref().unwrap(),
+                None => &underlying,
+            };
+
+            values.set_interpolated(property, values_at(from), values_at(to), t);
+        }
+    }
+}
+
+/// The timing of one of the animations of an element, from its `animation-*` properties.
+struct AnimationTiming {
+    duration: f64,
+    delay: f64,
+    iteration_count: f64,
+    direction: SingleAnimationDirection,
+    fill_mode: SingleAnimationFillMode,
+    play_state: SingleAnimationPlayState,
+    timing_function: TimingFunction,
+}
+
+impl AnimationTiming {
+    /// Gets the timing of the animation at `index` in the `animation-name` list.
+    ///
+    /// The other lists get repeated if they have fewer values than `animation-name`.
+    fn new(values: &ComputedValues, index: usize) -> AnimationTiming {
+        fn nth<T: Clone>(list: &[T], index: usize) -> T {
+            list[index % list.len()].clone()
+        }
+
+        AnimationTiming {
+            duration: nth(&values.animation_duration().0, index),
+            delay: nth(&values.animation_delay().0, index),
+            iteration_count: nth(&values.animation_iteration_count().0, index),
+            direction: nth(&values.animation_direction().0, index),
+            fill_mode: nth(&values.animation_fill_mode().0, index),
+            play_state: nth(&values.animation_play_state().0, index),
+            timing_function: nth(&values.animation_timing_function().0, index),
+        }
+    }
+
+    /// Computes the progress through the current iteration of the animation at `time`,
+    /// taking its direction into account, or `None` if the animation has no effect then.
+    ///
+    /// https://www.w3.org/TR/web-animations-1/#calculating-the-directed-progress
+    fn progress_at(&self, time: f64) -> Option<f64> {
+        let time = match self.play_state {
+            SingleAnimationPlayState::Running => time,
+            SingleAnimationPlayState::Paused => 0.0,
+        };
+
+        let elapsed = time - self.delay;
+
+        let active_duration = if self.duration > 0.0 {
+            self.duration * self.iteration_count
+        } else {
+            0.0
+        };
+
+        let fills_backwards = matches!(
+            self.fill_mode,
+            SingleAnimationFillMode::Backwards | SingleAnimationFillMode::Both
+        );
+
+        let fills_forwards = matches!(
+            self.fill_mode,
+            SingleAnimationFillMode::Forwards | SingleAnimationFillMode::Both
+        );
+
+        let (iteration, progress) = if elapsed < 0.0 {
+            if !fills_backwards {
+                return None;
+            }
+
+            (0.0, 0.0)
+        } else if elapsed >= active_duration {
+            if !fills_forwards {
+                return None;
+            }
+
+            let overall = self.iteration_count;
+            let iteration = overall.floor();
+
+            if overall > 0.0 && overall == iteration {
+                (iteration - 1.0, 1.0)
+            } else {
+                (iteration, overall - iteration)
+            }
+        } else {
+            let overall = elapsed / self.duration;
+            let iteration = overall.floor();
+            (iteration, overall - iteration)
+        };
+
+        let reversed = match self.direction {
+            SingleAnimationDirection::Normal => false,
+            SingleAnimationDirection::Reverse => true,
+            SingleAnimationDirection::Alternate => iteration % 2.0 == 1.0,
+            SingleAnimationDirection::AlternateReverse => iteration % 2.0 == 0.0,
+        };
+
+        if reversed {
+            Some(1.0 - progress)
+        } else {
+            Some(progress)
+        }
+    }
+}
+
+/// The `@keyframes` rules of a document, to be evaluated at a certain time.
+pub struct CssAnimations<'a> {
+    keyframes: Vec<&'a Keyframes>,
+
+    /// Time in seconds, or `None` to render the document without its animations.
+    time: Option<f64>,
+}
+
+impl<'a> CssAnimations<'a> {
+    pub fn new(keyframes: Vec<&'a Keyframes>, time: Option<f64>) -> CssAnimations<'a> {
+        CssAnimations { keyframes, time }
+    }
+
+    /// Replaces the computed `values` of an element with the ones from its animations.
+    ///
+    /// The element's `specified` values and the computed values of its `parent` are
+    /// needed to compute the values in the keyframes.
+    pub fn apply(
+        &self,
+        specified: &SpecifiedValues,
+        parent: &ComputedValues,
+        values: &mut ComputedValues,
+    ) {
+        let time = match self.time {
+            Some(time) if !self.keyframes.is_empty() => time,
+            _ => return,
+        };
+
+        let AnimationName(names) = values.animation_name();
+
+        // Animations later in the list take precedence, so they are applied last.
+        for (index, name) in names.iter().enumerate() {
+            let name = match name {
+                Some(name) => name,
+                None => continue,
+            };
+
+            // If several @keyframes rules have the same name, the last one wins.
+            let keyframes = match self.keyframes.iter().rev().find(|k| k.name == *name) {
+                Some(keyframes) => keyframes,
+                None => continue,
+            };
+
+            let timing = AnimationTiming::new(values, index);
+
+            if let Some(progress) = timing.progress_at(time) {
+                keyframes.apply(progress, &timing.timing_function, specified, parent, values);
+            }
+        }
+    }
+}
+
+/// Values that can be interpolated by animations.
+pub trait Interpolate: Sized {
+    /// Returns the value at `t` from `self` (at 0) to `to` (at 1), or `None` if the
+    /// values cannot be interpolated.
+    fn interpolate(&self, to: &Self, t: f64) -> Option<Self>;
+}
+
+impl Interpolate for f64 {
+    fn interpolate(&self, to: &f64, t: f64) -> Option<f64> {
+        Some(self + (to - self) * t)
+    }
+}
+
+impl Interpolate for UnitInterval {
+    fn interpolate(&self, to: &UnitInterval, t: f64) -> Option<UnitInterval> {
+        Some(UnitInterval::clamp(self.0.interpolate(&to.0, t)?))
+    }
+}
+
+impl Interpolate for Angle {
+    fn interpolate(&self, to: &Angle, t: f64) -> Option<Angle> {
+        Some(Angle::new(self.radians().interpolate(&to.radians(), t)?))
+    }
+}
+
+impl<N: Normalize, V: Validate> Interpolate for CssLength<N, V> {
+    fn interpolate(&self, to: &Self, t: f64) -> Option<Self> {
+        if self.unit == to.unit {
+            Some(CssLength::new(
+                self.length.interpolate(&to.length, t)?,
+                self.unit,
+            ))
+        } else {
+            None
+        }
+    }
+}
+
+impl Interpolate for cssparser::RGBA {
+    fn interpolate(&self, to: &cssparser::RGBA, t: f64) -> Option<cssparser::RGBA> {
+        // Colors are interpolated with premultiplied alpha, so that fading from a
+        // transparent color does not show the transparent color's channels.
+        let alpha = util::clamp(
+            f64::from(self.alpha).interpolate(&f64::from(to.alpha), t)?,
+            0.0,
+            255.0,
+        );
+
+        let channel = |from: u8, to_channel: u8| {
+            if alpha == 0.0 {
+                0
+            } else {
+                let from = f64::from(from) * f64::from(self.alpha);
+                let to_channel = f64::from(to_channel) * f64::from(to.alpha);
+                let v = from.interpolate(&to_channel, t).unwrap() / alpha;
+                util::clamp(v.round(), 0.0, 255.0) as u8
+            }
+        };
+
+        Some(cssparser::RGBA::new(
+            channel(self.red, to.red),
+            channel(self.green, to.green),
+            channel(self.blue, to.blue),
+            alpha.round() as u8,
+        ))
+    }
+}
+
+impl Interpolate for cssparser::Color {
+    fn interpolate(&self, to: &cssparser::Color, t: f64) -> Option<cssparser::Color> {
+        match (self, to) {
+            (cssparser::Color::RGBA(a), cssparser::Color::RGBA(b)) => {
+                Some(cssparser::Color::RGBA(a.interpolate(b, t)?))
+            }
+
+            (cssparser::Color::CurrentColor, cssparser::Color::CurrentColor) => {
+                Some(cssparser::Color::CurrentColor)
+            }
+
+            _ => None,
+        }
+    }
+}
+
+impl Interpolate for PaintServer {
+    fn interpolate(&self, to: &PaintServer, t: f64) -> Option<PaintServer> {
+        match (self, to) {
+            (PaintServer::SolidColor(a), PaintServer::SolidColor(b)) => {
+                Some(PaintServer::SolidColor(a.interpolate(b, t)?))
+            }
+
+            _ => None,
+        }
+    }
+}
+
+impl Interpolate for FontSize {
+    fn interpolate(&self, to: &FontSize, t: f64) -> Option<FontSize> {
+        match (self, to) {
+            (FontSize::Value(a), FontSize::Value(b)) => Some(FontSize::Value(a.interpolate(b, t)?)),
+            _ => None,
+        }
+    }
+}
+
+impl Interpolate for LetterSpacing {
+    fn interpolate(&self, to: &LetterSpacing, t: f64) -> Option<LetterSpacing> {
+        match (self, to) {
+            (LetterSpacing::Value(a), LetterSpacing::Value(b)) => {
+                Some(LetterSpacing::Value(a.interpolate(b, t)?))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl Interpolate for WordSpacing {
+    fn interpolate(&self, to: &WordSpacing, t: f64) -> Option<WordSpacing> {
+        match (self, to) {
+            (WordSpacing::Value(a), WordSpacing::Value(b)) => {
+                Some(WordSpacing::Value(a.interpolate(b, t)?))
+            }
+            _ => None,
+        }
+    }
+}
+
+macro_rules! impl_interpolate_newtype {
+    ($($name:ident),+) => {
+        $(
+            impl Interpolate for $name {
+                fn interpolate(&self, to: &$name, t: f64) -> Option<$name> {
+                    Some($name(self.0.interpolate(&to.0, t)?))
+                }
+            }
+        )+
+    };
+}
+
+impl_interpolate_newtype!(
+    BaselineShift,
+    Color,
+    Fill,
+    FillOpacity,
+    FloodColor,
+    FloodOpacity,
+    LightingColor,
+    Opacity,
+    StopColor,
+    StopOpacity,
+    Stroke,
+    StrokeDashoffset,
+    StrokeMiterlimit,
+    StrokeOpacity,
+    StrokeWidth,
+    TextDecorationColor
+);
+
+impl Interpolate for TransformFunction {
+    fn interpolate(&self, to: &TransformFunction, t: f64) -> Option<TransformFunction> {
+        use TransformFunction::*;
+
+        Some(match (self, to) {
+            (Matrix(a), Matrix(b)) => Matrix(a.interpolate(b, t)?),
+            (Translate(ax, ay), Translate(bx, by)) => {
+                Translate(ax.interpolate(bx, t)?, ay.interpolate(by, t)?)
+            }
+            (TranslateX(a), TranslateX(b)) => TranslateX(a.interpolate(b, t)?),
+            (TranslateY(a), TranslateY(b)) => TranslateY(a.interpolate(b, t)?),
+            (Scale(ax, ay), Scale(bx, by)) => Scale(ax.interpolate(bx, t)?, ay.interpolate(by, t)?),
+            (ScaleX(a), ScaleX(b)) => ScaleX(a.interpolate(b, t)?),
+            (ScaleY(a), ScaleY(b)) => ScaleY(a.interpolate(b, t)?),
+            (Rotate(a), Rotate(b)) => Rotate(a.interpolate(b, t)?),
+            (Skew(ax, ay), Skew(bx, by)) => Skew(ax.interpolate(bx, t)?, ay.interpolate(by, t)?),
+            (SkewX(a), SkewX(b)) => SkewX(a.interpolate(b, t)?),
+            (SkewY(a), SkewY(b)) => SkewY(a.interpolate(b, t)?),
+            _ => return None,
+        })
+    }
+}
+
+/// Returns the identity transform function of the same kind as `f`, for interpolating
+/// to or from `none`.
+fn identity_function(f: &TransformFunction) -> TransformFunction {
+    use TransformFunction::*;
+
+    match *f {
+        Matrix(_) => Matrix(Transform::identity()),
+        Translate(x, y) => Translate(Length::new(0.0, x.unit), Length::new(0.0, y.unit)),
+        TranslateX(x) => TranslateX(Length::new(0.0, x.unit)),
+        TranslateY(y) => TranslateY(Length::new(0.0, y.unit)),
+        Scale(..) => Scale(1.0, 1.0),
+        ScaleX(_) => ScaleX(1.0),
+        ScaleY(_) => ScaleY(1.0),
+        Rotate(_) => Rotate(Angle::new(0.0)),
+        Skew(..) => Skew(Angle::new(0.0), Angle::new(0.0)),
+        SkewX(_) => SkewX(Angle::new(0.0)),
+        SkewY(_) => SkewY(Angle::new(0.0)),
+    }
+}
+
+impl Interpolate for TransformProperty {
+    fn interpolate(&self, to: &TransformProperty, t: f64) -> Option<TransformProperty> {
+        let (from_list, to_list) = match (self, to) {
+            (TransformProperty::None, TransformProperty::None) => {
+                return Some(TransformProperty::None)
+            }
+            (TransformProperty::List(a), TransformProperty::None) => {
+                (a.clone(), a.iter().map(identity_function).collect())
+            }
+            (TransformProperty::None, TransformProperty::List(b)) => {
+                (b.iter().map(identity_function).collect(), b.clone())
+            }
+            (TransformProperty::List(a), TransformProperty::List(b)) => (a.clone(), b.clone()),
+        };
+
+        // Lists with the same kinds of functions get interpolated function by function;
+        // otherwise, the whole matrices get interpolated.
+        if from_list.len() == to_list.len() {
+            let functions: Option<Vec<TransformFunction>> = from_list
+                .iter()
+                .zip(to_list.iter())
+                .map(|(a, b)| a.interpolate(b, t))
+                .collect();
+
+            if let Some(functions) = functions {
+                return Some(TransformProperty::List(functions));
+            }
+        }
+
+        let from = TransformProperty::List(from_list).to_transform();
+        let to = TransformProperty::List(to_list).to_transform();
+
+        Some(TransformProperty::List(vec![TransformFunction::Matrix(
+            from.interpolate(&to, t)?,
+        )]))
+    }
+}
+
+/// A 2D transform decomposed into translation, rotation, a remaining matrix with the
+/// skew, and scale, for interpolation.
+///
+/// https://www.w3.org/TR/css-transforms-1/#interpolation-of-2d-matrices
+struct DecomposedTransform {
+    translate: (f64, f64),
+    angle: f64,
+    skew: [f64; 4],
+    scale: (f64, f64),
+}
+
+impl DecomposedTransform {
+    fn new(transform: &Transform) -> DecomposedTransform {
+        let (mut c0x, mut c0y) = (transform.xx, transform.yx);
+        let (mut c1x, mut c1y) = (transform.xy, transform.yy);
+
+        let mut sx = c0x.hypot(c0y);
+        let mut sy = c1x.hypot(c1y);
+
+        // If the transform flips the coordinate system, one of the scales is negative.
+        if c0x * c1y - c0y * c1x < 0.0 {
+            if c0x < c1y {
+                sx = -sx;
+            } else {
+                sy = -sy;
+            }
+        }
+
+        if sx != 0.0 {
+            c0x /= sx;
+            c0y /= sx;
+        }
+
+        if sy != 0.0 {
+            c1x /= sy;
+            c1y /= sy;
+        }
+
+        let angle = c0y.atan2(c0x);
+        let (sin, cos) = angle.sin_cos();
+
+        // Rotate back by the angle, so only the skew remains.
+        let skew = [
+            cos * c0x + sin * c0y,
+            -sin * c0x + cos * c0y,
+            cos * c1x + sin * c1y,
+            -sin * c1x + cos * c1y,
+        ];
+
+        DecomposedTransform {
+            translate: (transform.x0, transform.y0),
+            angle,
+            skew,
+            scale: (sx, sy),
+        }
+    }
+
+    fn to_transform(&self) -> Transform {
+        let [xx, yx, xy, yy] = self.skew;
+
+        let scale = Transform::new_scale(self.scale.0, self.scale.1);
+        let skew = Transform::new_unchecked(xx, yx, xy, yy, 0.0, 0.0);
+        let rotate = Transform::new_rotate(Angle::new(self.angle));
+        let translate = Transform::new_translate(self.translate.0, self.translate.1);
+
+        [skew, rotate, translate]
+            .iter()
+            .fold(scale, |acc, t| Transform::multiply(&acc, t))
+    }
+}
+
+impl Interpolate for Transform {
+    fn interpolate(&self, to: &Transform, t: f64) -> Option<Transform> {
+        let mut a = DecomposedTransform::new(self);
+        let mut b = DecomposedTransform::new(to);
+
+        // If the transforms flip along different axes, rotate one of them half a turn
+        // instead of scaling it through zero.
+        if (a.scale.0 < 0.0 && b.scale.1 < 0.0) || (a.scale.1 < 0.0 && b.scale.0 < 0.0) {
+            a.scale = (-a.scale.0, -a.scale.1);
+            a.angle += if a.angle < 0.0 { PI } else { -PI };
+        }
+
+        // Don't rotate the long way around.
+        if a.angle == 0.0 {
+            a.angle = 2.0 * PI;
+        }
+
+        if b.angle == 0.0 {
+            b.angle = 2.0 * PI;
+        }
+
+        if (a.angle - b.angle).abs() > PI {
+            if a.angle > b.angle {
+                a.angle -= 2.0 * PI;
+            } else {
+                b.angle -= 2.0 * PI;
+            }
+        }
+
+        let lerp = |from: f64, to: f64| from.interpolate(&to, t).unwrap();
+
+        let mut skew = [0.0; 4];
+        for (i, s) in skew.iter_mut().enumerate() {
+            *s = lerp(a.skew[i], b.skew[i]);
+        }
+
+        let decomposed = DecomposedTransform {
+            translate: (
+                lerp(a.translate.0, b.translate.0),
+                lerp(a.translate.1, b.translate.1),
+            ),
+            angle: lerp(a.angle, b.angle),
+            skew,
+            scale: (lerp(a.scale.0, b.scale.0), lerp(a.scale.1, b.scale.1)),
+        };
+
+        Some(decomposed.to_transform())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::float_eq_cairo::ApproxEqCairo;
+
+    #[test]
+    fn parses_timing_functions() {
+        assert_eq!(
+            TimingFunction::parse_str("linear").unwrap(),
+            TimingFunction::Linear
+        );
+        assert_eq!(
+            TimingFunction::parse_str("ease").unwrap(),
+            TimingFunction::CubicBezier(KeySpline::new(0.25, 0.1, 0.25, 1.0))
+        );
+        assert_eq!(
+            TimingFunction::parse_str("cubic-bezier(0.25, -0.5, 0.75, 1.5)").unwrap(),
+            TimingFunction::CubicBezier(KeySpline::new(0.25, -0.5, 0.75, 1.5))
+        );
+        assert_eq!(
+            TimingFunction::parse_str("steps(4)").unwrap(),
+            TimingFunction::Steps(4, StepPosition::End)
+        );
+        assert_eq!(
+            TimingFunction::parse_str("steps(2, jump-none)").unwrap(),
+            TimingFunction::Steps(2, StepPosition::None)
+        );
+        assert_eq!(
+            TimingFunction::parse_str("step-start").unwrap(),
+            TimingFunction::Steps(1, StepPosition::Start)
+        );
+
+        assert!(TimingFunction::parse_str("cubic-bezier(2, 0, 1, 1)").is_err());
+        assert!(TimingFunction::parse_str("steps(0)").is_err());
+        assert!(TimingFunction::parse_str("steps(1, jump-none)").is_err());
+        assert!(TimingFunction::parse_str("bouncy").is_err());
+    }
+
+    #[test]
+    fn applies_timing_functions() {
+        assert_eq!(TimingFunction::Linear.apply(0.3), 0.3);
+
+        let steps = TimingFunction::Steps(4, StepPosition::End);
+        assert_eq!(steps.apply(0.0), 0.0);
+        assert_eq!(steps.apply(0.3), 0.25);
+        assert_eq!(steps.apply(1.0), 1.0);
+
+        let steps = TimingFunction::Steps(4, StepPosition::Start);
+        assert_eq!(steps.apply(0.0), 0.25);
+        assert_eq!(steps.apply(0.3), 0.5);
+
+        let steps = TimingFunction::Steps(3, StepPosition::None);
+        assert_eq!(steps.apply(0.5), 0.5);
+
+        let ease_in = TimingFunction::parse_str("ease-in").unwrap();
+        assert!(ease_in.apply(0.5) < 0.5);
+        assert!(ease_in.apply(1.0).approx_eq_cairo(1.0));
+    }
+
+    #[test]
+    fn parses_animation_properties() {
+        assert_eq!(
+            AnimationName::parse_str("spin, none, \"fade out\"").unwrap(),
+            AnimationName(vec![
+                Some("spin".to_string()),
+                None,
+                Some("fade out".to_string())
+            ])
+        );
+        assert_eq!(
+            AnimationDuration::parse_str("1s, 250ms").unwrap(),
+            AnimationDuration(vec![1.0, 0.25])
+        );
+        assert!(AnimationDuration::parse_str("-1s").is_err());
+        assert!(AnimationDuration::parse_str("1").is_err());
+        assert_eq!(
+            AnimationDelay::parse_str("-0.5s").unwrap(),
+            AnimationDelay(vec![-0.5])
+        );
+        assert_eq!(
+            AnimationIterationCount::parse_str("infinite, 2.5").unwrap(),
+            AnimationIterationCount(vec![f64::INFINITY, 2.5])
+        );
+        assert_eq!(
+            AnimationDirection::parse_str("alternate-reverse").unwrap(),
+            AnimationDirection(vec![SingleAnimationDirection::AlternateReverse])
+        );
+    }
+
+    #[test]
+    fn parses_animation_shorthand() {
+        assert_eq!(
+            Animation::parse_str("spin 2s linear 1s infinite alternate both").unwrap(),
+            Animation(vec![SingleAnimation {
+                name: Some("spin".to_string()),
+                duration: 2.0,
+                timing_function: TimingFunction::Linear,
+                delay: 1.0,
+                iteration_count: f64::INFINITY,
+                direction: SingleAnimationDirection::Alternate,
+                fill_mode: SingleAnimationFillMode::Both,
+                play_state: SingleAnimationPlayState::Running,
+            }])
+        );
+
+        assert_eq!(
+            Animation::parse_str("3s fade, paused 500ms spin").unwrap(),
+            Animation(vec![
+                SingleAnimation {
+                    name: Some("fade".to_string()),
+                    duration: 3.0,
+                    ..SingleAnimation::default()
+                },
+                SingleAnimation {
+                    name: Some("spin".to_string()),
+                    duration: 0.5,
+                    play_state: SingleAnimationPlayState::Paused,
+                    ..SingleAnimation::default()
+                },
+            ])
+        );
+
+        assert!(Animation::parse_str("spin 1s 2s 3s").is_err());
+    }
+
+    #[test]
+    fn parses_keyframe_selectors() {
+        let parse = |s| {
+            let mut input = cssparser::ParserInput::new(s);
+            let mut parser = Parser::new(&mut input);
+            parser.parse_comma_separated(parse_keyframe_selector)
+        };
+
+        assert_eq!(parse("from, 50%, to").unwrap(), vec![0.0, 0.5, 1.0]);
+        assert!(parse("150%").is_err());
+        assert!(parse("0.5").is_err());
+    }
+
+    fn timing(duration: f64, delay: f64, iteration_count: f64) -> AnimationTiming {
+        AnimationTiming {
+            duration,
+            delay,
+            iteration_count,
+            direction: SingleAnimationDirection::Normal,
+            fill_mode: SingleAnimationFillMode::None,
+            play_state: SingleAnimationPlayState::Running,
+            timing_function: TimingFunction::Linear,
+        }
+    }
+
+    #[test]
+    fn computes_progress() {
+        let t = timing(2.0, 1.0, 2.0);
+        assert_eq!(t.progress_at(0.5), None);
+        assert_eq!(t.progress_at(2.0), Some(0.5));
+        assert_eq!(t.progress_at(4.0), Some(0.5));
+        assert_eq!(t.progress_at(5.0), None);
+
+        let t = AnimationTiming {
+            fill_mode: SingleAnimationFillMode::Both,
+            direction: SingleAnimationDirection::Alternate,
+            ..timing(2.0, 1.0, 2.0)
+        };
+        assert_eq!(t.progress_at(0.5), Some(0.0));
+        assert_eq!(t.progress_at(3.5), Some(0.75));
+        assert_eq!(t.progress_at(10.0), Some(0.0));
+
+        let t = AnimationTiming {
+            play_state: SingleAnimationPlayState::Paused,
+            ..timing(4.0, -1.0, 1.0)
+        };
+        assert_eq!(t.progress_at(10.0), Some(0.25));
+
+        let t = timing(1.0, 0.0, f64::INFINITY);
+        assert_eq!(t.progress_at(1000.25), Some(0.25));
+    }
+
+    #[test]
+    fn interpolates_values() {
+        let red = cssparser::RGBA::new(255, 0, 0, 255);
+        let blue = cssparser::RGBA::new(0, 0, 255, 255);
+        assert_eq!(
+            red.interpolate(&blue, 0.5),
+            Some(cssparser::RGBA::new(128, 0, 128, 255))
+        );
+
+        let transparent = cssparser::RGBA::new(0, 255, 0, 0);
+        assert_eq!(
+            transparent.interpolate(&blue, 0.5),
+            Some(cssparser::RGBA::new(0, 0, 255, 128))
+        );
+
+        let a = Length::<Both>::new(10.0, LengthUnit::Px);
+        let b = Length::<Both>::new(20.0, LengthUnit::Px);
+        assert_eq!(
+            a.interpolate(&b, 0.25),
+            Some(Length::<Both>::new(12.5, LengthUnit::Px))
+        );
+
+        let c = Length::<Both>::new(20.0, LengthUnit::Percent);
+        assert_eq!(a.interpolate(&c, 0.25), None);
+    }
+
+    #[test]
+    fn interpolates_transforms() {
+        let a = TransformProperty::parse_str("translate(10px, 0) rotate(0deg)").unwrap();
+        let b = TransformProperty::parse_str("translate(30px, 10px) rotate(90deg)").unwrap();
+        let t = a.interpolate(&b, 0.5).unwrap().to_transform();
+        let expected = TransformProperty::parse_str("translate(20px, 5px) rotate(45deg)")
+            .unwrap()
+            .to_transform();
+        assert!(t.xx.approx_eq_cairo(expected.xx));
+        assert!(t.yx.approx_eq_cairo(expected.yx));
+        assert!(t.x0.approx_eq_cairo(expected.x0));
+        assert!(t.y0.approx_eq_cairo(expected.y0));
+
+        let none = TransformProperty::None;
+        let scale = TransformProperty::parse_str("scale(3)").unwrap();
+        assert_eq!(
+            none.interpolate(&scale, 0.5).unwrap(),
+            TransformProperty::parse_str("scale(2)").unwrap()
+        );
+
+        // Mismatched lists get interpolated through their matrices
+        let a = TransformProperty::parse_str("scale(2)").unwrap();
+        let b = TransformProperty::parse_str("translate(10px, 0) scale(4)").unwrap();
+        let t = a.interpolate(&b, 0.5).unwrap().to_transform();
+        assert!(t.xx.approx_eq_cairo(3.0));
+        assert!(t.yy.approx_eq_cairo(3.0));
+        assert!(t.x0.approx_eq_cairo(5.0));
+    }
+
+    #[test]
+    fn decomposes_transforms() {
+        let t = Transform::new_unchecked(1.0, 2.0, -3.0, 4.0, 5.0, 6.0);
+        let r = DecomposedTransform::new(&t).to_transform();
+
+        assert!(r.xx.approx_eq_cairo(t.xx));
+        assert!(r.yx.approx_eq_cairo(t.yx));
+        assert!(r.xy.approx_eq_cairo(t.xy));
+        assert!(r.yy.approx_eq_cairo(t.yy));
+        assert!(r.x0.approx_eq_cairo(t.x0));
+        assert!(r.y0.approx_eq_cairo(t.y0));
+
+        let rotated = Transform::new_rotate(Angle::from_degrees(90.0));
+        let half = Transform::identity().interpolate(&rotated, 0.5).unwrap();
+        let expected = Transform::new_rotate(Angle::from_degrees(45.0));
+        assert!(half.xx.approx_eq_cairo(expected.xx));
+        assert!(half.yx.approx_eq_cairo(expected.yx));
+    }
+}
@@ -2,9 +2,11 @@
 //!
 //! The idea is to take the DOM tree and produce a layout tree with SVG concepts.
 
+use float_cmp::approx_eq;
 use std::rc::Rc;
 
 use crate::aspect_ratio::AspectRatio;
+use crate::basic_shape::ShapeClip;
 use crate::coord_units::CoordUnits;
 use crate::dasharray::Dasharray;
 use crate::document::AcquiredNodes;
@@ -14,12 +16,16 @@ use crate::node::*;
 use crate::paint_server::PaintSource;
 use crate::path_builder::Path;
 use crate::properties::{
-    ClipRule, ComputedValues, Direction, FillRule, Filter, FontFamily, FontStretch, FontStyle,
-    FontVariant, FontWeight, MixBlendMode, Opacity, Overflow, PaintOrder, ShapeRendering,
-    StrokeDasharray, StrokeLinecap, StrokeLinejoin, StrokeMiterlimit, TextDecoration,
-    TextRendering, UnicodeBidi, WritingMode, XmlLang,
+    ClipPath, ClipRule, ColorInterpolation, ComputedValues, Direction, FillRule, Filter,
+    FontFamily, FontFeatureSettings, FontKerning, FontOpticalSizing, FontStretch, FontStyle,
+    FontVariant, FontVariantCaps, FontVariantEastAsian, FontVariantLigatures, FontVariantNumeric,
+    FontVariationSettings, FontWeight, Isolation, MaskMode, MixBlendMode, Opacity, Overflow,
+    PaintOrder, ShapeRendering, StrokeDasharray, StrokeLinecap, StrokeLinejoin, StrokeMiterlimit,
+    TextDecorationStyle, TextOrientation, TextRendering, UnicodeBidi, VectorEffect, WritingMode,
+    XmlLang,
 };
 use crate::rect::Rect;
+use crate::structure::ViewSpec;
 use crate::surface_utils::shared_surface::SharedImageSurface;
 use crate::transform::Transform;
 use crate::unit_interval::UnitInterval;
@@ -44,8 +50,13 @@ pub struct StackingContext {
     pub filter: Filter,
     pub clip_in_user_space: Option<Node>,
     pub clip_in_object_space: Option<Node>,
+    pub clip_shape: Option<ShapeClip>,
     pub mask: Option<Node>,
+    pub mask_mode: MaskMode,
     pub mix_blend_mode: MixBlendMode,
+    pub isolation: Isolation,
+    pub color_interpolation: ColorInterpolation,
+    pub vector_effect: VectorEffect,
 }
 
 /// Stroke parameters in user-space coordinates.
@@ -56,6 +67,9 @@ pub struct Stroke {
     pub line_join: StrokeLinejoin,
     pub dash_offset: f64,
     pub dashes: Box<[f64]>,
+
+    /// Whether to stroke in the host coordinate space, for `vector-effect: non-scaling-stroke`.
+    pub non_scaling: bool,
 }
 
 /// Paths and basic shapes resolved to a path.
@@ -93,32 +107,112 @@ pub struct Image {
     pub overflow: Overflow,
 }
 
+/// A view of another SVG document, referenced by an `<image>` element.
+pub struct SvgImage {
+    pub root: Node,
+    pub view: ViewSpec,
+    pub is_visible: bool,
+    pub rect: Rect,
+    pub aspect: AspectRatio,
+    pub overflow: Overflow,
+}
+
 /// A single text span in user-space coordinates.
 pub struct TextSpan {
     pub layout: pango::Layout,
     pub is_visible: bool,
     pub x: f64,
     pub y: f64,
+    /// Rotation in radians around `(x, y)`, e.g. for glyphs placed along a `<textPath>`.
+    pub rotation: f64,
+    /// Scale factor along the inline direction, e.g. for `lengthAdjust="spacingAndGlyphs"`.
+    pub inline_scale: f64,
     pub stroke: Stroke,
     pub stroke_paint: PaintSource,
     pub fill_paint: PaintSource,
     pub text_rendering: TextRendering,
+    pub decorations: Vec<Decoration>,
+}
+
+/// Which line of `text-decoration-line` a `Decoration` is.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum DecorationLine {
+    Underline,
+    Overline,
+    LineThrough,
+}
+
+/// A line to draw over a text span, from its `text-decoration` or an ancestor's.
+///
+/// Decorations propagate to the text of descendant elements, but they keep the style
+/// of the element that specified them.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Decoration {
+    pub line: DecorationLine,
+    pub style: TextDecorationStyle,
+    pub color: cssparser::RGBA,
+    /// In user-space units; `None` to use the thickness from the font.
+    pub thickness: Option<f64>,
+    /// Offset of underlines from the baseline; `None` to use the font's position.
+    pub underline_offset: Option<f64>,
+}
+
+/// A run of text as it gets rendered, for extracting the text in a document.
+///
+/// Positions are in the coordinate system of the viewport where the document is rendered.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextRun {
+    /// The characters in the run.
+    pub text: String,
+
+    /// `id` attribute of the element that contains the characters, if present.
+    pub id: Option<String>,
+
+    /// Language of the text, from the `xml:lang` attribute.
+    pub lang: Option<String>,
+
+    /// Value of the `font-family` property, with the families separated by commas.
+    pub font_family: String,
+
+    /// Font size in the element's user-space units.
+    pub font_size: f64,
+
+    /// Numeric font weight, where 400 is normal and 700 is bold.
+    pub font_weight: u16,
+
+    /// Whether the font style is italic or oblique.
+    pub italic: bool,
+
+    /// Logical bounds of the whole run.
+    pub bounds: Rect,
+
+    /// Logical bounds of each rendered character, with its byte offset in `text`.
+    pub characters: Vec<(usize, Rect)>,
 }
 
 /// Font-related properties extracted from `ComputedValues`.
 pub struct FontProperties {
     pub xml_lang: XmlLang,
     pub writing_mode: WritingMode,
+    pub text_orientation: TextOrientation,
     pub unicode_bidi: UnicodeBidi,
     pub direction: Direction,
     pub font_family: FontFamily,
     pub font_style: FontStyle,
     pub font_variant: FontVariant,
+    pub font_variant_caps: FontVariantCaps,
+    pub font_variant_ligatures: FontVariantLigatures,
+    pub font_variant_numeric: FontVariantNumeric,
+    pub font_variant_east_asian: FontVariantEastAsian,
+    pub font_feature_settings: FontFeatureSettings,
+    pub font_kerning: FontKerning,
+    pub font_variation_settings: FontVariationSettings,
+    pub font_optical_sizing: FontOpticalSizing,
     pub font_weight: FontWeight,
     pub font_stretch: FontStretch,
     pub font_size: f64,
     pub letter_spacing: f64,
-    pub text_decoration: TextDecoration,
+    pub word_spacing: f64,
 }
 
 impl StackingContext {
@@ -147,12 +241,16 @@ impl StackingContext {
             }
         }
 
-        let clip_path = values.clip_path();
-        let clip_uri = clip_path.0.get();
+        let (clip_uri, clip_shape) = match values.clip_path() {
+            ClipPath::None => (None, None),
+            ClipPath::Url(node_id) => (Some(node_id), None),
+            ClipPath::Shape(shape) => (None, Some(*shape)),
+        };
+
         let (clip_in_user_space, clip_in_object_space) = clip_uri
             .and_then(|node_id| {
                 acquired_nodes
-                    .acquire(node_id)
+                    .acquire(&node_id)
                     .ok()
                     .filter(|a| is_element_of_type!(*a.get(), ClipPath))
             })
@@ -195,7 +293,11 @@ impl StackingContext {
             }
         });
 
+        let mask_mode = values.mask_mode();
         let mix_blend_mode = values.mix_blend_mode();
+        let isolation = values.isolation();
+        let color_interpolation = values.color_interpolation();
+        let vector_effect = values.vector_effect();
 
         StackingContext {
             element_name,
@@ -204,10 +306,37 @@ impl StackingContext {
             filter,
             clip_in_user_space,
             clip_in_object_space,
+            clip_shape,
             mask,
+            mask_mode,
             mix_blend_mode,
+            isolation,
+            color_interpolation,
+            vector_effect,
         }
     }
+
+    /// Whether the element must be rendered as an isolated group.
+    ///
+    /// Its contents then get drawn to a temporary surface, so that descendants with
+    /// `mix-blend-mode` blend only with each other, and the result is composited with
+    /// the backdrop in a single step.
+    ///
+    /// https://www.w3.org/TR/compositing/#csscompositingrules_SVG
+    pub fn should_isolate(&self) -> bool {
+        let Opacity(UnitInterval(opacity)) = self.opacity;
+        let is_opaque = approx_eq!(f64, opacity, 1.0);
+
+        !(is_opaque
+            && self.filter == Filter::None
+            && self.mask.is_none()
+            && self.mix_blend_mode == MixBlendMode::Normal
+            && self.isolation == Isolation::Auto
+            // Clips that depend on the bounding box can only be applied once the contents
+            // are drawn; clip_in_user_space is applied directly before drawing.
+            && self.clip_in_object_space.is_none()
+            && self.clip_shape.is_none())
+    }
 }
 
 impl Stroke {
@@ -226,6 +355,8 @@ impl Stroke {
                 .collect::<Box<[f64]>>(),
         };
 
+        let non_scaling = values.vector_effect().non_scaling_stroke;
+
         Stroke {
             width,
             miter_limit,
@@ -233,6 +364,7 @@ impl Stroke {
             line_join,
             dash_offset,
             dashes,
+            non_scaling,
         }
     }
 }
@@ -242,16 +374,25 @@ impl FontProperties {
         FontProperties {
             xml_lang: values.xml_lang(),
             writing_mode: values.writing_mode(),
+            text_orientation: values.text_orientation(),
             unicode_bidi: values.unicode_bidi(),
             direction: values.direction(),
             font_family: values.font_family(),
             font_style: values.font_style(),
             font_variant: values.font_variant(),
+            font_variant_caps: values.font_variant_caps(),
+            font_variant_ligatures: values.font_variant_ligatures(),
+            font_variant_numeric: values.font_variant_numeric(),
+            font_variant_east_asian: values.font_variant_east_asian(),
+            font_feature_settings: values.font_feature_settings(),
+            font_kerning: values.font_kerning(),
+            font_variation_settings: values.font_variation_settings(),
+            font_optical_sizing: values.font_optical_sizing(),
             font_weight: values.font_weight(),
             font_stretch: values.font_stretch(),
             font_size: values.font_size().to_user(params),
             letter_spacing: values.letter_spacing().to_user(params),
-            text_decoration: values.text_decoration(),
+            word_spacing: values.word_spacing().to_user(params),
         }
     }
 }
@@ -2,8 +2,10 @@
 //!
 //! This crate can load SVG images and render them to Cairo surfaces,
 //! using a mixture of SVG's [static mode] and [secure static mode].
-//! Librsvg does not do animation nor scripting, and can load
-//! references to external data only in some situations; see below.
+//! Librsvg does not do scripting, and can load references to external
+//! data only in some situations; see below.  It does not play animations,
+//! but it can render SMIL and CSS animations at a given point in time with
+//! [`CairoRenderer::at_time`].
 //!
 //! Librsvg supports reading [SVG 1.1] data, and is gradually adding
 //! support for features in [SVG 2].  Librsvg also supports SVGZ
@@ -177,8 +179,10 @@ mod util;
 
 mod accept_language;
 mod angle;
+mod animation;
 mod api;
 mod aspect_ratio;
+mod basic_shape;
 mod bbox;
 pub mod c_api;
 mod color;
@@ -193,17 +197,21 @@ mod error;
 mod filter;
 mod filter_func;
 pub mod filters;
+mod font_face;
 mod font_props;
 mod gradient;
 mod handle;
+mod hatch;
 mod href;
 mod image;
 mod io;
 mod iri;
+mod keyframes;
 mod layout;
 mod length;
 mod limits;
 mod marker;
+mod mesh_gradient;
 mod paint_server;
 mod path_builder;
 mod path_parser;
@@ -216,6 +224,7 @@ mod space;
 mod structure;
 mod style;
 pub mod surface_utils;
+mod svg_font;
 mod text;
 mod transform;
 mod unit_interval;
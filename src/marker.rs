@@ -671,7 +671,7 @@ pub fn render_markers_for_shape(
                     x,
                     y,
                     computed_angle,
-                    shape.stroke.width,
+                    draw_ctx.stroke_width_in_user_space(&shape.stroke),
                     clipping,
                     marker_type,
                 )
@@ -0,0 +1,939 @@
+//! Mesh gradient paint servers; the `meshgradient`, `meshrow`, and `meshpatch` elements.
+//!
+//! A mesh gradient is a grid of patches, where each patch is bounded by four Bézier curves
+//! and has a color at each of its corners.  The `<stop>` elements inside a `<meshpatch>`
+//! define the sides of the patch with their `path` attribute, and the color at the start
+//! of each side with their `stop-color` and `stop-opacity` properties.
+//!
+//! Sides that are shared with the patch above or to the left are not repeated, so the
+//! first patch has four stops, the rest of the first row and the first patch of each
+//! following row have three, and all the other patches have two.
+//!
+//! See https://www.w3.org/TR/2016/CR-SVG2-20160915/pservers.html#MeshGradients
+
+use cssparser::{Parser, Token, RGBA};
+use markup5ever::{expanded_name, local_name, namespace_url, ns};
+
+use crate::bbox::BoundingBox;
+use crate::document::{AcquiredNodes, NodeId, NodeStack};
+use crate::drawing_ctx::ViewParams;
+use crate::element::{Draw, Element, ElementResult, SetAttributes};
+use crate::error::*;
+use crate::gradient::GradientUnits;
+use crate::href::{is_href, set_href};
+use crate::length::*;
+use crate::node::{CascadedValues, Node, NodeBorrow};
+use crate::paint_server::resolve_color;
+use crate::parsers::{NumberList, Parse, ParseValue};
+use crate::properties::ComputedValues;
+use crate::transform::{Transform, TransformAttribute};
+use crate::unit_interval::UnitInterval;
+use crate::util;
+use crate::xml::Attributes;
+
+/// Number of pieces into which each side of a patch is split for bicubic interpolation.
+///
+/// Cairo only interpolates colors bilinearly within a patch, so bicubic meshes are
+/// rendered with smaller patches whose corner colors come from the bicubic interpolation.
+const BICUBIC_SUBDIVISIONS: usize = 4;
+
+/// `type` attribute of mesh gradients
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum MeshType {
+    Bilinear,
+    Bicubic,
+}
+
+enum_default!(MeshType, MeshType::Bilinear);
+
+impl Parse for MeshType {
+    fn parse<'i>(parser: &mut Parser<'i, '_>) -> Result<MeshType, ParseError<'i>> {
+        Ok(parse_identifiers!(
+            parser,
+            "bilinear" => MeshType::Bilinear,
+            "bicubic" => MeshType::Bicubic,
+        )?)
+    }
+}
+
+/// One side of a mesh patch, from the `path` attribute of a `<stop>`.
+///
+/// This is a single `l`, `L`, `c`, or `C` path command.  The end point can be omitted for
+/// sides that end at a corner that is already known, like the last side of a patch.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct MeshSide {
+    relative: bool,
+    controls: Option<((f64, f64), (f64, f64))>,
+    end: Option<(f64, f64)>,
+}
+
+impl Parse for MeshSide {
+    fn parse<'i>(parser: &mut Parser<'i, '_>) -> Result<MeshSide, ParseError<'i>> {
+        let loc = parser.current_source_location();
+
+        let command = parser.expect_ident()?.clone();
+        let (relative, is_curve) = match command.as_ref() {
+            "l" => (true, false),
+            "L" => (false, false),
+            "c" => (true, true),
+            "C" => (false, true),
+            _ => return Err(loc.new_unexpected_token_error(Token::Ident(command))),
+        };
+
+        let NumberList::<0, 6>(v) = if parser.is_exhausted() {
+            NumberList(Vec::new())
+        } else {
+            NumberList::parse(parser)?
+        };
+
+        let (controls, end) = match (is_curve, v.len()) {
+            (false, 0) => (None, None),
+            (false, 2) => (None, Some((v[0], v[1]))),
+            (true, 4) => (Some(((v[0], v[1]), (v[2], v[3]))), None),
+            (true, 6) => (Some(((v[0], v[1]), (v[2], v[3]))), Some((v[4], v[5]))),
+            _ => {
+                return Err(loc.new_custom_error(ValueErrorKind::value_error(
+                    "wrong number of coordinates for a mesh patch side",
+                )))
+            }
+        };
+
+        Ok(MeshSide {
+            relative,
+            controls,
+            end,
+        })
+    }
+}
+
+/// A `<stop>` inside a `<meshpatch>`: one side of the patch and the color at its start.
+#[derive(Copy, Clone)]
+struct MeshStop {
+    side: MeshSide,
+    rgba: RGBA,
+}
+
+/// Node for the `<meshgradient>` element
+#[derive(Default)]
+pub struct MeshGradient {
+    units: Option<GradientUnits>,
+    transform: Option<Transform>,
+    x: Option<Length<Horizontal>>,
+    y: Option<Length<Vertical>>,
+    mesh_type: Option<MeshType>,
+
+    fallback: Option<NodeId>,
+}
+
+/// Node for the `<meshrow>` element
+#[derive(Default)]
+pub struct MeshRow;
+
+/// Node for the `<meshpatch>` element
+#[derive(Default)]
+pub struct MeshPatch;
+
+/// Mesh gradient during resolution of its `href` fallbacks.  Fields that are `None`
+/// were not specified.
+struct UnresolvedMeshGradient {
+    units: Option<GradientUnits>,
+    transform: Option<Transform>,
+    x: Option<Length<Horizontal>>,
+    y: Option<Length<Vertical>>,
+    mesh_type: Option<MeshType>,
+    rows: Option<Vec<Vec<Vec<MeshStop>>>>,
+}
+
+/// Resolved mesh gradient; this is memoizable after the initial resolution.
+#[derive(Clone)]
+pub struct ResolvedMeshGradient {
+    units: GradientUnits,
+    transform: Transform,
+    x: Length<Horizontal>,
+    y: Length<Vertical>,
+    mesh_type: MeshType,
+    rows: Vec<Vec<Vec<MeshStop>>>,
+}
+
+/// Control points of a cubic Bézier curve.
+type BezierCurve = [(f64, f64); 4];
+
+/// Control points of a tensor-product Bézier patch, by row.
+type PatchPoints = [[(f64, f64); 4]; 4];
+
+/// A tensor-product Bézier patch with a color at each corner.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TensorPatch {
+    /// Control points by row, so the corners are `points[0][0]`, `points[0][3]`,
+    /// `points[3][3]`, and `points[3][0]`.
+    pub points: PatchPoints,
+
+    /// Colors of the corners, in the same order as above.
+    pub colors: [RGBA; 4],
+}
+
+/// Mesh gradient normalized to user-space units.
+pub struct UserSpaceMeshGradient {
+    pub transform: Transform,
+    pub patches: Vec<TensorPatch>,
+}
+
+impl SetAttributes for MeshGradient {
+    fn set_attributes(&mut self, attrs: &Attributes) -> ElementResult {
+        for (attr, value) in attrs.iter() {
+            match attr.expanded() {
+                expanded_name!("", "gradientUnits") => self.units = attr.parse(value)?,
+                expanded_name!("", "gradientTransform") => {
+                    let transform_attr: TransformAttribute = attr.parse(value)?;
+                    self.transform = Some(transform_attr.to_transform());
+                }
+                expanded_name!("", "x") => self.x = attr.parse(value)?,
+                expanded_name!("", "y") => self.y = attr.parse(value)?,
+                expanded_name!("", "type") => self.mesh_type = attr.parse(value)?,
+                ref a if is_href(a) => {
+                    set_href(
+                        a,
+                        &mut self.fallback,
+                        NodeId::parse(value).attribute(attr.clone())?,
+                    );
+                }
+                _ => (),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Draw for MeshGradient {}
+
+impl SetAttributes for MeshRow {}
+
+impl Draw for MeshRow {}
+
+impl SetAttributes for MeshPatch {}
+
+impl Draw for MeshPatch {}
+
+impl MeshGradient {
+    fn get_unresolved(&self, node: &Node, opacity: UnitInterval) -> UnresolvedMeshGradient {
+        UnresolvedMeshGradient {
+            units: self.units,
+            transform: self.transform,
+            x: self.x,
+            y: self.y,
+            mesh_type: self.mesh_type,
+            rows: rows_from_node(node, opacity),
+        }
+    }
+
+    pub fn resolve(
+        &self,
+        node: &Node,
+        acquired_nodes: &mut AcquiredNodes<'_>,
+        opacity: UnitInterval,
+    ) -> Result<ResolvedMeshGradient, AcquireError> {
+        let mut gradient = self.get_unresolved(node, opacity);
+        let mut fallback = self.fallback.clone();
+
+        let mut stack = NodeStack::new();
+
+        while !gradient.is_resolved() {
+            if let Some(node_id) = fallback {
+                let acquired = acquired_nodes.acquire(&node_id)?;
+                let acquired_node = acquired.get();
+
+                if stack.contains(acquired_node) {
+                    return Err(AcquireError::CircularReference(acquired_node.clone()));
+                }
+
+                let unresolved = match *acquired_node.borrow_element() {
+                    Element::MeshGradient(ref g) => {
+                        fallback = g.fallback.clone();
+                        g.get_unresolved(acquired_node, opacity)
+                    }
+                    _ => return Err(AcquireError::InvalidLinkType(node_id.clone())),
+                };
+
+                gradient = gradient.resolve_from_fallback(unresolved);
+
+                stack.push(acquired_node);
+            } else {
+                gradient = gradient.resolve_from_defaults();
+                break;
+            }
+        }
+
+        Ok(gradient.into_resolved())
+    }
+}
+
+/// Collects the stops of each `<meshpatch>` in each `<meshrow>` of a `<meshgradient>`.
+///
+/// Returns `None` if the gradient has no rows, so that they can be taken from its fallback.
+fn rows_from_node(node: &Node, opacity: UnitInterval) -> Option<Vec<Vec<Vec<MeshStop>>>> {
+    let children_of = |node: &Node, is_wanted: fn(&Element) -> bool| {
+        node.children().filter(|c| c.is_element()).filter(move |c| {
+            let elt = c.borrow_element();
+            is_wanted(&elt) && !elt.is_in_error()
+        })
+    };
+
+    let rows: Vec<_> = children_of(node, |e| matches!(*e, Element::MeshRow(_)))
+        .map(|row| {
+            children_of(&row, |e| matches!(*e, Element::MeshPatch(_)))
+                .map(|patch| {
+                    children_of(&patch, |e| matches!(*e, Element::Stop(_)))
+                        .filter_map(|stop| mesh_stop_from_node(&stop, opacity))
+                        .collect()
+                })
+                .collect()
+        })
+        .collect();
+
+    if rows.is_empty() {
+        None
+    } else {
+        Some(rows)
+    }
+}
+
+fn mesh_stop_from_node(node: &Node, opacity: UnitInterval) -> Option<MeshStop> {
+    let side = match *node.borrow_element() {
+        Element::Stop(ref stop) => stop.get_mesh_side(),
+        _ => unreachable!(),
+    };
+
+    if side.is_none() {
+        rsvg_log!(
+            "(not using mesh gradient stop {} because it has no path)",
+            node
+        );
+    }
+
+    let cascaded = CascadedValues::new_from_node(node);
+    let values = cascaded.get();
+
+    let UnitInterval(stop_opacity) = values.stop_opacity().0;
+    let UnitInterval(o) = opacity;
+
+    let rgba = resolve_color(
+        &values.stop_color().0,
+        UnitInterval(stop_opacity * o),
+        values.color().0,
+    );
+
+    side.map(|side| MeshStop { side, rgba })
+}
+
+impl UnresolvedMeshGradient {
+    fn is_resolved(&self) -> bool {
+        self.units.is_some()
+            && self.transform.is_some()
+            && self.x.is_some()
+            && self.y.is_some()
+            && self.mesh_type.is_some()
+            && self.rows.is_some()
+    }
+
+    fn resolve_from_fallback(self, fallback: UnresolvedMeshGradient) -> UnresolvedMeshGradient {
+        UnresolvedMeshGradient {
+            units: self.units.or(fallback.units),
+            transform: self.transform.or(fallback.transform),
+            x: self.x.or(fallback.x),
+            y: self.y.or(fallback.y),
+            mesh_type: self.mesh_type.or(fallback.mesh_type),
+            rows: self.rows.or(fallback.rows),
+        }
+    }
+
+    fn resolve_from_defaults(self) -> UnresolvedMeshGradient {
+        UnresolvedMeshGradient {
+            units: self.units.or_else(|| Some(GradientUnits::default())),
+            transform: self.transform.or_else(|| Some(Transform::default())),
+            x: self
+                .x
+                .or_else(|| Some(Length::<Horizontal>::parse_str("0").unwrap())),
+            y: self
+                .y
+                .or_else(|| Some(Length::<Vertical>::parse_str("0").unwrap())),
+            mesh_type: self.mesh_type.or_else(|| Some(MeshType::default())),
+            rows: self.rows.or_else(|| Some(Vec::new())),
+        }
+    }
+
+    fn into_resolved(self) -> ResolvedMeshGradient {
+        assert!(self.is_resolved());
+
+        ResolvedMeshGradient {
+            units: self.units.unwrap(),
+            transform: self.transform.unwrap(),
+            x: self.x.unwrap(),
+            y: self.y.unwrap(),
+            mesh_type: self.mesh_type.unwrap(),
+            rows: self.rows.unwrap(),
+        }
+    }
+}
+
+impl ResolvedMeshGradient {
+    pub fn to_user_space(
+        &self,
+        bbox: &BoundingBox,
+        current_params: &ViewParams,
+        values: &ComputedValues,
+    ) -> Option<UserSpaceMeshGradient> {
+        let units = self.units.0;
+        let transform = bbox.rect_to_transform(units).ok()?;
+
+        let view_params = current_params.with_units(units);
+        let params = NormalizeParams::new(values, &view_params);
+
+        let transform = transform.pre_transform(&self.transform).invert()?;
+
+        let start = (self.x.to_user(&params), self.y.to_user(&params));
+
+        let grid = match MeshGrid::new(&self.rows, start) {
+            Some(grid) => grid,
+            None => {
+                rsvg_log!("(not using mesh gradient because its patches are incomplete)");
+                return None;
+            }
+        };
+
+        let patches = match self.mesh_type {
+            MeshType::Bilinear => grid.patches(),
+            MeshType::Bicubic => grid.bicubic_patches(BICUBIC_SUBDIVISIONS),
+        };
+
+        Some(UserSpaceMeshGradient { transform, patches })
+    }
+}
+
+/// Points and colors of a whole mesh, with the patches' shared sides merged.
+///
+/// For a mesh of `rows` by `columns` patches, there is a grid of `rows + 1` by
+/// `columns + 1` corner colors.
+struct MeshGrid {
+    patches: Vec<Vec<PatchPoints>>,
+    colors: Vec<Vec<RGBA>>,
+}
+
+impl MeshGrid {
+    /// Lays out the patches from their stops; returns `None` if a patch is incomplete.
+    fn new(rows: &[Vec<Vec<MeshStop>>], start: (f64, f64)) -> Option<MeshGrid> {
+        let num_rows = rows.len();
+        let num_columns = rows.first().map(|r| r.len()).unwrap_or(0);
+
+        if num_rows == 0 || num_columns == 0 {
+            return None;
+        }
+
+        // A grid of 3 * rows + 1 by 3 * columns + 1 points holds the corners of the patches
+        // and the control points along their sides.
+        let mut points = vec![vec![None; 3 * num_columns + 1]; 3 * num_rows + 1];
+        let mut colors = vec![vec![None; num_columns + 1]; num_rows + 1];
+
+        points[0][0] = Some(start);
+
+        for (r, row) in rows.iter().enumerate() {
+            if row.len() != num_columns {
+                return None;
+            }
+
+            for (c, stops) in row.iter().enumerate() {
+                // Corners in clockwise order from the top left, as (row, column) indices
+                // in the grid of colors; they are multiplied by 3 for the grid of points.
+                let corners = [(r, c), (r, c + 1), (r + 1, c + 1), (r + 1, c)];
+
+                // The top side is shared with the patch above, and the left side with
+                // the patch to the left.
+                let first_side = if r > 0 { 1 } else { 0 };
+                let end_side = if c > 0 { 3 } else { 4 };
+
+                if stops.len() < end_side - first_side {
+                    return None;
+                }
+
+                for (side, stop) in (first_side..end_side).zip(stops) {
+                    let (r0, c0) = corners[side];
+                    let (r1, c1) = corners[(side + 1) % 4];
+                    let (r0, c0, r1, c1) = (3 * r0, 3 * c0, 3 * r1, 3 * c1);
+
+                    let p0 = points[r0][c0]?;
+
+                    let offset = |(x, y): (f64, f64)| {
+                        if stop.side.relative {
+                            (p0.0 + x, p0.1 + y)
+                        } else {
+                            (x, y)
+                        }
+                    };
+
+                    // A corner that is already known wins over the side's end point.
+                    let p3 = points[r1][c1].or_else(|| stop.side.end.map(offset))?;
+
+                    let (p1, p2) = match stop.side.controls {
+                        Some((p1, p2)) => (offset(p1), offset(p2)),
+                        None => (lerp(p0, p3, 1.0 / 3.0), lerp(p0, p3, 2.0 / 3.0)),
+                    };
+
+                    // The side's control points lie between its corners in the grid.
+                    let step = |a: usize, b: usize, k: usize| (a * (3 - k) + b * k) / 3;
+                    points[step(r0, r1, 1)][step(c0, c1, 1)] = Some(p1);
+                    points[step(r0, r1, 2)][step(c0, c1, 2)] = Some(p2);
+                    points[r1][c1] = Some(p3);
+
+                    let (cr, cc) = corners[side];
+                    colors[cr][cc] = Some(stop.rgba);
+                }
+            }
+        }
+
+        let colors = colors
+            .into_iter()
+            .map(|row| row.into_iter().collect::<Option<Vec<_>>>())
+            .collect::<Option<Vec<_>>>()?;
+
+        let mut patches = Vec::with_capacity(num_rows);
+
+        for r in 0..num_rows {
+            let mut row = Vec::with_capacity(num_columns);
+
+            for c in 0..num_columns {
+                let mut patch = [[(0.0, 0.0); 4]; 4];
+
+                for (i, patch_row) in patch.iter_mut().enumerate() {
+                    for (j, point) in patch_row.iter_mut().enumerate() {
+                        if i % 3 == 0 || j % 3 == 0 {
+                            *point = points[3 * r + i][3 * c + j]?;
+                        }
+                    }
+                }
+
+                set_coons_control_points(&mut patch);
+                row.push(patch);
+            }
+
+            patches.push(row);
+        }
+
+        Some(MeshGrid { patches, colors })
+    }
+
+    /// Gets the patches with their corner colors, for bilinear interpolation.
+    fn patches(&self) -> Vec<TensorPatch> {
+        let mut patches = Vec::new();
+
+        for (r, row) in self.patches.iter().enumerate() {
+            for (c, points) in row.iter().enumerate() {
+                patches.push(TensorPatch {
+                    points: *points,
+                    colors: [
+                        self.colors[r][c],
+                        self.colors[r][c + 1],
+                        self.colors[r + 1][c + 1],
+                        self.colors[r + 1][c],
+                    ],
+                });
+            }
+        }
+
+        patches
+    }
+
+    /// Splits each patch in `n` by `n` smaller ones, with corner colors from a bicubic
+    /// interpolation across the whole mesh.
+    fn bicubic_patches(&self, n: usize) -> Vec<TensorPatch> {
+        let rows = self.colors.len() - 1;
+        let columns = self.colors[0].len() - 1;
+
+        let color = |i: usize, j: usize| rgba_to_floats(self.colors[i][j]);
+
+        // Rates of change of the colors at each corner, across columns and across rows
+        let slope = |prev: [f64; 4], next: [f64; 4], scale: f64| {
+            let mut s = [0.0; 4];
+            for (k, v) in s.iter_mut().enumerate() {
+                *v = (next[k] - prev[k]) * scale;
+            }
+            s
+        };
+
+        let du = |i: usize, j: usize| {
+            if j == 0 {
+                slope(color(i, 0), color(i, 1), 1.0)
+            } else if j == columns {
+                slope(color(i, j - 1), color(i, j), 1.0)
+            } else {
+                slope(color(i, j - 1), color(i, j + 1), 0.5)
+            }
+        };
+
+        let dv = |i: usize, j: usize| {
+            if i == 0 {
+                slope(color(0, j), color(1, j), 1.0)
+            } else if i == rows {
+                slope(color(i - 1, j), color(i, j), 1.0)
+            } else {
+                slope(color(i - 1, j), color(i + 1, j), 0.5)
+            }
+        };
+
+        let mut patches = Vec::new();
+
+        for (r, row) in self.patches.iter().enumerate() {
+            for (c, points) in row.iter().enumerate() {
+                let corners = [(r, c), (r, c + 1), (r + 1, c + 1), (r + 1, c)];
+
+                let mut values = [[0.0; 4]; 4];
+                let mut slopes_u = [[0.0; 4]; 4];
+                let mut slopes_v = [[0.0; 4]; 4];
+
+                for (k, &(i, j)) in corners.iter().enumerate() {
+                    values[k] = color(i, j);
+                    slopes_u[k] = du(i, j);
+                    slopes_v[k] = dv(i, j);
+                }
+
+                let color_at = |u: f64, v: f64| hermite_color(&values, &slopes_u, &slopes_v, u, v);
+
+                for sv in 0..n {
+                    for su in 0..n {
+                        let u0 = su as f64 / n as f64;
+                        let u1 = (su + 1) as f64 / n as f64;
+                        let v0 = sv as f64 / n as f64;
+                        let v1 = (sv + 1) as f64 / n as f64;
+
+                        patches.push(TensorPatch {
+                            points: sub_patch(points, u0, u1, v0, v1),
+                            colors: [
+                                color_at(u0, v0),
+                                color_at(u1, v0),
+                                color_at(u1, v1),
+                                color_at(u0, v1),
+                            ],
+                        });
+                    }
+                }
+            }
+        }
+
+        patches
+    }
+}
+
+/// Computes the inner control points of a tensor patch so that it is equivalent to the
+/// Coons patch bounded by its sides, like cairo does for patches without control points.
+fn set_coons_control_points(p: &mut PatchPoints) {
+    for &(a, b) in &[(1, 1), (1, 2), (2, 2), (2, 1)] {
+        let q = |i: usize, j: usize| p[a ^ i][b ^ j];
+
+        let point = |k: usize| {
+            let coord = |(x, y): (f64, f64)| if k == 0 { x } else { y };
+
+            (-4.0 * coord(q(1, 1)) + 6.0 * (coord(q(1, 0)) + coord(q(0, 1)))
+                - 2.0 * (coord(q(1, 2)) + coord(q(2, 1)))
+                + 3.0 * (coord(q(2, 0)) + coord(q(0, 2)))
+                - coord(q(2, 2)))
+                / 9.0
+        };
+
+        let (x, y) = (point(0), point(1));
+        p[a][b] = (x, y);
+    }
+}
+
+/// Gets the part of a tensor patch between parameters `u0..u1` across and `v0..v1` down.
+fn sub_patch(p: &PatchPoints, u0: f64, u1: f64, v0: f64, v1: f64) -> PatchPoints {
+    let mut rows = [[(0.0, 0.0); 4]; 4];
+
+    for (row, p_row) in rows.iter_mut().zip(p) {
+        *row = bezier_segment(*p_row, u0, u1);
+    }
+
+    let mut result = [[(0.0, 0.0); 4]; 4];
+
+    for j in 0..4 {
+        let column = bezier_segment([rows[0][j], rows[1][j], rows[2][j], rows[3][j]], v0, v1);
+
+        for (i, point) in column.iter().enumerate() {
+            result[i][j] = *point;
+        }
+    }
+
+    result
+}
+
+/// Gets the part of a cubic Bézier curve between parameters `t0` and `t1`, with `t1 > 0`.
+fn bezier_segment(p: BezierCurve, t0: f64, t1: f64) -> BezierCurve {
+    let (head, _) = split_bezier(p, t1);
+    let (_, segment) = split_bezier(head, t0 / t1);
+    segment
+}
+
+fn split_bezier(p: BezierCurve, t: f64) -> (BezierCurve, BezierCurve) {
+    let p01 = lerp(p[0], p[1], t);
+    let p12 = lerp(p[1], p[2], t);
+    let p23 = lerp(p[2], p[3], t);
+    let p012 = lerp(p01, p12, t);
+    let p123 = lerp(p12, p23, t);
+    let p0123 = lerp(p012, p123, t);
+
+    ([p[0], p01, p012, p0123], [p0123, p123, p23, p[3]])
+}
+
+/// Interpolates the colors of a patch with cubic Hermite polynomials.
+///
+/// The corners are in clockwise order from `(u, v) = (0, 0)`, and `du` and `dv` are the
+/// rates of change of the colors at the corners.
+fn hermite_color(
+    values: &[[f64; 4]; 4],
+    du: &[[f64; 4]; 4],
+    dv: &[[f64; 4]; 4],
+    u: f64,
+    v: f64,
+) -> RGBA {
+    // Basis functions for the values at t = 0 and t = 1, and for the slopes there
+    let h = |end: usize, t: f64| match end {
+        0 => 2.0 * t * t * t - 3.0 * t * t + 1.0,
+        _ => -2.0 * t * t * t + 3.0 * t * t,
+    };
+    let g = |end: usize, t: f64| match end {
+        0 => t * t * t - 2.0 * t * t + t,
+        _ => t * t * t - t * t,
+    };
+
+    let ends = [(0, 0), (1, 0), (1, 1), (0, 1)];
+
+    let mut result = [0.0; 4];
+
+    for (k, &(a, b)) in ends.iter().enumerate() {
+        for (channel, value) in result.iter_mut().enumerate() {
+            *value += h(a, u) * h(b, v) * values[k][channel]
+                + g(a, u) * h(b, v) * du[k][channel]
+                + h(a, u) * g(b, v) * dv[k][channel];
+        }
+    }
+
+    let clamp = |v: f64| util::clamp(v, 0.0, 1.0) as f32;
+    RGBA::from_floats(
+        clamp(result[0]),
+        clamp(result[1]),
+        clamp(result[2]),
+        clamp(result[3]),
+    )
+}
+
+fn rgba_to_floats(rgba: RGBA) -> [f64; 4] {
+    [
+        f64::from(rgba.red_f32()),
+        f64::from(rgba.green_f32()),
+        f64::from(rgba.blue_f32()),
+        f64::from(rgba.alpha_f32()),
+    ]
+}
+
+fn lerp(a: (f64, f64), b: (f64, f64), t: f64) -> (f64, f64) {
+    (a.0 + (b.0 - a.0) * t, a.1 + (b.1 - a.1) * t)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::node::{Node, NodeData};
+    use float_cmp::approx_eq;
+    use markup5ever::{namespace_url, ns, LocalName, QualName};
+
+    fn side(s: &str) -> MeshSide {
+        MeshSide::parse_str(s).unwrap()
+    }
+
+    fn stop(path: &str, rgba: RGBA) -> MeshStop {
+        MeshStop {
+            side: side(path),
+            rgba,
+        }
+    }
+
+    #[test]
+    fn parses_mesh_type() {
+        assert_eq!(MeshType::parse_str("bilinear").unwrap(), MeshType::Bilinear);
+        assert_eq!(MeshType::parse_str("bicubic").unwrap(), MeshType::Bicubic);
+        assert!(MeshType::parse_str("linear").is_err());
+    }
+
+    #[test]
+    fn parses_mesh_sides() {
+        assert_eq!(
+            side("l 10,20"),
+            MeshSide {
+                relative: true,
+                controls: None,
+                end: Some((10.0, 20.0)),
+            }
+        );
+
+        assert_eq!(
+            side("C 1 2 3 4 5 6"),
+            MeshSide {
+                relative: false,
+                controls: Some(((1.0, 2.0), (3.0, 4.0))),
+                end: Some((5.0, 6.0)),
+            }
+        );
+
+        assert_eq!(
+            side("c 1,2 3,4"),
+            MeshSide {
+                relative: true,
+                controls: Some(((1.0, 2.0), (3.0, 4.0))),
+                end: None,
+            }
+        );
+
+        assert_eq!(
+            side("L"),
+            MeshSide {
+                relative: false,
+                controls: None,
+                end: None,
+            }
+        );
+    }
+
+    #[test]
+    fn invalid_mesh_sides_yield_error() {
+        assert!(MeshSide::parse_str("").is_err());
+        assert!(MeshSide::parse_str("m 10,20").is_err());
+        assert!(MeshSide::parse_str("l 10").is_err());
+        assert!(MeshSide::parse_str("l 1,2,3,4").is_err());
+        assert!(MeshSide::parse_str("c 1,2").is_err());
+        assert!(MeshSide::parse_str("c 1,2,3,4,5,6,7,8").is_err());
+    }
+
+    #[test]
+    fn mesh_gradient_resolved_from_defaults_is_really_resolved() {
+        let node = Node::new(NodeData::new_element(
+            &QualName::new(None, ns!(svg), LocalName::from("meshgradient")),
+            Attributes::new(),
+        ));
+
+        let unresolved =
+            borrow_element_as!(node, MeshGradient).get_unresolved(&node, UnitInterval::clamp(1.0));
+        let gradient = unresolved.resolve_from_defaults();
+        assert!(gradient.is_resolved());
+    }
+
+    #[test]
+    fn lays_out_patches_with_shared_sides() {
+        let red = RGBA::new(255, 0, 0, 255);
+        let green = RGBA::new(0, 255, 0, 255);
+        let blue = RGBA::new(0, 0, 255, 255);
+        let black = RGBA::new(0, 0, 0, 255);
+
+        let rows = vec![vec![
+            vec![
+                stop("l 30,0", red),
+                stop("l 0,30", green),
+                stop("L 0,30", blue),
+                stop("l 0,-30", black),
+            ],
+            vec![
+                stop("C 40,-10 50,10 60,0", green),
+                stop("l 0,30", red),
+                stop("l -30,0", blue),
+            ],
+        ]];
+
+        let grid = MeshGrid::new(&rows, (0.0, 0.0)).unwrap();
+        let patches = grid.patches();
+        assert_eq!(patches.len(), 2);
+
+        assert_eq!(patches[0].points[0][0], (0.0, 0.0));
+        assert_eq!(patches[0].points[0][1], (10.0, 0.0));
+        assert_eq!(patches[0].points[0][3], (30.0, 0.0));
+        assert_eq!(patches[0].points[3][3], (30.0, 30.0));
+        assert_eq!(patches[0].points[3][0], (0.0, 30.0));
+        assert_eq!(patches[0].colors, [red, green, blue, black]);
+
+        // The second patch starts at the first one's top right corner, and its left side
+        // is the first one's right side.
+        assert_eq!(patches[1].points[0][0], (30.0, 0.0));
+        assert_eq!(patches[1].points[0][1], (40.0, -10.0));
+        assert_eq!(patches[1].points[0][2], (50.0, 10.0));
+        assert_eq!(patches[1].points[0][3], (60.0, 0.0));
+        assert_eq!(patches[1].points[3][3], (60.0, 30.0));
+        assert_eq!(patches[1].points[3][0], (30.0, 30.0));
+        assert_eq!(patches[1].points[1][0], (30.0, 10.0));
+        assert_eq!(patches[1].colors, [green, red, blue, blue]);
+    }
+
+    #[test]
+    fn incomplete_patches_yield_no_grid() {
+        let red = RGBA::new(255, 0, 0, 255);
+
+        // The first patch needs four sides.
+        let rows = vec![vec![vec![
+            stop("l 30,0", red),
+            stop("l 0,30", red),
+            stop("l -30,0", red),
+        ]]];
+        assert!(MeshGrid::new(&rows, (0.0, 0.0)).is_none());
+
+        // The end of the first side is not known.
+        let rows = vec![vec![vec![
+            stop("l", red),
+            stop("l 0,30", red),
+            stop("l -30,0", red),
+            stop("l 0,-30", red),
+        ]]];
+        assert!(MeshGrid::new(&rows, (0.0, 0.0)).is_none());
+
+        assert!(MeshGrid::new(&[], (0.0, 0.0)).is_none());
+    }
+
+    #[test]
+    fn computes_coons_control_points() {
+        let mut points = [[(0.0, 0.0); 4]; 4];
+
+        for (i, row) in points.iter_mut().enumerate() {
+            for (j, point) in row.iter_mut().enumerate() {
+                if i % 3 == 0 || j % 3 == 0 {
+                    *point = (j as f64 * 10.0, i as f64 * 20.0);
+                }
+            }
+        }
+
+        set_coons_control_points(&mut points);
+
+        // A patch with straight sides has evenly spaced control points.
+        assert!(approx_eq!(f64, points[1][1].0, 10.0));
+        assert!(approx_eq!(f64, points[1][1].1, 20.0));
+        assert!(approx_eq!(f64, points[1][2].0, 20.0));
+        assert!(approx_eq!(f64, points[2][2].1, 40.0));
+        assert!(approx_eq!(f64, points[2][1].0, 10.0));
+    }
+
+    #[test]
+    fn bicubic_patches_keep_corner_colors() {
+        let red = RGBA::new(255, 0, 0, 255);
+        let blue = RGBA::new(0, 0, 255, 255);
+
+        let rows = vec![vec![vec![
+            stop("l 30,0", red),
+            stop("l 0,30", blue),
+            stop("l -30,0", blue),
+            stop("l 0,-30", red),
+        ]]];
+
+        let grid = MeshGrid::new(&rows, (0.0, 0.0)).unwrap();
+        let patches = grid.bicubic_patches(2);
+        assert_eq!(patches.len(), 4);
+
+        assert_eq!(patches[0].points[0][0], (0.0, 0.0));
+        assert_eq!(patches[0].points[3][3], (15.0, 15.0));
+        assert_eq!(patches[0].colors[0], red);
+        assert_eq!(patches[3].points[3][3], (30.0, 30.0));
+        assert_eq!(patches[3].colors[2], blue);
+    }
+}
@@ -16,6 +16,7 @@ use crate::document::AcquiredNodes;
 use crate::drawing_ctx::DrawingCtx;
 use crate::element::*;
 use crate::error::*;
+use crate::keyframes::CssAnimations;
 use crate::paint_server::PaintSource;
 use crate::properties::ComputedValues;
 use crate::text::Chars;
@@ -61,9 +62,27 @@ pub type WeakNode = rctree::WeakNode<NodeData>;
 /// `borrow_chars`, `borrow_element`, or `borrow_element_mut`.
 pub enum NodeData {
     Element(Element),
+
+    /// An element that is the target of animations, with the attributes they give it at
+    /// the time being rendered.
+    ///
+    /// The element as parsed from the document stays in `parsed` without changes, so
+    /// that the animations can be evaluated at another time, or removed, without
+    /// parsing the document again.  The rest of librsvg only sees the `animated` element.
+    Animated(Box<AnimatedElement>),
+
     Text(Chars),
 }
 
+pub struct AnimatedElement {
+    parsed: Element,
+    animated: Element,
+
+    /// Declarations for the properties that the animations change, which override the
+    /// ones in the element's style.
+    properties: String,
+}
+
 impl NodeData {
     pub fn new_element(name: &QualName, attrs: Attributes) -> NodeData {
         NodeData::Element(Element::new(name, attrs))
@@ -80,6 +99,9 @@ impl fmt::Display for NodeData {
             NodeData::Element(ref e) => {
                 write!(f, "{}", e)?;
             }
+            NodeData::Animated(ref a) => {
+                write!(f, "{}", a.animated)?;
+            }
             NodeData::Text(_) => {
                 write!(f, "Chars")?;
             }
@@ -214,7 +236,7 @@ pub trait NodeBorrow {
 
 impl NodeBorrow for Node {
     fn is_element(&self) -> bool {
-        matches!(*self.borrow(), NodeData::Element(_))
+        matches!(*self.borrow(), NodeData::Element(_) | NodeData::Animated(_))
     }
 
     fn is_chars(&self) -> bool {
@@ -231,6 +253,7 @@ impl NodeBorrow for Node {
     fn borrow_element(&self) -> Ref<'_, Element> {
         Ref::map(self.borrow(), |n| match *n {
             NodeData::Element(ref e) => e,
+            NodeData::Animated(ref a) => &a.animated,
             _ => panic!("tried to borrow_element for a non-element node"),
         })
     }
@@ -238,11 +261,76 @@ impl NodeBorrow for Node {
     fn borrow_element_mut(&mut self) -> RefMut<'_, Element> {
         RefMut::map(self.borrow_mut(), |n| match *n {
             NodeData::Element(ref mut e) => e,
+            NodeData::Animated(ref mut a) => &mut a.animated,
             _ => panic!("tried to borrow_element_mut for a non-element node"),
         })
     }
 }
 
+/// Helper trait for the elements that are changed by animations.
+pub trait NodeAnimate {
+    /// Borrows the element as it was parsed from the document, without the changes from
+    /// animations.
+    ///
+    /// Panics: will panic if `&self` is not an element node
+    fn borrow_parsed_element(&self) -> Ref<'_, Element>;
+
+    /// Makes the rest of librsvg see `animated` instead of the parsed element, along with
+    /// the `properties` declarations from the animations.  With `None`, the parsed
+    /// element is used again.
+    ///
+    /// Panics: will panic if `&self` is not an element node
+    fn set_animated_element(&mut self, animated: Option<(Element, String)>);
+
+    /// Applies the properties from the animations, if any, to the element.
+    ///
+    /// The cascade calls this after applying the element's style attribute.
+    fn apply_animated_properties(&mut self);
+}
+
+impl NodeAnimate for Node {
+    fn borrow_parsed_element(&self) -> Ref<'_, Element> {
+        Ref::map(self.borrow(), |n| match *n {
+            NodeData::Element(ref e) => e,
+            NodeData::Animated(ref a) => &a.parsed,
+            _ => panic!("tried to borrow_parsed_element for a non-element node"),
+        })
+    }
+
+    fn set_animated_element(&mut self, animated: Option<(Element, String)>) {
+        let mut data = self.borrow_mut();
+
+        if let NodeData::Text(_) = *data {
+            panic!("tried to set_animated_element for a non-element node");
+        }
+
+        // Temporarily put an empty node in place, to take ownership of the parsed element.
+        let parsed = match std::mem::replace(&mut *data, NodeData::new_chars("")) {
+            NodeData::Element(e) => e,
+            NodeData::Animated(a) => a.parsed,
+            NodeData::Text(_) => unreachable!(),
+        };
+
+        *data = match animated {
+            Some((animated, properties)) => NodeData::Animated(Box::new(AnimatedElement {
+                parsed,
+                animated,
+                properties,
+            })),
+
+            None => NodeData::Element(parsed),
+        };
+    }
+
+    fn apply_animated_properties(&mut self) {
+        if let NodeData::Animated(ref mut a) = *self.borrow_mut() {
+            if !a.properties.is_empty() {
+                a.animated.apply_animated_properties(&a.properties);
+            }
+        }
+    }
+}
+
 #[macro_export]
 macro_rules! is_element_of_type {
     ($node:expr, $element_type:ident) => {
@@ -265,22 +353,23 @@ macro_rules! borrow_element_as {
 
 /// Helper trait for cascading recursively
 pub trait NodeCascade {
-    fn cascade(&mut self, values: &ComputedValues);
+    fn cascade(&mut self, values: &ComputedValues, animations: &CssAnimations<'_>);
 }
 
 impl NodeCascade for Node {
-    fn cascade(&mut self, values: &ComputedValues) {
-        let mut values = values.clone();
+    fn cascade(&mut self, parent_values: &ComputedValues, animations: &CssAnimations<'_>) {
+        let mut values = parent_values.clone();
 
         {
             let mut elt = self.borrow_element_mut();
 
             elt.get_specified_values().to_computed_values(&mut values);
+            animations.apply(elt.get_specified_values(), parent_values, &mut values);
             elt.set_computed_values(&values);
         }
 
         for mut child in self.children().filter(|c| c.is_element()) {
-            child.cascade(&values);
+            child.cascade(&values, animations);
         }
     }
 }
@@ -314,6 +403,10 @@ impl NodeDraw for Node {
     ) -> Result<BoundingBox, RenderingError> {
         match *self.borrow() {
             NodeData::Element(ref e) => e.draw(self, acquired_nodes, cascaded, draw_ctx, clipping),
+            NodeData::Animated(ref a) => {
+                a.animated
+                    .draw(self, acquired_nodes, cascaded, draw_ctx, clipping)
+            }
             _ => Ok(draw_ctx.empty_bbox()),
         }
     }
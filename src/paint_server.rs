@@ -8,6 +8,8 @@ use crate::drawing_ctx::ViewParams;
 use crate::element::Element;
 use crate::error::{AcquireError, NodeIdError, ParseError, ValueErrorKind};
 use crate::gradient::{ResolvedGradient, UserSpaceGradient};
+use crate::hatch::{ResolvedHatch, UserSpaceHatch};
+use crate::mesh_gradient::{ResolvedMeshGradient, UserSpaceMeshGradient};
 use crate::node::NodeBorrow;
 use crate::parsers::Parse;
 use crate::pattern::{ResolvedPattern, UserSpacePattern};
@@ -51,6 +53,8 @@ pub enum PaintServer {
 pub enum PaintSource {
     None,
     Gradient(ResolvedGradient, Option<cssparser::RGBA>),
+    Hatch(ResolvedHatch, Option<cssparser::RGBA>),
+    MeshGradient(ResolvedMeshGradient, Option<cssparser::RGBA>),
     Pattern(ResolvedPattern, Option<cssparser::RGBA>),
     SolidColor(cssparser::RGBA),
 }
@@ -61,6 +65,8 @@ pub enum PaintSource {
 pub enum UserSpacePaintSource {
     None,
     Gradient(UserSpaceGradient, Option<cssparser::RGBA>),
+    Hatch(UserSpaceHatch, Option<cssparser::RGBA>),
+    MeshGradient(UserSpaceMeshGradient, Option<cssparser::RGBA>),
     Pattern(UserSpacePattern, Option<cssparser::RGBA>),
     SolidColor(cssparser::RGBA),
 }
@@ -138,6 +144,14 @@ impl PaintServer {
                     assert!(node.is_element());
 
                     match *node.borrow_element() {
+                        Element::Hatch(ref h) => {
+                            h.resolve(node, acquired_nodes, opacity).map(|h| {
+                                PaintSource::Hatch(
+                                    h,
+                                    alternate.map(|c| resolve_color(&c, opacity, current_color)),
+                                )
+                            })
+                        }
                         Element::LinearGradient(ref g) => {
                             g.resolve(node, acquired_nodes, opacity).map(|g| {
                                 PaintSource::Gradient(
@@ -146,6 +160,14 @@ impl PaintServer {
                                 )
                             })
                         }
+                        Element::MeshGradient(ref g) => {
+                            g.resolve(node, acquired_nodes, opacity).map(|g| {
+                                PaintSource::MeshGradient(
+                                    g,
+                                    alternate.map(|c| resolve_color(&c, opacity, current_color)),
+                                )
+                            })
+                        }
                         Element::Pattern(ref p) => {
                             p.resolve(node, acquired_nodes, opacity).map(|p| {
                                 PaintSource::Pattern(
@@ -242,6 +264,22 @@ impl PaintSource {
                 }
             }
 
+            PaintSource::Hatch(ref h, c) => {
+                match (h.to_user_space(bbox, current_params, values), c) {
+                    (Some(hatch), c) => UserSpacePaintSource::Hatch(hatch, c),
+                    (None, Some(c)) => UserSpacePaintSource::SolidColor(c),
+                    (None, None) => UserSpacePaintSource::None,
+                }
+            }
+
+            PaintSource::MeshGradient(ref g, c) => {
+                match (g.to_user_space(bbox, current_params, values), c) {
+                    (Some(gradient), c) => UserSpacePaintSource::MeshGradient(gradient, c),
+                    (None, Some(c)) => UserSpacePaintSource::SolidColor(c),
+                    (None, None) => UserSpacePaintSource::None,
+                }
+            }
+
             PaintSource::Pattern(ref p, c) => {
                 match (p.to_user_space(bbox, current_params, values), c) {
                     (Some(pattern), c) => UserSpacePaintSource::Pattern(pattern, c),
@@ -286,6 +324,8 @@ impl std::fmt::Debug for PaintSource {
         match *self {
             PaintSource::None => f.write_str("PaintSource::None"),
             PaintSource::Gradient(_, _) => f.write_str("PaintSource::Gradient"),
+            PaintSource::Hatch(_, _) => f.write_str("PaintSource::Hatch"),
+            PaintSource::MeshGradient(_, _) => f.write_str("PaintSource::MeshGradient"),
             PaintSource::Pattern(_, _) => f.write_str("PaintSource::Pattern"),
             PaintSource::SolidColor(_) => f.write_str("PaintSource::SolidColor"),
         }
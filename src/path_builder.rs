@@ -26,7 +26,9 @@ use std::slice;
 
 use crate::float_eq_cairo::ApproxEqCairo;
 use crate::path_parser::{ParseError, PathParser};
-use crate::util::clamp;
+use crate::rect::Rect;
+use crate::transform::Transform;
+use crate::util::{clamp, total_cmp};
 
 /// Whether an arc's sweep should be >= 180 degrees, or smaller.
 #[derive(Debug, Copy, Clone, PartialEq)]
@@ -722,6 +724,252 @@ impl Path {
     pub fn is_empty(&self) -> bool {
         self.commands.is_empty()
     }
+
+    /// Approximates the path with line segments, so that distances can be measured along it.
+    pub fn flatten(&self) -> FlattenedPath {
+        let mut flat = FlattenedPath::default();
+
+        for subpath in self.iter_subpath() {
+            let origin = subpath.origin();
+            let mut current = origin;
+            flat.is_closed = false;
+
+            for cmd in subpath.iter_commands().skip(1) {
+                match cmd {
+                    PathCommand::MoveTo(..) => unreachable!(),
+
+                    PathCommand::LineTo(x, y) => {
+                        flat.push_line(current, (x, y));
+                        current = (x, y);
+                    }
+
+                    PathCommand::CurveTo(curve) => {
+                        flat.push_curve(current, &curve);
+                        current = curve.to;
+                    }
+
+                    PathCommand::Arc(arc) => {
+                        match arc.center_parameterization() {
+                            ArcParameterization::CenterParameters {
+                                center,
+                                radii,
+                                theta1,
+                                delta_theta,
+                            } => {
+                                let n_segs = (delta_theta / (PI * 0.5 + 0.001)).abs().ceil() as u32;
+                                let d_theta = delta_theta / f64::from(n_segs);
+
+                                let mut theta = theta1;
+                                for _ in 0..n_segs {
+                                    let curve = arc_segment(
+                                        center,
+                                        radii,
+                                        arc.x_axis_rotation,
+                                        theta,
+                                        theta + d_theta,
+                                    );
+                                    flat.push_curve(current, &curve);
+                                    current = curve.to;
+                                    theta += d_theta;
+                                }
+                            }
+
+                            ArcParameterization::LineTo => flat.push_line(current, arc.to),
+
+                            ArcParameterization::Omit => (),
+                        }
+
+                        current = arc.to;
+                    }
+
+                    PathCommand::ClosePath => {
+                        flat.push_line(current, origin);
+                        current = origin;
+                        flat.is_closed = true;
+                    }
+                }
+            }
+        }
+
+        flat
+    }
+}
+
+/// Number of line segments used to approximate each cubic Bézier curve in a `FlattenedPath`.
+const CURVE_SEGMENTS: u32 = 32;
+
+/// A line segment of a `FlattenedPath`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct FlatSegment {
+    from: (f64, f64),
+    to: (f64, f64),
+    /// Distance along the path at which this segment starts.
+    start: f64,
+}
+
+impl FlatSegment {
+    fn length(&self) -> f64 {
+        let (dx, dy) = (self.to.0 - self.from.0, self.to.1 - self.from.1);
+        (dx * dx + dy * dy).sqrt()
+    }
+}
+
+/// A path approximated by line segments, used to measure distances along it.
+///
+/// This is used for `<textPath>`, which needs to place glyphs at a certain
+/// distance from the start of the path.  Moving from one subpath to the next one
+/// does not count as distance along the path.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct FlattenedPath {
+    segments: Vec<FlatSegment>,
+    length: f64,
+    is_closed: bool,
+}
+
+impl FlattenedPath {
+    fn push_line(&mut self, from: (f64, f64), to: (f64, f64)) {
+        let segment = FlatSegment {
+            from,
+            to,
+            start: self.length,
+        };
+
+        let length = segment.length();
+        if length > 0.0 {
+            self.length += length;
+            self.segments.push(segment);
+        }
+    }
+
+    fn push_curve(&mut self, from: (f64, f64), curve: &CubicBezierCurve) {
+        let CubicBezierCurve { pt1, pt2, to } = *curve;
+
+        let mut prev = from;
+        for i in 1..=CURVE_SEGMENTS {
+            let t = f64::from(i) / f64::from(CURVE_SEGMENTS);
+            let mt = 1.0 - t;
+
+            let a = mt * mt * mt;
+            let b = 3.0 * mt * mt * t;
+            let c = 3.0 * mt * t * t;
+            let d = t * t * t;
+
+            let pt = (
+                a * from.0 + b * pt1.0 + c * pt2.0 + d * to.0,
+                a * from.1 + b * pt1.1 + c * pt2.1 + d * to.1,
+            );
+
+            self.push_line(prev, pt);
+            prev = pt;
+        }
+    }
+
+    /// Total length of the path.
+    pub fn length(&self) -> f64 {
+        self.length
+    }
+
+    /// Whether the path ends with a `ClosePath` command.
+    pub fn is_closed(&self) -> bool {
+        self.is_closed
+    }
+
+    /// Returns the point at `distance` along the path, and the angle in radians
+    /// of the path's direction at that point.
+    ///
+    /// Returns `None` if the distance is outside of the path.
+    pub fn point_at(&self, distance: f64) -> Option<((f64, f64), f64)> {
+        if distance < 0.0 || distance > self.length || self.segments.is_empty() {
+            return None;
+        }
+
+        let idx = self
+            .segments
+            .iter()
+            .rposition(|s| s.start <= distance)
+            .unwrap_or(0);
+        let segment = &self.segments[idx];
+
+        let t = clamp((distance - segment.start) / segment.length(), 0.0, 1.0);
+        let (dx, dy) = (segment.to.0 - segment.from.0, segment.to.1 - segment.from.1);
+
+        Some((
+            (segment.from.0 + t * dx, segment.from.1 + t * dy),
+            dy.atan2(dx),
+        ))
+    }
+
+    /// Returns the bounding box of the path, or `None` if it is empty.
+    pub fn extents(&self) -> Option<Rect> {
+        self.segments
+            .iter()
+            .map(|s| {
+                Rect::new(
+                    s.from.0.min(s.to.0),
+                    s.from.1.min(s.to.1),
+                    s.from.0.max(s.to.0),
+                    s.from.1.max(s.to.1),
+                )
+            })
+            .fold(None, |acc: Option<Rect>, r| {
+                Some(acc.map_or(r, |acc| acc.union(&r)))
+            })
+    }
+
+    /// Returns the horizontal intervals, sorted from left to right, where the
+    /// horizontal line at `y` is inside the path.
+    ///
+    /// This uses the even-odd rule, and assumes that subpaths are closed.
+    pub fn scanline(&self, y: f64) -> Vec<(f64, f64)> {
+        let mut crossings: Vec<f64> = self
+            .segments
+            .iter()
+            .filter(|s| (s.from.1 <= y && y < s.to.1) || (s.to.1 <= y && y < s.from.1))
+            .map(|s| {
+                let t = (y - s.from.1) / (s.to.1 - s.from.1);
+                s.from.0 + t * (s.to.0 - s.from.0)
+            })
+            .collect();
+
+        crossings.sort_by(|a, b| total_cmp(*a, *b));
+
+        crossings
+            .chunks_exact(2)
+            .map(|pair| (pair[0], pair[1]))
+            .filter(|(x0, x1)| x1 > x0)
+            .collect()
+    }
+
+    /// Returns the same path, but traversed in the opposite direction.
+    pub fn reversed(&self) -> FlattenedPath {
+        let mut reversed = FlattenedPath {
+            is_closed: self.is_closed,
+            ..Default::default()
+        };
+
+        for segment in self.segments.iter().rev() {
+            reversed.push_line(segment.to, segment.from);
+        }
+
+        reversed
+    }
+
+    /// Applies an affine transformation to all the points in the path.
+    pub fn transformed(&self, transform: &Transform) -> FlattenedPath {
+        let mut transformed = FlattenedPath {
+            is_closed: self.is_closed,
+            ..Default::default()
+        };
+
+        for segment in &self.segments {
+            transformed.push_line(
+                transform.transform_point(segment.from.0, segment.from.1),
+                transform.transform_point(segment.to.0, segment.to.1),
+            );
+        }
+
+        transformed
+    }
 }
 
 fn take_one(iter: &mut slice::Iter<'_, f64>) -> f64 {
@@ -872,4 +1120,105 @@ mod tests {
             ]
         );
     }
+
+    #[test]
+    fn flattened_path_measures_lines() {
+        let mut builder = PathBuilder::default();
+        builder.move_to(0.0, 0.0);
+        builder.line_to(10.0, 0.0);
+        builder.move_to(100.0, 100.0);
+        builder.line_to(100.0, 110.0);
+
+        let flat = builder.into_path().flatten();
+
+        assert!(flat.length().approx_eq_cairo(20.0));
+        assert!(!flat.is_closed());
+
+        let ((x, y), angle) = flat.point_at(5.0).unwrap();
+        assert!(x.approx_eq_cairo(5.0) && y.approx_eq_cairo(0.0));
+        assert!(angle.approx_eq_cairo(0.0));
+
+        let ((x, y), angle) = flat.point_at(15.0).unwrap();
+        assert!(x.approx_eq_cairo(100.0) && y.approx_eq_cairo(105.0));
+        assert!(angle.approx_eq_cairo(FRAC_PI_2));
+
+        assert!(flat.point_at(-1.0).is_none());
+        assert!(flat.point_at(21.0).is_none());
+    }
+
+    #[test]
+    fn flattened_path_closes_and_reverses() {
+        let mut builder = PathBuilder::default();
+        builder.move_to(0.0, 0.0);
+        builder.line_to(10.0, 0.0);
+        builder.line_to(10.0, 10.0);
+        builder.close_path();
+
+        let flat = builder.into_path().flatten();
+        assert!(flat.is_closed());
+        assert!(flat.length().approx_eq_cairo(20.0 + 200.0f64.sqrt()));
+
+        let reversed = flat.reversed();
+        assert!(reversed.length().approx_eq_cairo(flat.length()));
+
+        let ((x, y), angle) = reversed.point_at(0.0).unwrap();
+        assert!(x.approx_eq_cairo(0.0) && y.approx_eq_cairo(0.0));
+        assert!(angle.approx_eq_cairo(FRAC_PI_4));
+    }
+
+    #[test]
+    fn flattened_path_approximates_arcs() {
+        let mut builder = PathBuilder::default();
+        builder.move_to(0.0, 0.0);
+        builder.arc(
+            0.0,
+            0.0,
+            50.0,
+            50.0,
+            0.0,
+            LargeArc(false),
+            Sweep::Positive,
+            100.0,
+            0.0,
+        );
+
+        let flat = builder.into_path().flatten();
+        assert!((flat.length() - 50.0 * PI).abs() < 0.1);
+    }
+
+    #[test]
+    fn flattened_path_scanlines() {
+        let mut builder = PathBuilder::default();
+        builder.move_to(0.0, 0.0);
+        builder.line_to(30.0, 0.0);
+        builder.line_to(30.0, 20.0);
+        builder.line_to(0.0, 20.0);
+        builder.close_path();
+        builder.move_to(10.0, 5.0);
+        builder.line_to(20.0, 5.0);
+        builder.line_to(20.0, 15.0);
+        builder.line_to(10.0, 15.0);
+        builder.close_path();
+
+        let flat = builder.into_path().flatten();
+
+        assert_eq!(flat.extents(), Some(Rect::new(0.0, 0.0, 30.0, 20.0)));
+        assert_eq!(flat.scanline(2.0), vec![(0.0, 30.0)]);
+        assert_eq!(flat.scanline(10.0), vec![(0.0, 10.0), (20.0, 30.0)]);
+        assert!(flat.scanline(25.0).is_empty());
+    }
+
+    #[test]
+    fn flattened_path_scanline_with_nan_does_not_panic() {
+        let mut builder = PathBuilder::default();
+        builder.move_to(0.0, 0.0);
+        builder.line_to(f64::NAN, 20.0);
+        builder.line_to(0.0, 20.0);
+        builder.line_to(10.0, 0.0);
+        builder.close_path();
+
+        let flat = builder.into_path().flatten();
+
+        flat.scanline(10.0);
+    }
 }
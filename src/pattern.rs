@@ -51,8 +51,10 @@ struct Unresolved {
 }
 
 /// Keeps track of which Pattern provided a non-empty set of children during pattern resolution
+///
+/// This is also used for the children of hatches.
 #[derive(Clone)]
-enum UnresolvedChildren {
+pub enum UnresolvedChildren {
     /// Points back to the original Pattern if it had no usable children
     Unresolved,
 
@@ -67,7 +69,7 @@ enum UnresolvedChildren {
 
 /// Keeps track of which Pattern provided a non-empty set of children during pattern resolution
 #[derive(Clone)]
-enum Children {
+pub enum Children {
     Empty,
 
     /// Points back to the Pattern that had usable children
@@ -258,7 +260,7 @@ impl UnresolvedPattern {
 }
 
 impl UnresolvedChildren {
-    fn from_node(node: &Node) -> UnresolvedChildren {
+    pub fn from_node(node: &Node) -> UnresolvedChildren {
         let weak = node.downgrade();
 
         if node.children().any(|child| child.is_element()) {
@@ -268,11 +270,11 @@ impl UnresolvedChildren {
         }
     }
 
-    fn is_resolved(&self) -> bool {
+    pub fn is_resolved(&self) -> bool {
         !matches!(*self, UnresolvedChildren::Unresolved)
     }
 
-    fn resolve_from_fallback(&self, fallback: &UnresolvedChildren) -> UnresolvedChildren {
+    pub fn resolve_from_fallback(&self, fallback: &UnresolvedChildren) -> UnresolvedChildren {
         use UnresolvedChildren::*;
 
         match (self, fallback) {
@@ -283,7 +285,7 @@ impl UnresolvedChildren {
         }
     }
 
-    fn resolve_from_defaults(&self) -> UnresolvedChildren {
+    pub fn resolve_from_defaults(&self) -> UnresolvedChildren {
         use UnresolvedChildren::*;
 
         match *self {
@@ -292,7 +294,7 @@ impl UnresolvedChildren {
         }
     }
 
-    fn to_resolved(&self) -> Children {
+    pub fn to_resolved(&self) -> Children {
         use UnresolvedChildren::*;
 
         assert!(self.is_resolved());
@@ -24,6 +24,11 @@ use std::collections::HashSet;
 
 use crate::css::{DeclParser, Declaration, Origin};
 use crate::error::*;
+use crate::keyframes::{
+    Animation, AnimationDelay, AnimationDirection, AnimationDuration, AnimationFillMode,
+    AnimationIterationCount, AnimationName, AnimationPlayState, AnimationTimingFunction,
+    Interpolate,
+};
 use crate::parsers::{Parse, ParseValue};
 use crate::property_macros::Property;
 use crate::transform::{Transform, TransformAttribute, TransformProperty};
@@ -332,6 +337,24 @@ macro_rules! make_properties {
             }
         }
 
+        /// Whether `name` is one of the CSS properties that we support.
+        pub fn is_property(name: &QualName) -> bool {
+            match name.expanded() {
+                $(expanded_name!("", $long_str) => true,)+
+
+                $(
+                    e if e == ExpandedName {
+                        ns: &ns!(),
+                        local: &LocalName::from($long_m5e_str),
+                    } => true,
+                )+
+
+                $(expanded_name!("", $short_str) => true,)+
+
+                _ => false,
+            }
+        }
+
         pub fn parse_property<'i>(
             prop_name: &QualName,
             input: &mut Parser<'i, '_>,
@@ -353,7 +376,7 @@ macro_rules! make_properties {
 
                 $(
                     expanded_name!("", $short_str) => {
-                        if accept_shorthands {
+                        if accept_shorthands || PropertyId::$short_name.is_presentation_attribute() {
                             Ok(ParsedProperty::$short_name(parse_input(input)?))
                         } else {
                             let loc = input.current_source_location();
@@ -374,18 +397,24 @@ macro_rules! make_properties {
 #[rustfmt::skip]
 make_properties! {
     shorthands: {
+        "animation"                   => animation                   : Animation,
         "font"                        => font                        : Font,
+        "glyph-orientation-vertical"  => glyph_orientation_vertical  : GlyphOrientationVertical,
         "marker"                      => marker                      : Marker,
+        "text-decoration"             => text_decoration             : TextDecoration,
     }
 
     longhands: {
+        "alignment-baseline"          => alignment_baseline          : AlignmentBaseline,
         "baseline-shift"              => baseline_shift              : BaselineShift,
         "clip-path"                   => clip_path                   : ClipPath,
         "clip-rule"                   => clip_rule                   : ClipRule,
         "color"                       => color                       : Color,
+        "color-interpolation"         => color_interpolation         : ColorInterpolation,
         "color-interpolation-filters" => color_interpolation_filters : ColorInterpolationFilters,
         "direction"                   => direction                   : Direction,
         "display"                     => display                     : Display,
+        "dominant-baseline"           => dominant_baseline           : DominantBaseline,
         "enable-background"           => enable_background           : EnableBackground,
         "fill"                        => fill                        : Fill,
         "fill-opacity"                => fill_opacity                : FillOpacity,
@@ -419,18 +448,47 @@ make_properties! {
         "stroke-opacity"              => stroke_opacity              : StrokeOpacity,
         "stroke-width"                => stroke_width                : StrokeWidth,
         "text-anchor"                 => text_anchor                 : TextAnchor,
-        "text-decoration"             => text_decoration             : TextDecoration,
         "text-rendering"              => text_rendering              : TextRendering,
         "transform"                   => transform_property          : TransformProperty,
         "unicode-bidi"                => unicode_bidi                : UnicodeBidi,
         "visibility"                  => visibility                  : Visibility,
+        "word-spacing"                => word_spacing                : WordSpacing,
         "writing-mode"                => writing_mode                : WritingMode,
     }
 
     longhands_not_supported_by_markup5ever: {
+        "animation-delay"             => animation_delay             : AnimationDelay,
+        "animation-direction"         => animation_direction         : AnimationDirection,
+        "animation-duration"          => animation_duration          : AnimationDuration,
+        "animation-fill-mode"         => animation_fill_mode         : AnimationFillMode,
+        "animation-iteration-count"   => animation_iteration_count   : AnimationIterationCount,
+        "animation-name"              => animation_name              : AnimationName,
+        "animation-play-state"        => animation_play_state        : AnimationPlayState,
+        "animation-timing-function"   => animation_timing_function   : AnimationTimingFunction,
+        "font-feature-settings"       => font_feature_settings       : FontFeatureSettings,
+        "font-kerning"                => font_kerning                : FontKerning,
+        "font-optical-sizing"         => font_optical_sizing         : FontOpticalSizing,
+        "font-variant-caps"           => font_variant_caps           : FontVariantCaps,
+        "font-variant-east-asian"     => font_variant_east_asian     : FontVariantEastAsian,
+        "font-variant-ligatures"      => font_variant_ligatures      : FontVariantLigatures,
+        "font-variant-numeric"        => font_variant_numeric        : FontVariantNumeric,
+        "font-variation-settings"     => font_variation_settings     : FontVariationSettings,
+        "inline-size"                 => inline_size                 : InlineSize,
+        "isolation"                   => isolation                   : Isolation,
         "line-height"                 => line_height                 : LineHeight,
+        "mask-mode"                   => mask_mode                   : MaskMode,
+        "mask-type"                   => mask_type                   : MaskType,
         "mix-blend-mode"              => mix_blend_mode              : MixBlendMode,
         "paint-order"                 => paint_order                 : PaintOrder,
+        "shape-inside"                => shape_inside                : ShapeInside,
+        "text-decoration-color"       => text_decoration_color       : TextDecorationColor,
+        "text-decoration-line"        => text_decoration_line        : TextDecorationLine,
+        "text-decoration-style"       => text_decoration_style       : TextDecorationStyle,
+        "text-decoration-thickness"   => text_decoration_thickness   : TextDecorationThickness,
+        "text-orientation"            => text_orientation            : TextOrientation,
+        "text-underline-offset"       => text_underline_offset       : TextUnderlineOffset,
+        "vector-effect"               => vector_effect               : VectorEffect,
+        "white-space"                 => white_space                 : WhiteSpace,
     }
 
     // These are not properties, but presentation attributes.  However,
@@ -442,6 +500,90 @@ make_properties! {
     }
 }
 
+impl PropertyId {
+    /// Whether a shorthand property can also be given as a presentation attribute.
+    ///
+    /// This is the case for `text-decoration`, which was a single property in SVG 1.1,
+    /// and for `glyph-orientation-vertical`, which is an alias for `text-orientation`.
+    fn is_presentation_attribute(self) -> bool {
+        matches!(
+            self,
+            PropertyId::GlyphOrientationVertical | PropertyId::TextDecoration
+        )
+    }
+}
+
+impl ComputedValues {
+    /// Sets the properties in `prop` to the values interpolated between the ones in `from`
+    /// and `to`, at `t` between 0 and 1.
+    ///
+    /// Values that cannot be interpolated switch from `from` to `to` halfway.
+    pub fn set_interpolated(
+        &mut self,
+        prop: &ParsedProperty,
+        from: &ComputedValues,
+        to: &ComputedValues,
+        t: f64,
+    ) {
+        // This expands shorthands into their longhands.
+        let mut specified = SpecifiedValues::default();
+        specified.set_parsed_property(prop);
+
+        for longhand in &specified.props {
+            let id = longhand.get_property_id();
+            self.set_value(interpolate_value(from.get_value(id), to.get_value(id), t));
+        }
+
+        if specified
+            .property_index(PropertyId::TransformProperty)
+            .is_some()
+        {
+            self.transform = self.transform_property.to_transform();
+        }
+    }
+}
+
+fn interpolate_value(from: ComputedValue, to: ComputedValue, t: f64) -> ComputedValue {
+    macro_rules! interpolate {
+        ($($name:ident,)+) => {
+            match (&from, &to) {
+                $(
+                    (ComputedValue::$name(a), ComputedValue::$name(b)) => {
+                        a.interpolate(b, t).map(ComputedValue::$name)
+                    }
+                )+
+
+                _ => None,
+            }
+        };
+    }
+
+    let interpolated = interpolate!(
+        BaselineShift,
+        Color,
+        Fill,
+        FillOpacity,
+        FloodColor,
+        FloodOpacity,
+        FontSize,
+        LetterSpacing,
+        LightingColor,
+        Opacity,
+        StopColor,
+        StopOpacity,
+        Stroke,
+        StrokeDashoffset,
+        StrokeMiterlimit,
+        StrokeOpacity,
+        StrokeWidth,
+        TextDecorationColor,
+        TransformProperty,
+        WordSpacing,
+    );
+
+    interpolated.unwrap_or(if t < 0.5 { from } else { to })
+}
+
 impl SpecifiedValues {
     fn property_index(&self, id: PropertyId) -> Option<usize> {
         let v = self.indices[id.as_usize()];
@@ -480,17 +622,82 @@ impl SpecifiedValues {
 
     fn set_property_expanding_shorthands(&mut self, prop: &ParsedProperty, replace: bool) {
         match *prop {
+            ParsedProperty::Animation(SpecifiedValue::Specified(ref a)) => {
+                self.expand_animation_shorthand(a, replace)
+            }
             ParsedProperty::Font(SpecifiedValue::Specified(ref f)) => {
                 self.expand_font_shorthand(f, replace)
             }
+            ParsedProperty::GlyphOrientationVertical(SpecifiedValue::Specified(ref g)) => {
+                self.set_property(
+                    &ParsedProperty::TextOrientation(SpecifiedValue::Specified(g.0)),
+                    replace,
+                );
+            }
             ParsedProperty::Marker(SpecifiedValue::Specified(ref m)) => {
                 self.expand_marker_shorthand(m, replace)
             }
+            ParsedProperty::TextDecoration(SpecifiedValue::Specified(ref t)) => {
+                self.expand_text_decoration_shorthand(t, replace)
+            }
 
             _ => self.set_property(prop, replace),
         }
     }
 
+    fn expand_animation_shorthand(&mut self, animation: &Animation, replace: bool) {
+        let Animation(animations) = animation;
+
+        self.set_property(
+            &ParsedProperty::AnimationName(SpecifiedValue::Specified(AnimationName(
+                animations.iter().map(|a| a.name.clone()).collect(),
+            ))),
+            replace,
+        );
+        self.set_property(
+            &ParsedProperty::AnimationDuration(SpecifiedValue::Specified(AnimationDuration(
+                animations.iter().map(|a| a.duration).collect(),
+            ))),
+            replace,
+        );
+        self.set_property(
+            &ParsedProperty::AnimationTimingFunction(SpecifiedValue::Specified(
+                AnimationTimingFunction(animations.iter().map(|a| a.timing_function).collect()),
+            )),
+            replace,
+        );
+        self.set_property(
+            &ParsedProperty::AnimationDelay(SpecifiedValue::Specified(AnimationDelay(
+                animations.iter().map(|a| a.delay).collect(),
+            ))),
+            replace,
+        );
+        self.set_property(
+            &ParsedProperty::AnimationIterationCount(SpecifiedValue::Specified(
+                AnimationIterationCount(animations.iter().map(|a| a.iteration_count).collect()),
+            )),
+            replace,
+        );
+        self.set_property(
+            &ParsedProperty::AnimationDirection(SpecifiedValue::Specified(AnimationDirection(
+                animations.iter().map(|a| a.direction).collect(),
+            ))),
+            replace,
+        );
+        self.set_property(
+            &ParsedProperty::AnimationFillMode(SpecifiedValue::Specified(AnimationFillMode(
+                animations.iter().map(|a| a.fill_mode).collect(),
+            ))),
+            replace,
+        );
+        self.set_property(
+            &ParsedProperty::AnimationPlayState(SpecifiedValue::Specified(AnimationPlayState(
+                animations.iter().map(|a| a.play_state).collect(),
+            ))),
+            replace,
+        );
+    }
+
     fn expand_font_shorthand(&mut self, font: &Font, replace: bool) {
         let FontSpec {
             style,
@@ -549,6 +756,32 @@ impl SpecifiedValues {
         );
     }
 
+    fn expand_text_decoration_shorthand(&mut self, decoration: &TextDecoration, replace: bool) {
+        let TextDecoration {
+            line,
+            style,
+            color,
+            thickness,
+        } = decoration.clone();
+
+        self.set_property(
+            &ParsedProperty::TextDecorationLine(SpecifiedValue::Specified(line)),
+            replace,
+        );
+        self.set_property(
+            &ParsedProperty::TextDecorationStyle(SpecifiedValue::Specified(style)),
+            replace,
+        );
+        self.set_property(
+            &ParsedProperty::TextDecorationColor(SpecifiedValue::Specified(color)),
+            replace,
+        );
+        self.set_property(
+            &ParsedProperty::TextDecorationThickness(SpecifiedValue::Specified(thickness)),
+            replace,
+        );
+    }
+
     pub fn set_parsed_property(&mut self, prop: &ParsedProperty) {
         self.set_property_expanding_shorthands(prop, true);
     }
@@ -585,13 +818,24 @@ impl SpecifiedValues {
 
         // Then, do all the other properties.
 
+        compute!(AlignmentBaseline, alignment_baseline);
+        compute!(AnimationDelay, animation_delay);
+        compute!(AnimationDirection, animation_direction);
+        compute!(AnimationDuration, animation_duration);
+        compute!(AnimationFillMode, animation_fill_mode);
+        compute!(AnimationIterationCount, animation_iteration_count);
+        compute!(AnimationName, animation_name);
+        compute!(AnimationPlayState, animation_play_state);
+        compute!(AnimationTimingFunction, animation_timing_function);
         compute!(BaselineShift, baseline_shift);
         compute!(ClipPath, clip_path);
         compute!(ClipRule, clip_rule);
         compute!(Color, color);
+        compute!(ColorInterpolation, color_interpolation);
         compute!(ColorInterpolationFilters, color_interpolation_filters);
         compute!(Direction, direction);
         compute!(Display, display);
+        compute!(DominantBaseline, dominant_baseline);
         compute!(EnableBackground, enable_background);
         compute!(Fill, fill);
         compute!(FillOpacity, fill_opacity);
@@ -600,20 +844,34 @@ impl SpecifiedValues {
         compute!(FloodColor, flood_color);
         compute!(FloodOpacity, flood_opacity);
         compute!(FontFamily, font_family);
+        compute!(FontFeatureSettings, font_feature_settings);
+        compute!(FontKerning, font_kerning);
+        compute!(FontOpticalSizing, font_optical_sizing);
         compute!(FontStretch, font_stretch);
         compute!(FontStyle, font_style);
         compute!(FontVariant, font_variant);
+        compute!(FontVariantCaps, font_variant_caps);
+        compute!(FontVariantEastAsian, font_variant_east_asian);
+        compute!(FontVariantLigatures, font_variant_ligatures);
+        compute!(FontVariantNumeric, font_variant_numeric);
+        compute!(FontVariationSettings, font_variation_settings);
         compute!(FontWeight, font_weight);
+        compute!(InlineSize, inline_size);
+        compute!(Isolation, isolation);
         compute!(LetterSpacing, letter_spacing);
         compute!(LightingColor, lighting_color);
+        compute!(LineHeight, line_height);
         compute!(MarkerEnd, marker_end);
         compute!(MarkerMid, marker_mid);
         compute!(MarkerStart, marker_start);
         compute!(Mask, mask);
+        compute!(MaskMode, mask_mode);
+        compute!(MaskType, mask_type);
         compute!(MixBlendMode, mix_blend_mode);
         compute!(Opacity, opacity);
         compute!(Overflow, overflow);
         compute!(PaintOrder, paint_order);
+        compute!(ShapeInside, shape_inside);
         compute!(ShapeRendering, shape_rendering);
         compute!(StopColor, stop_color);
         compute!(StopOpacity, stop_opacity);
@@ -626,11 +884,19 @@ impl SpecifiedValues {
         compute!(StrokeMiterlimit, stroke_miterlimit);
         compute!(StrokeWidth, stroke_width);
         compute!(TextAnchor, text_anchor);
-        compute!(TextDecoration, text_decoration);
+        compute!(TextDecorationColor, text_decoration_color);
+        compute!(TextDecorationLine, text_decoration_line);
+        compute!(TextDecorationStyle, text_decoration_style);
+        compute!(TextDecorationThickness, text_decoration_thickness);
+        compute!(TextOrientation, text_orientation);
         compute!(TextRendering, text_rendering);
+        compute!(TextUnderlineOffset, text_underline_offset);
         compute!(TransformProperty, transform_property);
         compute!(UnicodeBidi, unicode_bidi);
+        compute!(VectorEffect, vector_effect);
         compute!(Visibility, visibility);
+        compute!(WhiteSpace, white_space);
+        compute!(WordSpacing, word_spacing);
         compute!(WritingMode, writing_mode);
         compute!(XmlLang, xml_lang);
         compute!(XmlSpace, xml_space);
@@ -928,6 +1194,36 @@ mod tests {
         assert_eq!(computed.marker_end(), MarkerEnd(iri2.clone()));
     }
 
+    #[test]
+    fn expands_text_decoration_presentation_attribute() {
+        let mut input = ParserInput::new("underline dashed blue");
+        let mut parser = Parser::new(&mut input);
+
+        let name = QualName::new(None, ns!(), local_name!("text-decoration"));
+        let prop = parse_property(&name, &mut parser, false).unwrap();
+
+        let mut specified = SpecifiedValues::default();
+        specified.set_parsed_property(&prop);
+
+        let mut computed = ComputedValues::default();
+        specified.to_computed_values(&mut computed);
+
+        assert!(computed.text_decoration_line().underline);
+        assert_eq!(
+            computed.text_decoration_style(),
+            TextDecorationStyle::Dashed
+        );
+        assert_eq!(
+            computed.text_decoration_color(),
+            TextDecorationColor(cssparser::Color::RGBA(cssparser::RGBA::new(0, 0, 255, 255)))
+        );
+
+        let name = QualName::new(None, ns!(), local_name!("marker"));
+        let mut input = ParserInput::new("url(#foo)");
+        let mut parser = Parser::new(&mut input);
+        assert!(parse_property(&name, &mut parser, false).is_err());
+    }
+
     #[test]
     fn computes_property_that_does_not_inherit_automatically() {
         assert_eq!(<Opacity as Property>::inherits_automatically(), false);
@@ -38,11 +38,23 @@ use std::convert::TryInto;
 
 use cssparser::{Parser, Token};
 
+use crate::angle::Angle;
+use crate::basic_shape::ShapeClip;
 use crate::dasharray::Dasharray;
+use crate::document::NodeId;
 use crate::error::*;
 use crate::filter::FilterValueList;
-use crate::font_props::{Font, FontFamily, FontSize, FontWeight, LetterSpacing, LineHeight};
+use crate::font_props::{
+    Font, FontFamily, FontFeatureSettings, FontSize, FontVariantEastAsian, FontVariantLigatures,
+    FontVariantNumeric, FontVariationSettings, FontWeight, LetterSpacing, LineHeight, WordSpacing,
+};
 use crate::iri::Iri;
+use crate::keyframes::{
+    Animation, AnimationDelay, AnimationDirection, AnimationDuration, AnimationFillMode,
+    AnimationIterationCount, AnimationName, AnimationPlayState, AnimationTimingFunction,
+    SingleAnimation, SingleAnimationDirection, SingleAnimationFillMode, SingleAnimationPlayState,
+    TimingFunction,
+};
 use crate::length::*;
 use crate::paint_server::PaintServer;
 use crate::parsers::Parse;
@@ -52,6 +64,122 @@ use crate::rect::Rect;
 use crate::transform::TransformProperty;
 use crate::unit_interval::UnitInterval;
 
+make_property!(
+    /// `alignment-baseline` property.
+    ///
+    /// https://www.w3.org/TR/SVG2/text.html#AlignmentBaselineProperty
+    ///
+    /// https://www.w3.org/TR/css-inline-3/#alignment-baseline-property
+    ///
+    /// The `auto`, `before-edge`, `text-before-edge`, `after-edge` and `text-after-edge`
+    /// values are from SVG 1.1; they are equivalent to `baseline`, `text-top`, and
+    /// `text-bottom` in CSS.
+    AlignmentBaseline,
+    default: Baseline,
+    inherits_automatically: false,
+
+    identifiers:
+    "auto" => Auto,
+    "baseline" => Baseline,
+    "alphabetic" => Alphabetic,
+    "ideographic" => Ideographic,
+    "hanging" => Hanging,
+    "mathematical" => Mathematical,
+    "central" => Central,
+    "middle" => Middle,
+    "text-top" => TextTop,
+    "text-bottom" => TextBottom,
+    "before-edge" => BeforeEdge,
+    "text-before-edge" => TextBeforeEdge,
+    "after-edge" => AfterEdge,
+    "text-after-edge" => TextAfterEdge,
+);
+
+#[cfg(test)]
+#[test]
+fn parses_alignment_baseline() {
+    assert_eq!(
+        AlignmentBaseline::parse_str("auto").unwrap(),
+        AlignmentBaseline::Auto
+    );
+
+    assert_eq!(
+        AlignmentBaseline::parse_str("central").unwrap(),
+        AlignmentBaseline::Central
+    );
+
+    assert_eq!(
+        AlignmentBaseline::parse_str("text-before-edge").unwrap(),
+        AlignmentBaseline::TextBeforeEdge
+    );
+
+    assert!(AlignmentBaseline::parse_str("top").is_err());
+    assert!(AlignmentBaseline::parse_str("use-script").is_err());
+}
+
+make_property!(
+    // docs are in keyframes.rs
+    Animation,
+    default: Animation(vec![SingleAnimation::default()]),
+    inherits_automatically: false,
+);
+
+make_property!(
+    // docs are in keyframes.rs
+    AnimationDelay,
+    default: AnimationDelay(vec![0.0]),
+    inherits_automatically: false,
+);
+
+make_property!(
+    // docs are in keyframes.rs
+    AnimationDirection,
+    default: AnimationDirection(vec![SingleAnimationDirection::Normal]),
+    inherits_automatically: false,
+);
+
+make_property!(
+    // docs are in keyframes.rs
+    AnimationDuration,
+    default: AnimationDuration(vec![0.0]),
+    inherits_automatically: false,
+);
+
+make_property!(
+    // docs are in keyframes.rs
+    AnimationFillMode,
+    default: AnimationFillMode(vec![SingleAnimationFillMode::None]),
+    inherits_automatically: false,
+);
+
+make_property!(
+    // docs are in keyframes.rs
+    AnimationIterationCount,
+    default: AnimationIterationCount(vec![1.0]),
+    inherits_automatically: false,
+);
+
+make_property!(
+    // docs are in keyframes.rs
+    AnimationName,
+    default: AnimationName(vec![None]),
+    inherits_automatically: false,
+);
+
+make_property!(
+    // docs are in keyframes.rs
+    AnimationPlayState,
+    default: AnimationPlayState(vec![SingleAnimationPlayState::Running]),
+    inherits_automatically: false,
+);
+
+make_property!(
+    // docs are in keyframes.rs
+    AnimationTimingFunction,
+    default: AnimationTimingFunction(vec![TimingFunction::default()]),
+    inherits_automatically: false,
+);
+
 make_property!(
     /// `baseline-shift` property.
     ///
@@ -115,18 +243,65 @@ make_property!(
     }
 );
 
+/// `clip-path` property.
+///
+/// https://www.w3.org/TR/SVG/masking.html#ClipPathPropert
+///
+/// https://www.w3.org/TR/css-masking-1/#the-clip-path
+///
+/// Besides references to `clipPath` elements, this supports the CSS basic shapes with
+/// an optional reference box, like `circle(50%) fill-box`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ClipPath {
+    None,
+    Url(Box<NodeId>),
+    Shape(Box<ShapeClip>),
+}
+
 make_property!(
-    /// `clip-path` property.
-    ///
-    /// https://www.w3.org/TR/SVG/masking.html#ClipPathPropert
-    ///
-    /// https://www.w3.org/TR/css-masking-1/#the-clip-path
     ClipPath,
-    default: Iri::None,
+    default: ClipPath::None,
     inherits_automatically: false,
-    newtype_parse: Iri,
+    parse_impl: {
+        impl Parse for ClipPath {
+            fn parse<'i>(parser: &mut Parser<'i, '_>) -> Result<Self, crate::error::ParseError<'i>> {
+                match parser.try_parse(Iri::parse) {
+                    Ok(Iri::None) => Ok(ClipPath::None),
+                    Ok(Iri::Resource(node_id)) => Ok(ClipPath::Url(node_id)),
+                    Err(_) => Ok(ClipPath::Shape(Box::new(ShapeClip::parse(parser)?))),
+                }
+            }
+        }
+    }
 );
 
+#[cfg(test)]
+#[test]
+fn parses_clip_path() {
+    use crate::basic_shape::{BasicShape, ShapeBox};
+
+    assert_eq!(ClipPath::parse_str("none").unwrap(), ClipPath::None);
+
+    assert_eq!(
+        ClipPath::parse_str("url(#clip)").unwrap(),
+        ClipPath::Url(Box::new(NodeId::Internal("clip".to_string())))
+    );
+
+    assert_eq!(
+        ClipPath::parse_str("inset(50%) fill-box").unwrap(),
+        ClipPath::Shape(Box::new(ShapeClip {
+            shape: BasicShape::Inset {
+                offsets: [Length::<Both>::new(0.5, LengthUnit::Percent); 4],
+                radii: [ULength::<Both>::new(0.0, LengthUnit::Px); 4],
+            },
+            reference_box: ShapeBox::Fill,
+        }))
+    );
+
+    assert!(ClipPath::parse_str("url(#clip) fill-box").is_err());
+    assert!(ClipPath::parse_str("square(10px)").is_err());
+}
+
 make_property!(
     /// `clip-rule` property.
     ///
@@ -161,6 +336,22 @@ make_property!(
     newtype_parse: cssparser::RGBA,
 );
 
+make_property!(
+    /// `color-interpolation` property.
+    ///
+    /// https://www.w3.org/TR/SVG11/painting.html#ColorInterpolationProperty
+    ///
+    /// https://www.w3.org/TR/SVG2/painting.html#ColorInterpolation
+    ColorInterpolation,
+    default: Srgb,
+    inherits_automatically: true,
+
+    identifiers:
+    "auto" => Auto,
+    "linearRGB" => LinearRgb,
+    "sRGB" => Srgb,
+);
+
 make_property!(
     /// `color-interpolation-filters` property.
     ///
@@ -222,6 +413,56 @@ make_property!(
     "none" => None,
 );
 
+make_property!(
+    /// `dominant-baseline` property.
+    ///
+    /// https://www.w3.org/TR/SVG2/text.html#DominantBaselineProperty
+    ///
+    /// https://www.w3.org/TR/css-inline-3/#dominant-baseline-property
+    ///
+    /// The `text-before-edge` and `text-after-edge` values are from SVG 1.1; they are
+    /// equivalent to `text-top` and `text-bottom` in CSS.
+    DominantBaseline,
+    default: Auto,
+    inherits_automatically: true,
+
+    identifiers:
+    "auto" => Auto,
+    "alphabetic" => Alphabetic,
+    "ideographic" => Ideographic,
+    "hanging" => Hanging,
+    "mathematical" => Mathematical,
+    "central" => Central,
+    "middle" => Middle,
+    "text-top" => TextTop,
+    "text-bottom" => TextBottom,
+    "text-before-edge" => TextBeforeEdge,
+    "text-after-edge" => TextAfterEdge,
+);
+
+#[cfg(test)]
+#[test]
+fn parses_dominant_baseline() {
+    assert_eq!(
+        DominantBaseline::parse_str("auto").unwrap(),
+        DominantBaseline::Auto
+    );
+
+    assert_eq!(
+        DominantBaseline::parse_str("middle").unwrap(),
+        DominantBaseline::Middle
+    );
+
+    assert_eq!(
+        DominantBaseline::parse_str("text-after-edge").unwrap(),
+        DominantBaseline::TextAfterEdge
+    );
+
+    // This is only valid for alignment-baseline.
+    assert!(DominantBaseline::parse_str("baseline").is_err());
+    assert!(DominantBaseline::parse_str("reset-size").is_err());
+}
+
 /// `enable-background` property.
 ///
 /// https://www.w3.org/TR/SVG/filters.html#EnableBackgroundProperty
@@ -404,6 +645,40 @@ make_property!(
     inherits_automatically: true,
 );
 
+make_property!(
+    // docs are in font_props.rs
+    FontFeatureSettings,
+    default: FontFeatureSettings(Vec::new()),
+    inherits_automatically: true,
+);
+
+make_property!(
+    /// `font-kerning` property.
+    ///
+    /// https://www.w3.org/TR/css-fonts-3/#font-kerning-prop
+    FontKerning,
+    default: Auto,
+    inherits_automatically: true,
+
+    identifiers:
+    "auto" => Auto,
+    "normal" => Normal,
+    "none" => None,
+);
+
+make_property!(
+    /// `font-optical-sizing` property.
+    ///
+    /// https://www.w3.org/TR/css-fonts-4/#font-optical-sizing-def
+    FontOpticalSizing,
+    default: Auto,
+    inherits_automatically: true,
+
+    identifiers:
+    "auto" => Auto,
+    "none" => None,
+);
+
 make_property!(
     // docs are in font_props.rs
     FontSize,
@@ -478,6 +753,67 @@ make_property!(
     "small-caps" => SmallCaps,
 );
 
+make_property!(
+    /// `font-variant-caps` property.
+    ///
+    /// https://www.w3.org/TR/css-fonts-3/#font-variant-caps-prop
+    FontVariantCaps,
+    default: Normal,
+    inherits_automatically: true,
+
+    identifiers:
+    "normal" => Normal,
+    "small-caps" => SmallCaps,
+    "all-small-caps" => AllSmallCaps,
+    "petite-caps" => PetiteCaps,
+    "all-petite-caps" => AllPetiteCaps,
+    "unicase" => Unicase,
+    "titling-caps" => TitlingCaps,
+);
+
+make_property!(
+    // docs are in font_props.rs
+    FontVariantEastAsian,
+    default: FontVariantEastAsian {
+        variant: None,
+        width: None,
+        ruby: false,
+    },
+    inherits_automatically: true,
+);
+
+make_property!(
+    // docs are in font_props.rs
+    FontVariantLigatures,
+    default: FontVariantLigatures {
+        common: None,
+        discretionary: None,
+        historical: None,
+        contextual: None,
+    },
+    inherits_automatically: true,
+);
+
+make_property!(
+    // docs are in font_props.rs
+    FontVariantNumeric,
+    default: FontVariantNumeric {
+        figure: None,
+        spacing: None,
+        fraction: None,
+        ordinal: false,
+        slashed_zero: false,
+    },
+    inherits_automatically: true,
+);
+
+make_property!(
+    // docs are in font_props.rs
+    FontVariationSettings,
+    default: FontVariationSettings(Vec::new()),
+    inherits_automatically: true,
+);
+
 make_property!(
     // docs are in font_props.rs
     FontWeight,
@@ -495,6 +831,124 @@ make_property!(
     }
 );
 
+make_property!(
+    /// `glyph-orientation-vertical` property.
+    ///
+    /// https://www.w3.org/TR/SVG11/text.html#GlyphOrientationVerticalProperty
+    ///
+    /// https://www.w3.org/TR/css-writing-modes-3/#propdef-glyph-orientation-vertical
+    ///
+    /// This is a shorthand for the `text-orientation` property, like CSS Writing Modes
+    /// requires: `auto` is `mixed`, `0deg` is `upright`, and `90deg` is `sideways`.  The
+    /// other angles from SVG 1.1 are not supported.
+    GlyphOrientationVertical,
+    default: TextOrientation::Mixed,
+    inherits_automatically: true,
+    newtype: TextOrientation,
+    parse_impl: {
+        impl Parse for GlyphOrientationVertical {
+            fn parse<'i>(parser: &mut Parser<'i, '_>) -> Result<GlyphOrientationVertical, ParseError<'i>> {
+                if parser.try_parse(|p| p.expect_ident_matching("auto")).is_ok() {
+                    return Ok(GlyphOrientationVertical(TextOrientation::Mixed));
+                }
+
+                let loc = parser.current_source_location();
+                let degrees = Angle::parse(parser)?.radians().to_degrees().round();
+
+                if degrees == 0.0 {
+                    Ok(GlyphOrientationVertical(TextOrientation::Upright))
+                } else if degrees == 90.0 {
+                    Ok(GlyphOrientationVertical(TextOrientation::Sideways))
+                } else {
+                    Err(loc.new_custom_error(ValueErrorKind::parse_error(
+                        "only 0deg and 90deg are supported for 'glyph-orientation-vertical'",
+                    )))
+                }
+            }
+        }
+    },
+);
+
+#[cfg(test)]
+#[test]
+fn parses_glyph_orientation_vertical() {
+    assert_eq!(
+        GlyphOrientationVertical::parse_str("auto").unwrap(),
+        GlyphOrientationVertical(TextOrientation::Mixed)
+    );
+
+    assert_eq!(
+        GlyphOrientationVertical::parse_str("0").unwrap(),
+        GlyphOrientationVertical(TextOrientation::Upright)
+    );
+
+    assert_eq!(
+        GlyphOrientationVertical::parse_str("90deg").unwrap(),
+        GlyphOrientationVertical(TextOrientation::Sideways)
+    );
+
+    assert!(GlyphOrientationVertical::parse_str("180").is_err());
+}
+
+make_property!(
+    /// `inline-size` property.
+    ///
+    /// https://www.w3.org/TR/SVG2/text.html#InlineSize
+    ///
+    /// Setting this to something other than `auto` makes a `<text>` element wrap its
+    /// contents into lines of up to the given size.  A `None` value means `auto`.
+    InlineSize,
+    default: None,
+    inherits_automatically: false,
+    newtype: Option<ULength<Horizontal>>,
+    parse_impl: {
+        impl Parse for InlineSize {
+            fn parse<'i>(parser: &mut Parser<'i, '_>) -> Result<InlineSize, ParseError<'i>> {
+                if parser.try_parse(|p| p.expect_ident_matching("auto")).is_ok() {
+                    Ok(InlineSize(None))
+                } else {
+                    Ok(InlineSize(Some(ULength::<Horizontal>::parse(parser)?)))
+                }
+            }
+        }
+    },
+);
+
+#[cfg(test)]
+#[test]
+fn parses_inline_size() {
+    assert_eq!(InlineSize::parse_str("auto").unwrap(), InlineSize(None));
+
+    assert_eq!(
+        InlineSize::parse_str("100").unwrap(),
+        InlineSize(Some(ULength::<Horizontal>::new(100.0, LengthUnit::Px)))
+    );
+
+    assert_eq!(
+        InlineSize::parse_str("50%").unwrap(),
+        InlineSize(Some(ULength::<Horizontal>::new(0.5, LengthUnit::Percent)))
+    );
+
+    assert!(InlineSize::parse_str("-10").is_err());
+    assert!(InlineSize::parse_str("none").is_err());
+}
+
+make_property!(
+    /// `isolation` property.
+    ///
+    /// https://www.w3.org/TR/compositing/#isolation
+    ///
+    /// With `isolate`, an element creates an isolated group, so that its descendants with
+    /// `mix-blend-mode` only blend with the contents of the group.
+    Isolation,
+    default: Auto,
+    inherits_automatically: false,
+
+    identifiers:
+    "auto" => Auto,
+    "isolate" => Isolate,
+);
+
 make_property!(
     // docs are in font_props.rs
     LetterSpacing,
@@ -516,7 +970,17 @@ make_property!(
     // docs are in font_props.rs
     LineHeight,
     default: LineHeight::Normal,
-    inherits_automatically: true,
+    property_impl: {
+        impl Property for LineHeight {
+            fn inherits_automatically() -> bool {
+                true
+            }
+
+            fn compute(&self, v: &ComputedValues) -> Self {
+                self.compute(v)
+            }
+        }
+    }
 );
 
 make_property!(
@@ -588,6 +1052,38 @@ make_property!(
     newtype_parse: Iri,
 );
 
+make_property!(
+    /// `mask-mode` property.
+    ///
+    /// https://www.w3.org/TR/css-masking-1/#the-mask-mode
+    ///
+    /// With `match-source`, the `mask-type` of the referenced `<mask>` element decides
+    /// whether the mask uses luminance or alpha values.
+    MaskMode,
+    default: MatchSource,
+    inherits_automatically: false,
+
+    identifiers:
+    "alpha" => Alpha,
+    "luminance" => Luminance,
+    "match-source" => MatchSource,
+);
+
+make_property!(
+    /// `mask-type` property.
+    ///
+    /// https://www.w3.org/TR/css-masking-1/#the-mask-type
+    ///
+    /// This applies to `<mask>` elements.
+    MaskType,
+    default: Luminance,
+    inherits_automatically: false,
+
+    identifiers:
+    "luminance" => Luminance,
+    "alpha" => Alpha,
+);
+
 make_property!(
     /// `mix-blend-mode` property.
     ///
@@ -733,6 +1229,56 @@ fn parses_paint_order() {
     assert!(PaintOrder::parse_str("markers stroke fill hello").is_err());
 }
 
+make_property!(
+    /// `shape-inside` property.
+    ///
+    /// https://www.w3.org/TR/SVG2/text.html#TextShapeInside
+    ///
+    /// https://drafts.csswg.org/css-shapes-2/#shape-inside-property
+    ///
+    /// Librsvg only supports references to SVG shape elements for this property.  An
+    /// `Iri::None` value means `auto`.
+    ShapeInside,
+    default: Iri::None,
+    inherits_automatically: false,
+    newtype: Iri,
+    parse_impl: {
+        impl Parse for ShapeInside {
+            fn parse<'i>(parser: &mut Parser<'i, '_>) -> Result<ShapeInside, ParseError<'i>> {
+                if parser.try_parse(|p| p.expect_ident_matching("auto")).is_ok() {
+                    return Ok(ShapeInside(Iri::None));
+                }
+
+                match Iri::parse(parser)? {
+                    // "none" is not a valid value for shape-inside
+                    Iri::None => Err(parser.new_custom_error(ValueErrorKind::parse_error(
+                        "expected auto or url()",
+                    ))),
+                    iri => Ok(ShapeInside(iri)),
+                }
+            }
+        }
+    },
+);
+
+#[cfg(test)]
+#[test]
+fn parses_shape_inside() {
+    assert_eq!(
+        ShapeInside::parse_str("auto").unwrap(),
+        ShapeInside(Iri::None)
+    );
+
+    assert_eq!(
+        ShapeInside::parse_str("url(#shape)").unwrap(),
+        ShapeInside(Iri::Resource(Box::new(NodeId::Internal(
+            "shape".to_string()
+        ))))
+    );
+
+    assert!(ShapeInside::parse_str("none").is_err());
+}
+
 make_property!(
     /// `shape-rendering` property.
     ///
@@ -877,40 +1423,194 @@ make_property!(
     ///
     /// https://www.w3.org/TR/css-text-decor-3/#text-decoration-property
     ///
-    /// Note that librsvg implements SVG1.1 semantics, where this is not a shorthand.
+    /// This is a shorthand, which expands to the `text-decoration-line`,
+    /// `text-decoration-style`, `text-decoration-color`, and `text-decoration-thickness`
+    /// longhand properties.  Since it was a single property in SVG 1.1, it is also
+    /// accepted as a presentation attribute.
     TextDecoration,
     inherits_automatically: false,
 
     fields: {
-        overline: bool, default: false,
-        underline: bool, default: false,
-        strike: bool, default: false,
+        line: TextDecorationLine, default: TextDecorationLine {
+            overline: false,
+            underline: false,
+            strike: false,
+        },
+        style: TextDecorationStyle, default: TextDecorationStyle::Solid,
+        color: TextDecorationColor, default: TextDecorationColor(cssparser::Color::CurrentColor),
+        thickness: TextDecorationThickness, default: TextDecorationThickness::Auto,
     }
 
     parse_impl: {
         impl Parse for TextDecoration {
             fn parse<'i>(parser: &mut Parser<'i, '_>) -> Result<TextDecoration, ParseError<'i>> {
-                let mut overline = false;
-                let mut underline = false;
-                let mut strike = false;
+                let loc = parser.current_source_location();
+
+                let mut line = None;
+                let mut style = None;
+                let mut color = None;
+                let mut thickness = None;
+
+                // The components can come in any order, but each one at most once.
+                loop {
+                    if line.is_none() {
+                        if let Ok(l) = parser.try_parse(TextDecorationLine::parse) {
+                            line = Some(l);
+                            continue;
+                        }
+                    }
+
+                    if style.is_none() {
+                        if let Ok(s) = parser.try_parse(TextDecorationStyle::parse) {
+                            style = Some(s);
+                            continue;
+                        }
+                    }
+
+                    if thickness.is_none() {
+                        if let Ok(t) = parser.try_parse(TextDecorationThickness::parse) {
+                            thickness = Some(t);
+                            continue;
+                        }
+                    }
+
+                    if color.is_none() {
+                        if let Ok(c) = parser.try_parse(TextDecorationColor::parse) {
+                            color = Some(c);
+                            continue;
+                        }
+                    }
 
+                    break;
+                }
+
+                if line.is_none() && style.is_none() && color.is_none() && thickness.is_none() {
+                    return Err(loc.new_custom_error(ValueErrorKind::parse_error(
+                        "invalid syntax for 'text-decoration' property",
+                    )));
+                }
+
+                let default = TextDecoration::default();
+
+                Ok(TextDecoration {
+                    line: line.unwrap_or(default.line),
+                    style: style.unwrap_or(default.style),
+                    color: color.unwrap_or(default.color),
+                    thickness: thickness.unwrap_or(default.thickness),
+                })
+            }
+        }
+    }
+);
+
+#[cfg(test)]
+#[test]
+fn parses_text_decoration() {
+    let underline = TextDecorationLine {
+        overline: false,
+        underline: true,
+        strike: false,
+    };
+
+    assert_eq!(
+        TextDecoration::parse_str("underline").unwrap(),
+        TextDecoration {
+            line: underline.clone(),
+            ..Default::default()
+        }
+    );
+
+    assert_eq!(
+        TextDecoration::parse_str("red wavy underline").unwrap(),
+        TextDecoration {
+            line: underline.clone(),
+            style: TextDecorationStyle::Wavy,
+            color: TextDecorationColor(cssparser::Color::RGBA(cssparser::RGBA::new(
+                255, 0, 0, 255
+            ))),
+            ..Default::default()
+        }
+    );
+
+    assert_eq!(
+        TextDecoration::parse_str("underline dotted 2px").unwrap(),
+        TextDecoration {
+            line: underline,
+            style: TextDecorationStyle::Dotted,
+            thickness: TextDecorationThickness::Length(Length::<Both>::new(2.0, LengthUnit::Px)),
+            ..Default::default()
+        }
+    );
+
+    assert!(TextDecoration::parse_str("").is_err());
+    assert!(TextDecoration::parse_str("airline").is_err());
+    assert!(TextDecoration::parse_str("underline solid dashed").is_err());
+}
+
+make_property!(
+    /// `text-decoration-color` property.
+    ///
+    /// https://www.w3.org/TR/css-text-decor-3/#text-decoration-color-property
+    TextDecorationColor,
+    default: cssparser::Color::CurrentColor,
+    inherits_automatically: false,
+    newtype_parse: cssparser::Color,
+);
+
+make_property!(
+    /// `text-decoration-line` property.
+    ///
+    /// https://www.w3.org/TR/css-text-decor-3/#text-decoration-line-property
+    ///
+    /// The `blink` value is accepted, but ignored.
+    TextDecorationLine,
+    inherits_automatically: false,
+
+    fields: {
+        overline: bool, default: false,
+        underline: bool, default: false,
+        strike: bool, default: false,
+    }
+
+    parse_impl: {
+        impl Parse for TextDecorationLine {
+            fn parse<'i>(parser: &mut Parser<'i, '_>) -> Result<TextDecorationLine, ParseError<'i>> {
                 if parser.try_parse(|p| p.expect_ident_matching("none")).is_ok() {
-                    return Ok(TextDecoration::default());
+                    return Ok(TextDecorationLine::default());
                 }
 
-                while !parser.is_exhausted() {
-                    let loc = parser.current_source_location();
-                    let token = parser.next()?;
+                let loc = parser.current_source_location();
 
-                    match token {
-                        Token::Ident(ref cow) if cow.eq_ignore_ascii_case("overline") => overline = true,
-                        Token::Ident(ref cow) if cow.eq_ignore_ascii_case("underline") => underline = true,
-                        Token::Ident(ref cow) if cow.eq_ignore_ascii_case("line-through") => strike = true,
-                        _ => return Err(loc.new_basic_unexpected_token_error(token.clone()).into()),
+                // Parse the keywords until something else, like the other values of
+                // the text-decoration shorthand, comes up.
+                let mut flags = [false; 4];
+
+                while let Ok(i) = parser.try_parse(|p| -> Result<_, ParseError<'_>> {
+                    Ok(parse_identifiers!(
+                        p,
+                        "overline" => 0,
+                        "underline" => 1,
+                        "line-through" => 2,
+                        "blink" => 3,
+                    )?)
+                }) {
+                    if flags[i] {
+                        return Err(loc.new_custom_error(ValueErrorKind::parse_error(
+                            "repeated keyword in 'text-decoration-line' property",
+                        )));
                     }
+
+                    flags[i] = true;
                 }
 
-                Ok(TextDecoration {
+                let [overline, underline, strike, blink] = flags;
+
+                if !(overline || underline || strike || blink) {
+                    let token = parser.next()?.clone();
+                    return Err(loc.new_basic_unexpected_token_error(token).into());
+                }
+
+                Ok(TextDecorationLine {
                     overline,
                     underline,
                     strike,
@@ -922,10 +1622,10 @@ make_property!(
 
 #[cfg(test)]
 #[test]
-fn parses_text_decoration() {
+fn parses_text_decoration_line() {
     assert_eq!(
-        TextDecoration::parse_str("none").unwrap(),
-        TextDecoration {
+        TextDecorationLine::parse_str("none").unwrap(),
+        TextDecorationLine {
             overline: false,
             underline: false,
             strike: false,
@@ -933,8 +1633,8 @@ fn parses_text_decoration() {
     );
 
     assert_eq!(
-        TextDecoration::parse_str("overline").unwrap(),
-        TextDecoration {
+        TextDecorationLine::parse_str("overline").unwrap(),
+        TextDecorationLine {
             overline: true,
             underline: false,
             strike: false,
@@ -942,8 +1642,8 @@ fn parses_text_decoration() {
     );
 
     assert_eq!(
-        TextDecoration::parse_str("underline").unwrap(),
-        TextDecoration {
+        TextDecorationLine::parse_str("underline").unwrap(),
+        TextDecorationLine {
             overline: false,
             underline: true,
             strike: false,
@@ -951,8 +1651,8 @@ fn parses_text_decoration() {
     );
 
     assert_eq!(
-        TextDecoration::parse_str("line-through").unwrap(),
-        TextDecoration {
+        TextDecorationLine::parse_str("line-through").unwrap(),
+        TextDecorationLine {
             overline: false,
             underline: false,
             strike: true,
@@ -960,17 +1660,85 @@ fn parses_text_decoration() {
     );
 
     assert_eq!(
-        TextDecoration::parse_str("underline overline").unwrap(),
-        TextDecoration {
+        TextDecorationLine::parse_str("underline overline").unwrap(),
+        TextDecorationLine {
             overline: true,
             underline: true,
             strike: false,
         }
     );
 
-    assert!(TextDecoration::parse_str("airline").is_err())
+    assert!(TextDecorationLine::parse_str("airline").is_err());
+    assert!(TextDecorationLine::parse_str("underline underline").is_err());
+}
+
+make_property!(
+    /// `text-decoration-style` property.
+    ///
+    /// https://www.w3.org/TR/css-text-decor-3/#text-decoration-style-property
+    TextDecorationStyle,
+    default: Solid,
+    inherits_automatically: false,
+
+    identifiers:
+    "solid" => Solid,
+    "double" => Double,
+    "dotted" => Dotted,
+    "dashed" => Dashed,
+    "wavy" => Wavy,
+);
+
+/// `text-decoration-thickness` property.
+///
+/// https://www.w3.org/TR/css-text-decor-4/#text-decoration-width-property
+///
+/// Percentages refer to the font size.  For both `auto` and `from-font`, librsvg
+/// uses the thickness from the font's metrics.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TextDecorationThickness {
+    Auto,
+    FromFont,
+    Length(Length<Both>),
 }
 
+make_property!(
+    TextDecorationThickness,
+    default: TextDecorationThickness::Auto,
+    inherits_automatically: false,
+
+    parse_impl: {
+        impl Parse for TextDecorationThickness {
+            fn parse<'i>(parser: &mut Parser<'i, '_>) -> Result<Self, crate::error::ParseError<'i>> {
+                if let Ok(length) = parser.try_parse(Length::<Both>::parse) {
+                    return Ok(TextDecorationThickness::Length(length));
+                }
+
+                Ok(parse_identifiers!(
+                    parser,
+                    "auto" => TextDecorationThickness::Auto,
+                    "from-font" => TextDecorationThickness::FromFont,
+                )?)
+            }
+        }
+    }
+);
+
+make_property!(
+    /// `text-orientation` property.
+    ///
+    /// https://www.w3.org/TR/css-writing-modes-3/#text-orientation
+    ///
+    /// This only has an effect in vertical writing modes.
+    TextOrientation,
+    default: Mixed,
+    inherits_automatically: true,
+
+    identifiers:
+    "mixed" => Mixed,
+    "upright" => Upright,
+    "sideways" => Sideways,
+);
+
 make_property!(
     /// `text-rendering` property.
     ///
@@ -986,6 +1754,35 @@ make_property!(
     "geometricPrecision" => GeometricPrecision,
 );
 
+/// `text-underline-offset` property.
+///
+/// https://www.w3.org/TR/css-text-decor-4/#underline-offset
+///
+/// The offset is from the alphabetic baseline, and percentages refer to the font size.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TextUnderlineOffset {
+    Auto,
+    Length(Length<Both>),
+}
+
+make_property!(
+    TextUnderlineOffset,
+    default: TextUnderlineOffset::Auto,
+    inherits_automatically: true,
+
+    parse_impl: {
+        impl Parse for TextUnderlineOffset {
+            fn parse<'i>(parser: &mut Parser<'i, '_>) -> Result<Self, crate::error::ParseError<'i>> {
+                if parser.try_parse(|p| p.expect_ident_matching("auto")).is_ok() {
+                    Ok(TextUnderlineOffset::Auto)
+                } else {
+                    Ok(TextUnderlineOffset::Length(Length::<Both>::parse(parser)?))
+                }
+            }
+        }
+    }
+);
+
 make_property!(
     /// `transform` property.
     ///
@@ -1007,9 +1804,109 @@ make_property!(
     identifiers:
     "normal" => Normal,
     "embed" => Embed,
+    "isolate" => Isolate,
     "bidi-override" => Override,
+    "isolate-override" => IsolateOverride,
+    "plaintext" => Plaintext,
+);
+
+make_property!(
+    /// `vector-effect` property.
+    ///
+    /// https://www.w3.org/TR/SVG2/coords.html#VectorEffects
+    ///
+    /// With `non-scaling-stroke`, the stroke is computed in the host coordinate space, so
+    /// its width does not depend on the element's transforms.  The other values constrain
+    /// the transform from the element's user space to the host coordinate space.
+    VectorEffect,
+    inherits_automatically: false,
+
+    fields: {
+        non_scaling_stroke: bool, default: false,
+        non_scaling_size: bool, default: false,
+        non_rotation: bool, default: false,
+        fixed_position: bool, default: false,
+    }
+
+    parse_impl: {
+        impl Parse for VectorEffect {
+            fn parse<'i>(parser: &mut Parser<'i, '_>) -> Result<VectorEffect, ParseError<'i>> {
+                if parser.try_parse(|p| p.expect_ident_matching("none")).is_ok() {
+                    return Ok(VectorEffect::default());
+                }
+
+                let loc = parser.current_source_location();
+
+                let mut flags = [false; 4];
+
+                while let Ok(i) = parser.try_parse(|p| -> Result<_, ParseError<'_>> {
+                    Ok(parse_identifiers!(
+                        p,
+                        "non-scaling-stroke" => 0,
+                        "non-scaling-size" => 1,
+                        "non-rotation" => 2,
+                        "fixed-position" => 3,
+                    )?)
+                }) {
+                    if flags[i] {
+                        return Err(loc.new_custom_error(ValueErrorKind::parse_error(
+                            "repeated keyword in 'vector-effect' property",
+                        )));
+                    }
+
+                    flags[i] = true;
+                }
+
+                let [non_scaling_stroke, non_scaling_size, non_rotation, fixed_position] = flags;
+
+                if !(non_scaling_stroke || non_scaling_size || non_rotation || fixed_position) {
+                    let token = parser.next()?.clone();
+                    return Err(loc.new_basic_unexpected_token_error(token).into());
+                }
+
+                Ok(VectorEffect {
+                    non_scaling_stroke,
+                    non_scaling_size,
+                    non_rotation,
+                    fixed_position,
+                })
+            }
+        }
+    }
 );
 
+#[cfg(test)]
+#[test]
+fn parses_vector_effect() {
+    assert_eq!(
+        VectorEffect::parse_str("none").unwrap(),
+        VectorEffect::default()
+    );
+
+    assert_eq!(
+        VectorEffect::parse_str("non-scaling-stroke").unwrap(),
+        VectorEffect {
+            non_scaling_stroke: true,
+            ..Default::default()
+        }
+    );
+
+    assert_eq!(
+        VectorEffect::parse_str("fixed-position non-scaling-size non-rotation").unwrap(),
+        VectorEffect {
+            non_scaling_stroke: false,
+            non_scaling_size: true,
+            non_rotation: true,
+            fixed_position: true,
+        }
+    );
+
+    assert!(VectorEffect::parse_str("").is_err());
+    assert!(VectorEffect::parse_str("non-scaling").is_err());
+    assert!(VectorEffect::parse_str("none non-rotation").is_err());
+    assert!(VectorEffect::parse_str("non-rotation non-rotation").is_err());
+}
+
 make_property!(
     /// `visibility` property.
     ///
@@ -1026,6 +1923,56 @@ make_property!(
     "collapse" => Collapse,
 );
 
+make_property!(
+    /// `white-space` property.
+    ///
+    /// https://www.w3.org/TR/SVG2/text.html#TextWhiteSpace
+    ///
+    /// https://www.w3.org/TR/css-text-3/#white-space-property
+    ///
+    /// When this is `normal`, librsvg uses the value of the `xml:space` attribute instead.
+    WhiteSpace,
+    default: Normal,
+    inherits_automatically: true,
+
+    identifiers:
+    "normal" => Normal,
+    "pre" => Pre,
+    "nowrap" => NoWrap,
+    "pre-wrap" => PreWrap,
+    "break-spaces" => BreakSpaces,
+    "pre-line" => PreLine,
+);
+
+impl WhiteSpace {
+    /// Whether lines may be broken automatically to fit in the available space.
+    pub fn allows_wrapping(self) -> bool {
+        !matches!(self, WhiteSpace::Pre | WhiteSpace::NoWrap)
+    }
+
+    /// Whether newlines in the text force line breaks.
+    pub fn preserves_newlines(self) -> bool {
+        !matches!(self, WhiteSpace::Normal | WhiteSpace::NoWrap)
+    }
+}
+
+make_property!(
+    // docs are in font_props.rs
+    WordSpacing,
+    default: WordSpacing::Normal,
+    property_impl: {
+        impl Property for WordSpacing {
+            fn inherits_automatically() -> bool {
+                true
+            }
+
+            fn compute(&self, _v: &ComputedValues) -> Self {
+                self.compute()
+            }
+        }
+    }
+);
+
 make_property!(
     /// `writing-mode` property.
     ///
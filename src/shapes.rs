@@ -9,7 +9,7 @@ use std::rc::Rc;
 use crate::bbox::BoundingBox;
 use crate::document::AcquiredNodes;
 use crate::drawing_ctx::DrawingCtx;
-use crate::element::{Draw, ElementResult, SetAttributes};
+use crate::element::{Draw, Element, ElementResult, SetAttributes};
 use crate::error::*;
 use crate::iri::Iri;
 use crate::layout::{Marker, Shape, StackingContext, Stroke};
@@ -40,6 +40,24 @@ trait BasicShape {
     fn make_shape(&self, params: &NormalizeParams) -> ShapeDef;
 }
 
+/// Returns the path of a basic shape element, or `None` if the element is not a shape.
+///
+/// This is for properties that reference shapes, like `shape-inside`.
+pub fn shape_element_path(element: &Element, params: &NormalizeParams) -> Option<Rc<SvgPath>> {
+    let shape_def = match *element {
+        Element::Circle(ref e) => e.make_shape(params),
+        Element::Ellipse(ref e) => e.make_shape(params),
+        Element::Line(ref e) => e.make_shape(params),
+        Element::Path(ref e) => e.make_shape(params),
+        Element::Polygon(ref e) => e.make_shape(params),
+        Element::Polyline(ref e) => e.make_shape(params),
+        Element::Rect(ref e) => e.make_shape(params),
+        _ => return None,
+    };
+
+    Some(shape_def.path)
+}
+
 macro_rules! impl_draw {
     ($name:ident) => {
         impl Draw for $name {
@@ -166,7 +184,7 @@ fn acquire_marker(acquired_nodes: &mut AcquiredNodes<'_>, iri: &Iri) -> Option<N
     })
 }
 
-fn make_ellipse(cx: f64, cy: f64, rx: f64, ry: f64) -> SvgPath {
+pub(crate) fn make_ellipse(cx: f64, cy: f64, rx: f64, ry: f64) -> SvgPath {
     let mut builder = PathBuilder::default();
 
     // Per the spec, rx and ry must be nonnegative
@@ -229,6 +247,12 @@ pub struct Path {
 
 impl_draw!(Path);
 
+impl Path {
+    pub fn get_path(&self) -> Rc<SvgPath> {
+        self.path.clone()
+    }
+}
+
 impl SetAttributes for Path {
     fn set_attributes(&mut self, attrs: &Attributes) -> ElementResult {
         for (attr, value) in attrs.iter() {
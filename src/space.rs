@@ -1,4 +1,4 @@
-//! Processing of the `xml:space` attribute.
+//! Processing of the `xml:space` attribute and the `white-space` property.
 
 use itertools::Itertools;
 
@@ -10,6 +10,12 @@ pub struct NormalizeDefault {
 pub enum XmlSpaceNormalize {
     Default(NormalizeDefault),
     Preserve,
+
+    /// Like `Preserve`, but keeps newlines; for `white-space: pre | pre-wrap | break-spaces`.
+    PreserveNewlines,
+
+    /// Like `Default`, but keeps newlines; for `white-space: pre-line`.
+    CollapseSpaces(NormalizeDefault),
 }
 
 /// Implements `xml:space` handling per the SVG spec
@@ -21,6 +27,8 @@ pub fn xml_space_normalize(mode: XmlSpaceNormalize, s: &str) -> String {
     match mode {
         XmlSpaceNormalize::Default(d) => normalize_default(d, s),
         XmlSpaceNormalize::Preserve => normalize_preserve(s),
+        XmlSpaceNormalize::PreserveNewlines => normalize_preserve_newlines(s),
+        XmlSpaceNormalize::CollapseSpaces(d) => normalize_collapse_spaces(d, s),
     }
 }
 
@@ -74,6 +82,51 @@ fn normalize_preserve(s: &str) -> String {
         .collect()
 }
 
+// From https://www.w3.org/TR/css-text-3/#white-space-phase-1
+//
+// For white-space: pre, pre-wrap, and break-spaces, all spaces and
+// newlines are preserved.  Newlines become forced line breaks.  We turn
+// tabs into spaces, just like for xml:space="preserve".
+fn normalize_preserve_newlines(s: &str) -> String {
+    s.chars()
+        .map(|ch| match ch {
+            '\t' => ' ',
+
+            c => c,
+        })
+        .collect()
+}
+
+// From https://www.w3.org/TR/css-text-3/#white-space-phase-1
+//
+// For white-space: pre-line, sequences of spaces and tabs collapse into a
+// single space, and spaces around newlines are removed.  Newlines are kept
+// as forced line breaks.
+fn normalize_collapse_spaces(elements: NormalizeDefault, mut s: &str) -> String {
+    let is_space = |ch: char| ch == ' ' || ch == '\t';
+
+    if !elements.has_element_before {
+        s = s.trim_start_matches(is_space);
+    }
+
+    if !elements.has_element_after {
+        s = s.trim_end_matches(is_space);
+    }
+
+    s.chars()
+        .map(|ch| match ch {
+            '\t' => ' ',
+            c => c,
+        })
+        .coalesce(|current, next| match (current, next) {
+            (' ', ' ') => Ok(' '),
+            (' ', '\n') => Ok('\n'),
+            ('\n', ' ') => Ok('\n'),
+            (_, _) => Err((current, next)),
+        })
+        .collect::<String>()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -181,4 +234,40 @@ mod tests {
             "       WS    example      duplicate letters       "
         );
     }
+
+    #[test]
+    fn white_space_preserve_newlines() {
+        assert_eq!(
+            xml_space_normalize(
+                XmlSpaceNormalize::PreserveNewlines,
+                "\n  WS\texample\n  indented lines  "
+            ),
+            "\n  WS example\n  indented lines  "
+        );
+    }
+
+    #[test]
+    fn white_space_collapse_spaces() {
+        assert_eq!(
+            xml_space_normalize(
+                XmlSpaceNormalize::CollapseSpaces(NormalizeDefault {
+                    has_element_before: false,
+                    has_element_after: false,
+                }),
+                "  WS \t example  \n  indented   lines\n\n  "
+            ),
+            "WS example\nindented lines\n\n"
+        );
+
+        assert_eq!(
+            xml_space_normalize(
+                XmlSpaceNormalize::CollapseSpaces(NormalizeDefault {
+                    has_element_before: true,
+                    has_element_after: true,
+                }),
+                "  foo  "
+            ),
+            " foo "
+        );
+    }
 }
@@ -1,11 +1,13 @@
 //! Structural elements in SVG: the `g`, `switch`, `svg`, `use`, `symbol`, `clip_path`, `mask`, `link` elements.
 
+use cssparser::{Parser, Token};
 use markup5ever::{expanded_name, local_name, namespace_url, ns};
 
 use crate::aspect_ratio::*;
 use crate::bbox::BoundingBox;
 use crate::coord_units::CoordUnits;
 use crate::document::{AcquiredNodes, NodeId};
+use crate::dpi::Dpi;
 use crate::drawing_ctx::{ClipMode, DrawingCtx, ViewParams};
 use crate::element::{Draw, ElementResult, SetAttributes};
 use crate::error::*;
@@ -14,6 +16,7 @@ use crate::layout::StackingContext;
 use crate::length::*;
 use crate::node::{CascadedValues, Node, NodeBorrow, NodeDraw};
 use crate::parsers::{Parse, ParseValue};
+use crate::properties::ComputedValues;
 use crate::rect::Rect;
 use crate::viewbox::*;
 use crate::xml::Attributes;
@@ -115,6 +118,32 @@ pub struct IntrinsicDimensions {
     pub vbox: Option<ViewBox>,
 }
 
+impl IntrinsicDimensions {
+    /// If the `width` and `height` are in physical units, computes their pixel size, or
+    /// returns `None`.
+    ///
+    /// If any of the width/height are percentages, we cannot compute the size here.  Here
+    /// just normalize lengths with physical units, or units based on the font size, which
+    /// comes from the `values` of the `<svg>` element.
+    pub fn size_in_pixels(&self, values: &ComputedValues, dpi: Dpi) -> Option<(f64, f64)> {
+        // If either of width/height don't exist, the spec says they should default to 100%,
+        // which is a percentage-based unit - which we can't resolve here.
+        let (w, h) = match (self.width, self.height) {
+            (Some(w), Some(h)) => (w, h),
+            _ => return None,
+        };
+
+        if w.unit == LengthUnit::Percent || h.unit == LengthUnit::Percent {
+            return None;
+        }
+
+        let view_params = ViewParams::new(dpi, 0.0, 0.0);
+        let params = NormalizeParams::new(values, &view_params);
+
+        Some((w.to_user(&params), h.to_user(&params)))
+    }
+}
+
 #[derive(Default)]
 pub struct Svg {
     preserve_aspect_ratio: AspectRatio,
@@ -126,7 +155,8 @@ pub struct Svg {
 }
 
 impl Svg {
-    pub fn get_intrinsic_dimensions(&self) -> IntrinsicDimensions {
+    /// Gets the intrinsic dimensions, with the `viewBox` overridden by `view` if it has one.
+    pub fn get_intrinsic_dimensions(&self, view: Option<ViewSpec>) -> IntrinsicDimensions {
         let w = self.width.map(|length_or_auto| match length_or_auto {
             LengthOrAuto::Auto => ULength::<Horizontal>::parse_str("100%").unwrap(),
             LengthOrAuto::Length(l) => l,
@@ -140,10 +170,21 @@ impl Svg {
         IntrinsicDimensions {
             width: w,
             height: h,
-            vbox: self.vbox,
+            vbox: self.resolve_view(view).0,
         }
     }
 
+    /// Gets the `viewBox` and `preserveAspectRatio` to use, as overridden by `view`.
+    pub fn resolve_view(&self, view: Option<ViewSpec>) -> (Option<ViewBox>, AspectRatio) {
+        let view = view.unwrap_or_default();
+
+        (
+            view.vbox.or(self.vbox),
+            view.preserve_aspect_ratio
+                .unwrap_or(self.preserve_aspect_ratio),
+        )
+    }
+
     fn get_unnormalized_offset(&self) -> (Length<Horizontal>, Length<Vertical>) {
         // these defaults are per the spec
         let x = self
@@ -190,6 +231,7 @@ impl Svg {
         node: &Node,
         cascaded: &CascadedValues<'_>,
         draw_ctx: &mut DrawingCtx,
+        view: Option<ViewSpec>,
     ) -> Option<ViewParams> {
         let values = cascaded.get();
 
@@ -208,18 +250,20 @@ impl Svg {
 
         let is_measuring_toplevel_svg = !has_parent && draw_ctx.is_measuring();
 
+        let (vbox, preserve_aspect_ratio) = self.resolve_view(view);
+
         let (viewport, vbox) = if is_measuring_toplevel_svg || has_parent {
             // We are obtaining the toplevel SVG's geometry.  This means, don't care about the
             // DrawingCtx's viewport, just use the SVG's intrinsic dimensions and see how far
             // it wants to extend.
-            (svg_viewport, self.vbox)
+            (svg_viewport, vbox)
         } else {
             (
                 // The client's viewport overrides the toplevel's x/y/w/h viewport
                 draw_ctx.toplevel_viewport(),
                 // Use our viewBox if available, or try to derive one from
                 // the intrinsic dimensions.
-                self.vbox.or_else(|| {
+                vbox.or_else(|| {
                     Some(ViewBox::from(Rect::from_size(
                         svg_viewport.width(),
                         svg_viewport.height(),
@@ -228,7 +272,7 @@ impl Svg {
             )
         };
 
-        draw_ctx.push_new_viewport(vbox, viewport, self.preserve_aspect_ratio, clip_mode)
+        draw_ctx.push_new_viewport(vbox, viewport, preserve_aspect_ratio, clip_mode)
     }
 }
 
@@ -273,7 +317,7 @@ impl Draw for Svg {
             clipping,
             None,
             &mut |an, dc| {
-                let _params = self.push_viewport(node, cascaded, dc);
+                let _params = self.push_viewport(node, cascaded, dc, an.root_view(node));
                 node.draw_children(an, cascaded, dc, clipping)
             },
         )
@@ -413,6 +457,92 @@ impl SetAttributes for Symbol {
 
 impl Draw for Symbol {}
 
+/// Overrides for the outermost `<svg>` element's `viewBox` and `preserveAspectRatio`.
+///
+/// These come from a `<view>` element, or from an `svgView(...)` fragment identifier.
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct ViewSpec {
+    pub vbox: Option<ViewBox>,
+    pub preserve_aspect_ratio: Option<AspectRatio>,
+}
+
+impl Parse for ViewSpec {
+    // Parse an SVG view specification
+    // https://www.w3.org/TR/SVG2/linking.html#SVGFragmentIdentifiers
+    //
+    // svgView(viewBox(0,0,24,24);preserveAspectRatio(xMidYMid))
+    //
+    // The transform() and zoomAndPan() specs are accepted but ignored.
+    fn parse<'i>(parser: &mut Parser<'i, '_>) -> Result<ViewSpec, ParseError<'i>> {
+        parser.expect_function_matching("svgView")?;
+
+        parser.parse_nested_block(|p| {
+            let mut spec = ViewSpec::default();
+
+            loop {
+                let loc = p.current_source_location();
+                let name = p.expect_function()?.clone();
+
+                match name.as_ref() {
+                    "viewBox" => spec.vbox = Some(p.parse_nested_block(ViewBox::parse)?),
+
+                    "preserveAspectRatio" => {
+                        spec.preserve_aspect_ratio = Some(p.parse_nested_block(AspectRatio::parse)?)
+                    }
+
+                    "transform" | "zoomAndPan" => p.parse_nested_block(|p| {
+                        while p.next().is_ok() {}
+                        Ok(())
+                    })?,
+
+                    _ => return Err(loc.new_unexpected_token_error(Token::Function(name))),
+                }
+
+                if p.is_exhausted() {
+                    break;
+                }
+
+                p.expect_semicolon()?;
+            }
+
+            Ok(spec)
+        })
+    }
+}
+
+/// The `view` element.
+///
+/// A view is selected with a fragment identifier like `sprites.svg#icon`, and its
+/// attributes override those of the outermost `<svg>` element.
+#[derive(Default)]
+pub struct View {
+    spec: ViewSpec,
+}
+
+impl View {
+    pub fn get_view_spec(&self) -> ViewSpec {
+        self.spec
+    }
+}
+
+impl SetAttributes for View {
+    fn set_attributes(&mut self, attrs: &Attributes) -> ElementResult {
+        for (attr, value) in attrs.iter() {
+            match attr.expanded() {
+                expanded_name!("", "preserveAspectRatio") => {
+                    self.spec.preserve_aspect_ratio = Some(attr.parse(value)?)
+                }
+                expanded_name!("", "viewBox") => self.spec.vbox = attr.parse(value)?,
+                _ => (),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Draw for View {}
+
 coord_units!(ClipPathUnits, CoordUnits::UserSpaceOnUse);
 
 #[derive(Default)]
@@ -557,3 +687,44 @@ impl Draw for Link {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_view_specs() {
+        assert_eq!(
+            ViewSpec::parse_str("svgView(viewBox(0,0,24,24))").unwrap(),
+            ViewSpec {
+                vbox: Some(ViewBox::from(Rect::new(0.0, 0.0, 24.0, 24.0))),
+                preserve_aspect_ratio: None,
+            }
+        );
+
+        assert_eq!(
+            ViewSpec::parse_str("svgView(preserveAspectRatio(none);viewBox(10 20 30 40))").unwrap(),
+            ViewSpec {
+                vbox: Some(ViewBox::from(Rect::new(10.0, 20.0, 40.0, 60.0))),
+                preserve_aspect_ratio: Some(AspectRatio::parse_str("none").unwrap()),
+            }
+        );
+
+        assert_eq!(
+            ViewSpec::parse_str("svgView(transform(rotate(45));zoomAndPan(disable))").unwrap(),
+            ViewSpec::default()
+        );
+    }
+
+    #[test]
+    fn invalid_view_specs_yield_error() {
+        assert!(ViewSpec::parse_str("").is_err());
+        assert!(ViewSpec::parse_str("svgView()").is_err());
+        assert!(ViewSpec::parse_str("view(viewBox(0,0,24,24))").is_err());
+        assert!(ViewSpec::parse_str("svgView(viewBox(0,0,24))").is_err());
+        assert!(
+            ViewSpec::parse_str("svgView(viewBox(0,0,24,24)preserveAspectRatio(none))").is_err()
+        );
+        assert!(ViewSpec::parse_str("svgView(viewTarget(foo))").is_err());
+    }
+}
@@ -511,6 +511,30 @@ impl ImageSurface<Shared> {
         SharedImageSurface::wrap(output_surface, self.surface_type)
     }
 
+    /// Returns a surface whose alpha channel is the alpha of this surface multiplied by
+    /// `opacity`.  As with `to_mask()`, the RGB values are not meaningful.
+    ///
+    /// This is to get an alpha mask suitable for use with cairo_mask_surface().
+    pub fn to_alpha_mask(&self, opacity: UnitInterval) -> Result<SharedImageSurface, cairo::Error> {
+        let bounds = IRect::from_size(self.width, self.height);
+
+        let mut output_surface =
+            cairo::ImageSurface::create(cairo::Format::ARgb32, self.width, self.height)?;
+
+        let stride = output_surface.stride() as usize;
+        {
+            let mut data = output_surface.data().unwrap();
+            let opacity = u32::from(u8::from(opacity));
+
+            for (x, y, pixel) in Pixels::within(self, bounds) {
+                let a = ((u32::from(pixel.a) * opacity + 127) / 255) as u8;
+                data.set_pixel(stride, Pixel::new(0, 0, 0, a), x, y);
+            }
+        }
+
+        SharedImageSurface::wrap(output_surface, self.surface_type)
+    }
+
     /// Returns a surface with pre-multiplication of color values undone.
     ///
     /// HACK: this is storing unpremultiplied pixels in an ARGB32 image surface (which is supposed
@@ -1448,4 +1472,25 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn test_to_alpha_mask() {
+        let mut surface = ExclusiveImageSurface::new(4, 1, SurfaceType::SRgb).unwrap();
+
+        surface.modify(&mut |data, stride| {
+            data.set_pixel(stride, Pixel::new(255, 255, 255, 255), 0, 0);
+            data.set_pixel(stride, Pixel::new(0, 0, 0, 255), 1, 0);
+            data.set_pixel(stride, Pixel::new(0, 64, 0, 128), 2, 0);
+        });
+
+        let surface = surface.share().unwrap();
+
+        let mask = surface.to_alpha_mask(UnitInterval::clamp(1.0)).unwrap();
+        let alphas: Vec<u8> = (0..4).map(|x| mask.get_pixel(x, 0).a).collect();
+        assert_eq!(alphas, vec![255, 255, 128, 0]);
+
+        let mask = surface.to_alpha_mask(UnitInterval::clamp(0.5)).unwrap();
+        let alphas: Vec<u8> = (0..4).map(|x| mask.get_pixel(x, 0).a).collect();
+        assert_eq!(alphas, vec![128, 128, 64, 0]);
+    }
 }
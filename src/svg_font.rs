@@ -0,0 +1,661 @@
+//! SVG 1.1 fonts: the `font`, `font-face`, `glyph`, `missing-glyph` and `hkern` elements.
+//!
+//! https://www.w3.org/TR/SVG11/fonts.html
+//!
+//! The fonts in a document are collected into [`SvgFonts`] when it is loaded.  Text
+//! that uses one of them still gets laid out by Pango, but each glyph is a Pango
+//! shape attribute whose size is the glyph's advance.  A Cairo shape renderer then
+//! draws the glyph's path data in place of the shape.
+
+use glib::translate::*;
+use markup5ever::{expanded_name, local_name, namespace_url, ns};
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use crate::element::{Draw, Element, ElementResult, SetAttributes};
+use crate::node::{Node, NodeBorrow};
+use crate::parsers::ParseValue;
+use crate::path_builder::{Path as SvgPath, PathBuilder};
+use crate::rect::Rect;
+use crate::text::to_pango_units;
+use crate::xml::Attributes;
+
+/// The `<font>` element.
+#[derive(Default)]
+pub struct Font {
+    horiz_adv_x: f64,
+}
+
+impl SetAttributes for Font {
+    fn set_attributes(&mut self, attrs: &Attributes) -> ElementResult {
+        for (attr, value) in attrs.iter() {
+            if let expanded_name!("", "horiz-adv-x") = attr.expanded() {
+                self.horiz_adv_x = attr.parse(value)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Draw for Font {}
+
+/// The `<font-face>` element, when it is a child of a `<font>`.
+pub struct FontFace {
+    font_family: Option<String>,
+    units_per_em: f64,
+    ascent: Option<f64>,
+    descent: Option<f64>,
+}
+
+impl Default for FontFace {
+    fn default() -> FontFace {
+        FontFace {
+            font_family: None,
+            units_per_em: 1000.0,
+            ascent: None,
+            descent: None,
+        }
+    }
+}
+
+impl SetAttributes for FontFace {
+    fn set_attributes(&mut self, attrs: &Attributes) -> ElementResult {
+        for (attr, value) in attrs.iter() {
+            match attr.expanded() {
+                expanded_name!("", "font-family") => {
+                    self.font_family = Some(value.trim().trim_matches(&['"', '\''][..]).to_string())
+                }
+                expanded_name!("", "units-per-em") => self.units_per_em = attr.parse(value)?,
+                expanded_name!("", "ascent") => self.ascent = attr.parse(value)?,
+                expanded_name!("", "descent") => self.descent = attr.parse(value)?,
+                _ => (),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Draw for FontFace {}
+
+/// Parses the `d` attribute of a glyph.
+fn parse_glyph_path(value: &str) -> Rc<SvgPath> {
+    let mut builder = PathBuilder::default();
+    if let Err(e) = builder.parse(value) {
+        // Like for the <path> element, a partial path is OK per the spec.
+        rsvg_log!("could not parse glyph path: {}", e);
+    }
+
+    Rc::new(builder.into_path())
+}
+
+/// The `<glyph>` element.
+///
+/// FIXME: the `arabic-form`, `lang` and `orientation` attributes are not supported, so
+/// the first glyph for a string always gets used.
+#[derive(Default)]
+pub struct Glyph {
+    unicode: Option<String>,
+    glyph_names: Vec<String>,
+    path: Rc<SvgPath>,
+    extents: Option<Rect>,
+    horiz_adv_x: Option<f64>,
+}
+
+impl SetAttributes for Glyph {
+    fn set_attributes(&mut self, attrs: &Attributes) -> ElementResult {
+        for (attr, value) in attrs.iter() {
+            match attr.expanded() {
+                expanded_name!("", "unicode") => self.unicode = Some(value.to_string()),
+                expanded_name!("", "glyph-name") => {
+                    self.glyph_names = value.split(',').map(|s| s.trim().to_string()).collect()
+                }
+                expanded_name!("", "d") => {
+                    self.path = parse_glyph_path(value);
+                    self.extents = self.path.flatten().extents();
+                }
+                expanded_name!("", "horiz-adv-x") => self.horiz_adv_x = attr.parse(value)?,
+                _ => (),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Draw for Glyph {}
+
+/// The `<missing-glyph>` element.
+#[derive(Default)]
+pub struct MissingGlyph {
+    path: Rc<SvgPath>,
+    extents: Option<Rect>,
+    horiz_adv_x: Option<f64>,
+}
+
+impl SetAttributes for MissingGlyph {
+    fn set_attributes(&mut self, attrs: &Attributes) -> ElementResult {
+        for (attr, value) in attrs.iter() {
+            match attr.expanded() {
+                expanded_name!("", "d") => {
+                    self.path = parse_glyph_path(value);
+                    self.extents = self.path.flatten().extents();
+                }
+                expanded_name!("", "horiz-adv-x") => self.horiz_adv_x = attr.parse(value)?,
+                _ => (),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Draw for MissingGlyph {}
+
+/// An item in the `u1` or `u2` attributes of `<hkern>`.
+#[derive(Debug, Clone, PartialEq)]
+enum UnicodeMatch {
+    /// A string that must be the same as a glyph's `unicode`.
+    Literal(String),
+
+    /// A range of code points like `U+0041-005A` or `U+00??`, inclusive.
+    Range(u32, u32),
+}
+
+impl UnicodeMatch {
+    fn parse_list(value: &str) -> Vec<UnicodeMatch> {
+        value
+            .split(',')
+            .filter_map(|item| {
+                let trimmed = item.trim();
+
+                match trimmed.strip_prefix("U+") {
+                    Some(range) => {
+                        let (start, end) = match range.split_once('-') {
+                            Some((start, end)) => (start.to_string(), end.to_string()),
+                            None => (range.replace('?', "0"), range.replace('?', "F")),
+                        };
+
+                        let start = u32::from_str_radix(&start, 16).ok()?;
+                        let end = u32::from_str_radix(&end, 16).ok()?;
+
+                        Some(UnicodeMatch::Range(start, end))
+                    }
+
+                    // A single comma can't be written as a list item, so take the
+                    // item verbatim if trimming leaves nothing.
+                    None if trimmed.is_empty() => {
+                        (!item.is_empty()).then(|| UnicodeMatch::Literal(item.to_string()))
+                    }
+
+                    None => Some(UnicodeMatch::Literal(trimmed.to_string())),
+                }
+            })
+            .collect()
+    }
+
+    fn matches(&self, unicode: &str) -> bool {
+        match *self {
+            UnicodeMatch::Literal(ref s) => s == unicode,
+
+            UnicodeMatch::Range(start, end) => {
+                let mut chars = unicode.chars();
+
+                match (chars.next(), chars.next()) {
+                    (Some(c), None) => (start..=end).contains(&u32::from(c)),
+                    _ => false,
+                }
+            }
+        }
+    }
+}
+
+/// The `<hkern>` element.
+#[derive(Default)]
+pub struct HKern {
+    u1: Vec<UnicodeMatch>,
+    g1: Vec<String>,
+    u2: Vec<UnicodeMatch>,
+    g2: Vec<String>,
+    k: f64,
+}
+
+impl SetAttributes for HKern {
+    fn set_attributes(&mut self, attrs: &Attributes) -> ElementResult {
+        let names = |value: &str| value.split(',').map(|s| s.trim().to_string()).collect();
+
+        for (attr, value) in attrs.iter() {
+            match attr.expanded() {
+                expanded_name!("", "u1") => self.u1 = UnicodeMatch::parse_list(value),
+                expanded_name!("", "g1") => self.g1 = names(value),
+                expanded_name!("", "u2") => self.u2 = UnicodeMatch::parse_list(value),
+                expanded_name!("", "g2") => self.g2 = names(value),
+                expanded_name!("", "k") => self.k = attr.parse(value)?,
+                _ => (),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Draw for HKern {}
+
+/// A glyph of an [`SvgFont`], in font units with the y axis pointing up.
+struct SvgGlyph {
+    unicode: String,
+    glyph_names: Vec<String>,
+    path: Rc<SvgPath>,
+    extents: Option<Rect>,
+    horiz_adv_x: f64,
+}
+
+/// Matches one side of a kerning pair.
+struct GlyphMatch {
+    unicode: Vec<UnicodeMatch>,
+    glyph_names: Vec<String>,
+}
+
+impl GlyphMatch {
+    fn matches(&self, glyph: &SvgGlyph) -> bool {
+        self.unicode.iter().any(|u| u.matches(&glyph.unicode))
+            || glyph
+                .glyph_names
+                .iter()
+                .any(|name| self.glyph_names.contains(name))
+    }
+}
+
+struct KerningPair {
+    first: GlyphMatch,
+    second: GlyphMatch,
+    k: f64,
+}
+
+/// A font defined with a `<font>` element.
+pub struct SvgFont {
+    units_per_em: f64,
+    ascent: f64,
+    descent: f64,
+
+    /// Sorted so that glyphs for longer strings come first, to match ligatures.
+    glyphs: Vec<SvgGlyph>,
+
+    missing_glyph: SvgGlyph,
+    kerning: Vec<KerningPair>,
+}
+
+impl SvgFont {
+    /// Creates a font from a `<font>` element and its children.
+    ///
+    /// Returns the font family and the font, or `None` if the element does not have
+    /// a `<font-face>` child with a family name.
+    fn from_node(node: &Node) -> Option<(String, SvgFont)> {
+        let default_advance = match *node.borrow_element() {
+            Element::Font(ref font) => font.horiz_adv_x,
+            _ => unreachable!(),
+        };
+
+        let mut family = None;
+        let mut units_per_em = 1000.0;
+        let mut ascent = None;
+        let mut descent = None;
+        let mut glyphs = Vec::new();
+        let mut missing_glyph = None;
+        let mut kerning = Vec::new();
+
+        for child in node.children().filter(|c| c.is_element()) {
+            let elt = child.borrow_element();
+
+            if elt.is_in_error() {
+                continue;
+            }
+
+            match *elt {
+                Element::FontFace(ref face) if family.is_none() => {
+                    family = face.font_family.clone();
+                    units_per_em = face.units_per_em;
+                    ascent = face.ascent;
+                    descent = face.descent;
+                }
+
+                Element::Glyph(ref glyph) => {
+                    if let Some(ref unicode) = glyph.unicode {
+                        glyphs.push(SvgGlyph {
+                            unicode: unicode.clone(),
+                            glyph_names: glyph.glyph_names.clone(),
+                            path: glyph.path.clone(),
+                            extents: glyph.extents,
+                            horiz_adv_x: glyph.horiz_adv_x.unwrap_or(default_advance),
+                        });
+                    }
+                }
+
+                Element::MissingGlyph(ref glyph) if missing_glyph.is_none() => {
+                    missing_glyph = Some(SvgGlyph {
+                        unicode: String::new(),
+                        glyph_names: Vec::new(),
+                        path: glyph.path.clone(),
+                        extents: glyph.extents,
+                        horiz_adv_x: glyph.horiz_adv_x.unwrap_or(default_advance),
+                    });
+                }
+
+                Element::HKern(ref hkern) => kerning.push(KerningPair {
+                    first: GlyphMatch {
+                        unicode: hkern.u1.clone(),
+                        glyph_names: hkern.g1.clone(),
+                    },
+                    second: GlyphMatch {
+                        unicode: hkern.u2.clone(),
+                        glyph_names: hkern.g2.clone(),
+                    },
+                    k: hkern.k,
+                }),
+
+                _ => (),
+            }
+        }
+
+        let family = family?;
+
+        // Stable sort, so glyphs for the same string keep the document order.
+        glyphs.sort_by_key(|g| Reverse(g.unicode.len()));
+
+        // If there is no <missing-glyph>, missing characters are just blank.
+        let missing_glyph = missing_glyph.unwrap_or_else(|| SvgGlyph {
+            unicode: String::new(),
+            glyph_names: Vec::new(),
+            path: Rc::new(SvgPath::default()),
+            extents: None,
+            horiz_adv_x: default_advance,
+        });
+
+        // The font-face element's defaults per the SVG 1.1 spec.  Some fonts specify
+        // the descent as a negative number, so take the absolute value.
+        let ascent = ascent.unwrap_or(units_per_em);
+        let descent = descent.map(f64::abs).unwrap_or(0.0);
+
+        Some((
+            family,
+            SvgFont {
+                units_per_em,
+                ascent,
+                descent,
+                glyphs,
+                missing_glyph,
+                kerning,
+            },
+        ))
+    }
+
+    /// Finds the glyph for the start of `text`, and the number of bytes it covers.
+    fn glyph_for(&self, text: &str) -> (&SvgGlyph, usize) {
+        self.glyphs
+            .iter()
+            .find(|g| !g.unicode.is_empty() && text.starts_with(&g.unicode))
+            .map(|g| (g, g.unicode.len()))
+            .unwrap_or_else(|| {
+                let len = text.chars().next().map_or(0, char::len_utf8);
+                (&self.missing_glyph, len)
+            })
+    }
+
+    fn kerning(&self, first: &SvgGlyph, second: &SvgGlyph) -> f64 {
+        self.kerning
+            .iter()
+            .find(|pair| pair.first.matches(first) && pair.second.matches(second))
+            .map_or(0.0, |pair| pair.k)
+    }
+
+    /// Adds shape attributes to render `text` with this font.
+    ///
+    /// The `text` starts at byte `start` of the text in the layout for `attr_list`.
+    pub fn add_glyph_shapes(
+        &self,
+        attr_list: &pango::AttrList,
+        text: &str,
+        start: usize,
+        font_size: f64,
+    ) {
+        let mut glyphs = Vec::new();
+        let mut offset = 0;
+
+        while offset < text.len() {
+            let (glyph, len) = self.glyph_for(&text[offset..]);
+            glyphs.push((offset..offset + len, glyph));
+            offset += len;
+        }
+
+        let scale = font_size / self.units_per_em;
+
+        for (i, (range, glyph)) in glyphs.iter().enumerate() {
+            let kerning = glyphs
+                .get(i + 1)
+                .map_or(0.0, |(_, next)| self.kerning(glyph, next));
+            let advance = glyph.horiz_adv_x - kerning;
+
+            let logical_rect = pango::Rectangle {
+                x: 0,
+                y: to_pango_units(-self.ascent * scale),
+                width: to_pango_units(advance * scale),
+                height: to_pango_units((self.ascent + self.descent) * scale),
+            };
+
+            let ink_rect = glyph.extents.map_or(logical_rect, |r| pango::Rectangle {
+                x: to_pango_units(r.x0 * scale),
+                y: to_pango_units(-r.y1 * scale),
+                width: to_pango_units(r.width() * scale),
+                height: to_pango_units(r.height() * scale),
+            });
+
+            let shape = GlyphShape {
+                path: glyph.path.clone(),
+                scale,
+            };
+
+            let mut attr = shape.into_attribute(&ink_rect, &logical_rect);
+            attr.set_start_index((start + range.start) as u32);
+            attr.set_end_index((start + range.end) as u32);
+            attr_list.insert(attr);
+        }
+    }
+}
+
+/// The SVG fonts in a document, by font family.
+#[derive(Default)]
+pub struct SvgFonts {
+    fonts: HashMap<String, SvgFont>,
+}
+
+impl SvgFonts {
+    /// Collects the `<font>` elements in a tree.
+    ///
+    /// If several fonts have the same family, the first one is used.
+    pub fn new(root: &Node) -> SvgFonts {
+        let mut fonts = HashMap::new();
+
+        for node in root
+            .descendants()
+            .filter(|n| n.is_element() && is_element_of_type!(n, Font))
+        {
+            if node.borrow_element().is_in_error() {
+                continue;
+            }
+
+            if let Some((family, font)) = SvgFont::from_node(&node) {
+                fonts.entry(family).or_insert(font);
+            }
+        }
+
+        SvgFonts { fonts }
+    }
+
+    /// Finds the font for a `font-family` value.
+    ///
+    /// This uses the first family in the list that names an SVG font.
+    pub fn lookup(&self, font_family: &str) -> Option<&SvgFont> {
+        if self.fonts.is_empty() {
+            return None;
+        }
+
+        font_family
+            .split(',')
+            .find_map(|family| self.fonts.get(family.trim()))
+    }
+}
+
+/// The data for a Pango shape attribute that represents a glyph.
+#[derive(Clone)]
+struct GlyphShape {
+    path: Rc<SvgPath>,
+    scale: f64,
+}
+
+impl GlyphShape {
+    fn into_attribute(
+        self,
+        ink_rect: &pango::Rectangle,
+        logical_rect: &pango::Rectangle,
+    ) -> pango::Attribute {
+        let data = Box::into_raw(Box::new(self));
+
+        unsafe {
+            from_glib_full(pango::ffi::pango_attr_shape_new_with_data(
+                ink_rect.to_glib_none().0,
+                logical_rect.to_glib_none().0,
+                data as glib::ffi::gpointer,
+                Some(copy_glyph_shape),
+                Some(destroy_glyph_shape),
+            ))
+        }
+    }
+}
+
+unsafe extern "C" fn copy_glyph_shape(data: glib::ffi::gconstpointer) -> glib::ffi::gpointer {
+    let shape = &*(data as *const GlyphShape);
+    Box::into_raw(Box::new(shape.clone())) as glib::ffi::gpointer
+}
+
+unsafe extern "C" fn destroy_glyph_shape(data: glib::ffi::gpointer) {
+    drop(Box::from_raw(data as *mut GlyphShape));
+}
+
+/// Draws the glyphs from `SvgFont::add_glyph_shapes`.
+///
+/// Pango calls this with the current point at the glyph's origin on the baseline.
+unsafe extern "C" fn render_glyph_shape(
+    cr: *mut cairo::ffi::cairo_t,
+    attr: *mut pango::ffi::PangoAttrShape,
+    do_path: glib::ffi::gboolean,
+    _data: glib::ffi::gpointer,
+) {
+    let attr = &*attr;
+
+    // The only shape attributes in our layouts are the ones from add_glyph_shapes(),
+    // which always have data.
+    if attr.data.is_null() {
+        return;
+    }
+
+    let shape = &*(attr.data as *const GlyphShape);
+    if shape.path.is_empty() {
+        return;
+    }
+
+    let cr = cairo::Context::from_raw_none(cr);
+
+    let (x, y) = match cr.current_point() {
+        Ok(p) => p,
+        Err(_) => return,
+    };
+
+    let matrix = cr.matrix();
+    cr.translate(x, y);
+    cr.scale(shape.scale, -shape.scale);
+    let res = shape.path.to_cairo(&cr, false);
+    cr.set_matrix(matrix);
+
+    if res.is_ok() && do_path == glib::ffi::GFALSE {
+        let _ = cr.fill();
+    }
+}
+
+/// Makes a Pango context draw the glyphs of SVG fonts.
+pub fn set_shape_renderer(context: &pango::Context) {
+    unsafe {
+        pangocairo::ffi::pango_cairo_context_set_shape_renderer(
+            context.to_glib_none().0,
+            Some(render_glyph_shape),
+            std::ptr::null_mut(),
+            None,
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_unicode_match_lists() {
+        assert_eq!(
+            UnicodeMatch::parse_list("A, fi,U+0061-007A, U+03??"),
+            vec![
+                UnicodeMatch::Literal(String::from("A")),
+                UnicodeMatch::Literal(String::from("fi")),
+                UnicodeMatch::Range(0x61, 0x7a),
+                UnicodeMatch::Range(0x300, 0x3ff),
+            ]
+        );
+
+        assert!(UnicodeMatch::Range(0x61, 0x7a).matches("q"));
+        assert!(!UnicodeMatch::Range(0x61, 0x7a).matches("qq"));
+        assert!(!UnicodeMatch::Range(0x61, 0x7a).matches("Q"));
+    }
+
+    #[test]
+    fn lays_out_glyphs_with_kerning() {
+        let document = crate::document::Document::load_from_bytes(
+            br#"<?xml version="1.0" encoding="UTF-8"?>
+<svg xmlns="http://www.w3.org/2000/svg">
+  <font horiz-adv-x="500">
+    <font-face font-family="Test" units-per-em="1000" ascent="800" descent="-200"/>
+    <missing-glyph d="M0,0 H400 V700 H0 Z"/>
+    <glyph unicode="f" horiz-adv-x="300" d="M0,0 H300 V700 H0 Z"/>
+    <glyph unicode="ffi" horiz-adv-x="800" glyph-name="f_f_i" d="M0,0 H800 V700 H0 Z"/>
+    <glyph unicode="A" d="M0,0 L250,700 L500,0 Z"/>
+    <glyph unicode="V" d="M0,700 L250,0 L500,700 Z"/>
+    <hkern u1="A" u2="U+0056-0057" k="100"/>
+    <hkern g1="f_f_i" g2="f_f_i" k="50"/>
+  </font>
+</svg>"#,
+        );
+
+        let fonts = SvgFonts::new(&document.root());
+        assert!(fonts.lookup("Foo, Bar").is_none());
+
+        let font = fonts.lookup("Foo,Test").unwrap();
+        assert_eq!(font.ascent, 800.0);
+        assert_eq!(font.descent, 200.0);
+
+        let (glyph, len) = font.glyph_for("ffix");
+        assert_eq!((glyph.unicode.as_str(), len), ("ffi", 3));
+
+        let (glyph, len) = font.glyph_for("fx");
+        assert_eq!((glyph.unicode.as_str(), len), ("f", 1));
+
+        let (glyph, len) = font.glyph_for("éa");
+        assert_eq!((glyph.horiz_adv_x, len), (500.0, 2));
+
+        let (a, _) = font.glyph_for("A");
+        let (v, _) = font.glyph_for("V");
+        let (ffi, _) = font.glyph_for("ffi");
+        assert_eq!(font.kerning(a, v), 100.0);
+        assert_eq!(font.kerning(v, a), 0.0);
+        assert_eq!(font.kerning(ffi, ffi), 50.0);
+        assert_eq!(font.kerning(a, ffi), 0.0);
+    }
+}
@@ -1,23 +1,38 @@
-//! Text elements: `text`, `tspan`, `tref`.
+//! Text elements: `text`, `tspan`, `tref`, `textPath`.
 
+use cssparser::Parser;
 use markup5ever::{expanded_name, local_name, namespace_url, ns};
 use std::cell::RefCell;
+use std::cmp::Reverse;
+use std::ops::Range;
 use std::rc::Rc;
+use unicode_segmentation::UnicodeSegmentation;
 
 use crate::bbox::BoundingBox;
 use crate::document::{AcquiredNodes, NodeId};
 use crate::drawing_ctx::DrawingCtx;
 use crate::element::{Draw, Element, ElementResult, SetAttributes};
 use crate::error::*;
-use crate::layout::{self, FontProperties, StackingContext, Stroke};
+use crate::href::{is_href, set_href};
+use crate::layout::{
+    self, Decoration, DecorationLine, FontProperties, StackingContext, Stroke, TextRun,
+};
 use crate::length::*;
 use crate::node::{CascadedValues, Node, NodeBorrow};
-use crate::parsers::ParseValue;
+use crate::paint_server::resolve_color;
+use crate::parsers::{optional_comma, Parse, ParseValue};
+use crate::path_builder::{FlattenedPath, Path as SvgPath, PathBuilder};
 use crate::properties::{
-    ComputedValues, Direction, FontStretch, FontStyle, FontVariant, FontWeight, TextAnchor,
-    UnicodeBidi, WritingMode, XmlLang, XmlSpace,
+    AlignmentBaseline, ComputedValues, Direction, DominantBaseline, EastAsianVariant,
+    EastAsianWidth, FontKerning, FontOpticalSizing, FontStretch, FontStyle, FontVariant,
+    FontVariantCaps, FontWeight, NumericFigure, NumericFraction, NumericSpacing, TextAnchor,
+    TextDecorationThickness, TextOrientation, TextUnderlineOffset, UnicodeBidi, WhiteSpace,
+    WritingMode, XmlLang, XmlSpace,
 };
+use crate::shapes::shape_element_path;
 use crate::space::{xml_space_normalize, NormalizeDefault, XmlSpaceNormalize};
+use crate::unit_interval::UnitInterval;
+use crate::util::total_cmp;
 use crate::xml::Attributes;
 
 /// An absolutely-positioned array of `Span`s
@@ -32,18 +47,26 @@ use crate::xml::Attributes;
 /// Subsequent children of the `<text>` element will create new chunks
 /// whenever they have `x` or `y` attributes.
 ///
+/// A `<textPath>` element also starts a chunk.  For such chunks, `x` is the
+/// distance along the path at which the text starts, and `y` is an offset
+/// perpendicular to the path.
+///
 /// [text chunk]: https://www.w3.org/TR/SVG11/text.html#TextLayoutIntroduction
 struct Chunk {
     values: Rc<ComputedValues>,
+    text_anchor: TextAnchor,
     x: Option<f64>,
     y: Option<f64>,
+    text_path: Option<Rc<FlattenedPath>>,
     spans: Vec<Span>,
 }
 
 struct MeasuredChunk {
     values: Rc<ComputedValues>,
+    text_anchor: TextAnchor,
     x: Option<f64>,
     y: Option<f64>,
+    text_path: Option<Rc<FlattenedPath>>,
     advance: (f64, f64),
     spans: Vec<MeasuredSpan>,
 }
@@ -59,16 +82,34 @@ struct Span {
     text: String,
     dx: f64,
     dy: f64,
-    _depth: usize,
+    /// Rotation in radians of each glyph, from the `rotate` attribute.
+    rotation: f64,
+    /// The `textLength` of the span's ancestors that have one, from outermost to innermost.
+    text_lengths: Vec<Rc<TextLength>>,
+    /// The decorations from the `text-decoration` of the span's ancestors.
+    decorations: Vec<Decoration>,
+    /// The bidi embeddings of the span's ancestors, from outermost to innermost.
+    bidi_embeddings: Vec<Rc<BidiEmbedding>>,
+    /// The embedding level of the span's characters, once `reorder_bidi` has resolved it.
+    bidi_level: Option<u8>,
+    /// The `id` of the element that contains the span's characters.
+    id: Option<String>,
+    depth: usize,
 }
 
 struct MeasuredSpan {
     values: Rc<ComputedValues>,
     layout: pango::Layout,
-    _layout_size: (f64, f64),
+    layout_size: (f64, f64),
     advance: (f64, f64),
     dx: f64,
     dy: f64,
+    rotation: f64,
+    text_lengths: Vec<Rc<TextLength>>,
+    decorations: Vec<Decoration>,
+    id: Option<String>,
+    num_chars: usize,
+    adjustment: LengthAdjustment,
 }
 
 struct PositionedSpan {
@@ -78,14 +119,76 @@ struct PositionedSpan {
     rendered_position: (f64, f64),
     next_span_x: f64,
     next_span_y: f64,
+    rotation: f64,
+    adjustment: LengthAdjustment,
+    decorations: Vec<Decoration>,
+    id: Option<String>,
+
+    /// Glyph clusters placed individually, for spans laid out along a `<textPath>`,
+    /// or whose glyphs are rotated or adjusted per `textLength`.
+    clusters: Option<Vec<PositionedCluster>>,
+}
+
+/// A glyph cluster of a span, with its logical extents in user-space units.
+struct Cluster {
+    range: Range<usize>,
+    x: f64,
+    width: f64,
+}
+
+/// A glyph cluster that gets drawn on its own, rotated around its position.
+struct PositionedCluster {
+    /// Byte range of the cluster in the span's text.
+    range: Range<usize>,
+    layout: pango::Layout,
+    x: f64,
+    y: f64,
+    rotation: f64,
+    scale: f64,
+}
+
+/// How the glyphs of a span get adjusted to fit the `textLength` of its elements.
+///
+/// Each glyph cluster gets placed at `scale` times its natural position, plus
+/// `spacing` times the number of characters before it.  The glyphs themselves
+/// are scaled by `scale` along the inline direction.  `end_spacing` is the
+/// extra space after the span's last character.
+#[derive(Debug, Copy, Clone, PartialEq)]
+struct LengthAdjustment {
+    scale: f64,
+    spacing: f64,
+    end_spacing: f64,
+}
+
+impl Default for LengthAdjustment {
+    fn default() -> LengthAdjustment {
+        LengthAdjustment {
+            scale: 1.0,
+            spacing: 0.0,
+            end_spacing: 0.0,
+        }
+    }
 }
 
 impl Chunk {
     fn new(values: &ComputedValues, x: Option<f64>, y: Option<f64>) -> Chunk {
         Chunk {
             values: Rc::new(values.clone()),
+            text_anchor: values.text_anchor(),
             x,
             y,
+            text_path: None,
+            spans: Vec::new(),
+        }
+    }
+
+    fn new_on_path(values: &ComputedValues, start_offset: f64, path: FlattenedPath) -> Chunk {
+        Chunk {
+            values: Rc::new(values.clone()),
+            text_anchor: values.text_anchor(),
+            x: Some(start_offset),
+            y: Some(0.0),
+            text_path: Some(Rc::new(path)),
             spans: Vec::new(),
         }
     }
@@ -105,8 +208,10 @@ impl MeasuredChunk {
 
         MeasuredChunk {
             values: chunk.values.clone(),
+            text_anchor: chunk.text_anchor,
             x: chunk.x,
             y: chunk.y,
+            text_path: chunk.text_path.clone(),
             advance,
             spans: measured_spans,
         }
@@ -125,7 +230,7 @@ impl PositionedChunk {
         // Adjust the specified coordinates with the text_anchor
 
         let adjusted_advance = text_anchor_advance(
-            measured.values.text_anchor(),
+            measured.text_anchor,
             measured.values.writing_mode(),
             measured.advance,
         );
@@ -136,7 +241,15 @@ impl PositionedChunk {
         // Position each span
 
         for measured_span in &measured.spans {
-            let positioned_span = PositionedSpan::from_measured(measured_span, draw_ctx, x, y);
+            let mut positioned_span = PositionedSpan::from_measured(measured_span, draw_ctx, x, y);
+
+            if let Some(ref path) = measured.text_path {
+                positioned_span.place_on_path(path);
+            } else if positioned_span.rotation != 0.0
+                || positioned_span.adjustment != LengthAdjustment::default()
+            {
+                positioned_span.place_clusters();
+            }
 
             x = positioned_span.next_span_x;
             y = positioned_span.next_span_y;
@@ -144,9 +257,19 @@ impl PositionedChunk {
             positioned.push(positioned_span);
         }
 
+        // Text that comes after a <textPath> continues at the point where the path text ended.
+        let (next_chunk_x, next_chunk_y) = match measured.text_path {
+            Some(ref path) => path
+                .point_at(x.max(0.0).min(path.length()))
+                .map(|(point, _)| point)
+                .unwrap_or((x, y)),
+
+            None => (x, y),
+        };
+
         PositionedChunk {
-            next_chunk_x: x,
-            next_chunk_y: y,
+            next_chunk_x,
+            next_chunk_y,
             spans: positioned,
         }
     }
@@ -173,13 +296,30 @@ fn text_anchor_advance(
 }
 
 impl Span {
-    fn new(text: &str, values: Rc<ComputedValues>, dx: f64, dy: f64, depth: usize) -> Span {
+    fn new(
+        text: &str,
+        values: Rc<ComputedValues>,
+        dx: f64,
+        dy: f64,
+        rotation: f64,
+        text_lengths: Vec<Rc<TextLength>>,
+        decorations: Vec<Decoration>,
+        bidi_embeddings: Vec<Rc<BidiEmbedding>>,
+        id: Option<String>,
+        depth: usize,
+    ) -> Span {
         Span {
             values,
             text: text.to_string(),
             dx,
             dy,
-            _depth: depth,
+            rotation,
+            text_lengths,
+            decorations,
+            bidi_embeddings,
+            bidi_level: None,
+            id,
+            depth,
         }
     }
 }
@@ -193,6 +333,17 @@ impl MeasuredSpan {
 
         let properties = FontProperties::new(&values, &params);
         let layout = create_pango_layout(draw_ctx, &properties, &span.text);
+
+        // Spans that got reordered are laid out in the direction of their embedding level.
+        if let (Some(level), Some(context)) = (span.bidi_level, layout.context()) {
+            context.set_base_dir(if level % 2 == 1 {
+                pango::Direction::Rtl
+            } else {
+                pango::Direction::Ltr
+            });
+            layout.context_changed();
+        }
+
         let (w, h) = layout.size();
 
         let w = f64::from(w) / f64::from(pango::SCALE);
@@ -207,12 +358,186 @@ impl MeasuredSpan {
         MeasuredSpan {
             values,
             layout,
-            _layout_size: (w, h),
+            layout_size: (w, h),
             advance,
             dx: span.dx,
             dy: span.dy,
+            rotation: span.rotation,
+            text_lengths: span.text_lengths.clone(),
+            decorations: span.decorations.clone(),
+            id: span.id.clone(),
+            num_chars: span.text.graphemes(true).count(),
+            adjustment: LengthAdjustment::default(),
+        }
+    }
+
+    /// Recomputes the advance after changing the `adjustment`.
+    fn update_advance(&mut self) {
+        let LengthAdjustment {
+            scale,
+            spacing,
+            end_spacing,
+        } = self.adjustment;
+
+        let gaps = self.num_chars.saturating_sub(1) as f64;
+        let w = scale * self.layout_size.0 + gaps * spacing + end_spacing;
+
+        self.advance = if self.values.writing_mode().is_vertical() {
+            (0.0, w)
+        } else {
+            (w, 0.0)
+        };
+    }
+}
+
+/// Adjusts the spans of each element with a `textLength` so that their total advance
+/// matches it.
+///
+/// Per the `lengthAdjust` attribute, this either adds the same amount of space between
+/// each pair of characters, or scales the glyphs and their positions.  When elements
+/// with `textLength` are nested, the innermost ones get adjusted first, and the outer
+/// ones then treat each of them as a single character, per the "resolve text length"
+/// step of the SVG2 text layout algorithm.
+///
+/// https://www.w3.org/TR/SVG2/text.html#TextLayoutAlgorithm
+fn adjust_text_lengths(chunks: &mut [MeasuredChunk]) {
+    let mut text_lengths: Vec<Rc<TextLength>> = Vec::new();
+
+    for span in chunks.iter().flat_map(|c| c.spans.iter()) {
+        for text_length in &span.text_lengths {
+            if !text_lengths.iter().any(|t| Rc::ptr_eq(t, text_length)) {
+                text_lengths.push(text_length.clone());
+            }
+        }
+    }
+
+    text_lengths.sort_by_key(|t| Reverse(t.depth));
+
+    for text_length in &text_lengths {
+        let mut spans: Vec<&mut MeasuredSpan> = chunks
+            .iter_mut()
+            .flat_map(|c| c.spans.iter_mut())
+            .filter(|s| s.text_lengths.iter().any(|t| Rc::ptr_eq(t, text_length)))
+            .collect();
+
+        // FIXME: we don't adjust vertical text yet.
+        if spans.is_empty() || spans[0].values.writing_mode().is_vertical() {
+            continue;
+        }
+
+        let advance: f64 = spans
+            .iter()
+            .enumerate()
+            .map(|(i, s)| {
+                if i > 0 {
+                    s.advance.0 + s.dx
+                } else {
+                    s.advance.0
+                }
+            })
+            .sum();
+
+        let last = spans.len() - 1;
+
+        // Descendants with their own `textLength` were already adjusted, so they keep
+        // their length and move as a whole, as if they were a single character.  This
+        // finds the outermost of them that contains each span, if any.
+        let units: Vec<Option<Rc<TextLength>>> = spans
+            .iter()
+            .map(|s| {
+                s.text_lengths
+                    .iter()
+                    .filter(|t| t.depth > text_length.depth)
+                    .min_by_key(|t| t.depth)
+                    .cloned()
+            })
+            .collect();
+
+        let same_unit = |i: usize, j: usize| match (&units[i], &units[j]) {
+            (Some(a), Some(b)) => Rc::ptr_eq(a, b),
+            _ => false,
+        };
+
+        let starts_unit = |i: usize| i == 0 || !same_unit(i - 1, i);
+        let ends_unit = |i: usize| i == last || !same_unit(i, i + 1);
+
+        let num_units: usize = spans
+            .iter()
+            .enumerate()
+            .map(|(i, s)| match units[i] {
+                None => s.num_chars,
+                Some(_) if ends_unit(i) => 1,
+                Some(_) => 0,
+            })
+            .sum();
+
+        match text_length.adjust {
+            LengthAdjust::Spacing => {
+                if num_units < 2 {
+                    continue;
+                }
+
+                let delta = (text_length.length - advance) / (num_units - 1) as f64;
+
+                for (i, span) in spans.iter_mut().enumerate() {
+                    if units[i].is_none() {
+                        span.adjustment.spacing += delta;
+                    }
+
+                    if i != last && (units[i].is_none() || ends_unit(i)) {
+                        span.adjustment.end_spacing += delta;
+                    }
+
+                    span.update_advance();
+                }
+            }
+
+            LengthAdjust::SpacingAndGlyphs => {
+                // Only the spans outside of adjusted descendants get scaled, along with
+                // the offsets that position the descendants.
+                let scaled_dx = |i: usize| i > 0 && (units[i].is_none() || starts_unit(i));
+
+                let fixed: f64 = spans
+                    .iter()
+                    .enumerate()
+                    .filter(|&(i, _)| units[i].is_some())
+                    .map(|(i, s)| {
+                        if i > 0 && !scaled_dx(i) {
+                            s.advance.0 + s.dx
+                        } else {
+                            s.advance.0
+                        }
+                    })
+                    .sum();
+
+                if advance - fixed <= 0.0 {
+                    continue;
+                }
+
+                let scale = (text_length.length - fixed) / (advance - fixed);
+
+                for (i, span) in spans.iter_mut().enumerate() {
+                    if units[i].is_none() {
+                        span.adjustment.scale *= scale;
+                        span.adjustment.spacing *= scale;
+                        span.adjustment.end_spacing *= scale;
+                    }
+
+                    if scaled_dx(i) {
+                        span.dx *= scale;
+                    }
+
+                    span.update_advance();
+                }
+            }
         }
     }
+
+    for chunk in chunks {
+        chunk.advance = chunk.spans.iter().fold((0.0, 0.0), |acc, measured| {
+            (acc.0 + measured.advance.0, acc.1 + measured.advance.1)
+        });
+    }
 }
 
 impl PositionedSpan {
@@ -230,7 +555,8 @@ impl PositionedSpan {
 
         let baseline = f64::from(layout.baseline()) / f64::from(pango::SCALE);
         let baseline_shift = values.baseline_shift().0.to_user(&params);
-        let offset = baseline + baseline_shift;
+        let alignment = Baseline::from_values(&values).offset(&layout);
+        let offset = baseline + baseline_shift - alignment;
 
         let dx = measured.dx;
         let dy = measured.dy;
@@ -248,7 +574,133 @@ impl PositionedSpan {
             rendered_position: (render_x, render_y),
             next_span_x: x + measured.advance.0 + dx,
             next_span_y: y + measured.advance.1 + dy,
+            rotation: measured.rotation,
+            adjustment: measured.adjustment,
+            decorations: measured.decorations.clone(),
+            id: measured.id.clone(),
+            clusters: None,
+        }
+    }
+
+    /// Places each glyph cluster of the span along a path.
+    ///
+    /// The span's position is interpreted as a distance along the path and an
+    /// offset perpendicular to it.  Each cluster is centered on the point at
+    /// the distance corresponding to its midpoint, and rotated to follow the
+    /// path's direction there.  Clusters whose midpoint falls outside of the
+    /// path are not rendered.
+    fn place_on_path(&mut self, path: &FlattenedPath) {
+        let text = match self.layout.text() {
+            Some(text) => text,
+            None => return,
+        };
+
+        let (x, y) = self.rendered_position;
+        let baseline = f64::from(self.layout.baseline()) / f64::from(pango::SCALE);
+
+        let scale = self.adjustment.scale;
+
+        let clusters = self
+            .adjusted_clusters()
+            .into_iter()
+            .filter_map(|cluster| {
+                let half_width = cluster.width / 2.0;
+                let ((px, py), angle) = path.point_at(x + cluster.x + half_width)?;
+
+                let layout = cluster_layout(&self.layout, &text, cluster.range.clone())?;
+                let cluster_baseline = f64::from(layout.baseline()) / f64::from(pango::SCALE);
+
+                // Offset from the point on the path to the cluster's top-left corner,
+                // in the rotated coordinate system.
+                let (ox, oy) = (-half_width, y + baseline - cluster_baseline);
+                let rotation = angle + self.rotation;
+                let (sin, cos) = rotation.sin_cos();
+
+                Some(PositionedCluster {
+                    range: cluster.range,
+                    layout,
+                    x: px + ox * cos - oy * sin,
+                    y: py + ox * sin + oy * cos,
+                    rotation,
+                    scale,
+                })
+            })
+            .collect();
+
+        self.clusters = Some(clusters);
+    }
+
+    /// Places each glyph cluster of the span individually, rotated around its origin
+    /// on the baseline and adjusted per `textLength`.
+    fn place_clusters(&mut self) {
+        // FIXME: we don't place glyphs individually in vertical text yet.
+        if self.values.writing_mode().is_vertical() {
+            return;
+        }
+
+        let text = match self.layout.text() {
+            Some(text) => text,
+            None => return,
+        };
+
+        let (x, y) = self.rendered_position;
+        let baseline = f64::from(self.layout.baseline()) / f64::from(pango::SCALE);
+        let rotation = self.rotation;
+        let scale = self.adjustment.scale;
+        let (sin, cos) = rotation.sin_cos();
+
+        let clusters = self
+            .adjusted_clusters()
+            .into_iter()
+            .filter_map(|cluster| {
+                let layout = cluster_layout(&self.layout, &text, cluster.range.clone())?;
+                let cluster_baseline = f64::from(layout.baseline()) / f64::from(pango::SCALE);
+
+                // The cluster's top-left corner is above its origin by the baseline.
+                let (px, py) = (x + cluster.x, y + baseline);
+
+                Some(PositionedCluster {
+                    range: cluster.range,
+                    layout,
+                    x: px + cluster_baseline * sin,
+                    y: py - cluster_baseline * cos,
+                    rotation,
+                    scale,
+                })
+            })
+            .collect();
+
+        self.clusters = Some(clusters);
+    }
+
+    /// Returns the glyph clusters of the span, with their positions and widths
+    /// adjusted per `textLength`.
+    fn adjusted_clusters(&self) -> Vec<Cluster> {
+        let mut clusters = layout_clusters(&self.layout);
+
+        let text = match self.layout.text() {
+            Some(text) => text,
+            None => return clusters,
+        };
+
+        let LengthAdjustment { scale, spacing, .. } = self.adjustment;
+
+        // Extra spacing goes between characters in visual order.
+        let mut visual_order: Vec<usize> = (0..clusters.len()).collect();
+        visual_order.sort_by(|&a, &b| total_cmp(clusters[a].x, clusters[b].x));
+
+        let mut chars_before = 0;
+
+        for i in visual_order {
+            let cluster = &mut clusters[i];
+
+            cluster.x = scale * cluster.x + chars_before as f64 * spacing;
+            cluster.width *= scale;
+
+            chars_before += text[cluster.range.clone()].graphemes(true).count();
         }
+
+        clusters
     }
 
     fn draw(
@@ -256,13 +708,128 @@ impl PositionedSpan {
         acquired_nodes: &mut AcquiredNodes<'_>,
         draw_ctx: &mut DrawingCtx,
         clipping: bool,
+    ) -> Result<BoundingBox, RenderingError> {
+        if draw_ctx.is_collecting_text_runs() {
+            self.add_text_run(draw_ctx);
+            return Ok(draw_ctx.empty_bbox());
+        }
+
+        match self.clusters {
+            Some(ref clusters) => {
+                let mut bbox = draw_ctx.empty_bbox();
+
+                for cluster in clusters {
+                    let cluster_bbox = self.draw_layout(
+                        &cluster.layout,
+                        cluster.x,
+                        cluster.y,
+                        cluster.rotation,
+                        cluster.scale,
+                        acquired_nodes,
+                        draw_ctx,
+                        clipping,
+                    )?;
+                    bbox.insert(&cluster_bbox);
+                }
+
+                Ok(bbox)
+            }
+
+            None => {
+                let (x, y) = self.rendered_position;
+                self.draw_layout(
+                    &self.layout,
+                    x,
+                    y,
+                    0.0,
+                    1.0,
+                    acquired_nodes,
+                    draw_ctx,
+                    clipping,
+                )
+            }
+        }
+    }
+
+    /// Adds the span to the text runs that the `draw_ctx` is collecting.
+    fn add_text_run(&self, draw_ctx: &DrawingCtx) {
+        if !self.values.is_visible() {
+            return;
+        }
+
+        let text = match self.layout.text() {
+            Some(text) => text.to_string(),
+            None => return,
+        };
+
+        let mut characters = match self.clusters {
+            Some(ref clusters) => clusters
+                .iter()
+                .flat_map(|cluster| {
+                    draw_ctx
+                        .text_character_bounds(
+                            &cluster.layout,
+                            cluster.x,
+                            cluster.y,
+                            cluster.rotation,
+                            cluster.scale,
+                        )
+                        .into_iter()
+                        .map(move |(index, rect)| (cluster.range.start + index, rect))
+                })
+                .collect(),
+
+            None => {
+                let (x, y) = self.rendered_position;
+                draw_ctx.text_character_bounds(&self.layout, x, y, 0.0, 1.0)
+            }
+        };
+
+        if characters.is_empty() {
+            return;
+        }
+
+        characters.sort_by_key(|&(index, _)| index);
+
+        let bounds = characters
+            .iter()
+            .skip(1)
+            .fold(characters[0].1, |bounds, (_, rect)| bounds.union(rect));
+
+        let view_params = draw_ctx.get_view_params();
+        let params = NormalizeParams::new(&self.values, &view_params);
+
+        let font_style = self.values.font_style();
+
+        draw_ctx.add_text_run(TextRun {
+            text,
+            id: self.id.clone(),
+            lang: self.values.xml_lang().0,
+            font_family: self.values.font_family().0,
+            font_size: self.values.font_size().to_user(&params),
+            font_weight: self.values.font_weight().numeric_weight(),
+            italic: matches!(font_style, FontStyle::Italic | FontStyle::Oblique),
+            bounds,
+            characters,
+        });
+    }
+
+    fn draw_layout(
+        &self,
+        layout: &pango::Layout,
+        x: f64,
+        y: f64,
+        rotation: f64,
+        inline_scale: f64,
+        acquired_nodes: &mut AcquiredNodes<'_>,
+        draw_ctx: &mut DrawingCtx,
+        clipping: bool,
     ) -> Result<BoundingBox, RenderingError> {
         let view_params = draw_ctx.get_view_params();
         let params = NormalizeParams::new(&self.values, &view_params);
 
-        let layout = self.layout.clone();
+        let layout = layout.clone();
         let is_visible = self.values.is_visible();
-        let (x, y) = self.rendered_position;
 
         let stroke = Stroke::new(&self.values, &params);
 
@@ -283,45 +850,144 @@ impl PositionedSpan {
         );
 
         let text_rendering = self.values.text_rendering();
+        let decorations = self.decorations.clone();
 
         let span = layout::TextSpan {
             layout,
             is_visible,
             x,
             y,
+            rotation,
+            inline_scale,
             stroke,
             stroke_paint,
             fill_paint,
             text_rendering,
+            decorations,
         };
 
         draw_ctx.draw_text_span(&view_params, &span, acquired_nodes, &self.values, clipping)
     }
 }
 
+/// A baseline of a span's font, for the `dominant-baseline` and `alignment-baseline`
+/// properties.
+#[derive(Debug, Copy, Clone, PartialEq)]
+enum Baseline {
+    Alphabetic,
+    Ideographic,
+    Hanging,
+    Mathematical,
+    Central,
+    Middle,
+    TextTop,
+    TextBottom,
+}
+
+impl Baseline {
+    /// Returns the baseline that gets aligned to the span's position.
+    ///
+    /// We don't have a baseline table for the parent element, so we use the
+    /// span's own font for both the `alignment-baseline` and the `dominant-baseline`;
+    /// the former takes precedence.
+    fn from_values(values: &ComputedValues) -> Baseline {
+        match values.alignment_baseline() {
+            AlignmentBaseline::Auto | AlignmentBaseline::Baseline => {
+                match values.dominant_baseline() {
+                    DominantBaseline::Auto | DominantBaseline::Alphabetic => Baseline::Alphabetic,
+                    DominantBaseline::Ideographic => Baseline::Ideographic,
+                    DominantBaseline::Hanging => Baseline::Hanging,
+                    DominantBaseline::Mathematical => Baseline::Mathematical,
+                    DominantBaseline::Central => Baseline::Central,
+                    DominantBaseline::Middle => Baseline::Middle,
+                    DominantBaseline::TextTop | DominantBaseline::TextBeforeEdge => {
+                        Baseline::TextTop
+                    }
+                    DominantBaseline::TextBottom | DominantBaseline::TextAfterEdge => {
+                        Baseline::TextBottom
+                    }
+                }
+            }
+
+            AlignmentBaseline::Alphabetic => Baseline::Alphabetic,
+            AlignmentBaseline::Ideographic => Baseline::Ideographic,
+            AlignmentBaseline::Hanging => Baseline::Hanging,
+            AlignmentBaseline::Mathematical => Baseline::Mathematical,
+            AlignmentBaseline::Central => Baseline::Central,
+            AlignmentBaseline::Middle => Baseline::Middle,
+            AlignmentBaseline::TextTop
+            | AlignmentBaseline::BeforeEdge
+            | AlignmentBaseline::TextBeforeEdge => Baseline::TextTop,
+            AlignmentBaseline::TextBottom
+            | AlignmentBaseline::AfterEdge
+            | AlignmentBaseline::TextAfterEdge => Baseline::TextBottom,
+        }
+    }
+
+    /// Returns the distance from the alphabetic baseline up to this baseline.
+    ///
+    /// Pango does not give us the font's baseline table, so we derive the baselines
+    /// from the font's metrics like browsers do: the `middle` baseline uses the
+    /// strikethrough position, which fonts put at about half the x-height, and the
+    /// `hanging` and `mathematical` baselines are fractions of the ascent.
+    fn offset(self, layout: &pango::Layout) -> f64 {
+        if self == Baseline::Alphabetic {
+            return 0.0;
+        }
+
+        let metrics = match layout
+            .context()
+            .and_then(|c| c.metrics(layout.font_description().as_ref(), None))
+        {
+            Some(metrics) => metrics,
+            None => return 0.0,
+        };
+
+        let scale = f64::from(pango::SCALE);
+        let ascent = f64::from(metrics.ascent()) / scale;
+        let descent = f64::from(metrics.descent()) / scale;
+
+        match self {
+            Baseline::Alphabetic => 0.0,
+            Baseline::Ideographic | Baseline::TextBottom => -descent,
+            Baseline::Hanging => 0.8 * ascent,
+            Baseline::Mathematical => 0.5 * ascent,
+            Baseline::Central => (ascent - descent) / 2.0,
+            Baseline::Middle => {
+                let position = f64::from(metrics.strikethrough_position()) / scale;
+                let thickness = f64::from(metrics.strikethrough_thickness()) / scale;
+                position - thickness / 2.0
+            }
+            Baseline::TextTop => ascent,
+        }
+    }
+}
+
 /// Walks the children of a `<text>`, `<tspan>`, or `<tref>` element
 /// and appends chunks/spans from them into the specified `chunks`
 /// array.
 ///
-/// `x` and `y` are the absolute position for the first chunk.  If the
-/// first child is a `<tspan>` with a specified absolute position, it
-/// will be used instead of the given arguments.
+/// The characters get their positioning values from `positions`, which must
+/// already have the lists for `node`.
 fn children_to_chunks(
     chunks: &mut Vec<Chunk>,
     node: &Node,
     acquired_nodes: &mut AcquiredNodes<'_>,
     cascaded: &CascadedValues<'_>,
     draw_ctx: &mut DrawingCtx,
-    dx: f64,
-    dy: f64,
+    positions: &mut CharacterPositions,
     depth: usize,
 ) {
     for child in node.children() {
         if child.is_chars() {
             let values = cascaded.get();
-            child
-                .borrow_chars()
-                .to_chunks(&child, Rc::new(values.clone()), chunks, dx, dy, depth);
+            child.borrow_chars().to_chunks(
+                &child,
+                Rc::new(values.clone()),
+                chunks,
+                positions,
+                depth,
+            );
         } else {
             assert!(child.is_element());
 
@@ -334,15 +1000,37 @@ fn children_to_chunks(
                         &cascaded,
                         draw_ctx,
                         chunks,
-                        dx,
-                        dy,
+                        positions,
                         depth + 1,
                     );
                 }
 
                 Element::TRef(ref tref) => {
                     let cascaded = CascadedValues::new(cascaded, &child);
-                    tref.to_chunks(&child, acquired_nodes, &cascaded, chunks, depth + 1);
+                    tref.to_chunks(
+                        &child,
+                        acquired_nodes,
+                        &cascaded,
+                        draw_ctx,
+                        chunks,
+                        positions,
+                        depth + 1,
+                    );
+                }
+
+                Element::TextPath(ref text_path) => {
+                    let path_cascaded = CascadedValues::new(cascaded, &child);
+                    text_path.to_chunks(
+                        &child,
+                        acquired_nodes,
+                        &path_cascaded,
+                        draw_ctx,
+                        chunks,
+                        positions,
+                        depth + 1,
+                    );
+
+                    chunks.push(Chunk::new(cascaded.get(), None, None));
                 }
 
                 _ => (),
@@ -401,56 +1089,97 @@ impl Chars {
         let mut normalized = self.space_normalized.borrow_mut();
 
         if (*normalized).is_none() {
-            let mode = match values.xml_space() {
-                XmlSpace::Default => XmlSpaceNormalize::Default(NormalizeDefault {
-                    has_element_before: node.previous_sibling().is_some(),
-                    has_element_after: node.next_sibling().is_some(),
-                }),
-
-                XmlSpace::Preserve => XmlSpaceNormalize::Preserve,
+            let elements = NormalizeDefault {
+                has_element_before: node.previous_sibling().is_some(),
+                has_element_after: node.next_sibling().is_some(),
             };
 
-            *normalized = Some(xml_space_normalize(mode, &self.string.borrow()));
-        }
-    }
+            // The white-space property takes precedence over xml:space, unless it is `normal`.
+            let mode = match (values.white_space(), values.xml_space()) {
+                (WhiteSpace::Normal, XmlSpace::Default)
+                | (WhiteSpace::NoWrap, XmlSpace::Default) => XmlSpaceNormalize::Default(elements),
 
-    fn make_span(
-        &self,
-        node: &Node,
-        values: Rc<ComputedValues>,
-        dx: f64,
-        dy: f64,
-        depth: usize,
-    ) -> Option<Span> {
-        self.ensure_normalized_string(node, &*values);
+                (WhiteSpace::Normal, XmlSpace::Preserve)
+                | (WhiteSpace::NoWrap, XmlSpace::Preserve) => XmlSpaceNormalize::Preserve,
 
-        if self.space_normalized.borrow().as_ref().unwrap() == "" {
-            None
-        } else {
-            Some(Span::new(
-                self.space_normalized.borrow().as_ref().unwrap(),
-                values,
-                dx,
-                dy,
-                depth,
-            ))
+                (WhiteSpace::PreLine, _) => XmlSpaceNormalize::CollapseSpaces(elements),
+
+                (WhiteSpace::Pre, _) | (WhiteSpace::PreWrap, _) | (WhiteSpace::BreakSpaces, _) => {
+                    XmlSpaceNormalize::PreserveNewlines
+                }
+            };
+
+            *normalized = Some(xml_space_normalize(mode, &self.string.borrow()));
         }
     }
 
+    /// Appends the characters to the last chunk, splitting them into spans as
+    /// needed by their positioning values.
+    ///
+    /// A character with an absolute position starts a new chunk, and a character
+    /// with a relative position or a different rotation starts a new span.  Each
+    /// grapheme cluster counts as a single character.
     fn to_chunks(
         &self,
         node: &Node,
         values: Rc<ComputedValues>,
         chunks: &mut Vec<Chunk>,
-        dx: f64,
-        dy: f64,
+        positions: &mut CharacterPositions,
         depth: usize,
     ) {
-        if let Some(span) = self.make_span(node, values, dx, dy, depth) {
-            let num_chunks = chunks.len();
-            assert!(num_chunks > 0);
+        self.ensure_normalized_string(node, &*values);
+
+        let normalized = self.space_normalized.borrow();
+        let text = normalized.as_ref().unwrap();
+
+        let id = node
+            .parent()
+            .and_then(|parent| parent.borrow_element().get_id().map(String::from));
+
+        let mut span: Option<Span> = None;
+
+        for grapheme in text.graphemes(true) {
+            let pos = positions.next_character();
+            let rotation = pos.rotate.unwrap_or(0.0);
+
+            // FIXME: absolute positions inside a <textPath> are ignored; the text
+            // just keeps flowing along the path.
+            let in_text_path = matches!(chunks.last(), Some(c) if c.text_path.is_some());
+            let starts_chunk = (pos.x.is_some() || pos.y.is_some()) && !in_text_path;
+
+            let starts_span = starts_chunk
+                || pos.dx.is_some()
+                || pos.dy.is_some()
+                || !matches!(span, Some(ref s) if s.rotation == rotation);
+
+            if starts_span {
+                if let Some(span) = span.take() {
+                    push_span(chunks, span);
+                }
+
+                if starts_chunk {
+                    start_chunk(chunks, &values, pos.x, pos.y);
+                }
 
-            chunks[num_chunks - 1].spans.push(span);
+                span = Some(Span::new(
+                    "",
+                    values.clone(),
+                    pos.dx.unwrap_or(0.0),
+                    pos.dy.unwrap_or(0.0),
+                    rotation,
+                    positions.text_lengths(),
+                    positions.decorations(),
+                    positions.bidi_embeddings(),
+                    id.clone(),
+                    depth,
+                ));
+            }
+
+            span.as_mut().unwrap().text.push_str(grapheme);
+        }
+
+        if let Some(span) = span {
+            push_span(chunks, span);
         }
     }
 
@@ -459,47 +1188,1007 @@ impl Chars {
     }
 }
 
-#[derive(Default)]
-pub struct Text {
-    x: Length<Horizontal>,
-    y: Length<Vertical>,
-    dx: Length<Horizontal>,
-    dy: Length<Vertical>,
+fn push_span(chunks: &mut Vec<Chunk>, span: Span) {
+    let num_chunks = chunks.len();
+    assert!(num_chunks > 0);
+
+    chunks[num_chunks - 1].spans.push(span);
 }
 
-impl Text {
-    fn make_chunks(
-        &self,
-        node: &Node,
-        acquired_nodes: &mut AcquiredNodes<'_>,
-        cascaded: &CascadedValues<'_>,
-        draw_ctx: &mut DrawingCtx,
-        x: f64,
-        y: f64,
+/// Starts a new chunk for a character with an absolute position.
+///
+/// If the last chunk does not have any spans yet, it gets replaced instead.
+fn start_chunk(chunks: &mut Vec<Chunk>, values: &ComputedValues, x: Option<f64>, y: Option<f64>) {
+    match chunks.last_mut() {
+        Some(chunk) if chunk.spans.is_empty() && chunk.text_path.is_none() => {
+            *chunk = Chunk::new(values, x.or(chunk.x), y.or(chunk.y));
+        }
+
+        _ => chunks.push(Chunk::new(values, x, y)),
+    }
+}
+
+/// A list of values for the `x`, `y`, `dx`, `dy`, and `rotate` attributes of text elements.
+///
+/// Each value in the list applies to a successive character in the element.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValueList<T>(Vec<T>);
+
+impl<T> Default for ValueList<T> {
+    fn default() -> ValueList<T> {
+        ValueList(Vec::new())
+    }
+}
+
+impl<T: Parse> Parse for ValueList<T> {
+    fn parse<'i>(parser: &mut Parser<'i, '_>) -> Result<ValueList<T>, ParseError<'i>> {
+        let mut values = Vec::new();
+
+        while !parser.is_exhausted() {
+            values.push(T::parse(parser)?);
+            optional_comma(parser);
+        }
+
+        Ok(ValueList(values))
+    }
+}
+
+/// The positioning values of a text element, normalized to user space.
+#[derive(Debug, Default)]
+struct PositionLists {
+    x: Vec<f64>,
+    y: Vec<f64>,
+    dx: Vec<f64>,
+    dy: Vec<f64>,
+    /// In radians.
+    rotate: Vec<f64>,
+    text_length: Option<Rc<TextLength>>,
+    /// The decorations that the element's `text-decoration-line` starts.
+    decorations: Vec<Decoration>,
+    /// The bidi embedding that the element's `unicode-bidi` starts.
+    bidi: Option<Rc<BidiEmbedding>>,
+}
+
+impl PositionLists {
+    fn new(
+        x: &ValueList<Length<Horizontal>>,
+        y: &ValueList<Length<Vertical>>,
+        dx: &ValueList<Length<Horizontal>>,
+        dy: &ValueList<Length<Vertical>>,
+        rotate: &ValueList<f64>,
+        params: &NormalizeParams,
+    ) -> PositionLists {
+        PositionLists {
+            x: x.0.iter().map(|l| l.to_user(params)).collect(),
+            y: y.0.iter().map(|l| l.to_user(params)).collect(),
+            dx: dx.0.iter().map(|l| l.to_user(params)).collect(),
+            dy: dy.0.iter().map(|l| l.to_user(params)).collect(),
+            rotate: rotate.0.iter().map(|r| r.to_radians()).collect(),
+            text_length: None,
+            decorations: Vec::new(),
+            bidi: None,
+        }
+    }
+}
+
+/// `lengthAdjust` attribute of text content elements.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum LengthAdjust {
+    Spacing,
+    SpacingAndGlyphs,
+}
+
+enum_default!(LengthAdjust, LengthAdjust::Spacing);
+
+impl Parse for LengthAdjust {
+    fn parse<'i>(parser: &mut Parser<'i, '_>) -> Result<LengthAdjust, ParseError<'i>> {
+        Ok(parse_identifiers!(
+            parser,
+            "spacing" => LengthAdjust::Spacing,
+            "spacingAndGlyphs" => LengthAdjust::SpacingAndGlyphs,
+        )?)
+    }
+}
+
+/// The `textLength` of an element, which all its characters must span.
+#[derive(Debug)]
+struct TextLength {
+    length: f64,
+    adjust: LengthAdjust,
+    depth: usize,
+}
+
+impl TextLength {
+    fn new(
+        length: Option<ULength<Horizontal>>,
+        adjust: LengthAdjust,
+        depth: usize,
+        params: &NormalizeParams,
+    ) -> Option<Rc<TextLength>> {
+        length.map(|l| {
+            Rc::new(TextLength {
+                length: l.to_user(params),
+                adjust,
+                depth,
+            })
+        })
+    }
+}
+
+/// Computes the decorations that an element's `text-decoration-line` starts.
+///
+/// Lengths are resolved with the element's font size, since the decorations keep
+/// the element's style when they propagate to descendants.
+fn element_decorations(values: &ComputedValues, params: &NormalizeParams) -> Vec<Decoration> {
+    let line = values.text_decoration_line();
+    let font_size = values.font_size().to_user(params);
+
+    let to_user = |length: Length<Both>| match length.unit {
+        LengthUnit::Percent => length.length * font_size,
+        _ => length.to_user(params),
+    };
+
+    let thickness = match values.text_decoration_thickness() {
+        TextDecorationThickness::Length(l) => Some(to_user(l)),
+        TextDecorationThickness::Auto | TextDecorationThickness::FromFont => None,
+    };
+
+    let underline_offset = match values.text_underline_offset() {
+        TextUnderlineOffset::Length(l) => Some(to_user(l)),
+        TextUnderlineOffset::Auto => None,
+    };
+
+    let color = resolve_color(
+        &values.text_decoration_color().0,
+        UnitInterval(1.0),
+        values.color().0,
+    );
+
+    [
+        (line.underline, DecorationLine::Underline),
+        (line.overline, DecorationLine::Overline),
+        (line.strike, DecorationLine::LineThrough),
+    ]
+    .iter()
+    .filter(|(on, _)| *on)
+    .map(|&(_, line)| Decoration {
+        line,
+        style: values.text_decoration_style(),
+        color,
+        thickness,
+        underline_offset,
+    })
+    .collect()
+}
+
+/// A bidi embedding, isolate, or override that an element starts with its
+/// `unicode-bidi` property.
+#[derive(Debug)]
+struct BidiEmbedding {
+    unicode_bidi: UnicodeBidi,
+    direction: Direction,
+}
+
+impl BidiEmbedding {
+    fn new(values: &ComputedValues) -> Option<Rc<BidiEmbedding>> {
+        match values.unicode_bidi() {
+            UnicodeBidi::Normal => None,
+            unicode_bidi => Some(Rc::new(BidiEmbedding {
+                unicode_bidi,
+                direction: values.direction(),
+            })),
+        }
+    }
+
+    /// The explicit formatting characters that start the embedding.
+    fn opening(&self) -> &'static str {
+        let rtl = self.direction == Direction::Rtl;
+
+        match (self.unicode_bidi, rtl) {
+            (UnicodeBidi::Normal, _) => "",
+            (UnicodeBidi::Embed, false) => "\u{202a}",
+            (UnicodeBidi::Embed, true) => "\u{202b}",
+            (UnicodeBidi::Isolate, false) => "\u{2066}",
+            (UnicodeBidi::Isolate, true) => "\u{2067}",
+            (UnicodeBidi::Override, false) => "\u{202d}",
+            (UnicodeBidi::Override, true) => "\u{202e}",
+            (UnicodeBidi::IsolateOverride, false) => "\u{2066}\u{202d}",
+            (UnicodeBidi::IsolateOverride, true) => "\u{2067}\u{202e}",
+            (UnicodeBidi::Plaintext, _) => "\u{2068}",
+        }
+    }
+
+    /// The explicit formatting characters that end the embedding.
+    fn closing(&self) -> &'static str {
+        match self.unicode_bidi {
+            UnicodeBidi::Normal => "",
+            UnicodeBidi::Embed | UnicodeBidi::Override => "\u{202c}",
+            UnicodeBidi::Isolate | UnicodeBidi::Plaintext => "\u{2069}",
+            UnicodeBidi::IsolateOverride => "\u{202c}\u{2069}",
+        }
+    }
+
+    fn is_override(&self) -> bool {
+        matches!(
+            self.unicode_bidi,
+            UnicodeBidi::Override | UnicodeBidi::IsolateOverride
+        )
+    }
+}
+
+/// Positioning values for a single character; see `CharacterPositions`.
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+struct CharacterPosition {
+    x: Option<f64>,
+    y: Option<f64>,
+    dx: Option<f64>,
+    dy: Option<f64>,
+    rotate: Option<f64>,
+}
+
+/// Assigns the positioning values of nested text elements to their characters.
+///
+/// The values in each element's lists apply to its characters in order, including
+/// the characters of its descendants.  A character gets each value from the innermost
+/// element that has one for it.  For `rotate`, the last value in an element's list also
+/// applies to the rest of the element's characters.
+///
+/// This has a stack with the lists of the elements being traversed, and the number of
+/// characters that each element has seen so far.
+#[derive(Default)]
+struct CharacterPositions {
+    stack: Vec<(PositionLists, usize)>,
+}
+
+impl CharacterPositions {
+    fn push(&mut self, lists: PositionLists) {
+        self.stack.push((lists, 0));
+    }
+
+    fn pop(&mut self) {
+        self.stack.pop();
+    }
+
+    fn text_lengths(&self) -> Vec<Rc<TextLength>> {
+        self.stack
+            .iter()
+            .filter_map(|(lists, _)| lists.text_length.clone())
+            .collect()
+    }
+
+    fn decorations(&self) -> Vec<Decoration> {
+        self.stack
+            .iter()
+            .flat_map(|(lists, _)| lists.decorations.iter().cloned())
+            .collect()
+    }
+
+    fn bidi_embeddings(&self) -> Vec<Rc<BidiEmbedding>> {
+        self.stack
+            .iter()
+            .filter_map(|(lists, _)| lists.bidi.clone())
+            .collect()
+    }
+
+    fn next_character(&mut self) -> CharacterPosition {
+        fn value(
+            stack: &[(PositionLists, usize)],
+            list: fn(&PositionLists) -> &[f64],
+        ) -> Option<f64> {
+            stack
+                .iter()
+                .rev()
+                .find_map(|(lists, index)| list(lists).get(*index).copied())
+        }
+
+        let pos = CharacterPosition {
+            x: value(&self.stack, |l| &l.x),
+            y: value(&self.stack, |l| &l.y),
+            dx: value(&self.stack, |l| &l.dx),
+            dy: value(&self.stack, |l| &l.dy),
+            rotate: self
+                .stack
+                .iter()
+                .rev()
+                .find_map(|(lists, index)| lists.rotate.get(*index).or_else(|| lists.rotate.last()))
+                .copied(),
+        };
+
+        for (_, index) in self.stack.iter_mut() {
+            *index += 1;
+        }
+
+        pos
+    }
+}
+
+#[derive(Default)]
+pub struct Text {
+    x: ValueList<Length<Horizontal>>,
+    y: ValueList<Length<Vertical>>,
+    dx: ValueList<Length<Horizontal>>,
+    dy: ValueList<Length<Vertical>>,
+    rotate: ValueList<f64>,
+    text_length: Option<ULength<Horizontal>>,
+    length_adjust: LengthAdjust,
+}
+
+impl Text {
+    /// Returns the absolute position of the first character.
+    fn position(&self, params: &NormalizeParams) -> (f64, f64) {
+        let x = self.x.0.first().map_or(0.0, |l| l.to_user(params));
+        let y = self.y.0.first().map_or(0.0, |l| l.to_user(params));
+
+        (x, y)
+    }
+
+    fn make_chunks(
+        &self,
+        node: &Node,
+        acquired_nodes: &mut AcquiredNodes<'_>,
+        cascaded: &CascadedValues<'_>,
+        draw_ctx: &mut DrawingCtx,
+        x: f64,
+        y: f64,
     ) -> Vec<Chunk> {
         let mut chunks = Vec::new();
 
-        let values = cascaded.get();
-        let view_params = draw_ctx.get_view_params();
-        let params = NormalizeParams::new(values, &view_params);
+        let values = cascaded.get();
+        let view_params = draw_ctx.get_view_params();
+        let params = NormalizeParams::new(values, &view_params);
+
+        chunks.push(Chunk::new(values, Some(x), Some(y)));
+
+        let mut lists =
+            PositionLists::new(&self.x, &self.y, &self.dx, &self.dy, &self.rotate, &params);
+        lists.text_length = TextLength::new(self.text_length, self.length_adjust, 0, &params);
+        lists.decorations = element_decorations(values, &params);
+
+        // On the <text> element, `unicode-bidi: plaintext` determines the direction of
+        // the paragraph itself; see reorder_bidi().
+        if values.unicode_bidi() != UnicodeBidi::Plaintext {
+            lists.bidi = BidiEmbedding::new(values);
+        }
+
+        let mut positions = CharacterPositions::default();
+        positions.push(lists);
+
+        children_to_chunks(
+            &mut chunks,
+            node,
+            acquired_nodes,
+            cascaded,
+            draw_ctx,
+            &mut positions,
+            0,
+        );
+        chunks
+    }
+}
+
+/// Where the lines of auto-wrapped text go; see `wrap_chunks`.
+enum LineBox {
+    /// Lines start at the text's position, and are at most this wide if a width is given.
+    ///
+    /// This is for the `inline-size` property, or for text that has forced line breaks
+    /// per the `white-space` property.
+    Anchored(Option<f64>),
+
+    /// Lines fill the inside of a shape, for the `shape-inside` property.
+    Shape(FlattenedPath),
+}
+
+impl LineBox {
+    /// Returns how to lay out the lines of a `<text>`, or `None` if it is not auto-wrapped.
+    fn new(
+        values: &ComputedValues,
+        acquired_nodes: &mut AcquiredNodes<'_>,
+        draw_ctx: &DrawingCtx,
+    ) -> Option<LineBox> {
+        // FIXME: we don't support auto-wrapped vertical text yet.
+        if values.writing_mode().is_vertical() {
+            if values.shape_inside().0.get().is_some() || values.inline_size().0.is_some() {
+                rsvg_log!("auto-wrapped vertical text is not supported; not wrapping it");
+            }
+
+            return None;
+        }
+
+        let view_params = draw_ctx.get_view_params();
+
+        if let Some(shape_id) = values.shape_inside().0.get() {
+            match acquired_nodes.acquire(shape_id) {
+                Ok(acquired) => {
+                    let node = acquired.get();
+                    let elt = node.borrow_element();
+                    let shape_values = elt.get_computed_values();
+                    let params = NormalizeParams::new(shape_values, &view_params);
+
+                    if let Some(path) = shape_element_path(&elt, &params) {
+                        let path = path.flatten().transformed(&shape_values.transform());
+                        return Some(LineBox::Shape(path));
+                    }
+
+                    rsvg_log!(
+                        "shape-inside references \"{}\" which is not a shape",
+                        shape_id
+                    );
+                }
+
+                Err(_) => {
+                    rsvg_log!("shape-inside references nonexistent shape \"{}\"", shape_id);
+                }
+            }
+
+            // An invalid shape-inside falls back to using inline-size.
+        }
+
+        let white_space = values.white_space();
+        let inline_size = values.inline_size().0;
+
+        if inline_size.is_some() || white_space.preserves_newlines() {
+            let params = NormalizeParams::new(values, &view_params);
+
+            let width = inline_size
+                .filter(|_| white_space.allows_wrapping())
+                .map(|l| l.to_user(&params));
+
+            Some(LineBox::Anchored(width))
+        } else {
+            None
+        }
+    }
+}
+
+/// Lays out the spans of an auto-wrapped `<text>` as lines, and returns a chunk per line.
+///
+/// SVG2 auto-wrapped text gets laid out per the `inline-size` or `shape-inside`
+/// properties.  We lay out the text of all the spans in a single Pango layout to find
+/// where to break each line, and then create a chunk with the pieces of the spans that
+/// fall in that line.  This way, spans with different styles can still be measured and
+/// drawn individually.
+///
+/// Lines are `line-height` apart.  With `inline-size`, the first line's baseline is at
+/// the text's position, and `text-anchor` aligns the lines with respect to that
+/// position.  With `shape-inside`, lines go from the top of the shape and `text-anchor`
+/// aligns them inside the shape; text that does not fit in the shape is not rendered.
+/// In both cases, `text-anchor` refers to the start and end of lines in the text's
+/// `direction`.
+fn wrap_chunks(
+    chunks: Vec<Chunk>,
+    line_box: &LineBox,
+    values: &ComputedValues,
+    draw_ctx: &DrawingCtx,
+    x: f64,
+    y: f64,
+) -> Vec<Chunk> {
+    let view_params = draw_ctx.get_view_params();
+    let params = NormalizeParams::new(values, &view_params);
+
+    let props = FontProperties::new(values, &params);
+    let white_space = values.white_space();
+
+    let text_anchor = match (values.text_anchor(), values.direction()) {
+        (TextAnchor::Start, Direction::Rtl) => TextAnchor::End,
+        (TextAnchor::End, Direction::Rtl) => TextAnchor::Start,
+        (anchor, _) => anchor,
+    };
+
+    let spans: Vec<Span> = chunks.into_iter().flat_map(|c| c.spans).collect();
+
+    let mut text = String::new();
+    let mut span_ranges = Vec::new();
+
+    for span in &spans {
+        let start = text.len();
+        text.push_str(&span.text);
+
+        let span_params = NormalizeParams::new(&span.values, &view_params);
+        span_ranges.push((
+            start..text.len(),
+            FontProperties::new(&span.values, &span_params),
+        ));
+    }
+
+    if text.is_empty() {
+        return Vec::new();
+    }
+
+    // All the text is laid out in a single Pango layout, with the spans' attributes.
+    let make_layout = || {
+        let layout = create_pango_layout(draw_ctx, &props, &text);
+
+        let attr_list = layout.attributes().unwrap_or_default();
+        for (range, span_props) in &span_ranges {
+            add_pango_attributes(
+                &attr_list,
+                draw_ctx,
+                span_props,
+                &text,
+                range.start,
+                range.end,
+            );
+        }
+        layout.set_attributes(Some(&attr_list));
+
+        layout
+    };
+
+    let line_height = values
+        .line_height()
+        .to_user(&params)
+        .unwrap_or_else(|| normal_line_height(&make_layout(), props.font_size));
+
+    let trim_line = |range: Range<usize>| {
+        let mut line_text = &text[range.clone()];
+        if !matches!(
+            white_space,
+            WhiteSpace::Pre | WhiteSpace::PreWrap | WhiteSpace::BreakSpaces
+        ) {
+            line_text = line_text.trim_end_matches(' ');
+        }
+        range.start..range.start + line_text.len()
+    };
+
+    let lines = match *line_box {
+        LineBox::Anchored(width) => {
+            // All lines have the same width, so Pango can break them by itself.
+            let layout = make_layout();
+
+            if let Some(width) = width {
+                layout.set_width(to_pango_units(width.max(0.0)));
+                layout.set_wrap(pango::WrapMode::WordChar);
+            }
+
+            layout
+                .lines_readonly()
+                .iter()
+                .enumerate()
+                .map(|(i, line)| {
+                    let start = line.start_index() as usize;
+                    let end = start + line.length() as usize;
+
+                    (trim_line(start..end), x, y + i as f64 * line_height)
+                })
+                .collect()
+        }
+
+        LineBox::Shape(ref path) => {
+            let measure = LineMeasure::new(&make_layout(), &text);
+            let mut lines = Vec::new();
+
+            let (mut line_top, bottom) = match path.extents() {
+                Some(r) => (r.y0, r.y1),
+                None => (0.0, 0.0),
+            };
+
+            let mut segment = 0;
+
+            while segment < measure.segments.len() && line_top + line_height <= bottom {
+                let (x0, x1) = match shape_line_interval(path, line_top, line_top + line_height) {
+                    Some(interval) => interval,
+
+                    None => {
+                        line_top += line_height;
+                        continue;
+                    }
+                };
+
+                // Take as many segments as fit in the line.  If not even the first one
+                // fits, we don't break it like Pango would, but try the next line, which
+                // may be wider.
+                let start = measure.segments[segment].start;
+                let mut end = None;
+
+                for (i, seg) in measure.segments.iter().enumerate().skip(segment) {
+                    let seg_text = &text[start..seg.end];
+                    let width = measure.width(start, start + seg_text.trim_end().len());
+
+                    if width > x1 - x0 {
+                        break;
+                    }
+
+                    end = Some(i);
+
+                    if seg.forced_break {
+                        break;
+                    }
+                }
+
+                if let Some(end) = end {
+                    let (ascent, height) = measure.line_extents(start);
+                    let baseline = line_top + (line_height - height) / 2.0 + ascent;
+
+                    let line_x = match text_anchor {
+                        TextAnchor::Start => x0,
+                        TextAnchor::Middle => (x0 + x1) / 2.0,
+                        TextAnchor::End => x1,
+                    };
+
+                    lines.push((
+                        trim_line(start..measure.segments[end].end),
+                        line_x,
+                        baseline,
+                    ));
+                    segment = end + 1;
+                }
+
+                line_top += line_height;
+            }
+
+            lines
+        }
+    };
+
+    lines
+        .into_iter()
+        .map(|(line_range, line_x, baseline)| {
+            let mut chunk = Chunk::new(values, Some(line_x), Some(baseline));
+            chunk.text_anchor = text_anchor;
+
+            for (span, (range, _)) in spans.iter().zip(span_ranges.iter()) {
+                let seg_start = range.start.max(line_range.start);
+                let seg_end = range.end.min(line_range.end);
+
+                if seg_start < seg_end {
+                    // dx/dy only apply to the first piece of a span.
+                    let (dx, dy) = if seg_start == range.start {
+                        (span.dx, span.dy)
+                    } else {
+                        (0.0, 0.0)
+                    };
+
+                    // FIXME: textLength is not supported in auto-wrapped text.
+                    chunk.spans.push(Span::new(
+                        &text[seg_start..seg_end],
+                        span.values.clone(),
+                        dx,
+                        dy,
+                        span.rotation,
+                        Vec::new(),
+                        span.decorations.clone(),
+                        span.bidi_embeddings.clone(),
+                        span.id.clone(),
+                        span.depth,
+                    ));
+                }
+            }
+
+            chunk
+        })
+        .collect()
+}
+
+/// Measurements of a paragraph of text, to break it into lines of different widths.
+///
+/// Pango can only wrap a layout to a single width, so for lines inside a shape we find
+/// the possible line breaks and the width of each cluster from a single unwrapped
+/// layout, and then fill each line by hand.
+struct LineMeasure {
+    /// Pieces of text between line break opportunities, in logical order.
+    segments: Vec<Segment>,
+
+    /// Start index of each cluster and the total width of the clusters before it.
+    clusters: Vec<(usize, f64)>,
+
+    /// Start index, ascent and height of each line of the unwrapped layout.
+    lines: Vec<(usize, f64, f64)>,
+}
+
+struct Segment {
+    start: usize,
+    end: usize,
+
+    /// Whether the text must break after this segment, as after a newline.
+    forced_break: bool,
+}
+
+impl LineMeasure {
+    fn new(layout: &pango::Layout, text: &str) -> LineMeasure {
+        let scale = f64::from(pango::SCALE);
+
+        let mut clusters = Vec::new();
+        if let Some(mut iter) = layout.iter() {
+            loop {
+                let (_, logical) = iter.cluster_extents();
+                clusters.push((iter.index() as usize, f64::from(logical.width) / scale));
+
+                if !iter.next_cluster() {
+                    break;
+                }
+            }
+        }
+
+        // The iterator goes through clusters in visual order.
+        clusters.sort_by_key(|&(index, _)| index);
+
+        let mut total = 0.0;
+        for cluster in &mut clusters {
+            let width = cluster.1;
+            cluster.1 = total;
+            total += width;
+        }
+        clusters.push((text.len(), total));
+
+        let lines = layout
+            .lines_readonly()
+            .iter()
+            .map(|line| {
+                let (_, logical) = line.extents();
+                (
+                    line.start_index() as usize,
+                    -f64::from(logical.y) / scale,
+                    f64::from(logical.height) / scale,
+                )
+            })
+            .collect();
+
+        // With a zero width, Pango puts each piece of text between line break
+        // opportunities in its own line, without breaking words.
+        layout.set_width(0);
+        layout.set_wrap(pango::WrapMode::Word);
+
+        let starts: Vec<(usize, usize)> = layout
+            .lines_readonly()
+            .iter()
+            .map(|line| {
+                let start = line.start_index() as usize;
+                (start, start + line.length() as usize)
+            })
+            .collect();
+
+        let segments = starts
+            .iter()
+            .enumerate()
+            .map(|(i, &(start, end))| {
+                let next_start = starts.get(i + 1).map_or(text.len(), |s| s.0);
+
+                Segment {
+                    start,
+                    end,
+                    forced_break: next_start > end,
+                }
+            })
+            .collect();
+
+        LineMeasure {
+            segments,
+            clusters,
+            lines,
+        }
+    }
+
+    /// Width of the clusters that start between the `start` and `end` indices.
+    fn width(&self, start: usize, end: usize) -> f64 {
+        let before = |index| {
+            let i = self.clusters.partition_point(|&(s, _)| s < index);
+            self.clusters[i.min(self.clusters.len() - 1)].1
+        };
+
+        before(end) - before(start)
+    }
+
+    /// Ascent and height of the text that starts at `index`.
+    fn line_extents(&self, index: usize) -> (f64, f64) {
+        self.lines
+            .iter()
+            .rev()
+            .find(|&&(start, _, _)| start <= index)
+            .map_or((0.0, 0.0), |&(_, ascent, height)| (ascent, height))
+    }
+}
+
+/// Finds the horizontal extent available for a line of text inside a shape.
+///
+/// The line occupies the band between `top` and `bottom`; we intersect the
+/// shape's interval at a few points within the band.
+fn shape_line_interval(path: &FlattenedPath, top: f64, bottom: f64) -> Option<(f64, f64)> {
+    let inset = (bottom - top) * 0.01;
+    let mut result: Option<(f64, f64)> = None;
+
+    for &y in &[top + inset, (top + bottom) / 2.0, bottom - inset] {
+        let intervals = path.scanline(y);
+
+        let interval = match result {
+            None => intervals.first().copied(),
+            Some((x0, x1)) => intervals.iter().copied().find(|&(a, b)| a < x1 && b > x0),
+        }?;
+
+        result = Some(match result {
+            None => interval,
+            Some((x0, x1)) => (x0.max(interval.0), x1.min(interval.1)),
+        });
+    }
+
+    result
+}
+
+/// Runs the Unicode Bidirectional Algorithm over each chunk, and puts its spans in
+/// visual order.
+///
+/// The text of all the spans in a chunk gets resolved as a single paragraph, with the
+/// `unicode-bidi` of their elements turned into explicit embedding, isolate, and
+/// override characters.  The spans are then split into runs of characters with the
+/// same embedding level, which get reordered per rule L2.  Pango lays out each run in
+/// the direction of its level.
+fn reorder_bidi(chunks: &mut [Chunk], values: &ComputedValues) {
+    // FIXME: we don't reorder vertical text.
+    if values.writing_mode().is_vertical() {
+        return;
+    }
+
+    let base_dir = match values.unicode_bidi() {
+        UnicodeBidi::Plaintext => pango::Direction::Neutral,
+        _ => pango::Direction::from(values.direction()),
+    };
+
+    for chunk in chunks {
+        // A single span gets reordered by Pango just fine.
+        let needs_reordering =
+            chunk.spans.len() > 1 || chunk.spans.iter().any(|s| !s.bidi_embeddings.is_empty());
+
+        if needs_reordering {
+            let spans = std::mem::take(&mut chunk.spans);
+            chunk.spans = reorder_spans(spans, base_dir);
+        }
+    }
+}
+
+fn reorder_spans(spans: Vec<Span>, base_dir: pango::Direction) -> Vec<Span> {
+    // The index of the first character of each span in the paragraph's text.
+    let mut span_starts = Vec::new();
+
+    let mut text = String::new();
+    let mut num_chars = 0;
+    let mut open: &[Rc<BidiEmbedding>] = &[];
+
+    for span in &spans {
+        let common = open
+            .iter()
+            .zip(span.bidi_embeddings.iter())
+            .take_while(|(a, b)| Rc::ptr_eq(a, b))
+            .count();
+
+        let controls: String = open[common..]
+            .iter()
+            .rev()
+            .map(|e| e.closing())
+            .chain(span.bidi_embeddings[common..].iter().map(|e| e.opening()))
+            .collect();
+
+        num_chars += controls.chars().count();
+        text.push_str(&controls);
+
+        span_starts.push(num_chars);
+        num_chars += span.text.chars().count();
+        text.push_str(&span.text);
+
+        open = &span.bidi_embeddings;
+    }
+
+    let levels = embedding_levels(&text, base_dir);
+
+    struct Run {
+        span: usize,
+        range: Range<usize>,
+        level: u8,
+    }
+
+    let mut runs: Vec<Run> = Vec::new();
+
+    for (i, span) in spans.iter().enumerate() {
+        // Pango would apply the bidi algorithm again to the characters in a run, so
+        // overridden characters each get a run of their own.
+        let overridden = matches!(span.bidi_embeddings.last(), Some(e) if e.is_override());
+
+        let mut char_index = span_starts[i];
+
+        for (start, grapheme) in span.text.grapheme_indices(true) {
+            let level = levels.get(char_index).copied().unwrap_or(0);
+            let end = start + grapheme.len();
+            char_index += grapheme.chars().count();
+
+            match runs.last_mut() {
+                Some(run) if run.span == i && run.level == level && !overridden => {
+                    run.range.end = end;
+                }
+
+                _ => runs.push(Run {
+                    span: i,
+                    range: start..end,
+                    level,
+                }),
+            }
+        }
+    }
+
+    let run_levels: Vec<u8> = runs.iter().map(|r| r.level).collect();
+
+    visual_order(&run_levels)
+        .into_iter()
+        .map(|i| {
+            let run = &runs[i];
+            let span = &spans[run.span];
+
+            // dx/dy only apply to the first run of a span.
+            let (dx, dy) = if run.range.start == 0 {
+                (span.dx, span.dy)
+            } else {
+                (0.0, 0.0)
+            };
+
+            let mut reordered = Span::new(
+                &span.text[run.range.clone()],
+                span.values.clone(),
+                dx,
+                dy,
+                span.rotation,
+                span.text_lengths.clone(),
+                span.decorations.clone(),
+                span.bidi_embeddings.clone(),
+                span.id.clone(),
+                span.depth,
+            );
+            reordered.bidi_level = Some(run.level);
+            reordered
+        })
+        .collect()
+}
+
+/// Resolves the embedding level of each character in a paragraph.
+///
+/// If `base_dir` is `Neutral`, the paragraph's direction comes from its first strong
+/// character.
+fn embedding_levels(text: &str, base_dir: pango::Direction) -> Vec<u8> {
+    use glib::translate::{IntoGlib, ToGlibPtr};
+
+    let num_chars = text.chars().count();
+    let mut dir = base_dir.into_glib();
+
+    unsafe {
+        let levels = pango::ffi::pango_log2vis_get_embedding_levels(
+            text.to_glib_none().0,
+            text.len() as i32,
+            &mut dir,
+        );
+
+        if levels.is_null() {
+            return vec![0; num_chars];
+        }
 
-        chunks.push(Chunk::new(values, Some(x), Some(y)));
+        let result = std::slice::from_raw_parts(levels, num_chars).to_vec();
+        glib::ffi::g_free(levels as glib::ffi::gpointer);
+        result
+    }
+}
 
-        let dx = self.dx.to_user(&params);
-        let dy = self.dy.to_user(&params);
+/// Computes the visual order of a line's runs from their embedding levels, per rule
+/// L2 of the Unicode Bidirectional Algorithm.
+///
+/// Returns the indices of the runs from left to right.
+fn visual_order(levels: &[u8]) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..levels.len()).collect();
 
-        children_to_chunks(
-            &mut chunks,
-            node,
-            acquired_nodes,
-            cascaded,
-            draw_ctx,
-            dx,
-            dy,
-            0,
-        );
-        chunks
+    let highest = levels.iter().copied().max().unwrap_or(0);
+    let lowest_odd = levels.iter().copied().min().unwrap_or(0) | 1;
+
+    for level in (lowest_odd..=highest).rev() {
+        let mut i = 0;
+
+        while i < order.len() {
+            if levels[order[i]] >= level {
+                let start = i;
+                while i < order.len() && levels[order[i]] >= level {
+                    i += 1;
+                }
+                order[start..i].reverse();
+            } else {
+                i += 1;
+            }
+        }
     }
+
+    order
 }
 
 impl SetAttributes for Text {
@@ -510,6 +2199,9 @@ impl SetAttributes for Text {
                 expanded_name!("", "y") => self.y = attr.parse(value)?,
                 expanded_name!("", "dx") => self.dx = attr.parse(value)?,
                 expanded_name!("", "dy") => self.dy = attr.parse(value)?,
+                expanded_name!("", "rotate") => self.rotate = attr.parse(value)?,
+                expanded_name!("", "textLength") => self.text_length = attr.parse(value)?,
+                expanded_name!("", "lengthAdjust") => self.length_adjust = attr.parse(value)?,
                 _ => (),
             }
         }
@@ -542,16 +2234,23 @@ impl Draw for Text {
             clipping,
             None,
             &mut |an, dc| {
-                let mut x = self.x.to_user(&params);
-                let mut y = self.y.to_user(&params);
+                let (mut x, mut y) = self.position(&params);
+
+                let mut chunks = self.make_chunks(node, an, cascaded, dc, x, y);
 
-                let chunks = self.make_chunks(node, an, cascaded, dc, x, y);
+                if let Some(line_box) = LineBox::new(values, an, dc) {
+                    chunks = wrap_chunks(chunks, &line_box, values, dc, x, y);
+                }
+
+                reorder_bidi(&mut chunks, values);
 
                 let mut measured_chunks = Vec::new();
                 for chunk in &chunks {
                     measured_chunks.push(MeasuredChunk::from_chunk(chunk, dc));
                 }
 
+                adjust_text_lengths(&mut measured_chunks);
+
                 let mut positioned_chunks = Vec::new();
                 for chunk in &measured_chunks {
                     let chunk_x = chunk.x.unwrap_or(x);
@@ -591,7 +2290,9 @@ impl TRef {
         node: &Node,
         acquired_nodes: &mut AcquiredNodes<'_>,
         cascaded: &CascadedValues<'_>,
+        draw_ctx: &DrawingCtx,
         chunks: &mut Vec<Chunk>,
+        positions: &mut CharacterPositions,
         depth: usize,
     ) {
         if self.link.is_none() {
@@ -606,8 +2307,25 @@ impl TRef {
         }
 
         if let Ok(acquired) = acquired_nodes.acquire(link) {
+            let view_params = draw_ctx.get_view_params();
+            let params = NormalizeParams::new(values, &view_params);
+
+            positions.push(PositionLists {
+                decorations: element_decorations(values, &params),
+                bidi: BidiEmbedding::new(values),
+                ..Default::default()
+            });
+
             let c = acquired.get();
-            extract_chars_children_to_chunks_recursively(chunks, c, Rc::new(values.clone()), depth);
+            extract_chars_children_to_chunks_recursively(
+                chunks,
+                c,
+                Rc::new(values.clone()),
+                positions,
+                depth,
+            );
+
+            positions.pop();
         } else {
             rsvg_log!(
                 "element {} references a nonexistent text source \"{}\"",
@@ -622,6 +2340,7 @@ fn extract_chars_children_to_chunks_recursively(
     chunks: &mut Vec<Chunk>,
     node: &Node,
     values: Rc<ComputedValues>,
+    positions: &mut CharacterPositions,
     depth: usize,
 ) {
     for child in node.children() {
@@ -630,9 +2349,15 @@ fn extract_chars_children_to_chunks_recursively(
         if child.is_chars() {
             child
                 .borrow_chars()
-                .to_chunks(&child, values, chunks, 0.0, 0.0, depth)
+                .to_chunks(&child, values, chunks, positions, depth)
         } else {
-            extract_chars_children_to_chunks_recursively(chunks, &child, values, depth + 1)
+            extract_chars_children_to_chunks_recursively(
+                chunks,
+                &child,
+                values,
+                positions,
+                depth + 1,
+            )
         }
     }
 }
@@ -655,10 +2380,13 @@ impl Draw for TRef {}
 
 #[derive(Default)]
 pub struct TSpan {
-    x: Option<Length<Horizontal>>,
-    y: Option<Length<Vertical>>,
-    dx: Length<Horizontal>,
-    dy: Length<Vertical>,
+    x: ValueList<Length<Horizontal>>,
+    y: ValueList<Length<Vertical>>,
+    dx: ValueList<Length<Horizontal>>,
+    dy: ValueList<Length<Vertical>>,
+    rotate: ValueList<f64>,
+    text_length: Option<ULength<Horizontal>>,
+    length_adjust: LengthAdjust,
 }
 
 impl TSpan {
@@ -669,8 +2397,7 @@ impl TSpan {
         cascaded: &CascadedValues<'_>,
         draw_ctx: &mut DrawingCtx,
         chunks: &mut Vec<Chunk>,
-        dx: f64,
-        dy: f64,
+        positions: &mut CharacterPositions,
         depth: usize,
     ) {
         let values = cascaded.get();
@@ -681,15 +2408,12 @@ impl TSpan {
         let view_params = draw_ctx.get_view_params();
         let params = NormalizeParams::new(values, &view_params);
 
-        let x = self.x.map(|l| l.to_user(&params));
-        let y = self.y.map(|l| l.to_user(&params));
-
-        let span_dx = dx + self.dx.to_user(&params);
-        let span_dy = dy + self.dy.to_user(&params);
-
-        if x.is_some() || y.is_some() {
-            chunks.push(Chunk::new(values, x, y));
-        }
+        let mut lists =
+            PositionLists::new(&self.x, &self.y, &self.dx, &self.dy, &self.rotate, &params);
+        lists.text_length = TextLength::new(self.text_length, self.length_adjust, depth, &params);
+        lists.decorations = element_decorations(values, &params);
+        lists.bidi = BidiEmbedding::new(values);
+        positions.push(lists);
 
         children_to_chunks(
             chunks,
@@ -697,10 +2421,11 @@ impl TSpan {
             acquired_nodes,
             cascaded,
             draw_ctx,
-            span_dx,
-            span_dy,
+            positions,
             depth,
         );
+
+        positions.pop();
     }
 }
 
@@ -712,6 +2437,9 @@ impl SetAttributes for TSpan {
                 expanded_name!("", "y") => self.y = attr.parse(value)?,
                 expanded_name!("", "dx") => self.dx = attr.parse(value)?,
                 expanded_name!("", "dy") => self.dy = attr.parse(value)?,
+                expanded_name!("", "rotate") => self.rotate = attr.parse(value)?,
+                expanded_name!("", "textLength") => self.text_length = attr.parse(value)?,
+                expanded_name!("", "lengthAdjust") => self.length_adjust = attr.parse(value)?,
                 _ => (),
             }
         }
@@ -722,7 +2450,252 @@ impl SetAttributes for TSpan {
 
 impl Draw for TSpan {}
 
-fn to_pango_units(v: f64) -> i32 {
+/// `method` attribute of the `<textPath>` element.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum TextPathMethod {
+    Align,
+    Stretch,
+}
+
+enum_default!(TextPathMethod, TextPathMethod::Align);
+
+impl Parse for TextPathMethod {
+    fn parse<'i>(parser: &mut Parser<'i, '_>) -> Result<TextPathMethod, ParseError<'i>> {
+        Ok(parse_identifiers!(
+            parser,
+            "align" => TextPathMethod::Align,
+            "stretch" => TextPathMethod::Stretch,
+        )?)
+    }
+}
+
+/// `side` attribute of the `<textPath>` element.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum TextPathSide {
+    Left,
+    Right,
+}
+
+enum_default!(TextPathSide, TextPathSide::Left);
+
+impl Parse for TextPathSide {
+    fn parse<'i>(parser: &mut Parser<'i, '_>) -> Result<TextPathSide, ParseError<'i>> {
+        Ok(parse_identifiers!(
+            parser,
+            "left" => TextPathSide::Left,
+            "right" => TextPathSide::Right,
+        )?)
+    }
+}
+
+/// The `<textPath>` element, which lays out text along a path.
+///
+/// The path comes from the SVG2 `path` attribute, or from a `<path>` element
+/// referenced with `href`.
+///
+/// The `spacing` attribute is not supported; glyphs always get exact spacing, which is
+/// also what the spec allows for `spacing="auto"`.
+#[derive(Default)]
+pub struct TextPath {
+    link: Option<NodeId>,
+    path: Option<Rc<SvgPath>>,
+    start_offset: Length<Horizontal>,
+    method: TextPathMethod,
+    side: TextPathSide,
+}
+
+impl TextPath {
+    fn to_chunks(
+        &self,
+        node: &Node,
+        acquired_nodes: &mut AcquiredNodes<'_>,
+        cascaded: &CascadedValues<'_>,
+        draw_ctx: &mut DrawingCtx,
+        chunks: &mut Vec<Chunk>,
+        positions: &mut CharacterPositions,
+        depth: usize,
+    ) {
+        let values = cascaded.get();
+        if !values.is_displayed() {
+            return;
+        }
+
+        let path = match self.flattened_path(node, acquired_nodes) {
+            Some(path) => path,
+            None => return,
+        };
+
+        if self.method == TextPathMethod::Stretch {
+            // FIXME: we don't warp glyph outlines along the path yet.
+            rsvg_log!(
+                "element {} uses method=\"stretch\"; using \"align\" instead",
+                node
+            );
+        }
+
+        let view_params = draw_ctx.get_view_params();
+        let params = NormalizeParams::new(values, &view_params);
+
+        let mut start_offset = match self.start_offset.unit {
+            LengthUnit::Percent => self.start_offset.length * path.length(),
+            _ => self.start_offset.to_user(&params),
+        };
+
+        // On closed paths, the start offset wraps around the path.
+        if path.is_closed() && path.length() > 0.0 {
+            start_offset = start_offset.rem_euclid(path.length());
+        }
+
+        chunks.push(Chunk::new_on_path(values, start_offset, path));
+
+        positions.push(PositionLists {
+            decorations: element_decorations(values, &params),
+            bidi: BidiEmbedding::new(values),
+            ..Default::default()
+        });
+
+        children_to_chunks(
+            chunks,
+            node,
+            acquired_nodes,
+            cascaded,
+            draw_ctx,
+            positions,
+            depth,
+        );
+
+        positions.pop();
+    }
+
+    fn flattened_path(
+        &self,
+        node: &Node,
+        acquired_nodes: &mut AcquiredNodes<'_>,
+    ) -> Option<FlattenedPath> {
+        let path = if let Some(ref path) = self.path {
+            path.flatten()
+        } else {
+            let link = self.link.as_ref()?;
+
+            let acquired = match acquired_nodes.acquire(link) {
+                Ok(acquired) => acquired,
+                Err(_) => {
+                    rsvg_log!(
+                        "element {} references a nonexistent path \"{}\"",
+                        node,
+                        link
+                    );
+                    return None;
+                }
+            };
+
+            let path_node = acquired.get();
+            if !is_element_of_type!(path_node, Path) {
+                rsvg_log!(
+                    "element {} references \"{}\" which is not a path",
+                    node,
+                    link
+                );
+                return None;
+            }
+
+            // The path's transform applies to the text laid out along it.
+            let transform = path_node.borrow_element().get_computed_values().transform();
+
+            let path = borrow_element_as!(path_node, Path).get_path();
+            path.flatten().transformed(&transform)
+        };
+
+        match self.side {
+            TextPathSide::Left => Some(path),
+            TextPathSide::Right => Some(path.reversed()),
+        }
+    }
+}
+
+impl SetAttributes for TextPath {
+    fn set_attributes(&mut self, attrs: &Attributes) -> ElementResult {
+        for (attr, value) in attrs.iter() {
+            match attr.expanded() {
+                ref a if is_href(a) => set_href(
+                    a,
+                    &mut self.link,
+                    NodeId::parse(value).attribute(attr.clone())?,
+                ),
+
+                expanded_name!("", "path") => {
+                    let mut builder = PathBuilder::default();
+                    if let Err(e) = builder.parse(value) {
+                        // Like for the <path> element, a partial path is OK per the spec
+                        rsvg_log!("could not parse path: {}", e);
+                    }
+                    self.path = Some(Rc::new(builder.into_path()));
+                }
+
+                expanded_name!("", "startOffset") => self.start_offset = attr.parse(value)?,
+                expanded_name!("", "method") => self.method = attr.parse(value)?,
+
+                // markup5ever doesn't have an atom for "side", which is new in SVG2
+                ref a if *a.ns == ns!() && &**a.local == "side" => self.side = attr.parse(value)?,
+
+                _ => (),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Draw for TextPath {}
+
+/// Returns the glyph clusters of a layout in logical order.
+fn layout_clusters(layout: &pango::Layout) -> Vec<Cluster> {
+    let text_len = layout.text().map_or(0, |t| t.len());
+
+    let mut starts = Vec::new();
+
+    if let Some(mut iter) = layout.iter() {
+        loop {
+            let index = iter.index() as usize;
+
+            if index < text_len {
+                let (_, logical) = iter.cluster_extents();
+                starts.push((
+                    index,
+                    f64::from(logical.x) / f64::from(pango::SCALE),
+                    f64::from(logical.width) / f64::from(pango::SCALE),
+                ));
+            }
+
+            if !iter.next_cluster() {
+                break;
+            }
+        }
+    }
+
+    // The iterator goes in visual order; sort the clusters to find where each one ends.
+    starts.sort_by_key(|&(index, _, _)| index);
+    starts.dedup_by_key(|&mut (index, _, _)| index);
+
+    let ends: Vec<usize> = starts
+        .iter()
+        .skip(1)
+        .map(|&(index, _, _)| index)
+        .chain(std::iter::once(text_len))
+        .collect();
+
+    starts
+        .into_iter()
+        .zip(ends)
+        .map(|((start, x, width), end)| Cluster {
+            range: start..end,
+            x,
+            width,
+        })
+        .collect()
+}
+
+pub(crate) fn to_pango_units(v: f64) -> i32 {
     (v * f64::from(pango::SCALE) + 0.5) as i32
 }
 
@@ -809,6 +2782,19 @@ impl From<WritingMode> for pango::Gravity {
     }
 }
 
+/// Computes `line-height: normal` as the sum of the font's ascent and descent.
+///
+/// Falls back to the font size if Pango cannot give us the font's metrics.
+fn normal_line_height(layout: &pango::Layout, font_size: f64) -> f64 {
+    match layout
+        .context()
+        .and_then(|c| c.metrics(layout.font_description().as_ref(), None))
+    {
+        Some(metrics) => f64::from(metrics.ascent() + metrics.descent()) / f64::from(pango::SCALE),
+        None => font_size,
+    }
+}
+
 fn create_pango_layout(draw_ctx: &DrawingCtx, props: &FontProperties, text: &str) -> pango::Layout {
     let pango_context = pango::Context::from(draw_ctx);
 
@@ -819,21 +2805,30 @@ fn create_pango_layout(draw_ctx: &DrawingCtx, props: &FontProperties, text: &str
     pango_context.set_base_gravity(pango::Gravity::from(props.writing_mode));
 
     match (props.unicode_bidi, props.direction) {
-        (UnicodeBidi::Override, _) | (UnicodeBidi::Embed, _) => {
-            pango_context.set_base_dir(pango::Direction::from(props.direction));
+        (UnicodeBidi::Plaintext, direction) => {
+            // The direction comes from the first strong character, per rules P2 and P3.
+            let dir = match pango::find_base_dir(text) {
+                pango::Direction::Neutral => pango::Direction::from(direction),
+                dir => dir,
+            };
+            pango_context.set_base_dir(dir);
         }
 
-        (_, direction) if direction != Direction::Ltr => {
+        (UnicodeBidi::Normal, direction) if direction != Direction::Ltr => {
             pango_context.set_base_dir(pango::Direction::from(direction));
         }
 
-        (_, _) => {
+        (UnicodeBidi::Normal, _) => {
             pango_context.set_base_dir(pango::Direction::from(props.writing_mode));
         }
+
+        (_, direction) => {
+            pango_context.set_base_dir(pango::Direction::from(direction));
+        }
     }
 
     let mut font_desc = pango_context.font_description().unwrap();
-    font_desc.set_family(props.font_family.as_str());
+    font_desc.set_family(&draw_ctx.pango_font_family(props.font_family.as_str()));
     font_desc.set_style(pango::Style::from(props.font_style));
 
     // PANGO_VARIANT_SMALL_CAPS does nothing: https://gitlab.gnome.org/GNOME/pango/-/issues/566
@@ -845,53 +2840,298 @@ fn create_pango_layout(draw_ctx: &DrawingCtx, props: &FontProperties, text: &str
 
     font_desc.set_size(to_pango_units(props.font_size));
 
+    let variations = font_variations(props);
+    if !variations.is_empty() {
+        font_desc.set_variations(&variations);
+    }
+
     let layout = pango::Layout::new(&pango_context);
     layout.set_auto_dir(false);
     layout.set_font_description(Some(&font_desc));
 
-    // FIXME: For now we ignore the `line-height` property, even though we parse it.
-    // We would need to do something like this:
-    //
-    // layout.set_line_spacing(0.0); // "actually use the spacing I'll give you"
-    // layout.set_spacing(to_pango_units(???));
-    //
-    // However, Layout::set_spacing() takes an inter-line spacing (from the baseline of
-    // one line to the top of the next line), not the line height (from baseline to
-    // baseline).
-    //
-    // Maybe we need to implement layout of individual lines by hand.
+    // Note that we don't use Pango for laying out multiple lines of text: the
+    // `line-height` property is a baseline-to-baseline distance, but
+    // Layout::set_spacing() takes the spacing between the bottom of a line and
+    // the top of the next one.  Instead, wrap_chunks() positions each line by hand.
 
     let attr_list = pango::AttrList::new();
+    add_pango_attributes(&attr_list, draw_ctx, props, text, 0, text.len());
+
+    layout.set_attributes(Some(&attr_list));
+    layout.set_alignment(pango::Alignment::from(props.direction));
+    layout.set_text(text);
+
+    layout
+}
+
+/// Adds attributes for the font properties to a range of bytes in a Pango layout's text.
+///
+/// This lets a single layout have text with the styles of different spans.  If the
+/// `font-family` names an SVG font, its glyphs get added as shape attributes.
+fn add_pango_attributes(
+    attr_list: &pango::AttrList,
+    draw_ctx: &DrawingCtx,
+    props: &FontProperties,
+    text: &str,
+    start: usize,
+    end: usize,
+) {
+    let mut attributes = vec![
+        pango::Attribute::new_family(&draw_ctx.pango_font_family(props.font_family.as_str())),
+        pango::Attribute::new_style(pango::Style::from(props.font_style)),
+        pango::Attribute::new_weight(pango::Weight::from(props.font_weight)),
+        pango::Attribute::new_stretch(pango::Stretch::from(props.font_stretch)),
+        pango::Attribute::new_size(to_pango_units(props.font_size)),
+        pango::Attribute::new_letter_spacing(to_pango_units(props.letter_spacing)),
+    ];
+
+    // In vertical text, Pango's gravity decides which glyphs are upright.  With its
+    // default hint, that is just the glyphs of scripts that are usually set upright,
+    // like the `mixed` orientation requires.
+    if props.writing_mode.is_vertical() {
+        match props.text_orientation {
+            TextOrientation::Mixed => (),
+            TextOrientation::Upright => attributes.push(pango::Attribute::new_gravity_hint(
+                pango::GravityHint::Strong,
+            )),
+
+            // Lay out all the glyphs as in horizontal text, which turns them sideways
+            // when the layout gets rotated.
+            TextOrientation::Sideways => {
+                attributes.push(pango::Attribute::new_gravity(pango::Gravity::South))
+            }
+        }
+    }
+
+    let features = font_features(props);
+    if !features.is_empty() {
+        attributes.push(pango::Attribute::new_font_features(&features));
+    }
+
+    let variations = font_variations(props);
+    if !variations.is_empty() {
+        let mut desc = pango::FontDescription::new();
+        desc.set_variations(&variations);
+        attributes.push(font_desc_attribute(&desc));
+    }
+
+    for mut attr in attributes {
+        attr.set_start_index(start as u32);
+        attr.set_end_index(end as u32);
+        attr_list.insert(attr);
+    }
+
+    // Pango has no word spacing; widen the word-separator characters with extra letter
+    // spacing instead.  Their attributes start after the one for the whole range, so
+    // they take precedence.
+    if props.word_spacing != 0.0 {
+        let spacing = to_pango_units(props.letter_spacing + props.word_spacing);
+
+        for (i, c) in text[start..end].char_indices() {
+            if is_word_separator(c) {
+                let mut attr = pango::Attribute::new_letter_spacing(spacing);
+                attr.set_start_index((start + i) as u32);
+                attr.set_end_index((start + i + c.len_utf8()) as u32);
+                attr_list.insert(attr);
+            }
+        }
+    }
+
+    if let Some(font) = draw_ctx.svg_font(props.font_family.as_str()) {
+        font.add_glyph_shapes(attr_list, &text[start..end], start, props.font_size);
+    }
+}
+
+/// Whether a character separates words, for the `word-spacing` property.
+///
+/// https://www.w3.org/TR/css-text-3/#word-separator
+fn is_word_separator(c: char) -> bool {
+    matches!(
+        c,
+        '\u{0020}'
+            | '\u{00a0}'
+            | '\u{1361}'
+            | '\u{10100}'
+            | '\u{10101}'
+            | '\u{1039f}'
+            | '\u{1091f}'
+    )
+}
+
+/// Creates a layout for a range of the text in another layout, with the same attributes.
+fn cluster_layout(
+    layout: &pango::Layout,
+    text: &str,
+    range: Range<usize>,
+) -> Option<pango::Layout> {
+    let cluster = layout.copy()?;
+
+    if let Some(attrs) = layout.attributes().and_then(|a| a.copy()) {
+        let len = text.len() as i32;
+        attrs.update(range.end as i32, len - range.end as i32, 0);
+        attrs.update(0, range.start as i32, 0);
+        cluster.set_attributes(Some(&attrs));
+    }
+
+    cluster.set_text(&text[range]);
+
+    Some(cluster)
+}
+
+/// Creates an attribute that sets the fields that are present in a font description.
+///
+/// The pango crate does not wrap `pango_attr_font_desc_new()`.
+fn font_desc_attribute(desc: &pango::FontDescription) -> pango::Attribute {
+    use glib::translate::{from_glib_full, ToGlibPtr};
+
+    unsafe { from_glib_full(pango::ffi::pango_attr_font_desc_new(desc.to_glib_none().0)) }
+}
+
+/// Computes the font variations for the `font-variation-settings` and
+/// `font-optical-sizing` properties.
+///
+/// The result is in the syntax of `pango_font_description_set_variations()`, like
+/// `"wght=450,opsz=12"`, or an empty string if the font's defaults should be used.
+fn font_variations(props: &FontProperties) -> String {
+    let settings = &props.font_variation_settings.0;
+
+    // With font-optical-sizing: auto, the "opsz" axis follows the font size, unless
+    // font-variation-settings sets it explicitly.
+    let optical_size = match props.font_optical_sizing {
+        FontOpticalSizing::Auto if !settings.iter().any(|(tag, _)| tag == "opsz") => {
+            Some(("opsz", props.font_size))
+        }
+        _ => None,
+    };
+
+    optical_size
+        .into_iter()
+        .chain(settings.iter().map(|(tag, value)| (tag.as_str(), *value)))
+        .map(|(tag, value)| format!("{}={}", tag, value))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Computes the OpenType features for the `font-variant-*`, `font-kerning` and
+/// `font-feature-settings` properties.
+///
+/// The result is in the syntax of `pango_attr_font_features_new()`, or an empty
+/// string if the font's defaults should be used.  See the feature tags in
+/// https://docs.microsoft.com/en-us/typography/opentype/spec/featurelist
+fn font_features(props: &FontProperties) -> String {
+    let mut features: Vec<(&str, u32)> = Vec::new();
+
+    let ligatures = &props.font_variant_ligatures;
 
-    attr_list.insert(pango::Attribute::new_letter_spacing(to_pango_units(
-        props.letter_spacing,
-    )));
+    if let Some(on) = ligatures.common {
+        features.push(("liga", on.into()));
+        features.push(("clig", on.into()));
+    }
+
+    if let Some(on) = ligatures.discretionary {
+        features.push(("dlig", on.into()));
+    }
 
-    if props.text_decoration.underline {
-        attr_list.insert(pango::Attribute::new_underline(pango::Underline::Single));
+    if let Some(on) = ligatures.historical {
+        features.push(("hlig", on.into()));
     }
 
-    if props.text_decoration.strike {
-        attr_list.insert(pango::Attribute::new_strikethrough(true));
+    if let Some(on) = ligatures.contextual {
+        features.push(("calt", on.into()));
     }
 
     // FIXME: Using the "smcp" OpenType feature only works for fonts that support it.  We
     // should query if the font supports small caps, and synthesize them if it doesn't.
-    if props.font_variant == FontVariant::SmallCaps {
-        // smcp - small capitals - https://docs.microsoft.com/en-ca/typography/opentype/spec/features_pt#smcp
-        attr_list.insert(pango::Attribute::new_font_features("'smcp' 1"));
+    let caps: &[&str] = match props.font_variant_caps {
+        FontVariantCaps::Normal if props.font_variant == FontVariant::SmallCaps => &["smcp"],
+        FontVariantCaps::Normal => &[],
+        FontVariantCaps::SmallCaps => &["smcp"],
+        FontVariantCaps::AllSmallCaps => &["c2sc", "smcp"],
+        FontVariantCaps::PetiteCaps => &["pcap"],
+        FontVariantCaps::AllPetiteCaps => &["c2pc", "pcap"],
+        FontVariantCaps::Unicase => &["unic"],
+        FontVariantCaps::TitlingCaps => &["titl"],
+    };
+    features.extend(caps.iter().map(|tag| (*tag, 1)));
+
+    let numeric = &props.font_variant_numeric;
+
+    match numeric.figure {
+        Some(NumericFigure::Lining) => features.push(("lnum", 1)),
+        Some(NumericFigure::Oldstyle) => features.push(("onum", 1)),
+        None => (),
     }
 
-    layout.set_attributes(Some(&attr_list));
-    layout.set_alignment(pango::Alignment::from(props.direction));
-    layout.set_text(text);
+    match numeric.spacing {
+        Some(NumericSpacing::Proportional) => features.push(("pnum", 1)),
+        Some(NumericSpacing::Tabular) => features.push(("tnum", 1)),
+        None => (),
+    }
 
-    layout
+    match numeric.fraction {
+        Some(NumericFraction::Diagonal) => features.push(("frac", 1)),
+        Some(NumericFraction::Stacked) => features.push(("afrc", 1)),
+        None => (),
+    }
+
+    if numeric.ordinal {
+        features.push(("ordn", 1));
+    }
+
+    if numeric.slashed_zero {
+        features.push(("zero", 1));
+    }
+
+    let east_asian = &props.font_variant_east_asian;
+
+    if let Some(variant) = east_asian.variant {
+        features.push((
+            match variant {
+                EastAsianVariant::Jis78 => "jp78",
+                EastAsianVariant::Jis83 => "jp83",
+                EastAsianVariant::Jis90 => "jp90",
+                EastAsianVariant::Jis04 => "jp04",
+                EastAsianVariant::Simplified => "smpl",
+                EastAsianVariant::Traditional => "trad",
+            },
+            1,
+        ));
+    }
+
+    match east_asian.width {
+        Some(EastAsianWidth::Full) => features.push(("fwid", 1)),
+        Some(EastAsianWidth::Proportional) => features.push(("pwid", 1)),
+        None => (),
+    }
+
+    if east_asian.ruby {
+        features.push(("ruby", 1));
+    }
+
+    match props.font_kerning {
+        FontKerning::Auto => (),
+        FontKerning::Normal => features.push(("kern", 1)),
+        FontKerning::None => features.push(("kern", 0)),
+    }
+
+    // font-feature-settings goes last, so that it overrides the features above.
+    features
+        .into_iter()
+        .chain(
+            props
+                .font_feature_settings
+                .0
+                .iter()
+                .map(|(tag, value)| (tag.as_str(), *value)),
+        )
+        .map(|(tag, value)| format!("'{}' {}", tag, value))
+        .collect::<Vec<_>>()
+        .join(", ")
 }
 
 #[cfg(test)]
 mod tests {
-    use super::Chars;
+    use super::*;
 
     #[test]
     fn chars_default() {
@@ -907,4 +3147,87 @@ mod tests {
         assert_eq!(c.get_string(), example);
         assert!(c.space_normalized.borrow().is_none());
     }
+
+    #[test]
+    fn parses_value_lists() {
+        assert_eq!(
+            ValueList::<Length<Horizontal>>::parse_str("1 2px, 50%").unwrap(),
+            ValueList(vec![
+                Length::new(1.0, LengthUnit::Px),
+                Length::new(2.0, LengthUnit::Px),
+                Length::new(0.5, LengthUnit::Percent),
+            ])
+        );
+
+        assert_eq!(
+            ValueList::<f64>::parse_str("").unwrap(),
+            ValueList(Vec::new())
+        );
+
+        assert!(ValueList::<f64>::parse_str("1 foo").is_err());
+    }
+
+    #[test]
+    fn parses_length_adjust() {
+        assert_eq!(
+            LengthAdjust::parse_str("spacing").unwrap(),
+            LengthAdjust::Spacing
+        );
+        assert_eq!(
+            LengthAdjust::parse_str("spacingAndGlyphs").unwrap(),
+            LengthAdjust::SpacingAndGlyphs
+        );
+        assert!(LengthAdjust::parse_str("glyphs").is_err());
+    }
+
+    #[test]
+    fn assigns_positions_to_nested_characters() {
+        let mut positions = CharacterPositions::default();
+
+        positions.push(PositionLists {
+            x: vec![1.0, 2.0, 3.0, 4.0],
+            rotate: vec![10.0, 20.0],
+            ..Default::default()
+        });
+
+        let first = positions.next_character();
+        assert_eq!(first.x, Some(1.0));
+        assert_eq!(first.rotate, Some(10.0));
+
+        positions.push(PositionLists {
+            x: vec![5.0],
+            dy: vec![6.0, 7.0],
+            ..Default::default()
+        });
+
+        let second = positions.next_character();
+        assert_eq!(second.x, Some(5.0));
+        assert_eq!(second.dy, Some(6.0));
+        assert_eq!(second.rotate, Some(20.0));
+
+        let third = positions.next_character();
+        assert_eq!(third.x, Some(3.0));
+        assert_eq!(third.dy, Some(7.0));
+        assert_eq!(third.rotate, Some(20.0));
+
+        positions.pop();
+
+        let fourth = positions.next_character();
+        assert_eq!(fourth.x, Some(4.0));
+        assert_eq!(fourth.dy, None);
+
+        let fifth = positions.next_character();
+        assert_eq!(fifth.x, None);
+        assert_eq!(fifth.rotate, Some(20.0));
+    }
+
+    #[test]
+    fn reorders_runs_by_embedding_level() {
+        assert_eq!(visual_order(&[]), Vec::<usize>::new());
+        assert_eq!(visual_order(&[0, 0, 0]), vec![0, 1, 2]);
+        assert_eq!(visual_order(&[1, 1, 1]), vec![2, 1, 0]);
+        assert_eq!(visual_order(&[0, 1, 1, 0]), vec![0, 2, 1, 3]);
+        assert_eq!(visual_order(&[1, 2, 2, 1]), vec![3, 1, 2, 0]);
+        assert_eq!(visual_order(&[0, 2, 1, 2]), vec![0, 3, 2, 1]);
+    }
 }
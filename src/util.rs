@@ -1,6 +1,7 @@
 //! Miscellaneous utilities.
 
 use std::borrow::Cow;
+use std::cmp::Ordering;
 use std::ffi::CStr;
 use std::str;
 
@@ -42,6 +43,20 @@ pub fn clamp<T: PartialOrd>(val: T, low: T, high: T) -> T {
     }
 }
 
+/// Compares two floats with a total order, like `f64::total_cmp()`.
+///
+/// Use this to sort values that come from documents, which may be NaN.  This is
+/// the same as the standard library's version, which needs Rust 1.62.
+pub fn total_cmp(a: f64, b: f64) -> Ordering {
+    let mut a = a.to_bits() as i64;
+    let mut b = b.to_bits() as i64;
+
+    a ^= (((a >> 63) as u64) >> 1) as i64;
+    b ^= (((b >> 63) as u64) >> 1) as i64;
+
+    a.cmp(&b)
+}
+
 #[macro_export]
 macro_rules! enum_default {
     ($name:ident, $default:expr) => {
@@ -100,6 +100,28 @@ impl Attributes {
         self.0.len()
     }
 
+    /// Gets the value of an attribute.
+    pub fn get(&self, name: &QualName) -> Option<&str> {
+        self.0
+            .iter()
+            .find(|(n, _)| n.expanded() == name.expanded())
+            .map(|(_, v)| v.as_ref())
+    }
+
+    /// Sets the value of an attribute, replacing the existing one if there is one.
+    pub fn set(&mut self, name: &QualName, value: &str) {
+        let value = AttributeValue::from(value);
+
+        match self
+            .0
+            .iter_mut()
+            .find(|(n, _)| n.expanded() == name.expanded())
+        {
+            Some((_, v)) => *v = value,
+            None => self.0.push((name.clone(), value)),
+        }
+    }
+
     /// Creates an iterator that yields `(QualName, &'a str)` tuples.
     pub fn iter(&self) -> AttributesIter<'_> {
         AttributesIter(self.0.iter())
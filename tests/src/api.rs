@@ -1,9 +1,10 @@
 use cairo;
 use librsvg::surface_utils::shared_surface::{SharedImageSurface, SurfaceType};
-use librsvg::{CairoRenderer, RenderingError};
+use librsvg::{CairoRenderer, Loader, RenderingError};
 
+use crate::compare_surfaces::{compare_surfaces, BufferDiff};
 use crate::reference_utils::{Compare, Evaluate, Reference};
-use crate::utils::load_svg;
+use crate::utils::{load_svg, render_document, setup_font_map, SurfaceSize};
 
 #[test]
 fn has_element_with_id_works() {
@@ -219,3 +220,521 @@ fn set_stylesheet() {
         .compare(&output_surf)
         .evaluate(&output_surf, "set_stylesheet");
 }
+
+#[test]
+fn render_at_time() {
+    let svg = load_svg(
+        br##"<?xml version="1.0" encoding="UTF-8"?>
+<svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
+  <rect id="moving" x="0" y="10" width="20" height="20">
+    <animate attributeName="x" from="0" to="80" dur="4s" fill="freeze"/>
+  </rect>
+  <rect id="stroked" x="10" y="50" width="20" height="20" stroke="black" stroke-width="0">
+    <animate attributeName="stroke-width" from="0" to="10" dur="4s"/>
+  </rect>
+</svg>
+"##,
+    )
+    .unwrap();
+
+    let viewport = cairo::Rectangle {
+        x: 0.0,
+        y: 0.0,
+        width: 100.0,
+        height: 100.0,
+    };
+
+    let geometry = |renderer: &CairoRenderer, id| {
+        let (ink_r, _) = renderer.geometry_for_layer(Some(id), &viewport).unwrap();
+        (ink_r.x, ink_r.width)
+    };
+
+    let static_renderer = CairoRenderer::new(&svg);
+    assert_eq!(geometry(&static_renderer, "#moving"), (0.0, 20.0));
+    assert_eq!(geometry(&static_renderer, "#stroked"), (10.0, 20.0));
+
+    let renderer = CairoRenderer::new(&svg).at_time(1.0);
+    assert_eq!(geometry(&renderer, "#moving"), (20.0, 20.0));
+
+    let renderer = CairoRenderer::new(&svg).at_time(2.0);
+    assert_eq!(geometry(&renderer, "#stroked"), (7.5, 25.0));
+
+    // The first animation freezes at its end, the second one is removed
+    let renderer = CairoRenderer::new(&svg).at_time(10.0);
+    assert_eq!(geometry(&renderer, "#moving"), (80.0, 20.0));
+    assert_eq!(geometry(&renderer, "#stroked"), (10.0, 20.0));
+
+    // Renderers without a time get the document as written
+    assert_eq!(geometry(&static_renderer, "#moving"), (0.0, 20.0));
+
+    // Renderers with different times can be used in turns
+    let early = CairoRenderer::new(&svg).at_time(1.0);
+    let late = CairoRenderer::new(&svg).at_time(10.0);
+    assert_eq!(geometry(&early, "#moving"), (20.0, 20.0));
+    assert_eq!(geometry(&late, "#moving"), (80.0, 20.0));
+    assert_eq!(geometry(&early, "#moving"), (20.0, 20.0));
+
+    // Times that are not finite are ignored
+    let renderer = CairoRenderer::new(&svg).at_time(f64::NAN);
+    assert_eq!(geometry(&renderer, "#moving"), (0.0, 20.0));
+
+    let renderer = CairoRenderer::new(&svg).at_time(1.0).at_time(f64::INFINITY);
+    assert_eq!(geometry(&renderer, "#moving"), (20.0, 20.0));
+}
+
+#[test]
+fn render_css_animations_at_time() {
+    let svg = load_svg(
+        br##"<?xml version="1.0" encoding="UTF-8"?>
+<svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
+  <style>
+    @keyframes slide { to { transform: translate(60px, 0px); } }
+    @keyframes grow { from { stroke-width: 0; } to { stroke-width: 10; } }
+    #moving { animation: slide 4s linear forwards; }
+    #stroked { animation: grow 4s linear; }
+  </style>
+  <rect id="moving" x="0" y="10" width="20" height="20"/>
+  <rect id="stroked" x="10" y="50" width="20" height="20" stroke="black" stroke-width="0"/>
+</svg>
+"##,
+    )
+    .unwrap();
+
+    let viewport = cairo::Rectangle {
+        x: 0.0,
+        y: 0.0,
+        width: 100.0,
+        height: 100.0,
+    };
+
+    let geometry = |renderer: &CairoRenderer, id| {
+        let (ink_r, _) = renderer.geometry_for_layer(Some(id), &viewport).unwrap();
+        (ink_r.x, ink_r.width)
+    };
+
+    let static_renderer = CairoRenderer::new(&svg);
+    assert_eq!(geometry(&static_renderer, "#moving"), (0.0, 20.0));
+    assert_eq!(geometry(&static_renderer, "#stroked"), (10.0, 20.0));
+
+    let renderer = CairoRenderer::new(&svg).at_time(1.0);
+    assert_eq!(geometry(&renderer, "#moving"), (15.0, 20.0));
+
+    let renderer = CairoRenderer::new(&svg).at_time(2.0);
+    assert_eq!(geometry(&renderer, "#stroked"), (7.5, 25.0));
+
+    // The first animation fills forwards, the second one is removed after it ends
+    let renderer = CairoRenderer::new(&svg).at_time(10.0);
+    assert_eq!(geometry(&renderer, "#moving"), (60.0, 20.0));
+    assert_eq!(geometry(&renderer, "#stroked"), (10.0, 20.0));
+}
+
+#[test]
+fn render_with_view() {
+    let svg = load_svg(
+        br##"<?xml version="1.0" encoding="UTF-8"?>
+<svg xmlns="http://www.w3.org/2000/svg" width="48" height="24" viewBox="0 0 48 24">
+  <view id="icon-right" viewBox="24 0 24 24"/>
+  <rect id="left" x="0" y="0" width="24" height="24"/>
+  <rect id="right" x="24" y="0" width="24" height="24"/>
+</svg>
+"##,
+    )
+    .unwrap();
+
+    let viewport = cairo::Rectangle {
+        x: 0.0,
+        y: 0.0,
+        width: 48.0,
+        height: 24.0,
+    };
+
+    let geometry = |renderer: &CairoRenderer| {
+        let (ink_r, _) = renderer
+            .geometry_for_layer(Some("#right"), &viewport)
+            .unwrap();
+        (ink_r.x, ink_r.width)
+    };
+
+    assert_eq!(geometry(&CairoRenderer::new(&svg)), (24.0, 24.0));
+
+    // The view's square viewBox gets centered in the wide viewport
+    let renderer = CairoRenderer::new(&svg).with_view("icon-right");
+    assert_eq!(geometry(&renderer), (12.0, 24.0));
+    assert_eq!(
+        renderer.intrinsic_dimensions().vbox,
+        Some(cairo::Rectangle {
+            x: 24.0,
+            y: 0.0,
+            width: 24.0,
+            height: 24.0,
+        })
+    );
+
+    let renderer = CairoRenderer::new(&svg)
+        .with_view("#svgView(viewBox(24,0,24,24);preserveAspectRatio(xMinYMid))");
+    assert_eq!(geometry(&renderer), (0.0, 24.0));
+
+    // Renderers with and without a view can be used in turns
+    let with_view = CairoRenderer::new(&svg).with_view("icon-right");
+    let without_view = CairoRenderer::new(&svg);
+    assert_eq!(geometry(&with_view), (12.0, 24.0));
+    assert_eq!(geometry(&without_view), (24.0, 24.0));
+    assert_eq!(geometry(&with_view), (12.0, 24.0));
+
+    // Fragments that don't select a view are ignored
+    let renderer = CairoRenderer::new(&svg).with_view("left");
+    assert_eq!(geometry(&renderer), (24.0, 24.0));
+    assert_eq!(
+        renderer.intrinsic_dimensions().vbox,
+        Some(cairo::Rectangle {
+            x: 0.0,
+            y: 0.0,
+            width: 48.0,
+            height: 24.0,
+        })
+    );
+}
+
+#[test]
+fn text_outlines() {
+    let svg = load_svg(
+        br##"<?xml version="1.0" encoding="UTF-8"?>
+<svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
+  <defs>
+    <text id="unused" x="10" y="50">Hidden</text>
+  </defs>
+  <text id="hello" x="10" y="50">Hello</text>
+  <text x="10" y="80">World</text>
+  <text id="empty" x="10" y="90"> </text>
+</svg>
+"##,
+    )
+    .unwrap();
+
+    let renderer = CairoRenderer::new(&svg);
+
+    let viewport = cairo::Rectangle {
+        x: 0.0,
+        y: 0.0,
+        width: 100.0,
+        height: 100.0,
+    };
+
+    let outlines = renderer.text_outlines(&viewport).unwrap();
+
+    assert_eq!(outlines.len(), 2);
+
+    assert_eq!(outlines[0].id.as_deref(), Some("hello"));
+    assert_eq!(outlines[1].id, None);
+
+    for outline in &outlines {
+        assert!(outline.path_data.starts_with("M "));
+        assert!(outline.path_data.ends_with('Z'));
+    }
+}
+
+#[test]
+fn text_runs() {
+    let svg = load_svg(
+        br##"<?xml version="1.0" encoding="UTF-8"?>
+<svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
+  <defs>
+    <text id="unused" x="10" y="50">Hidden</text>
+  </defs>
+  <g transform="translate(10, 0)">
+    <text id="hello" x="10" y="50" xml:lang="en">Hello <tspan id="bold" font-weight="bold">world</tspan></text>
+  </g>
+  <text x="10" y="80" visibility="hidden">Invisible</text>
+</svg>
+"##,
+    )
+    .unwrap();
+
+    let renderer = CairoRenderer::new(&svg);
+
+    let viewport = cairo::Rectangle {
+        x: 0.0,
+        y: 0.0,
+        width: 100.0,
+        height: 100.0,
+    };
+
+    let runs = renderer.text_runs(&viewport).unwrap();
+
+    assert_eq!(runs.len(), 2);
+
+    assert_eq!(runs[0].text, "Hello ");
+    assert_eq!(runs[0].id.as_deref(), Some("hello"));
+    assert_eq!(runs[0].lang.as_deref(), Some("en"));
+    assert_eq!(runs[0].font_weight, 400);
+    assert_eq!(runs[0].bounds.x0, 20.0);
+    assert_eq!(runs[0].characters.len(), 6);
+
+    assert_eq!(runs[1].text, "world");
+    assert_eq!(runs[1].id.as_deref(), Some("bold"));
+    assert_eq!(runs[1].lang.as_deref(), Some("en"));
+    assert_eq!(runs[1].font_weight, 700);
+    assert!(runs[1].bounds.x0 > runs[0].bounds.x0);
+
+    for run in &runs {
+        for &(_, rect) in &run.characters {
+            assert!(run.bounds.x0 <= rect.x0 && rect.x1 <= run.bounds.x1);
+        }
+    }
+}
+
+#[test]
+fn text_length_adjusts_to_a_fixed_width() {
+    let svg = load_svg(
+        br##"<?xml version="1.0" encoding="UTF-8"?>
+<svg xmlns="http://www.w3.org/2000/svg" width="200" height="200">
+  <text id="stretched" x="10" y="30" textLength="150">Hello</text>
+  <text id="compressed" x="10" y="60" textLength="20">Hello</text>
+  <text id="scaled" x="10" y="90" textLength="150" lengthAdjust="spacingAndGlyphs">Hello</text>
+  <text x="10" y="120" textLength="180">ab<tspan textLength="100">cdef</tspan>gh</text>
+  <text x="10" y="150" textLength="180" lengthAdjust="spacingAndGlyphs">ab<tspan textLength="100">cdef</tspan>gh</text>
+</svg>
+"##,
+    )
+    .unwrap();
+
+    let renderer = CairoRenderer::new(&svg);
+
+    let viewport = cairo::Rectangle {
+        x: 0.0,
+        y: 0.0,
+        width: 200.0,
+        height: 200.0,
+    };
+
+    let runs = renderer.text_runs(&viewport).unwrap();
+    let texts: Vec<&str> = runs.iter().map(|r| r.text.as_str()).collect();
+    assert_eq!(
+        texts,
+        ["Hello", "Hello", "Hello", "ab", "cdef", "gh", "ab", "cdef", "gh"]
+    );
+
+    let starts_at = |i: usize, x: f64| approx_eq!(f64, runs[i].bounds.x0, x, epsilon = 0.001);
+    let ends_at = |i: usize, x: f64| approx_eq!(f64, runs[i].bounds.x1, x, epsilon = 0.001);
+
+    assert!(starts_at(0, 10.0) && ends_at(0, 160.0));
+    assert!(starts_at(1, 10.0) && ends_at(1, 30.0));
+    assert!(starts_at(2, 10.0) && ends_at(2, 160.0));
+
+    // The nested textLength is kept when the outer one gets adjusted.
+    for &first in &[3, 6] {
+        assert!(starts_at(first, 10.0));
+        assert!(approx_eq!(
+            f64,
+            runs[first + 1].bounds.width(),
+            100.0,
+            epsilon = 0.001
+        ));
+        assert!(ends_at(first + 2, 190.0));
+    }
+
+    // The ink extents follow the adjusted glyphs.
+    for &(id, start, end) in &[
+        ("#stretched", 10.0, 160.0),
+        ("#compressed", 10.0, 30.0),
+        ("#scaled", 10.0, 160.0),
+    ] {
+        let (ink_r, _) = renderer.geometry_for_layer(Some(id), &viewport).unwrap();
+
+        assert!(ink_r.x >= start - 1.0);
+        assert!(ink_r.x + ink_r.width <= end + 1.0);
+        assert!(ink_r.width > (end - start) * 0.8);
+    }
+}
+
+#[test]
+fn dominant_baseline_shifts_text() {
+    let svg = load_svg(
+        br##"<?xml version="1.0" encoding="UTF-8"?>
+<svg xmlns="http://www.w3.org/2000/svg" width="300" height="100" font-size="20">
+  <text id="alphabetic" x="10" y="50">Abc</text>
+  <text id="central" x="110" y="50" dominant-baseline="central">Abc</text>
+  <text id="middle" x="210" y="50" dominant-baseline="middle">Abc</text>
+</svg>
+"##,
+    )
+    .unwrap();
+
+    let renderer = CairoRenderer::new(&svg);
+
+    let viewport = cairo::Rectangle {
+        x: 0.0,
+        y: 0.0,
+        width: 300.0,
+        height: 100.0,
+    };
+
+    let top = |id| {
+        renderer
+            .geometry_for_layer(Some(id), &viewport)
+            .unwrap()
+            .0
+            .y
+    };
+
+    let alphabetic = top("#alphabetic");
+
+    // Both baselines are between the alphabetic baseline and the top of the text, so
+    // aligning them to the text's position moves the glyphs down.
+    assert!(top("#central") > alphabetic + 2.0);
+    assert!(top("#middle") > alphabetic + 2.0);
+}
+
+#[test]
+fn renders_text_with_data_url_font_face() {
+    setup_font_map();
+
+    let font = glib::base64_encode(include_bytes!("../resources/Roboto-Bold.ttf"));
+
+    let input = format!(
+        r##"<?xml version="1.0" encoding="UTF-8"?>
+<svg xmlns="http://www.w3.org/2000/svg" width="200" height="60">
+  <style>
+    @font-face {{
+      font-family: "Data Font";
+      src: url(data:font/ttf;base64,{});
+    }}
+  </style>
+  <text x="10" y="45" font-family="Data Font" font-size="40">Hello</text>
+</svg>
+"##,
+        font
+    );
+
+    let bytes = glib::Bytes::from_owned(input.into_bytes());
+    let stream = gio::MemoryInputStream::from_bytes(&bytes);
+    let svg = Loader::new()
+        .read_stream(&stream, None::<&gio::File>, None::<&gio::Cancellable>)
+        .unwrap();
+
+    // The test fonts include Roboto Bold, so the text must look the same as with it.
+    let reference = load_svg(
+        br##"<?xml version="1.0" encoding="UTF-8"?>
+<svg xmlns="http://www.w3.org/2000/svg" width="200" height="60">
+  <text x="10" y="45" font-family="Roboto" font-weight="bold" font-size="40">Hello</text>
+</svg>
+"##,
+    )
+    .unwrap();
+
+    let viewport = cairo::Rectangle {
+        x: 0.0,
+        y: 0.0,
+        width: 200.0,
+        height: 60.0,
+    };
+
+    let output_surf = render_document(&svg, SurfaceSize(200, 60), |_| (), viewport).unwrap();
+
+    let reference_surf =
+        render_document(&reference, SurfaceSize(200, 60), |_| (), viewport).unwrap();
+
+    Reference::from_surface(reference_surf.into_image_surface().unwrap())
+        .compare(&output_surf)
+        .evaluate(&output_surf, "renders_text_with_data_url_font_face");
+}
+
+#[test]
+fn font_face_is_not_available_under_the_font_internal_name() {
+    setup_font_map();
+
+    let font = glib::base64_encode(include_bytes!("../resources/Roboto-Bold.ttf"));
+
+    // The font calls itself "Roboto", but the document only declares it as "Data Font".
+    let input = format!(
+        r##"<?xml version="1.0" encoding="UTF-8"?>
+<svg xmlns="http://www.w3.org/2000/svg" width="200" height="60">
+  <style>
+    @font-face {{
+      font-family: "Data Font";
+      src: url(data:font/ttf;base64,{});
+    }}
+  </style>
+  <text x="10" y="45" font-family="Roboto" font-size="40">Hello</text>
+</svg>
+"##,
+        font
+    );
+
+    let bytes = glib::Bytes::from_owned(input.into_bytes());
+    let stream = gio::MemoryInputStream::from_bytes(&bytes);
+    let svg = Loader::new()
+        .read_stream(&stream, None::<&gio::File>, None::<&gio::Cancellable>)
+        .unwrap();
+
+    let reference = load_svg(
+        br##"<?xml version="1.0" encoding="UTF-8"?>
+<svg xmlns="http://www.w3.org/2000/svg" width="200" height="60">
+  <text x="10" y="45" font-family="Roboto" font-weight="bold" font-size="40">Hello</text>
+</svg>
+"##,
+    )
+    .unwrap();
+
+    let viewport = cairo::Rectangle {
+        x: 0.0,
+        y: 0.0,
+        width: 200.0,
+        height: 60.0,
+    };
+
+    let output_surf = render_document(&svg, SurfaceSize(200, 60), |_| (), viewport).unwrap();
+
+    let reference_surf =
+        render_document(&reference, SurfaceSize(200, 60), |_| (), viewport).unwrap();
+
+    match compare_surfaces(&output_surf, &reference_surf).unwrap() {
+        BufferDiff::Diff(diff) => assert!(diff.num_pixels_changed > 0),
+        BufferDiff::DifferentSizes => unreachable!(),
+    }
+}
+
+#[test]
+fn text_decoration_styles() {
+    // The glyphs are not painted, so only the decorations show up.  With a thickness
+    // of 4 and an offset of 5, the underlines are centered 7 units below the baseline.
+    let svg = load_svg(
+        br##"<?xml version="1.0" encoding="UTF-8"?>
+<svg xmlns="http://www.w3.org/2000/svg" width="100" height="120">
+  <g font-family="sans-serif" font-size="40" fill="none" style="text-underline-offset: 5px">
+    <text x="10" y="50" style="text-decoration: underline dotted lime 4px">HHHHHHHH</text>
+    <text x="10" y="100" style="text-decoration: underline wavy lime 4px">HHHHHHHH</text>
+  </g>
+</svg>
+"##,
+    )
+    .unwrap();
+
+    let viewport = cairo::Rectangle {
+        x: 0.0,
+        y: 0.0,
+        width: 100.0,
+        height: 120.0,
+    };
+
+    let output = render_document(&svg, SurfaceSize(100, 120), |_| (), viewport).unwrap();
+
+    let is_opaque = |x, y| output.get_pixel(x, y).a == 255;
+    let is_transparent = |x, y| output.get_pixel(x, y).a == 0;
+
+    // Dots with a radius of 2, every 8 units
+    assert!(is_opaque(12, 57));
+    assert!(is_transparent(16, 57));
+    assert!(is_opaque(20, 57));
+    assert!(is_transparent(24, 57));
+    assert!(is_opaque(28, 57));
+
+    // A sine wave with an amplitude of 4 and a wavelength of 24, starting at the
+    // text's origin.  The line is 4 units tall.
+    assert!(is_opaque(10, 107));
+    assert!(is_opaque(16, 110));
+    assert!(is_transparent(16, 106));
+    assert!(is_opaque(22, 107));
+    assert!(is_opaque(28, 103));
+    assert!(is_transparent(28, 108));
+}
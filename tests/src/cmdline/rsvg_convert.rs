@@ -25,6 +25,7 @@ use url::Url;
 //  - limit on output size (32767 pixels) ✔
 //  - output formats (PNG, PDF, PS, EPS, SVG) ✔
 //  - multi-page output (for PDF) ✔
+//  - animation frames ✔
 //  - output file option ✔
 //  - SOURCE_DATA_EPOCH environment variable for PDF output ✔
 //  - background color option ✔
@@ -289,6 +290,85 @@ fn multiple_input_files_create_multi_page_pdf_output() {
         .stdout(file::is_pdf().with_page_count(3));
 }
 
+#[cfg(system_deps_have_cairo_pdf)]
+#[test]
+fn multiple_frames_create_multi_page_pdf_output() {
+    RsvgConvert::new_with_input("tests/fixtures/dimensions/521-with-viewbox.svg")
+        .arg("--format=pdf")
+        .arg("--frames=3")
+        .assert()
+        .success()
+        .stdout(file::is_pdf().with_page_count(3));
+}
+
+#[cfg(system_deps_have_cairo_pdf)]
+#[test]
+fn multiple_frames_of_multiple_input_files_create_multi_page_pdf_output() {
+    let one = Path::new("tests/fixtures/dimensions/521-with-viewbox.svg");
+    let two = Path::new("tests/fixtures/dimensions/sub-rect-no-unit.svg");
+    RsvgConvert::new()
+        .arg("--format=pdf")
+        .arg("--frames=2")
+        .arg(one)
+        .arg(two)
+        .assert()
+        .success()
+        .stdout(file::is_pdf().with_page_count(4));
+}
+
+#[test]
+fn multiple_frames_of_multiple_input_files_not_allowed_for_png_output() {
+    let dir = tempfile::tempdir().unwrap();
+    let output = dir.path().join("frame-%02d.png");
+    let one = Path::new("tests/fixtures/dimensions/521-with-viewbox.svg");
+    let two = Path::new("tests/fixtures/dimensions/sub-rect-no-unit.svg");
+
+    RsvgConvert::new()
+        .arg("--frames=2")
+        .arg(format!("--output={}", output.display()))
+        .arg(one)
+        .arg(two)
+        .assert()
+        .failure()
+        .stderr(contains(
+            "Multiple SVG files are only allowed for PDF and (E)PS output",
+        ));
+
+    assert!(predicates::path::is_file()
+        .not()
+        .eval(&dir.path().join("frame-00.png")));
+}
+
+#[test]
+fn multiple_frames_need_output_filename_placeholder() {
+    RsvgConvert::new_with_input("tests/fixtures/dimensions/521-with-viewbox.svg")
+        .arg("--frames=2")
+        .assert()
+        .failure()
+        .stderr(contains("frame number placeholder"));
+}
+
+#[test]
+fn multiple_frames_create_png_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let output = dir.path().join("frame-%02d.png");
+
+    RsvgConvert::new_with_input("tests/fixtures/dimensions/521-with-viewbox.svg")
+        .arg("--frames=2")
+        .arg("--frame-rate=5")
+        .arg(format!("--output={}", output.display()))
+        .assert()
+        .success()
+        .stdout(is_empty());
+
+    for name in &["frame-00.png", "frame-01.png"] {
+        assert!(predicates::path::is_file().eval(&dir.path().join(name)));
+    }
+    assert!(predicates::path::is_file()
+        .not()
+        .eval(&dir.path().join("frame-02.png")));
+}
+
 #[cfg(system_deps_have_cairo_pdf)]
 #[test]
 fn pdf_has_link() {
@@ -899,6 +979,66 @@ fn no_keep_image_data_option() {
     RsvgConvert::accepts_arg("--no-keep-image-data");
 }
 
+#[cfg(system_deps_have_cairo_svg)]
+#[test]
+fn text_to_path_option() {
+    // Cairo's SVG surface writes text as references to glyph symbols, unless it
+    // gets drawn as plain paths.
+    RsvgConvert::new_with_input("tests/fixtures/cmdline/text-to-path.svg")
+        .arg("--format=svg")
+        .assert()
+        .success()
+        .stdout(contains("<symbol").and(contains("glyph")));
+
+    RsvgConvert::new_with_input("tests/fixtures/cmdline/text-to-path.svg")
+        .arg("--format=svg")
+        .arg("--text-to-path")
+        .assert()
+        .success()
+        .stdout(file::is_svg())
+        .stdout(contains("<path").and(contains("d=\"M ")))
+        .stdout(contains("<symbol").or(contains("glyph")).not());
+}
+
+#[test]
+fn view_option() {
+    RsvgConvert::new_with_input("tests/fixtures/reftests/ignore-view-sprites.svg")
+        .arg("--view=blue")
+        .assert()
+        .success()
+        .stdout(file::is_png().with_size(40, 20));
+}
+
+#[test]
+fn time_option() {
+    // Without --time, animations are ignored
+    RsvgConvert::new_with_input("tests/fixtures/cmdline/animated.svg")
+        .assert()
+        .success()
+        .stdout(file::is_png().with_contents("tests/fixtures/cmdline/animated-0s.png"));
+
+    RsvgConvert::new_with_input("tests/fixtures/cmdline/animated.svg")
+        .arg("--time=0")
+        .assert()
+        .success()
+        .stdout(file::is_png().with_contents("tests/fixtures/cmdline/animated-0s.png"));
+
+    RsvgConvert::new_with_input("tests/fixtures/cmdline/animated.svg")
+        .arg("--time=2")
+        .assert()
+        .success()
+        .stdout(file::is_png().with_contents("tests/fixtures/cmdline/animated-2s.png"));
+}
+
+#[test]
+fn invalid_frame_count_yields_error() {
+    RsvgConvert::new_with_input("tests/fixtures/api/dpi.svg")
+        .arg("--frames=0")
+        .assert()
+        .failure()
+        .stderr(contains("Invalid number of frames"));
+}
+
 fn is_version_output() -> AndPredicate<StartsWithPredicate, TrimPredicate<EndsWithPredicate>, str> {
     starts_with("rsvg-convert version ").and(ends_with_pkg_version().trim())
 }
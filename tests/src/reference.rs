@@ -393,3 +393,406 @@ test_compare_render_output!(
   </svg>
     "##,
 );
+
+test_compare_render_output!(
+    bidi_reordering_across_tspans,
+    200,
+    50,
+    br##"<?xml version="1.0" encoding="UTF-8"?>
+    <svg xmlns="http://www.w3.org/2000/svg" width="200" height="50">
+      <text x="10" y="30" font-family="sans-serif" font-size="16">abc <tspan>&#x5e9;&#x5dc;&#x5d5;&#x5dd;</tspan> <tspan>&#x5e2;&#x5d5;&#x5dc;&#x5dd;</tspan> xyz</text>
+    </svg>"##,
+    br##"<?xml version="1.0" encoding="UTF-8"?>
+    <svg xmlns="http://www.w3.org/2000/svg" width="200" height="50">
+      <text x="10" y="30" font-family="sans-serif" font-size="16">abc &#x5e9;&#x5dc;&#x5d5;&#x5dd; &#x5e2;&#x5d5;&#x5dc;&#x5dd; xyz</text>
+    </svg>"##,
+);
+
+test_compare_render_output!(
+    mesh_gradient_with_href,
+    100,
+    100,
+    br##"<?xml version="1.0" encoding="UTF-8"?>
+    <svg xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink" width="100" height="100">
+      <meshgradient id="mesh" gradientUnits="userSpaceOnUse" x="10" y="10">
+        <meshrow>
+          <meshpatch>
+            <stop path="l 40,0" stop-color="lime"/>
+            <stop path="l 0,80" stop-color="lime"/>
+            <stop path="l -40,0" stop-color="lime"/>
+            <stop path="l 0,-80" stop-color="lime"/>
+          </meshpatch>
+          <meshpatch>
+            <stop path="c 10,0 30,0 40,0" stop-color="lime"/>
+            <stop path="L 90,90" stop-color="lime"/>
+            <stop path="l -40,0" stop-color="lime"/>
+          </meshpatch>
+        </meshrow>
+      </meshgradient>
+      <meshgradient id="ref" xlink:href="#mesh" type="bicubic"/>
+      <rect x="10" y="10" width="80" height="80" fill="url(#ref) red"/>
+    </svg>"##,
+    br##"<?xml version="1.0" encoding="UTF-8"?>
+    <svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
+      <rect x="10" y="10" width="80" height="80" fill="lime"/>
+    </svg>"##,
+);
+
+// With straight edges, the colors get interpolated bilinearly between the corners.  Here
+// the red channel increases from left to right and the green one from top to bottom, which
+// is the same as adding two linear gradients with the "screen" blend mode.
+test_compare_render_output!(
+    mesh_gradient_interpolates_corner_colors,
+    100,
+    100,
+    br##"<?xml version="1.0" encoding="UTF-8"?>
+    <svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
+      <meshgradient id="mesh" gradientUnits="userSpaceOnUse" x="10" y="10">
+        <meshrow>
+          <meshpatch>
+            <stop path="l 80,0" stop-color="black"/>
+            <stop path="l 0,80" stop-color="red"/>
+            <stop path="l -80,0" stop-color="yellow"/>
+            <stop path="l 0,-80" stop-color="lime"/>
+          </meshpatch>
+        </meshrow>
+      </meshgradient>
+      <rect x="10" y="10" width="80" height="80" fill="url(#mesh)"/>
+    </svg>"##,
+    br##"<?xml version="1.0" encoding="UTF-8"?>
+    <svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
+      <linearGradient id="red" gradientUnits="userSpaceOnUse" x1="10" y1="0" x2="90" y2="0">
+        <stop offset="0" stop-color="black"/>
+        <stop offset="1" stop-color="red"/>
+      </linearGradient>
+      <linearGradient id="green" gradientUnits="userSpaceOnUse" x1="0" y1="10" x2="0" y2="90">
+        <stop offset="0" stop-color="black"/>
+        <stop offset="1" stop-color="lime"/>
+      </linearGradient>
+      <rect x="10" y="10" width="80" height="80" fill="url(#red)"/>
+      <rect x="10" y="10" width="80" height="80" fill="url(#green)" style="mix-blend-mode: screen"/>
+    </svg>"##,
+);
+
+test_compare_render_output!(
+    hatch_with_href_and_rotate,
+    100,
+    100,
+    br##"<?xml version="1.0" encoding="UTF-8"?>
+    <svg xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink" width="100" height="100">
+      <hatch id="hatch" hatchUnits="userSpaceOnUse" pitch="10">
+        <hatchpath stroke="lime" stroke-width="5" offset="2.5"/>
+      </hatch>
+      <hatch id="rotated" xlink:href="#hatch" rotate="90"/>
+      <rect x="10" y="10" width="80" height="40" fill="url(#hatch)"/>
+      <rect x="10" y="50" width="80" height="40" fill="url(#rotated)"/>
+    </svg>"##,
+    br##"<?xml version="1.0" encoding="UTF-8"?>
+    <svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
+      <pattern id="vertical" patternUnits="userSpaceOnUse" width="10" height="10">
+        <rect width="5" height="10" fill="lime"/>
+      </pattern>
+      <pattern id="horizontal" patternUnits="userSpaceOnUse" width="10" height="10">
+        <rect width="10" height="5" fill="lime"/>
+      </pattern>
+      <rect x="10" y="10" width="80" height="40" fill="url(#vertical)"/>
+      <rect x="10" y="50" width="80" height="40" fill="url(#horizontal)"/>
+    </svg>"##,
+);
+
+test_compare_render_output!(
+    hatch_covers_stroke,
+    100,
+    100,
+    br##"<?xml version="1.0" encoding="UTF-8"?>
+    <svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
+      <hatch id="hatch" hatchUnits="userSpaceOnUse" pitch="10">
+        <hatchpath stroke="lime" stroke-width="4" offset="5" d="M0,0 V5 M0,7"/>
+      </hatch>
+      <rect x="20" y="20" width="60" height="33" fill="none" stroke="url(#hatch)" stroke-width="20"/>
+    </svg>"##,
+    br##"<?xml version="1.0" encoding="UTF-8"?>
+    <svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
+      <pattern id="dashes" patternUnits="userSpaceOnUse" width="10" height="7">
+        <rect x="3" width="4" height="5" fill="lime"/>
+      </pattern>
+      <rect x="20" y="20" width="60" height="33" fill="none" stroke="url(#dashes)" stroke-width="20"/>
+    </svg>"##,
+);
+
+test_compare_render_output!(
+    group_opacity_in_linear_rgb,
+    100,
+    100,
+    br##"<?xml version="1.0" encoding="UTF-8"?>
+    <svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
+      <rect width="100" height="100" fill="blue"/>
+      <g opacity="0.5" color-interpolation="linearRGB">
+        <rect x="20" y="20" width="60" height="60" fill="red"/>
+      </g>
+    </svg>"##,
+    br##"<?xml version="1.0" encoding="UTF-8"?>
+    <svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
+      <rect width="100" height="100" fill="blue"/>
+      <rect x="20" y="20" width="60" height="60" fill="rgb(188,0,187)"/>
+    </svg>"##,
+);
+
+test_compare_render_output!(
+    linear_rgb_compositing_leaves_backdrop_outside_group_untouched,
+    100,
+    100,
+    br##"<?xml version="1.0" encoding="UTF-8"?>
+    <svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
+      <rect width="100" height="100" fill="rgb(6,6,6)"/>
+      <g opacity="0.5" color-interpolation="linearRGB">
+        <rect x="20" y="20" width="60" height="60" fill="red"/>
+      </g>
+    </svg>"##,
+    br##"<?xml version="1.0" encoding="UTF-8"?>
+    <svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
+      <rect width="100" height="100" fill="rgb(6,6,6)"/>
+      <rect x="20" y="20" width="60" height="60" fill="rgb(188,0,0)"/>
+    </svg>"##,
+);
+
+test_compare_render_output!(
+    linear_rgb_compositing_leaves_unpainted_backdrop_untouched,
+    100,
+    100,
+    br##"<?xml version="1.0" encoding="UTF-8"?>
+    <svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
+      <clipPath id="clip">
+        <rect width="100" height="50"/>
+      </clipPath>
+      <rect width="100" height="100" fill="rgb(6,6,6)"/>
+      <g opacity="0.5" color-interpolation="linearRGB" clip-path="url(#clip)">
+        <rect x="10" y="10" width="20" height="80" fill="red"/>
+        <rect x="70" y="10" width="20" height="80" fill="red"/>
+      </g>
+    </svg>"##,
+    br##"<?xml version="1.0" encoding="UTF-8"?>
+    <svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
+      <rect width="100" height="100" fill="rgb(6,6,6)"/>
+      <rect x="10" y="10" width="20" height="40" fill="rgb(188,0,0)"/>
+      <rect x="70" y="10" width="20" height="40" fill="rgb(188,0,0)"/>
+    </svg>"##,
+);
+
+test_compare_render_output!(
+    linear_gradient_in_linear_rgb,
+    100,
+    100,
+    br##"<?xml version="1.0" encoding="UTF-8"?>
+    <svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
+      <linearGradient id="gradient" gradientUnits="userSpaceOnUse" x1="-9950" x2="10050"
+                      color-interpolation="linearRGB">
+        <stop offset="0" stop-color="black"/>
+        <stop offset="1" stop-color="white"/>
+      </linearGradient>
+      <rect width="100" height="100" fill="url(#gradient)"/>
+    </svg>"##,
+    br##"<?xml version="1.0" encoding="UTF-8"?>
+    <svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
+      <rect width="100" height="100" fill="rgb(188,188,188)"/>
+    </svg>"##,
+);
+
+test_compare_render_output!(
+    isolation_isolate_blends_within_group,
+    100,
+    100,
+    br##"<?xml version="1.0" encoding="UTF-8"?>
+    <svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
+      <rect width="100" height="100" fill="red"/>
+      <g style="isolation: isolate">
+        <rect x="20" y="20" width="60" height="60" fill="blue" mix-blend-mode="multiply"/>
+      </g>
+    </svg>"##,
+    br##"<?xml version="1.0" encoding="UTF-8"?>
+    <svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
+      <rect width="100" height="100" fill="red"/>
+      <rect x="20" y="20" width="60" height="60" fill="blue"/>
+    </svg>"##,
+);
+
+test_compare_render_output!(
+    user_space_clip_path_does_not_isolate_group,
+    100,
+    100,
+    br##"<?xml version="1.0" encoding="UTF-8"?>
+    <svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
+      <clipPath id="clip">
+        <rect x="20" y="20" width="60" height="60"/>
+      </clipPath>
+      <rect width="100" height="100" fill="yellow"/>
+      <g clip-path="url(#clip)">
+        <rect width="100" height="100" fill="cyan" mix-blend-mode="multiply"/>
+      </g>
+    </svg>"##,
+    br##"<?xml version="1.0" encoding="UTF-8"?>
+    <svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
+      <rect width="100" height="100" fill="yellow"/>
+      <rect x="20" y="20" width="60" height="60" fill="lime"/>
+    </svg>"##,
+);
+
+test_compare_render_output!(
+    vector_effect_non_scaling_stroke_and_size,
+    100,
+    100,
+    br##"<?xml version="1.0" encoding="UTF-8"?>
+    <svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
+      <path d="M5 5 H20" transform="scale(4 3)" stroke="lime" stroke-width="10"
+            vector-effect="non-scaling-stroke"/>
+      <rect width="10" height="10" fill="lime" transform="translate(50 50) rotate(45) scale(3)"
+            vector-effect="non-scaling-size non-rotation"/>
+    </svg>"##,
+    br##"<?xml version="1.0" encoding="UTF-8"?>
+    <svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
+      <path d="M20 15 H80" stroke="lime" stroke-width="10"/>
+      <rect x="50" y="50" width="10" height="10" fill="lime"/>
+    </svg>"##,
+);
+
+test_compare_render_output!(
+    vector_effect_in_pattern_is_relative_to_toplevel_viewport,
+    100,
+    100,
+    br##"<?xml version="1.0" encoding="UTF-8"?>
+    <svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
+      <pattern id="pattern" patternUnits="userSpaceOnUse" width="100" height="100"
+               patternTransform="translate(10 10)">
+        <rect x="20" y="20" width="20" height="20" fill="lime" transform="translate(50 50)"
+              vector-effect="fixed-position"/>
+      </pattern>
+      <rect width="100" height="100" fill="url(#pattern)"/>
+    </svg>"##,
+    br##"<?xml version="1.0" encoding="UTF-8"?>
+    <svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
+      <rect x="20" y="20" width="20" height="20" fill="lime"/>
+    </svg>"##,
+);
+
+test_compare_render_output!(
+    mask_type_and_mask_mode_alpha,
+    100,
+    100,
+    br##"<?xml version="1.0" encoding="UTF-8"?>
+    <svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
+      <mask id="alpha" mask-type="alpha">
+        <rect x="10" y="10" width="80" height="80" fill="black" fill-opacity="0.5"/>
+      </mask>
+      <mask id="luminance">
+        <rect x="10" y="10" width="80" height="80" fill="black" fill-opacity="0.5"/>
+      </mask>
+      <rect width="100" height="50" fill="lime" mask="url(#alpha)"/>
+      <rect y="50" width="50" height="50" fill="lime" mask="url(#luminance)"/>
+      <rect x="50" y="50" width="50" height="50" fill="lime" mask="url(#luminance)"
+            style="mask-mode: alpha"/>
+    </svg>"##,
+    br##"<?xml version="1.0" encoding="UTF-8"?>
+    <svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
+      <rect x="10" y="10" width="80" height="40" fill="lime" fill-opacity="0.5"/>
+      <rect x="50" y="50" width="40" height="40" fill="lime" fill-opacity="0.5"/>
+    </svg>"##,
+);
+
+test_compare_render_output!(
+    clip_path_basic_shapes,
+    100,
+    100,
+    br##"<?xml version="1.0" encoding="UTF-8"?>
+    <svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
+      <rect x="10" y="10" width="40" height="40" fill="lime" style="clip-path: circle(50%) fill-box"/>
+      <rect x="50" y="10" width="40" height="40" fill="lime" style="clip-path: inset(10px 0 0 25%)"/>
+      <rect x="10" y="50" width="80" height="40" fill="lime"
+            style="clip-path: polygon(evenodd, 0 0, 100% 0, 100% 100%, 0 100%, 0 0, 50% 0, 50% 50%, 0 50%)"/>
+    </svg>"##,
+    br##"<?xml version="1.0" encoding="UTF-8"?>
+    <svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
+      <circle cx="30" cy="30" r="20" fill="lime"/>
+      <rect x="60" y="20" width="30" height="30" fill="lime"/>
+      <path d="M 50 50 H 90 V 90 H 10 V 70 H 50 Z" fill="lime"/>
+    </svg>"##,
+);
+
+test_compare_render_output!(
+    text_path_with_start_offset,
+    200,
+    100,
+    br##"<?xml version="1.0" encoding="UTF-8"?>
+    <svg xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink" width="200" height="100">
+      <path id="line" d="M 10 50 H 190"/>
+      <text font-family="sans-serif" font-size="20" fill="black">
+        <textPath xlink:href="#line" startOffset="20">Hello</textPath>
+      </text>
+      <text font-family="sans-serif" font-size="20" fill="black">
+        <textPath path="M 10 80 H 190" startOffset="50%">Path</textPath>
+      </text>
+      <text font-family="sans-serif" font-size="20" fill="black">
+        <textPath path="M 170 10 V 100" startOffset="10">Down</textPath>
+      </text>
+    </svg>"##,
+    br##"<?xml version="1.0" encoding="UTF-8"?>
+    <svg xmlns="http://www.w3.org/2000/svg" width="200" height="100">
+      <text x="30" y="50" font-family="sans-serif" font-size="20" fill="black">Hello</text>
+      <text x="100" y="80" font-family="sans-serif" font-size="20" fill="black">Path</text>
+      <text transform="translate(170 20) rotate(90)" font-family="sans-serif" font-size="20" fill="black">Down</text>
+    </svg>"##,
+);
+
+test_compare_render_output!(
+    alignment_baseline_overrides_dominant_baseline,
+    200,
+    100,
+    br##"<?xml version="1.0" encoding="UTF-8"?>
+    <svg xmlns="http://www.w3.org/2000/svg" width="200" height="100">
+      <text x="10" y="30" font-family="sans-serif" font-size="20" dominant-baseline="hanging">Ab<tspan alignment-baseline="central">Cd</tspan></text>
+      <text x="10" y="70" font-family="sans-serif" font-size="20" dominant-baseline="central">Ab<tspan alignment-baseline="middle">Cd</tspan></text>
+    </svg>"##,
+    br##"<?xml version="1.0" encoding="UTF-8"?>
+    <svg xmlns="http://www.w3.org/2000/svg" width="200" height="100">
+      <text x="10" y="30" font-family="sans-serif" font-size="20"><tspan dominant-baseline="hanging">Ab</tspan><tspan dominant-baseline="central">Cd</tspan></text>
+      <text x="10" y="70" font-family="sans-serif" font-size="20"><tspan dominant-baseline="central">Ab</tspan><tspan dominant-baseline="middle">Cd</tspan></text>
+    </svg>"##,
+);
+
+test_compare_render_output!(
+    text_decoration_color_thickness_and_offset,
+    100,
+    100,
+    br##"<?xml version="1.0" encoding="UTF-8"?>
+    <svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
+      <clipPath id="middle">
+        <rect x="20" y="0" width="60" height="100"/>
+      </clipPath>
+      <text x="10" y="50" font-family="sans-serif" font-size="40" fill="none" color="red" clip-path="url(#middle)"
+            style="text-decoration: underline; text-decoration-color: lime;
+                   text-decoration-thickness: 4px; text-underline-offset: 5px">HHHHHHHH</text>
+    </svg>"##,
+    br##"<?xml version="1.0" encoding="UTF-8"?>
+    <svg xmlns="http://www.w3.org/2000/svg" width="100" height="100">
+      <rect x="20" y="55" width="60" height="4" fill="lime"/>
+    </svg>"##,
+);
+
+test_compare_render_output!(
+    text_decoration_propagates_from_ancestor,
+    300,
+    100,
+    br##"<?xml version="1.0" encoding="UTF-8"?>
+    <svg xmlns="http://www.w3.org/2000/svg" width="300" height="100">
+      <text x="10" y="60" font-family="sans-serif" font-size="40" fill="none"
+            style="text-decoration: underline lime; text-decoration-thickness: 4px"><tspan
+            style="text-decoration: overline blue">HHH</tspan><tspan
+            style="text-decoration: none">HHH</tspan></text>
+    </svg>"##,
+    br##"<?xml version="1.0" encoding="UTF-8"?>
+    <svg xmlns="http://www.w3.org/2000/svg" width="300" height="100">
+      <text x="10" y="60" font-family="sans-serif" font-size="40" fill="none"
+            style="text-decoration: underline lime; text-decoration-thickness: 4px"><tspan>HHH</tspan><tspan>HHH</tspan></text>
+      <text x="10" y="60" font-family="sans-serif" font-size="40" fill="none"
+            style="text-decoration: overline blue">HHH</text>
+    </svg>"##,
+);